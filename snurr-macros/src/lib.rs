@@ -0,0 +1,103 @@
+//! Attribute macros for [Snurr](https://crates.io/crates/snurr). Enable them with the `macros` feature of `snurr`.
+//!
+//! Annotate a function with the name or bpmn id of the element. The function keeps working as before and
+//! a registration is generated next to it. `snurr::collect_handlers!` registers every annotated function
+//! with the data type of the process.
+//!
+//! The data type is read from the last argument of the function, `Data<T>`. Functions with two arguments
+//! also receive the `Context`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    FnArg, GenericArgument, ItemFn, LitStr, PathArguments, Type, parse_macro_input,
+    spanned::Spanned,
+};
+
+/// Register the function as a task. `#[snurr::task("Count 1")]`
+#[proc_macro_attribute]
+pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand("Task", attr, item)
+}
+
+/// Register the function as an exclusive gateway. `#[snurr::exclusive("equal to 3")]`
+#[proc_macro_attribute]
+pub fn exclusive(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand("Exclusive", attr, item)
+}
+
+/// Register the function as an inclusive gateway. `#[snurr::inclusive("Inclusive")]`
+#[proc_macro_attribute]
+pub fn inclusive(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand("Inclusive", attr, item)
+}
+
+/// Register the function as an event based gateway. `#[snurr::event_based("Event based")]`
+#[proc_macro_attribute]
+pub fn event_based(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand("EventBased", attr, item)
+}
+
+fn expand(variant: &str, attr: TokenStream, item: TokenStream) -> TokenStream {
+    let name = parse_macro_input!(attr as LitStr);
+    let func = parse_macro_input!(item as ItemFn);
+    registration(variant, &name, &func)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn registration(variant: &str, name: &LitStr, func: &ItemFn) -> syn::Result<TokenStream2> {
+    let inputs = &func.sig.inputs;
+    let ident = &func.sig.ident;
+    // The registered handler always receives the context
+    let handler = match inputs.len() {
+        1 => quote!(|_, data| #ident(data)),
+        2 => quote!(#ident),
+        _ => {
+            return Err(syn::Error::new(
+                inputs.span(),
+                "expected the arguments `Data<T>` or `&Context, Data<T>`",
+            ));
+        }
+    };
+    let data = inputs
+        .last()
+        .and_then(data_type)
+        .ok_or_else(|| syn::Error::new(inputs.span(), "expected `Data<T>` as the last argument"))?;
+
+    let variant = format_ident!("{}", variant);
+    Ok(quote! {
+        #func
+
+        const _: () = {
+            static HANDLER: ::snurr::__private::Handler<#data> =
+                ::snurr::__private::Handler::#variant(#handler);
+
+            ::snurr::__private::inventory::submit! {
+                ::snurr::__private::Registration::new(#name, &HANDLER)
+            }
+        };
+    })
+}
+
+// T in the last path segment of Data<T>
+fn data_type(arg: &FnArg) -> Option<&Type> {
+    let FnArg::Typed(pat) = arg else {
+        return None;
+    };
+    let Type::Path(path) = pat.ty.as_ref() else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Data" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
@@ -1,17 +1,184 @@
-use crate::bpmn::Symbol;
+use crate::{
+    bpmn::{Assignment, Event, Form, Symbol},
+    diagram::{DataAssociations, export::escape},
+    error::{CHANNEL_CLOSED_ERROR_MSG, Error},
+    process::{
+        cache::DecisionCache,
+        options::{BoundaryTrigger, CancelToken},
+    },
+    time::{self, Instant},
+};
+#[cfg(not(feature = "parallel"))]
+use atomic_refcell::{AtomicRef, AtomicRefCell, AtomicRefMut};
 use std::{
-    fmt::Display,
-    sync::{Arc, Mutex},
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Display},
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    sync::{
+        Arc, LockResult, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+        mpsc::{Receiver, RecvTimeoutError},
+    },
+    time::Duration,
 };
 
+// How often a token waiting for a task completed outside the engine checks if the run is cancelled
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Generic type for the task and gateway inputs.
 pub type Data<T> = Arc<Mutex<T>>;
 
+/// Synchronization primitive around the data of a run. The registered functions get the data as `Arc<L>`,
+/// which is `Data<T>` with the default `Mutex<T>`. Choose another primitive with `Process::data_lock`, for example
+/// a `RwLock` for read-mostly processes where parallel readers should not wait for each other.
+///
+/// Implement it for your own type to plug in another primitive. The engine reads the data with `read` to split
+/// parallel branches and check conditions, and writes it with `lock` to merge the branches.
+pub trait SharedData<T>: Sized {
+    /// Exclusive access to the data
+    type Guard<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    /// Shared access to the data
+    type ReadGuard<'a>: Deref<Target = T>
+    where
+        Self: 'a;
+
+    /// Put the data behind the primitive
+    fn new(data: T) -> Self;
+
+    /// Exclusive access to the data. Fails if a function panicked while holding the lock.
+    fn lock(&self) -> LockResult<Self::Guard<'_>>;
+
+    /// Shared access to the data. Fails if a function panicked while holding the lock.
+    fn read(&self) -> LockResult<Self::ReadGuard<'_>>;
+
+    /// Clear the poisoned state after a panic was caught. See `Process::catch_panics`.
+    fn clear_poison(&self) {}
+
+    /// Consume the primitive and return the data
+    fn into_inner(self) -> LockResult<T>;
+}
+
+impl<T> SharedData<T> for Mutex<T> {
+    type Guard<'a>
+        = MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    type ReadGuard<'a>
+        = MutexGuard<'a, T>
+    where
+        T: 'a;
+
+    fn new(data: T) -> Self {
+        Mutex::new(data)
+    }
+
+    fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        Mutex::lock(self)
+    }
+
+    // A Mutex gives exclusive access to readers too
+    fn read(&self) -> LockResult<MutexGuard<'_, T>> {
+        Mutex::lock(self)
+    }
+
+    fn clear_poison(&self) {
+        Mutex::clear_poison(self);
+    }
+
+    fn into_inner(self) -> LockResult<T> {
+        Mutex::into_inner(self)
+    }
+}
+
+impl<T> SharedData<T> for RwLock<T> {
+    type Guard<'a>
+        = RwLockWriteGuard<'a, T>
+    where
+        T: 'a;
+
+    type ReadGuard<'a>
+        = RwLockReadGuard<'a, T>
+    where
+        T: 'a;
+
+    fn new(data: T) -> Self {
+        RwLock::new(data)
+    }
+
+    fn lock(&self) -> LockResult<RwLockWriteGuard<'_, T>> {
+        RwLock::write(self)
+    }
+
+    fn read(&self) -> LockResult<RwLockReadGuard<'_, T>> {
+        RwLock::read(self)
+    }
+
+    fn clear_poison(&self) {
+        RwLock::clear_poison(self);
+    }
+
+    fn into_inner(self) -> LockResult<T> {
+        RwLock::into_inner(self)
+    }
+}
+
+/// Data of a run without a lock, for processes that run without the `parallel` feature. Only one function runs
+/// at a time, so the data is borrowed instead of locked and a panic doesn't poison it. Borrowing the data while it
+/// is borrowed mutably panics. Choose it with `Process::sequential` and register functions that get `&mut T`
+/// with `task_mut` and friends.
+#[cfg(not(feature = "parallel"))]
+#[derive(Debug, Default)]
+pub struct Sequential<T>(AtomicRefCell<T>);
+
+#[cfg(not(feature = "parallel"))]
+impl<T> Sequential<T> {
+    // Call the function with the borrowed data
+    pub(crate) fn with_mut<R>(&self, func: impl FnOnce(&mut T) -> R) -> R {
+        func(&mut self.0.borrow_mut())
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+impl<T> SharedData<T> for Sequential<T> {
+    type Guard<'a>
+        = AtomicRefMut<'a, T>
+    where
+        T: 'a;
+
+    type ReadGuard<'a>
+        = AtomicRef<'a, T>
+    where
+        T: 'a;
+
+    fn new(data: T) -> Self {
+        Self(AtomicRefCell::new(data))
+    }
+
+    fn lock(&self) -> LockResult<AtomicRefMut<'_, T>> {
+        Ok(self.0.borrow_mut())
+    }
+
+    fn read(&self) -> LockResult<AtomicRef<'_, T>> {
+        Ok(self.0.borrow())
+    }
+
+    fn into_inner(self) -> LockResult<T> {
+        Ok(self.0.into_inner())
+    }
+}
+
 /// Task result type
 pub type TaskResult = Option<Boundary>;
 
 /// Information about the end node where the process completed
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EndNode {
     /// The BPMN ID of the end node
     pub id: String,
@@ -34,40 +201,309 @@ pub struct EndNode {
     pub symbol: Symbol,
 }
 
+impl From<Option<&Event>> for EndNode {
+    fn from(event: Option<&Event>) -> Self {
+        match event {
+            Some(event) => Self {
+                id: event.id.bpmn().to_string(),
+                name: event.name.as_deref().map(Into::into),
+                symbol: event.symbol.clone().unwrap_or(Symbol::None),
+            },
+            None => Self {
+                id: String::new(),
+                name: None,
+                symbol: Symbol::None,
+            },
+        }
+    }
+}
+
+/// Named intermediate none throw event reached during the run. Reports business progress, like "Offer sent".
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Milestone {
+    /// The BPMN ID of the event
+    pub id: Arc<str>,
+    pub name: Arc<str>,
+    /// Time since the run started
+    pub elapsed: Duration,
+    /// Time since the Unix epoch when the milestone was reached
+    pub reached_at: Duration,
+}
+
+/// Map the end events of a process to a user type, usually an enum. Register it with `Process::outcomes`
+/// and match on `ProcessOutput::outcome` instead of end node names.
+///
+/// ```
+/// use snurr::{EndNode, Outcome};
+///
+/// enum Approval {
+///     Approved,
+///     Rejected,
+/// }
+///
+/// impl Outcome for Approval {
+///     fn from_end_node(end_node: &EndNode) -> Option<Self> {
+///         match end_node.name.as_deref()? {
+///             "Approved" => Some(Approval::Approved),
+///             "Rejected" => Some(Approval::Rejected),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait Outcome: Sized {
+    /// Return the outcome of the end node, or `None` if the end node has no outcome.
+    fn from_end_node(end_node: &EndNode) -> Option<Self>;
+}
+
+impl Outcome for () {
+    fn from_end_node(_: &EndNode) -> Option<Self> {
+        Some(())
+    }
+}
+
 /// Process execution output containing the final data and end node information
 #[derive(Debug, Clone)]
-pub struct ProcessOutput<T> {
+pub struct ProcessOutput<T, O = ()> {
     /// The final state of the process data
     pub data: T,
     /// Information about the end node where the process completed
     pub end_node: EndNode,
+    /// Every end event reached by the processes, in the order they were reached.
+    /// Parallel branches can end in different end events. End events of sub processes are not included.
+    pub end_nodes: Vec<EndNode>,
+    /// Milestones reached by the processes and sub processes, in the order they were reached
+    pub milestones: Vec<Milestone>,
+    /// Process variables at the end of the run
+    #[cfg(feature = "variables")]
+    pub variables: crate::Variables,
+    /// The outcome mapped from the end node. See `Process::outcomes`.
+    pub outcome: O,
+    /// Correlation id given to the run with `RunOptions::correlation_id`
+    pub correlation_id: Option<String>,
+    /// Metadata given to the run with `RunOptions::metadata`
+    pub metadata: BTreeMap<String, String>,
+    pub(crate) stats: Stats,
+}
+
+impl<T, O> ProcessOutput<T, O> {
+    /// Time spent in the registered functions during the run
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+}
+
+/// A failed run returned by `Process::run_resumable`. Correct the data, or deploy a fix, and continue the run from
+/// the failed task with `Process::resume`.
+#[derive(Debug)]
+pub struct Snapshot<T, L = Mutex<T>> {
+    /// The error that failed the run
+    pub error: Error,
+    /// The data when the run failed
+    pub data: Arc<L>,
+    /// The BPMN ID of the failed task. None if the run didn't fail in a task.
+    pub element: Option<Arc<str>>,
+    pub(crate) position: Option<Box<Position>>,
+    pub(crate) _marker: PhantomData<fn() -> T>,
+}
+
+impl<T, L> Snapshot<T, L> {
+    /// The run can continue from the failed task. It can't when it didn't fail in a task, or when other tokens
+    /// were live in parallel branches. Then `Process::resume` runs the process again from the start.
+    pub fn is_resumable(&self) -> bool {
+        self.position.is_some()
+    }
+}
+
+// Where a task failed. The local ids of the enclosing subprocess activities and the task, outermost first,
+// and their BPMN ids to migrate the position to another version of the diagram.
+#[derive(Debug, Clone)]
+pub(crate) struct Position {
+    pub(crate) process: Option<Arc<str>>,
+    pub(crate) path: Vec<usize>,
+    pub(crate) ids: Vec<Arc<str>>,
+}
+
+/// Time spent in the registered functions of an element
+#[derive(Debug, Clone, Default)]
+pub struct ElementStats {
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+    /// Number of invocations
+    pub count: u32,
+    /// Total time spent
+    pub total: Duration,
+    /// Longest invocation
+    pub max: Duration,
+}
+
+/// Timing statistics per element. Keyed by BPMN ID.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    elements: HashMap<String, ElementStats>,
+}
+
+impl Stats {
+    /// Statistics for an element by BPMN ID or name
+    pub fn get(&self, id_or_name: &str) -> Option<&ElementStats> {
+        self.elements.get(id_or_name).or_else(|| {
+            self.elements
+                .values()
+                .find(|stats| stats.name.as_deref() == Some(id_or_name))
+        })
+    }
+
+    /// Iterate all elements with their BPMN ID
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ElementStats)> {
+        self.elements.iter().map(|(id, stats)| (id.as_str(), stats))
+    }
+
+    /// Elements sorted by total time spent, slowest first
+    pub fn slowest(&self) -> Vec<(&str, &ElementStats)> {
+        let mut elements = self.iter().collect::<Vec<_>>();
+        elements.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total));
+        elements
+    }
+
+    pub(crate) fn record(&mut self, id: &str, name: Option<&str>, elapsed: Duration) {
+        let stats = self
+            .elements
+            .entry(id.into())
+            .or_insert_with(|| ElementStats {
+                name: name.map(Into::into),
+                ..Default::default()
+            });
+        stats.count += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+    }
+}
+
+/// Registered functions checked against the diagram. Returned by `Process::build_report`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildReport {
+    /// Elements without a registered function, like `Task: Count 1`
+    pub missing: Vec<String>,
+    /// Registered functions with a name or id that matches no element, like `Exclusive: equal to 4`
+    pub unused: Vec<String>,
+}
+
+impl BuildReport {
+    /// No missing or unused functions
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unused.is_empty()
+    }
 }
 
 /// Inclusive gateway return type
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub enum With {
     #[default]
     Default,
     /// Outgoing sequence flow by name or id
-    Flow(&'static str),
+    Flow(Cow<'static, str>),
     /// Collection of outgoing sequence flow by name or id
-    Fork(Vec<&'static str>),
+    Fork(Vec<Cow<'static, str>>),
 }
 
 impl From<&'static str> for With {
     fn from(value: &'static str) -> Self {
-        Self::Flow(value)
+        Self::Flow(value.into())
+    }
+}
+
+impl From<String> for With {
+    fn from(value: String) -> Self {
+        Self::Flow(value.into())
     }
 }
 
 impl From<Vec<&'static str>> for With {
     fn from(value: Vec<&'static str>) -> Self {
-        Self::Fork(value)
+        Self::Fork(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Vec<String>> for With {
+    fn from(value: Vec<String>) -> Self {
+        Self::Fork(value.into_iter().map(Into::into).collect())
     }
 }
 
+/// Typed outgoing sequence flows of a gateway. Implement it with the `flow_names!` macro and register
+/// the gateway with `exclusive_flow` or `inclusive_flow`. `build` fails if a name is not an outgoing flow of the gateway.
+pub trait FlowName: Copy + 'static {
+    /// Name or id of the outgoing flow of every variant
+    const NAMES: &'static [&'static str];
+
+    /// Name or id of the outgoing flow
+    fn flow_name(&self) -> &'static str;
+}
+
+/// Declare an enum where every variant is an outgoing sequence flow name or id, and implement `FlowName` for it.
+///
+/// ```
+/// snurr::flow_names! {
+///     pub enum Decision {
+///         Yes = "YES",
+///         No = "NO",
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! flow_names {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident = $flow:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant),+
+        }
+
+        impl $crate::FlowName for $name {
+            const NAMES: &'static [&'static str] = &[$($flow),+];
+
+            fn flow_name(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => $flow),+
+                }
+            }
+        }
+    };
+}
+
+/// What to do when a gateway selects the same outgoing sequence flow more than once, for example
+/// an inclusive gateway returning `With::Fork(vec!["A", "A"])`. Set with `Process::duplicate_flows`.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateFlows {
+    /// Log a warning and start a single token
+    #[default]
+    Warn,
+    /// Stop the run with `Error::DuplicateFlow`
+    Error,
+    /// Start a token for every time the flow was selected
+    Allow,
+}
+
+/// Decision of the error hook registered with `Process::on_error`
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum ErrorDecision {
+    /// Stop the run and return the error
+    #[default]
+    Abort,
+    /// Call the function again
+    Retry,
+    /// Continue with a boundary event of the task. Other functions abort.
+    Boundary(Boundary),
+}
+
 /// Task return type
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Boundary {
     Symbol(Symbol),
     NameSymbol(&'static str, Symbol),
@@ -109,13 +545,549 @@ impl Display for Boundary {
     }
 }
 
+/// BPMN element type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ElementType {
+    StartEvent,
+    EndEvent,
+    BoundaryEvent,
+    IntermediateCatchEvent,
+    IntermediateThrowEvent,
+    Task,
+    ScriptTask,
+    UserTask,
+    ServiceTask,
+    CallActivity,
+    ReceiveTask,
+    SendTask,
+    ManualTask,
+    BusinessRuleTask,
+    SubProcess,
+    ExclusiveGateway,
+    ParallelGateway,
+    InclusiveGateway,
+    EventBasedGateway,
+    SequenceFlow,
+}
+
+impl Display for ElementType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self, f)
+    }
+}
+
+/// Execution event kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionEventKind {
+    /// A token entered the element
+    Enter,
+    /// A token left the element
+    Leave,
+    /// A token has waited longer than the idle threshold at a user task, receive task or catch event
+    Idle,
+}
+
+/// Execution event sent to registered listeners
+#[derive(Debug, Clone)]
+pub struct ExecutionEvent<'a> {
+    pub kind: ExecutionEventKind,
+    /// The BPMN ID of the element
+    pub id: &'a str,
+    /// The name of the element (if it has one)
+    pub name: Option<&'a str>,
+    pub element_type: ElementType,
+    /// Identifier of the token visiting the element. Unique within a run.
+    pub token: usize,
+    /// Token that forked or joined into this token. `None` for the first token of a process.
+    pub parent_token: Option<usize>,
+}
+
+/// Typed event sent to the channel given with `RunOptions::event_sink` while a run executes
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EngineEvent {
+    /// A token was created at the element or sequence flow with the BPMN id
+    TokenCreated {
+        token: usize,
+        /// Token that forked or joined into this token. `None` for the first token of a process.
+        parent_token: Option<usize>,
+        id: Arc<str>,
+    },
+    /// A token entered an element or a sequence flow
+    ElementEntered {
+        token: usize,
+        id: Arc<str>,
+        name: Option<Arc<str>>,
+        element_type: ElementType,
+    },
+    /// A token left an element or a sequence flow
+    ElementLeft {
+        token: usize,
+        id: Arc<str>,
+        name: Option<Arc<str>>,
+        element_type: ElementType,
+    },
+    /// A gateway selected its outgoing sequence flows
+    GatewayDecision {
+        token: usize,
+        id: Arc<str>,
+        name: Option<Arc<str>>,
+        /// BPMN ids of the selected sequence flows
+        flows: Vec<Arc<str>>,
+    },
+    /// A token continued with a boundary event of an activity
+    BoundaryTriggered {
+        token: usize,
+        id: Arc<str>,
+        name: Option<Arc<str>>,
+        symbol: Option<Symbol>,
+        /// BPMN id of the activity the boundary event is attached to
+        attached_to: Arc<str>,
+    },
+    /// A top level process ended
+    ProcessEnded {
+        /// BPMN id of the process
+        process: Arc<str>,
+        end_node: EndNode,
+    },
+}
+
+impl EngineEvent {
+    /// JSON line for a bpmn-js token simulation overlay. Tokens are scopes and elements have the BPMN ids of
+    /// the diagram, so a front end can animate the run on the original diagram. `timestamp` is the time since
+    /// the Unix epoch, for example taken when the event is received, and is written in milliseconds.
+    pub fn to_simulation_json(&self, timestamp: Duration) -> String {
+        let timestamp = timestamp.as_millis();
+        let name = |name: &Option<Arc<str>>| {
+            name.as_deref()
+                .map_or_else(|| "null".into(), |name| format!(r#""{}""#, escape(name)))
+        };
+        match self {
+            EngineEvent::TokenCreated {
+                token,
+                parent_token,
+                id,
+            } => format!(
+                r#"{{"action":"createScope","scope":{{"id":{token},"parent":{}}},"element":{{"id":"{}"}},"timestamp":{timestamp}}}"#,
+                parent_token.map_or_else(|| "null".into(), |parent| parent.to_string()),
+                escape(id)
+            ),
+            EngineEvent::ElementEntered {
+                token,
+                id,
+                name: element_name,
+                element_type,
+            } => format!(
+                r#"{{"action":"enter","scope":{{"id":{token}}},"element":{{"id":"{}","type":"bpmn:{element_type}","name":{}}},"timestamp":{timestamp}}}"#,
+                escape(id),
+                name(element_name)
+            ),
+            EngineEvent::ElementLeft { token, id, .. } => format!(
+                r#"{{"action":"exit","scope":{{"id":{token}}},"element":{{"id":"{}"}},"timestamp":{timestamp}}}"#,
+                escape(id)
+            ),
+            EngineEvent::GatewayDecision {
+                token, id, flows, ..
+            } => format!(
+                r#"{{"action":"decision","scope":{{"id":{token}}},"element":{{"id":"{}"}},"flows":[{}],"timestamp":{timestamp}}}"#,
+                escape(id),
+                flows
+                    .iter()
+                    .map(|flow| format!(r#""{}""#, escape(flow)))
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            EngineEvent::BoundaryTriggered {
+                token,
+                id,
+                attached_to,
+                ..
+            } => format!(
+                r#"{{"action":"trigger","scope":{{"id":{token}}},"element":{{"id":"{}"}},"attachedTo":"{}","timestamp":{timestamp}}}"#,
+                escape(id),
+                escape(attached_to)
+            ),
+            EngineEvent::ProcessEnded { process, end_node } => format!(
+                r#"{{"action":"end","process":"{}","element":{{"id":"{}"}},"timestamp":{timestamp}}}"#,
+                escape(process),
+                escape(&end_node.id)
+            ),
+        }
+    }
+}
+
+/// Transition of a token from an element to one of its outgoing sequence flows.
+/// Given to the hook registered with `Process::on_transition`.
+#[derive(Debug, Clone)]
+pub struct Transition<'a> {
+    /// The BPMN ID of the element the token leaves
+    pub id: &'a str,
+    /// The name of the element (if it has one)
+    pub name: Option<&'a str>,
+    pub element_type: ElementType,
+    /// Name or id of the chosen sequence flow
+    pub flow: &'a str,
+    /// Name or id of the element the chosen sequence flow leads to
+    pub target: &'a str,
+    /// Names or ids of every outgoing sequence flow of the element. A redirect must use one of them.
+    pub outputs: Vec<&'a str>,
+    /// Identifier of the token. Unique within a run.
+    pub token: usize,
+}
+
+/// Decision of the transition hook registered with `Process::on_transition`
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub enum TransitionDecision {
+    /// Follow the chosen sequence flow
+    #[default]
+    Continue,
+    /// Stop the run with `Error::TransitionVetoed`
+    Veto,
+    /// Follow another outgoing sequence flow of the element, by name or id
+    Redirect(String),
+}
+
+/// Message or signal delivered by a send task or a message or signal throw event.
+/// Given to the function registered with `Process::sender`.
+#[derive(Debug, Clone, Copy)]
+pub struct MessageMeta<'a> {
+    /// Name of the referenced message or signal. The name or id of the element if it references none.
+    pub name: &'a str,
+    /// `Symbol::Message` or `Symbol::Signal`. Send tasks deliver messages.
+    pub symbol: &'a Symbol,
+    /// The BPMN ID of the element
+    pub element_id: &'a str,
+    /// Correlation id of the run
+    pub correlation_id: Option<&'a str>,
+}
+
+/// Trace id and baggage propagated to every token in a run, across forks, joins and subprocesses.
+/// Given to the run with `RunOptions::trace` and available to the registered functions through `Context::trace`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: String,
+    baggage: BTreeMap<String, String>,
+}
+
+impl TraceContext {
+    pub fn new(trace_id: impl Into<String>) -> Self {
+        Self {
+            trace_id: trace_id.into(),
+            baggage: Default::default(),
+        }
+    }
+
+    /// Add a baggage item
+    pub fn with_baggage(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.baggage.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    /// Get a baggage item
+    pub fn baggage(&self, key: &str) -> Option<&str> {
+        self.baggage.get(key).map(String::as_str)
+    }
+
+    /// Iterate all baggage items ordered by key
+    pub fn baggage_iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.baggage
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+}
+
+/// Information about the BPMN element a handler is invoked for
+#[derive(Clone, Copy)]
+pub struct Context<'a> {
+    pub(crate) id: &'a str,
+    pub(crate) name: Option<&'a str>,
+    pub(crate) symbol: Option<&'a Symbol>,
+    pub(crate) attached_to: Option<&'a str>,
+    pub(crate) token: usize,
+    pub(crate) trace: Option<&'a TraceContext>,
+    pub(crate) correlation_id: Option<&'a str>,
+    pub(crate) metadata: &'a BTreeMap<String, String>,
+    // Correlation id in front of the log lines of the run
+    pub(crate) log_prefix: &'a str,
+    pub(crate) cancel: Option<&'a CancelToken>,
+    pub(crate) boundary_trigger: Option<&'a BoundaryTrigger>,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) subprocess_end: Option<&'a Event>,
+    pub(crate) attempt: u32,
+    pub(crate) task_type: Option<&'a str>,
+    pub(crate) assignment: Option<&'a Assignment>,
+    pub(crate) form: Option<&'a Form>,
+    pub(crate) message: Option<&'a str>,
+    pub(crate) properties: Option<&'a BTreeMap<String, String>>,
+    pub(crate) data: Option<&'a DataAssociations>,
+    pub(crate) services: Option<&'a (dyn Any + Send + Sync)>,
+    pub(crate) decisions: &'a DecisionCache,
+    #[cfg(feature = "variables")]
+    pub(crate) variables: &'a crate::Variables,
+}
+
+impl<'a> Context<'a> {
+    /// The BPMN ID of the element
+    pub fn id(&self) -> &'a str {
+        self.id
+    }
+
+    /// The name of the element (if it has one)
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
+
+    /// The symbol of the element if it is an event
+    pub fn symbol(&self) -> Option<&'a Symbol> {
+        self.symbol
+    }
+
+    /// Name or id of the activity a boundary event is attached to
+    pub fn attached_to(&self) -> Option<&'a str> {
+        self.attached_to
+    }
+
+    /// Identifier of the token invoking the function. Unique within a run.
+    pub fn token(&self) -> usize {
+        self.token
+    }
+
+    /// Attempt number of the function, starting at 1. Increases when the function is called again
+    /// by a `RetryPolicy` or the error hook.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Job type of the task from the `zeebe:taskDefinition` type or the `camunda:topic` attribute
+    pub fn task_type(&self) -> Option<&'a str> {
+        self.task_type
+    }
+
+    /// Assignee and candidates of a user task
+    pub fn assignment(&self) -> Option<&'a Assignment> {
+        self.assignment
+    }
+
+    /// Form of a user task
+    pub fn form(&self) -> Option<&'a Form> {
+        self.form
+    }
+
+    /// Name of the message or signal referenced by a send or receive task or a message or signal event
+    pub fn message(&self) -> Option<&'a str> {
+        self.message
+    }
+
+    /// Vendor property of the element from the BPMN `extensionElements`, for example a `camunda:property`
+    /// or `zeebe:header` by name, or `taskDefinition.type` for an attribute of `zeebe:taskDefinition`.
+    pub fn property(&self, key: &str) -> Option<&'a str> {
+        self.properties?.get(key).map(String::as_str)
+    }
+
+    /// All vendor properties of the element from the BPMN `extensionElements`
+    pub fn properties(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.properties
+            .into_iter()
+            .flatten()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Trace context given to the run with `RunOptions::trace`
+    pub fn trace(&self) -> Option<&'a TraceContext> {
+        self.trace
+    }
+
+    /// Correlation id given to the run with `RunOptions::correlation_id`
+    pub fn correlation_id(&self) -> Option<&'a str> {
+        self.correlation_id
+    }
+
+    /// Get a metadata item given to the run with `RunOptions::metadata`
+    pub fn metadata(&self, key: &str) -> Option<&'a str> {
+        self.metadata.get(key).map(String::as_str)
+    }
+
+    /// Iterate all metadata items of the run ordered by key
+    pub fn metadata_iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.metadata
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// The run has been cancelled with the `CancelToken` given in the `RunOptions`, or the run timeout has passed.
+    /// Long running functions can check it to stop early.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(CancelToken::is_cancelled)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// A boundary event of the activity has been triggered with the `BoundaryTrigger` given in the `RunOptions`.
+    /// Long running functions can check it to stop early, the result is discarded.
+    pub fn is_interrupted(&self) -> bool {
+        self.boundary_trigger
+            .is_some_and(|trigger| trigger.is_triggered(self.id, self.name))
+    }
+
+    // Wait for the result of a task completed outside the engine, like an external or user task.
+    // Returns `Error::Cancelled` or `Error::Timeout` with the element if the run stops first.
+    pub(crate) fn wait_for<R>(&self, receiver: &Receiver<Result<R, Error>>) -> Result<R, Error> {
+        self.wait_for_message(|timeout| receiver.recv_timeout(timeout))?
+    }
+
+    // Wait for a message from a channel. Returns an error if the channel is closed.
+    pub(crate) fn wait_for_message<M>(
+        &self,
+        mut recv_timeout: impl FnMut(Duration) -> Result<M, RecvTimeoutError>,
+    ) -> Result<M, Error> {
+        loop {
+            match recv_timeout(WAIT_POLL_INTERVAL) {
+                Ok(message) => return Ok(message),
+                Err(RecvTimeoutError::Timeout) if self.is_interrupted() => {
+                    return Err(Error::Interrupted(self.id.into()));
+                }
+                Err(RecvTimeoutError::Timeout) if !self.is_cancelled() => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::ProcessExecution(
+                        format!("{CHANNEL_CLOSED_ERROR_MSG} at {}", self.id).into(),
+                    ));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    let active = vec![self.id.to_string()];
+                    return Err(
+                        if self
+                            .deadline
+                            .is_some_and(|deadline| Instant::now() >= deadline)
+                        {
+                            Error::Timeout(active)
+                        } else {
+                            Error::Cancelled(active)
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    // Sleep for the duration, but stop early when the run is cancelled or the deadline passes.
+    // Returns false if the run is cancelled.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let until = Instant::now() + duration;
+        let until = self.deadline.map_or(until, |deadline| until.min(deadline));
+        loop {
+            if self.is_cancelled() {
+                return false;
+            }
+            let remaining = until.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return true;
+            }
+            time::sleep(remaining.min(WAIT_POLL_INTERVAL));
+        }
+    }
+
+    // Time does not pass on targets without a clock, so there is nothing to wait for
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        time::sleep(duration);
+        !self.is_cancelled()
+    }
+
+    /// Deadline of the run if a timeout was given in the `RunOptions`
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// End node of the subprocess when a boundary function is invoked because the subprocess
+    /// ended with a symbol end event, for example an error end event. Tells the parent why the subprocess ended.
+    pub fn subprocess_end(&self) -> Option<EndNode> {
+        self.subprocess_end.map(|event| EndNode::from(Some(event)))
+    }
+
+    /// Data objects and data stores the task reads with a data input association
+    pub fn data_inputs(&self) -> &'a [DataReference] {
+        self.data.map_or(&[], |data| data.inputs.as_slice())
+    }
+
+    /// Data objects and data stores the task writes with a data output association
+    pub fn data_outputs(&self) -> &'a [DataReference] {
+        self.data.map_or(&[], |data| data.outputs.as_slice())
+    }
+
+    /// Services given to the run with `RunOptions::services`. Returns `None` if no services was given
+    /// or if the type doesn't match.
+    pub fn services<S: Any>(&self) -> Option<&'a S> {
+        self.services?.downcast_ref()
+    }
+
+    /// Process variables of the run, shared by every function
+    #[cfg(feature = "variables")]
+    pub fn variables(&self) -> &'a crate::Variables {
+        self.variables
+    }
+}
+
+impl Debug for Context<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Context")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("symbol", &self.symbol)
+            .field("attached_to", &self.attached_to)
+            .field("token", &self.token)
+            .field("attempt", &self.attempt)
+            .field("properties", &self.properties)
+            .field("data", &self.data)
+            .field("trace", &self.trace)
+            .field("deadline", &self.deadline)
+            .field(
+                "subprocess_end",
+                &self.subprocess_end.map(|event| event.id.bpmn()),
+            )
+            .field("services", &self.services.is_some())
+            .finish()
+    }
+}
+
+/// Data object or data store referenced by a data association of a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataReference {
+    /// The BPMN ID of the `dataObjectReference` or `dataStoreReference`
+    pub id: String,
+    /// The name of the reference, or the name of the data object if the reference has none
+    pub name: Option<String>,
+    pub kind: DataKind,
+}
+
+/// Kind of a `DataReference`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DataKind {
+    Object,
+    Store,
+}
+
 /// Event based gateway return type
-#[derive(Debug)]
-pub struct IntermediateEvent(pub &'static str, pub Symbol);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntermediateEvent(pub Cow<'static, str>, pub Symbol);
 
 impl From<(&'static str, Symbol)> for IntermediateEvent {
     fn from(value: (&'static str, Symbol)) -> Self {
-        Self(value.0, value.1)
+        Self(value.0.into(), value.1)
+    }
+}
+
+impl From<(String, Symbol)> for IntermediateEvent {
+    fn from(value: (String, Symbol)) -> Self {
+        Self(value.0.into(), value.1)
     }
 }
 
@@ -0,0 +1,131 @@
+use snurr::{ExecutionEventKind, Process, RunOptions, Severity};
+use std::{process::ExitCode, time::Duration};
+
+const USAGE: &str = "Usage:
+  snurr validate <file.bpmn>
+  snurr scaffold <file.bpmn> [-o <handlers.rs>]
+  snurr graph <file.bpmn> [--dot | --mermaid]
+  snurr run <file.bpmn> --mock [--timeout <seconds>]";
+
+// Mock runs are stopped after the timeout, as gateways without a function can loop forever
+const DEFAULT_TIMEOUT: u64 = 10;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(command), Some(file)) = (args.first(), args.get(1)) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    let options = &args[2..];
+
+    let result = match command.as_str() {
+        "validate" => validate(file),
+        "scaffold" => scaffold(file, options),
+        "graph" => graph(file, options),
+        "run" => run(file, options),
+        _ => Err(format!("unknown command {command}\n{USAGE}")),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Print the parse warnings and diagnostics. Fails if any diagnostic is an error.
+fn validate(file: &str) -> Result<ExitCode, String> {
+    let bpmn = Process::<()>::new(file).map_err(|error| error.to_string())?;
+    for warning in bpmn.parse_warnings() {
+        println!("{warning}");
+    }
+    let diagnostics = bpmn.validate();
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("{file} is valid");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn scaffold(file: &str, options: &[String]) -> Result<ExitCode, String> {
+    let output = match options {
+        [] => "handlers.rs",
+        [flag, output] if flag == "-o" => output,
+        _ => return Err(format!("unexpected options {}", options.join(" "))),
+    };
+    let bpmn = Process::<()>::new(file).map_err(|error| error.to_string())?;
+    bpmn.scaffold_handlers(output)
+        .map_err(|error| error.to_string())?;
+    println!("Wrote {output}");
+    Ok(ExitCode::SUCCESS)
+}
+
+fn graph(file: &str, options: &[String]) -> Result<ExitCode, String> {
+    let bpmn = Process::<()>::new(file).map_err(|error| error.to_string())?;
+    match options.first().map(String::as_str) {
+        None | Some("--dot") => print!("{}", bpmn.to_dot()),
+        Some("--mermaid") => print!("{}", bpmn.to_mermaid()),
+        Some(option) => return Err(format!("unknown option {option}")),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// Run with no-op tasks and gateways that take the default or first flow, and print the visited elements
+fn run(file: &str, options: &[String]) -> Result<ExitCode, String> {
+    let mut mock = false;
+    let mut timeout = DEFAULT_TIMEOUT;
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.as_str() {
+            "--mock" => mock = true,
+            "--timeout" => {
+                timeout = options
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--timeout needs the number of seconds")?;
+            }
+            _ => return Err(format!("unknown option {option}")),
+        }
+    }
+    if !mock {
+        return Err("run needs --mock, there are no functions registered outside of Rust".into());
+    }
+
+    let bpmn = Process::<()>::new(file)
+        .and_then(|bpmn| {
+            bpmn.listener(|event| {
+                if event.kind == ExecutionEventKind::Enter {
+                    println!(
+                        "[{}] {} {}",
+                        event.token,
+                        event.element_type,
+                        event.name.unwrap_or(event.id)
+                    );
+                }
+            })
+            .build_lenient()
+        })
+        .map_err(|error| error.to_string())?;
+    let result = bpmn
+        .run_with(
+            (),
+            RunOptions::default().timeout(Duration::from_secs(timeout)),
+        )
+        .map_err(|error| error.to_string())?;
+    println!(
+        "Ended at {}",
+        result
+            .end_node
+            .name
+            .as_deref()
+            .unwrap_or(&result.end_node.id)
+    );
+    Ok(ExitCode::SUCCESS)
+}
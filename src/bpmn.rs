@@ -1,9 +1,10 @@
 use crate::{
+    api::ElementType,
     diagram::{Id, Outputs},
     error::Error,
 };
 use core::fmt;
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
 
 pub(crate) const DEFINITIONS: &[u8] = b"definitions";
 pub(crate) const PROCESS: &[u8] = b"process";
@@ -53,18 +54,105 @@ pub(crate) const PARALLEL_GATEWAY: &[u8] = b"parallelGateway";
 pub(crate) const INCLUSIVE_GATEWAY: &[u8] = b"inclusiveGateway";
 pub(crate) const EVENT_BASED_GATEWAY: &[u8] = b"eventBasedGateway";
 
+// Not supported flow elements and markers
+pub(crate) const COMPLEX_GATEWAY: &[u8] = b"complexGateway";
+pub(crate) const AD_HOC_SUB_PROCESS: &[u8] = b"adHocSubProcess";
+pub(crate) const STANDARD_LOOP_CHARACTERISTICS: &[u8] = b"standardLoopCharacteristics";
+pub(crate) const MULTI_INSTANCE_LOOP_CHARACTERISTICS: &[u8] = b"multiInstanceLoopCharacteristics";
+
+// Ignored elements without effect on the flow
+pub(crate) const TEXT_ANNOTATION: &[u8] = b"textAnnotation";
+pub(crate) const ASSOCIATION: &[u8] = b"association";
+pub(crate) const GROUP: &[u8] = b"group";
+pub(crate) const MESSAGE_FLOW: &[u8] = b"messageFlow";
+
+// Messages and signals referenced by send tasks and event definitions
+pub(crate) const MESSAGE: &[u8] = b"message";
+pub(crate) const SIGNAL: &[u8] = b"signal";
+pub(crate) const ESCALATION: &[u8] = b"escalation";
+pub(crate) const ERROR: &[u8] = b"error";
+
+// Data objects, data stores and the data associations of tasks
+pub(crate) const DATA_OBJECT: &[u8] = b"dataObject";
+pub(crate) const DATA_OBJECT_REFERENCE: &[u8] = b"dataObjectReference";
+pub(crate) const DATA_STORE_REFERENCE: &[u8] = b"dataStoreReference";
+pub(crate) const DATA_INPUT_ASSOCIATION: &[u8] = b"dataInputAssociation";
+pub(crate) const DATA_OUTPUT_ASSOCIATION: &[u8] = b"dataOutputAssociation";
+pub(crate) const SOURCE_REF: &[u8] = b"sourceRef";
+pub(crate) const DATA_INPUT: &[u8] = b"dataInput";
+pub(crate) const DATA_OUTPUT: &[u8] = b"dataOutput";
+pub(crate) const TARGET_REF: &[u8] = b"targetRef";
+
+// Vendor extensions
+pub(crate) const EXTENSION_ELEMENTS: &[u8] = b"extensionElements";
+
+// Input and output parameters of camunda:inputOutput and zeebe:ioMapping inside extensionElements
+pub(crate) const INPUT_PARAMETER: &[u8] = b"inputParameter";
+pub(crate) const OUTPUT_PARAMETER: &[u8] = b"outputParameter";
+pub(crate) const INPUT: &[u8] = b"input";
+pub(crate) const OUTPUT: &[u8] = b"output";
+
+// Fields of camunda:formData inside extensionElements
+pub(crate) const FORM_FIELD: &[u8] = b"formField";
+pub(crate) const FORM_VALUE: &[u8] = b"value";
+pub(crate) const FORM_CONSTRAINT: &[u8] = b"constraint";
+
+// Documentation
+pub(crate) const DOCUMENTATION: &[u8] = b"documentation";
+
+// Diagram interchange with the layout of the diagram. Skipped by the reader.
+pub(crate) const BPMN_DIAGRAM: &[u8] = b"BPMNDiagram";
+
 // Attributes
 pub(crate) const ATTRIB_ID: &[u8] = b"id";
-pub(crate) const _ATTRIB_IS_EXECUTABLE: &[u8] = b"isExecutable";
+pub(crate) const ATTRIB_IS_EXECUTABLE: &[u8] = b"isExecutable";
 pub(crate) const ATTRIB_NAME: &[u8] = b"name";
+pub(crate) const ATTRIB_KEY: &[u8] = b"key";
+pub(crate) const ATTRIB_VALUE: &[u8] = b"value";
 pub(crate) const _ATTRIB_SOURCE_REF: &[u8] = b"sourceRef";
 pub(crate) const ATTRIB_TARGET_REF: &[u8] = b"targetRef";
 pub(crate) const ATTRIB_DEFAULT: &[u8] = b"default";
+pub(crate) const ATTRIB_DATA_OBJECT_REF: &[u8] = b"dataObjectRef";
+pub(crate) const ATTRIB_SOURCE: &[u8] = b"source";
+pub(crate) const ATTRIB_TARGET: &[u8] = b"target";
+// camunda:topic of an external task
+pub(crate) const ATTRIB_TOPIC: &[u8] = b"topic";
+// camunda:assignee, camunda:candidateUsers and camunda:candidateGroups of a user task
+pub(crate) const ATTRIB_ASSIGNEE: &[u8] = b"assignee";
+pub(crate) const ATTRIB_CANDIDATE_USERS: &[u8] = b"candidateUsers";
+pub(crate) const ATTRIB_CANDIDATE_GROUPS: &[u8] = b"candidateGroups";
+// Message or signal referenced by a task or event definition
+pub(crate) const ATTRIB_MESSAGE_REF: &[u8] = b"messageRef";
+pub(crate) const ATTRIB_SIGNAL_REF: &[u8] = b"signalRef";
+pub(crate) const ATTRIB_ESCALATION_REF: &[u8] = b"escalationRef";
+pub(crate) const ATTRIB_ESCALATION_CODE: &[u8] = b"escalationCode";
+pub(crate) const ATTRIB_ERROR_REF: &[u8] = b"errorRef";
+pub(crate) const ATTRIB_ERROR_CODE: &[u8] = b"errorCode";
+// camunda:formKey of a user task
+pub(crate) const ATTRIB_FORM_KEY: &[u8] = b"formKey";
+// Attributes of a camunda:formField and its values and constraints
+pub(crate) const ATTRIB_LABEL: &[u8] = b"label";
+pub(crate) const ATTRIB_TYPE: &[u8] = b"type";
+pub(crate) const ATTRIB_DEFAULT_VALUE: &[u8] = b"defaultValue";
+pub(crate) const ATTRIB_CONFIG: &[u8] = b"config";
+
+// zeebe:taskDefinition type read from extensionElements
+pub(crate) const PROPERTY_TASK_TYPE: &str = "taskDefinition.type";
+// zeebe:assignmentDefinition read from extensionElements
+pub(crate) const PROPERTY_ASSIGNEE: &str = "assignmentDefinition.assignee";
+pub(crate) const PROPERTY_CANDIDATE_USERS: &str = "assignmentDefinition.candidateUsers";
+pub(crate) const PROPERTY_CANDIDATE_GROUPS: &str = "assignmentDefinition.candidateGroups";
+// zeebe:formDefinition read from extensionElements
+pub(crate) const PROPERTY_FORM_KEY: &str = "formDefinition.formKey";
+pub(crate) const PROPERTY_FORM_ID: &str = "formDefinition.formId";
+// Selects the connector of a task
+pub(crate) const PROPERTY_CONNECTOR: &str = "connector";
 pub(crate) const _ATTRIB_EXPORTER_VERSION: &[u8] = b"exporterVersion";
 pub(crate) const ATTRIB_ATTACHED_TO_REF: &[u8] = b"attachedToRef";
-pub(crate) const _ATTRIB_CANCEL_ACTIVITY: &[u8] = b"cancelActivity";
+pub(crate) const ATTRIB_CANCEL_ACTIVITY: &[u8] = b"cancelActivity";
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum EventType {
     Boundary,
     End,
@@ -99,6 +187,7 @@ impl Display for EventType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum ActivityType {
     SubProcess { data_index: Option<usize> },
     Task,
@@ -143,6 +232,7 @@ impl Display for ActivityType {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum GatewayType {
     Exclusive,
     Inclusive,
@@ -193,6 +283,7 @@ impl Display for GatewayType {
 /// | Terminate | ❌ | ✅ | ❌ | ❌ | End all parallel paths |
 /// | Timer | ✅ | ❌ | ✅ | ✅ | Time-based trigger |
 #[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Symbol {
     /// Default/untyped event (Start, Intermediate, End only)
     None,
@@ -224,6 +315,84 @@ impl Display for Symbol {
     }
 }
 
+impl Symbol {
+    /// All symbols
+    pub const ALL: [Symbol; 11] = [
+        Symbol::None,
+        Symbol::Cancel,
+        Symbol::Compensation,
+        Symbol::Conditional,
+        Symbol::Error,
+        Symbol::Escalation,
+        Symbol::Link,
+        Symbol::Message,
+        Symbol::Signal,
+        Symbol::Terminate,
+        Symbol::Timer,
+    ];
+
+    /// Event positions where the symbol is valid according to the BPMN 2.0 standard.
+    ///
+    /// ```
+    /// use snurr::{EventPosition, Symbol};
+    ///
+    /// assert_eq!(Symbol::Error.positions(), &[EventPosition::Boundary, EventPosition::End]);
+    /// ```
+    pub fn positions(&self) -> &'static [EventPosition] {
+        use EventPosition::*;
+        match self {
+            Symbol::None => &[Start, Intermediate, End],
+            Symbol::Cancel | Symbol::Error => &[Boundary, End],
+            Symbol::Compensation | Symbol::Escalation | Symbol::Message | Symbol::Signal => {
+                &[Start, Intermediate, Boundary, End]
+            }
+            Symbol::Conditional | Symbol::Timer => &[Start, Intermediate, Boundary],
+            Symbol::Link => &[Intermediate],
+            Symbol::Terminate => &[End],
+        }
+    }
+
+    /// Returns true if the symbol is valid for the event position.
+    ///
+    /// ```
+    /// use snurr::{EventPosition, Symbol};
+    ///
+    /// assert!(Symbol::Timer.is_valid_for(EventPosition::Boundary));
+    /// assert!(!Symbol::Link.is_valid_for(EventPosition::Boundary));
+    /// ```
+    pub fn is_valid_for(&self, position: EventPosition) -> bool {
+        self.positions().contains(&position)
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = Error;
+
+    /// Parse the symbol name used by `Display`, e.g. `"Message"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Symbol::ALL
+            .into_iter()
+            .find(|symbol| symbol.to_string() == s)
+            .ok_or_else(|| Error::UnknownSymbol(s.into()))
+    }
+}
+
+/// Event positions a `Symbol` can be used in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EventPosition {
+    Start,
+    /// Intermediate catch and throw events
+    Intermediate,
+    Boundary,
+    End,
+}
+
+impl Display for EventPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt::Debug::fmt(&self, f)
+    }
+}
+
 impl TryFrom<&[u8]> for Symbol {
     type Error = Error;
 
@@ -249,12 +418,13 @@ impl TryFrom<&[u8]> for Symbol {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Gateway {
     pub(crate) gateway_type: GatewayType,
     pub(crate) id: Id,
     pub(crate) func_idx: Option<usize>,
-    pub(crate) name: Option<String>,
+    pub(crate) name: Option<Arc<str>>,
     pub(crate) default: Option<Id>,
     pub(crate) outputs: Outputs,
     pub(crate) inputs: u16,
@@ -280,13 +450,19 @@ impl Display for Gateway {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Event {
     pub(crate) event_type: EventType,
     pub(crate) symbol: Option<Symbol>,
     pub(crate) id: Id,
-    pub(crate) name: Option<String>,
+    pub(crate) name: Option<Arc<str>>,
     pub(crate) attached_to_ref: Option<Id>,
+    // False for a non-interrupting boundary event
+    pub(crate) cancel_activity: bool,
+    // Code of the referenced error or escalation, or its id if it has no code
+    pub(crate) code: Option<String>,
+    pub(crate) func_idx: Option<usize>,
     pub(crate) outputs: Outputs,
 }
 
@@ -301,13 +477,137 @@ impl Display for Event {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Activity {
     pub(crate) activity_type: ActivityType,
     pub(crate) id: Id,
     pub(crate) func_idx: Option<usize>,
-    pub(crate) name: Option<String>,
+    pub(crate) name: Option<Arc<str>>,
     pub(crate) outputs: Outputs,
+    // Job type from zeebe:taskDefinition or camunda:topic
+    pub(crate) task_type: Option<String>,
+    pub(crate) assignment: Option<Assignment>,
+    pub(crate) form: Option<Form>,
+}
+
+/// Assignee and candidates of a user task, from the `camunda:assignee`, `camunda:candidateUsers` and
+/// `camunda:candidateGroups` attributes or the `zeebe:assignmentDefinition` extension element.
+/// Candidates are given as a comma separated list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assignment {
+    pub assignee: Option<String>,
+    pub candidate_users: Vec<String>,
+    pub candidate_groups: Vec<String>,
+}
+
+impl Assignment {
+    // None if the element has no assignee or candidates
+    pub(crate) fn new(
+        assignee: Option<String>,
+        candidate_users: Option<&str>,
+        candidate_groups: Option<&str>,
+    ) -> Option<Self> {
+        let list = |value: Option<&str>| -> Vec<String> {
+            value
+                .into_iter()
+                .flat_map(|value| value.split(','))
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(Into::into)
+                .collect()
+        };
+        let assignment = Self {
+            assignee,
+            candidate_users: list(candidate_users),
+            candidate_groups: list(candidate_groups),
+        };
+        (assignment != Self::default()).then_some(assignment)
+    }
+
+    /// The user is the assignee or a candidate user, or is in one of the candidate groups
+    pub fn is_candidate(&self, user: &str, groups: &[&str]) -> bool {
+        self.assignee.as_deref() == Some(user)
+            || self
+                .candidate_users
+                .iter()
+                .any(|candidate| candidate == user)
+            || self
+                .candidate_groups
+                .iter()
+                .any(|group| groups.contains(&group.as_str()))
+    }
+}
+
+/// Form of a user task, from the `camunda:formKey` attribute and the `camunda:formData` extension element,
+/// or the `zeebe:formDefinition` extension element
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Form {
+    /// Key or id of a form defined outside the diagram
+    pub key: Option<String>,
+    /// Fields of a form defined in the diagram
+    pub fields: Vec<FormField>,
+}
+
+/// Field of a `camunda:formData` form
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormField {
+    pub id: String,
+    pub label: Option<String>,
+    pub field_type: FormFieldType,
+    pub default_value: Option<String>,
+    /// Validation constraints, like `required` or `min`
+    pub constraints: Vec<FormConstraint>,
+}
+
+impl FormField {
+    pub fn is_required(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|constraint| constraint.name == "required")
+    }
+
+    pub(crate) fn new(mut attributes: HashMap<&[u8], String>) -> Option<Self> {
+        Some(Self {
+            id: attributes.remove(ATTRIB_ID)?,
+            label: attributes.remove(ATTRIB_LABEL),
+            field_type: match attributes.remove(ATTRIB_TYPE).as_deref() {
+                None | Some("string") => FormFieldType::String,
+                Some("long") => FormFieldType::Long,
+                Some("boolean") => FormFieldType::Boolean,
+                Some("date") => FormFieldType::Date,
+                Some("enum") => FormFieldType::Enum(Vec::new()),
+                Some(other) => FormFieldType::Custom(other.into()),
+            },
+            default_value: attributes.remove(ATTRIB_DEFAULT_VALUE),
+            constraints: Vec::new(),
+        })
+    }
+}
+
+/// Type of a form field
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormFieldType {
+    String,
+    Long,
+    Boolean,
+    Date,
+    /// Values with id and name
+    Enum(Vec<(String, String)>),
+    /// Type that is not built into Camunda
+    Custom(String),
+}
+
+/// Validation constraint of a form field with its configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormConstraint {
+    pub name: String,
+    pub config: Option<String>,
 }
 
 impl Display for Activity {
@@ -321,7 +621,8 @@ impl Display for Activity {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum Bpmn {
     Activity(Activity),
     Definitions {
@@ -332,15 +633,52 @@ pub(crate) enum Bpmn {
     Gateway(Gateway),
     Process {
         id: Id,
+        name: Option<Arc<str>>,
         data_index: Option<usize>,
+        // False for documentation only pools
+        executable: bool,
     },
     SequenceFlow {
         id: Id,
-        name: Option<String>,
+        name: Option<Arc<str>>,
         target_ref: Id,
     },
 }
 
+impl Bpmn {
+    pub(crate) fn element_type(&self) -> Option<ElementType> {
+        Some(match self {
+            Bpmn::Event(Event { event_type, .. }) => match event_type {
+                EventType::Boundary => ElementType::BoundaryEvent,
+                EventType::End => ElementType::EndEvent,
+                EventType::IntermediateCatch => ElementType::IntermediateCatchEvent,
+                EventType::IntermediateThrow => ElementType::IntermediateThrowEvent,
+                EventType::Start => ElementType::StartEvent,
+            },
+            Bpmn::Activity(Activity { activity_type, .. }) => match activity_type {
+                ActivityType::SubProcess { .. } => ElementType::SubProcess,
+                ActivityType::Task => ElementType::Task,
+                ActivityType::ScriptTask => ElementType::ScriptTask,
+                ActivityType::UserTask => ElementType::UserTask,
+                ActivityType::ServiceTask => ElementType::ServiceTask,
+                ActivityType::CallActivity => ElementType::CallActivity,
+                ActivityType::ReceiveTask => ElementType::ReceiveTask,
+                ActivityType::SendTask => ElementType::SendTask,
+                ActivityType::ManualTask => ElementType::ManualTask,
+                ActivityType::BusinessRuleTask => ElementType::BusinessRuleTask,
+            },
+            Bpmn::Gateway(Gateway { gateway_type, .. }) => match gateway_type {
+                GatewayType::Exclusive => ElementType::ExclusiveGateway,
+                GatewayType::Inclusive => ElementType::InclusiveGateway,
+                GatewayType::Parallel => ElementType::ParallelGateway,
+                GatewayType::EventBased => ElementType::EventBasedGateway,
+            },
+            Bpmn::SequenceFlow { .. } => ElementType::SequenceFlow,
+            _ => return None,
+        })
+    }
+}
+
 impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
     type Error = Error;
 
@@ -360,7 +698,11 @@ impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
                     .remove(ATTRIB_ID)
                     .ok_or_else(|| Error::MissingId(bpmn_type_str.into()))?
                     .into(),
+                name: attributes.remove(ATTRIB_NAME).map(Into::into),
                 data_index: None,
+                executable: attributes
+                    .remove(ATTRIB_IS_EXECUTABLE)
+                    .is_none_or(|value| value != "false"),
             },
             START_EVENT
             | END_EVENT
@@ -373,8 +715,13 @@ impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
                     .remove(ATTRIB_ID)
                     .ok_or_else(|| Error::MissingId(bpmn_type_str.into()))?
                     .into(),
-                name: attributes.remove(ATTRIB_NAME),
+                name: attributes.remove(ATTRIB_NAME).map(Into::into),
                 attached_to_ref: attributes.remove(ATTRIB_ATTACHED_TO_REF).map(Into::into),
+                cancel_activity: attributes
+                    .remove(ATTRIB_CANCEL_ACTIVITY)
+                    .is_none_or(|value| value != "false"),
+                code: None,
+                func_idx: None,
                 outputs: Default::default(),
             }),
             TASK | SCRIPT_TASK | USER_TASK | SERVICE_TASK | CALL_ACTIVITY | RECEIVE_TASK
@@ -386,8 +733,18 @@ impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
                         .ok_or_else(|| Error::MissingId(bpmn_type_str.into()))?
                         .into(),
                     func_idx: None,
-                    name: attributes.remove(ATTRIB_NAME),
+                    name: attributes.remove(ATTRIB_NAME).map(Into::into),
                     outputs: Default::default(),
+                    task_type: attributes.remove(ATTRIB_TOPIC),
+                    assignment: Assignment::new(
+                        attributes.remove(ATTRIB_ASSIGNEE),
+                        attributes.remove(ATTRIB_CANDIDATE_USERS).as_deref(),
+                        attributes.remove(ATTRIB_CANDIDATE_GROUPS).as_deref(),
+                    ),
+                    form: attributes.remove(ATTRIB_FORM_KEY).map(|key| Form {
+                        key: Some(key),
+                        fields: Vec::new(),
+                    }),
                 })
             }
             EXCLUSIVE_GATEWAY | PARALLEL_GATEWAY | INCLUSIVE_GATEWAY | EVENT_BASED_GATEWAY => {
@@ -398,7 +755,7 @@ impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
                         .ok_or_else(|| Error::MissingId(bpmn_type_str.into()))?
                         .into(),
                     func_idx: None,
-                    name: attributes.remove(ATTRIB_NAME),
+                    name: attributes.remove(ATTRIB_NAME).map(Into::into),
                     default: attributes.remove(ATTRIB_DEFAULT).map(Into::into),
                     outputs: Default::default(),
                     inputs: Default::default(),
@@ -409,7 +766,7 @@ impl TryFrom<(&[u8], HashMap<&[u8], String>)> for Bpmn {
                     .remove(ATTRIB_ID)
                     .ok_or_else(|| Error::MissingId(bpmn_type_str.into()))?
                     .into(),
-                name: attributes.remove(ATTRIB_NAME),
+                name: attributes.remove(ATTRIB_NAME).map(Into::into),
                 target_ref: attributes
                     .remove(ATTRIB_TARGET_REF)
                     .ok_or(Error::MissingTargetRef)?
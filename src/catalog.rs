@@ -0,0 +1,232 @@
+use crate::{
+    Process,
+    diagram::{Diagram, reader::read_bpmn},
+    error::Error,
+    process::cache::hash_key,
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+const BPMN_EXTENSION: &str = "bpmn";
+
+/// A collection of parsed BPMN files. Processes are indexed by their id and name.
+///
+/// ```
+/// use snurr::ProcessCatalog;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let catalog = ProcessCatalog::load_dir("tests/files")?;
+///     println!("{}", catalog.summary());
+///     let bpmn = catalog.process::<()>("Process_0ufiu7y")?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ProcessCatalog {
+    diagrams: Vec<(PathBuf, Diagram)>,
+    index: HashMap<String, usize>,
+    diagnostics: Vec<CatalogDiagnostic>,
+}
+
+/// Problem found while loading a catalog
+#[derive(Debug)]
+pub struct CatalogDiagnostic {
+    /// File the problem was found in
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Display for CatalogDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl ProcessCatalog {
+    /// Parse every `.bpmn` file in the directory. Files that cannot be parsed are not added
+    /// to the catalog and are reported in `diagnostics`. Only IO errors on the directory itself returns an `Error`.
+    pub fn load_dir(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut paths = std::fs::read_dir(path)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|extension| extension == BPMN_EXTENSION)
+            })
+            .collect::<Vec<_>>();
+
+        // Stable order independent of the file system
+        paths.sort();
+
+        let mut catalog = Self::default();
+        for path in paths {
+            match File::open(&path)
+                .map_err(Error::from)
+                .and_then(|file| read_bpmn(BufReader::new(file)))
+            {
+                Ok(diagram) => catalog.add(path, diagram),
+                Err(error) => catalog.diagnostic(path, error.to_string()),
+            }
+        }
+        Ok(catalog)
+    }
+
+    fn add(&mut self, path: PathBuf, diagram: Diagram) {
+        let diagram_index = self.diagrams.len();
+        let mut keys = Vec::new();
+        let mut processes = 0;
+        for (id, name) in diagram.processes() {
+            processes += 1;
+            keys.push(id.to_string());
+            if let Some(name) = name
+                && name != id
+            {
+                keys.push(name.to_string());
+            }
+        }
+
+        if processes == 0 {
+            self.diagnostic(path, "no process found".into());
+            return;
+        }
+
+        for key in keys {
+            if let Some(existing) = self.index.get(&key) {
+                let message = format!(
+                    r#"process "{key}" is already defined in {}"#,
+                    self.diagrams[*existing].0.display()
+                );
+                self.diagnostic(path.clone(), message);
+            } else {
+                self.index.insert(key, diagram_index);
+            }
+        }
+        self.diagrams.push((path, diagram));
+    }
+
+    fn diagnostic(&mut self, path: PathBuf, message: String) {
+        log::warn!("{}: {message}", path.display());
+        self.diagnostics.push(CatalogDiagnostic { path, message });
+    }
+
+    /// Create a new `Process` from the file containing the process with the id or name.
+    pub fn process<T>(&self, id_or_name: &str) -> Result<Process<T>, Error> {
+        self.index
+            .get(id_or_name)
+            .and_then(|index| self.diagrams.get(*index))
+            .map(|(_, diagram)| Process::from_diagram(diagram.clone()))
+            .ok_or_else(|| Error::MissingProcessData(id_or_name.into()))
+    }
+
+    /// Path to the file containing the process with the id or name.
+    pub fn path(&self, id_or_name: &str) -> Option<&Path> {
+        self.index
+            .get(id_or_name)
+            .and_then(|index| self.diagrams.get(*index))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// All indexed process ids and names.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+    /// Problems found while loading the catalog.
+    pub fn diagnostics(&self) -> &[CatalogDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Consolidated summary of the loaded files and the diagnostics.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} file(s) loaded, {} process key(s), {} diagnostic(s)",
+            self.diagrams.len(),
+            self.index.len(),
+            self.diagnostics.len()
+        );
+        for diagnostic in self.diagnostics.iter() {
+            summary.push_str(&format!("\n  {diagnostic}"));
+        }
+        summary
+    }
+}
+
+static GLOBAL_DIAGRAMS: LazyLock<DiagramCache> = LazyLock::new(DiagramCache::default);
+
+/// Parsed diagrams keyed by a hash of the BPMN content. Creating many processes from the same BPMN,
+/// for example one per test, only parses it once. Clones share the same cache.
+///
+/// ```
+/// use snurr::DiagramCache;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let first = DiagramCache::global().process::<()>("examples/example.bpmn")?;
+///     // Reuses the parsed diagram
+///     let second = DiagramCache::global().process::<()>("examples/example.bpmn")?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct DiagramCache {
+    diagrams: Arc<Mutex<HashMap<u64, Diagram>>>,
+}
+
+impl DiagramCache {
+    /// Cache shared by the whole program
+    pub fn global() -> &'static DiagramCache {
+        &GLOBAL_DIAGRAMS
+    }
+
+    /// Create a new `Process` from the BPMN file path. The file is read every time, but only parsed
+    /// if the content is not in the cache.
+    pub fn process<T>(&self, path: impl AsRef<Path>) -> Result<Process<T>, Error> {
+        self.parse(&std::fs::read(path)?)
+    }
+
+    /// Create a new `Process` from BPMN content. Only parsed if the content is not in the cache.
+    pub fn parse<T>(&self, bpmn: impl AsRef<[u8]>) -> Result<Process<T>, Error> {
+        let bpmn = bpmn.as_ref();
+        let key = hash_key(bpmn);
+        if let Some(diagram) = self
+            .diagrams
+            .lock()
+            .ok()
+            .and_then(|diagrams| diagrams.get(&key).cloned())
+        {
+            return Ok(Process::from_diagram(diagram));
+        }
+
+        let diagram = read_bpmn(bpmn)?;
+        if let Ok(mut diagrams) = self.diagrams.lock() {
+            diagrams.insert(key, diagram.clone());
+        }
+        Ok(Process::from_diagram(diagram))
+    }
+
+    /// Number of cached diagrams
+    pub fn len(&self) -> usize {
+        self.diagrams
+            .lock()
+            .map(|diagrams| diagrams.len())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if no diagrams are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached diagrams
+    pub fn clear(&self) {
+        if let Ok(mut diagrams) = self.diagrams.lock() {
+            diagrams.clear();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{
+    Process,
+    api::{ElementType, ExecutionEvent, ExecutionEventKind},
+    diagram::export::escape,
+};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+    sync::{Arc, Mutex},
+};
+
+/// Collect the elements and sequence flows entered across runs. Register the same `listener` on the processes
+/// of a test suite and create a `CoverageReport` with `report` at the end.
+///
+/// ```
+/// use snurr::{Coverage, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let coverage = Coverage::default();
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .listener(coverage.listener())
+///         .build()?;
+///
+///     bpmn.run(0)?;
+///     let report = coverage.report(&bpmn);
+///     assert!(report.is_complete(), "{report}");
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct Coverage {
+    // Number of times every BPMN id was entered
+    hits: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl Coverage {
+    /// Listener to register on the process with `.listener(...)`
+    pub fn listener(&self) -> impl Fn(&ExecutionEvent) + Sync + Send + 'static {
+        let hits = Arc::clone(&self.hits);
+        move |event| {
+            if event.kind == ExecutionEventKind::Enter
+                && let Ok(mut hits) = hits.lock()
+            {
+                *hits.entry(event.id.into()).or_default() += 1;
+            }
+        }
+    }
+
+    /// Coverage of the elements and sequence flows in the diagram of the process
+    pub fn report<T, S: Sync + Send>(&self, process: &Process<T, S>) -> CoverageReport {
+        let hits = self
+            .hits
+            .lock()
+            .map(|hits| hits.clone())
+            .unwrap_or_default();
+        let mut items = process.coverage_items();
+        for item in &mut items {
+            item.hits = hits.get(&item.id).copied().unwrap_or_default();
+        }
+        CoverageReport { items }
+    }
+
+    /// Forget what was collected
+    pub fn reset(&self) {
+        if let Ok(mut hits) = self.hits.lock() {
+            hits.clear();
+        }
+    }
+}
+
+/// Element or sequence flow in the coverage report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageItem {
+    /// Name or id of the process or sub process
+    pub process: String,
+    /// The BPMN ID of the element
+    pub id: String,
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+    pub element_type: ElementType,
+    /// Number of times the element was entered
+    pub hits: u32,
+}
+
+/// Elements and sequence flows of a diagram with the number of times they were entered.
+/// `Display` writes a text report with the uncovered items and `to_json` a JSON report with every item.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageReport {
+    /// Items in diagram order
+    pub items: Vec<CoverageItem>,
+}
+
+impl CoverageReport {
+    /// Number of items entered at least once
+    pub fn covered(&self) -> usize {
+        self.items.iter().filter(|item| item.hits > 0).count()
+    }
+
+    /// Items never entered
+    pub fn uncovered(&self) -> impl Iterator<Item = &CoverageItem> {
+        self.items.iter().filter(|item| item.hits == 0)
+    }
+
+    /// Every item was entered at least once
+    pub fn is_complete(&self) -> bool {
+        self.uncovered().next().is_none()
+    }
+
+    /// Covered items in percent. An empty diagram is fully covered.
+    pub fn percent(&self) -> f64 {
+        if self.items.is_empty() {
+            return 100.0;
+        }
+        self.covered() as f64 * 100.0 / self.items.len() as f64
+    }
+
+    /// JSON report with the totals and every item
+    pub fn to_json(&self) -> String {
+        let mut json = format!(
+            r#"{{"covered":{},"total":{},"items":["#,
+            self.covered(),
+            self.items.len()
+        );
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            let _ = write!(
+                json,
+                r#"{{"process":"{}","id":"{}","name":{},"type":"{}","hits":{}}}"#,
+                escape(&item.process),
+                escape(&item.id),
+                item.name
+                    .as_deref()
+                    .map_or_else(|| "null".into(), |name| format!(r#""{}""#, escape(name))),
+                item.element_type,
+                item.hits
+            );
+        }
+        json.push_str("]}");
+        json
+    }
+}
+
+impl Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "coverage {}/{} ({:.1}%)",
+            self.covered(),
+            self.items.len(),
+            self.percent()
+        )?;
+        for item in self.uncovered() {
+            write!(f, "uncovered {} {}", item.element_type, item.id)?;
+            if let Some(name) = &item.name {
+                write!(f, " \"{name}\"")?;
+            }
+            writeln!(f, " in {}", item.process)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+use crate::api::{ElementType, ExecutionEvent, ExecutionEventKind};
+use std::sync::{Arc, Mutex};
+
+/// Record every step of a run together with a snapshot of the data, to step through it afterwards with a `Debugger`.
+/// Register the `listener` on the process with `.listener_with_data(...)` and call `take` after the run.
+///
+/// The snapshot function decides how the data is stored. Clone it, or serialize it with serde for large data.
+///
+/// ```
+/// use snurr::{DebugRecorder, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let recorder = DebugRecorder::default();
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .listener_with_data(recorder.listener(|data: &u32| *data))
+///         .build()?;
+///
+///     bpmn.run(0)?;
+///     let mut debugger = recorder.take();
+///     while let Some(step) = debugger.backward() {
+///         println!("{:?} {} data: {}", step.kind, step.id, step.data);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct DebugRecorder<S> {
+    inner: Arc<Mutex<Vec<DebugStep<S>>>>,
+}
+
+impl<S> Default for DebugRecorder<S> {
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<S> Clone for DebugRecorder<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<S: Send + 'static> DebugRecorder<S> {
+    /// Listener to register on the process with `.listener_with_data(...)`. The snapshot function is called
+    /// with the data when an element is entered or left.
+    pub fn listener<T, F>(
+        &self,
+        snapshot: F,
+    ) -> impl Fn(&ExecutionEvent, &T) + Sync + Send + 'static
+    where
+        F: Fn(&T) -> S + 'static + Sync + Send,
+    {
+        let inner = Arc::clone(&self.inner);
+        move |event, data| {
+            let step = DebugStep {
+                kind: event.kind,
+                id: event.id.into(),
+                name: event.name.map(Into::into),
+                element_type: event.element_type,
+                token: event.token,
+                parent_token: event.parent_token,
+                data: snapshot(data),
+            };
+            if let Ok(mut steps) = inner.lock() {
+                steps.push(step);
+            }
+        }
+    }
+
+    /// Take the recorded steps and reset the recorder for the next run.
+    pub fn take(&self) -> Debugger<S> {
+        Debugger::new(
+            self.inner
+                .lock()
+                .map(|mut steps| std::mem::take(&mut *steps))
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// An element entered or left during a recorded run, with the data at that moment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DebugStep<S> {
+    pub kind: ExecutionEventKind,
+    /// The BPMN ID of the element
+    pub id: String,
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+    pub element_type: ElementType,
+    /// Identifier of the token visiting the element. Unique within a run.
+    pub token: usize,
+    /// Token that forked or joined into this token. `None` for the first token of a process.
+    pub parent_token: Option<usize>,
+    /// Snapshot of the data
+    pub data: S,
+}
+
+/// Step forward and backward through a run recorded with `DebugRecorder`.
+/// Steps of parallel tokens are in the order they happened. Filter on `DebugStep::token` to follow a single token.
+#[derive(Debug, Clone)]
+pub struct Debugger<S> {
+    steps: Vec<DebugStep<S>>,
+    // None before the first step
+    position: Option<usize>,
+}
+
+impl<S> Debugger<S> {
+    /// Start before the first step
+    pub fn new(steps: Vec<DebugStep<S>>) -> Self {
+        Self {
+            steps,
+            position: None,
+        }
+    }
+
+    /// All recorded steps in order
+    pub fn steps(&self) -> &[DebugStep<S>] {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Index of the current step. `None` before the first step.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+
+    pub fn current(&self) -> Option<&DebugStep<S>> {
+        self.steps.get(self.position?)
+    }
+
+    /// Move to the next step and return it. Returns `None` and stays at the last step at the end.
+    pub fn forward(&mut self) -> Option<&DebugStep<S>> {
+        let next = self.position.map_or(0, |position| position + 1);
+        if next >= self.steps.len() {
+            return None;
+        }
+        self.position = Some(next);
+        self.steps.get(next)
+    }
+
+    /// Move to the previous step and return it. From before the first step it moves to the last step.
+    /// Returns `None` and stays at the first step at the start.
+    pub fn backward(&mut self) -> Option<&DebugStep<S>> {
+        let previous = match self.position {
+            Some(0) => return None,
+            Some(position) => position - 1,
+            None => self.steps.len().checked_sub(1)?,
+        };
+        self.position = Some(previous);
+        self.steps.get(previous)
+    }
+
+    /// Move to the step at the index and return it
+    pub fn seek(&mut self, index: usize) -> Option<&DebugStep<S>> {
+        let step = self.steps.get(index)?;
+        self.position = Some(index);
+        Some(step)
+    }
+
+    /// Move to the next step where the element with the BPMN id or name is entered or left and return it
+    pub fn forward_to(&mut self, name_or_id: &str) -> Option<&DebugStep<S>> {
+        let start = self.position.map_or(0, |position| position + 1);
+        let index =
+            self.steps.iter().skip(start).position(|step| {
+                step.id == name_or_id || step.name.as_deref() == Some(name_or_id)
+            })? + start;
+        self.seek(index)
+    }
+}
@@ -1,27 +1,94 @@
+#[cfg(feature = "compiled")]
+pub(crate) mod compiled;
+pub(crate) mod elements;
+pub(crate) mod export;
 pub mod reader;
+pub(crate) mod validate;
 
 use crate::{
     Error,
-    api::IntermediateEvent,
-    bpmn::{Activity, ActivityType, Bpmn, Event, EventType, Gateway, GatewayType, Symbol},
+    api::{BuildReport, DataReference, IntermediateEvent},
+    bpmn::{
+        Activity, ActivityType, Bpmn, Event, EventType, Gateway, GatewayType, PROPERTY_CONNECTOR,
+        Symbol,
+    },
+    coverage::CoverageItem,
     error::ONLY_ONE_START_EVENT,
     process::handler::{HandlerMap, HandlerType},
 };
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     fmt::Display,
     ops::AddAssign,
+    sync::Arc,
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Diagram {
     data: Vec<ProcessData>,
+    // Elements skipped or not honored by the reader
+    warnings: Vec<ParseWarning>,
+}
+
+/// Part of the diagram that is skipped or not honored when the diagram is read
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseWarning {
+    pub kind: ParseWarningKind,
+    /// Local name of the XML element, like `complexGateway`
+    pub element: String,
+    /// The BPMN ID of the element. Markers and event definitions use the element they belong to.
+    pub id: Option<String>,
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+}
+
+/// Parse warning kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseWarningKind {
+    /// Element or marker that is not supported and is skipped, like a complex gateway or loop characteristics
+    Unsupported,
+    /// Element without effect on the flow that is skipped, like text annotations, groups and message flows
+    Ignored,
+    /// Event definition with content, like a timer duration or a condition. Only the symbol is used.
+    EventDefinitionContent,
+    /// Event with more than one event definition. Only the last symbol is used.
+    MultipleEventDefinitions,
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let element = &self.element;
+        let target = self
+            .name
+            .as_deref()
+            .or(self.id.as_deref())
+            .map(|value| format!(" ({value})"))
+            .unwrap_or_default();
+        match self.kind {
+            ParseWarningKind::Unsupported => {
+                write!(f, "{element}{target} is not supported and is skipped")
+            }
+            ParseWarningKind::Ignored => write!(f, "{element}{target} is ignored"),
+            ParseWarningKind::EventDefinitionContent => {
+                write!(f, "content of {element}{target} is ignored")
+            }
+            ParseWarningKind::MultipleEventDefinitions => {
+                write!(f, "{element}{target} replaces an earlier event definition")
+            }
+        }
+    }
 }
 
 impl Diagram {
     fn new(data: Vec<ProcessData>) -> Self {
-        Self { data }
+        Self {
+            data,
+            warnings: Default::default(),
+        }
     }
 
     // All top level processes defined in Definitions.
@@ -35,19 +102,228 @@ impl Diagram {
         self.data.get(process_id)
     }
 
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
     pub fn data(&self) -> &[ProcessData] {
         self.data.as_slice()
     }
 
-    pub fn install_and_check(&mut self, handler_map: HandlerMap) -> HashSet<String> {
-        let mut missing = HashSet::new();
-        for process_data in self.data.iter_mut() {
+    // Top level process id and name
+    pub fn processes(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.get_definition()
+            .into_iter()
+            .flat_map(ProcessData::iter)
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process { id, name, .. } => Some((id.bpmn(), name.as_deref())),
+                _ => None,
+            })
+    }
+
+    // End events of the top level processes, where a run can complete
+    pub fn end_events(&self) -> impl Iterator<Item = &Event> {
+        self.get_definition()
+            .into_iter()
+            .flat_map(ProcessData::iter)
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    data_index: Some(index),
+                    executable: true,
+                    ..
+                } => self.get_process(*index),
+                _ => None,
+            })
+            .flat_map(ProcessData::iter)
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Event(event) if event.event_type == EventType::End => Some(event),
+                _ => None,
+            })
+    }
+
+    // Install the functions. With task_types, tasks with a Zeebe job type or Camunda topic are looked up by that type.
+    // Elements and sequence flows of the executable processes and their sub processes, without hits
+    pub(crate) fn coverage_items(&self) -> Vec<CoverageItem> {
+        let titles = self.process_titles();
+        // Skip the definitions that is always last
+        self.data
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(1)
+            .rev()
+            .filter(|(_, process)| !process.non_executable)
+            .flat_map(|(index, process)| {
+                let title = titles.get(&index).copied().unwrap_or_default();
+                process.iter().filter_map(move |bpmn| {
+                    Some(CoverageItem {
+                        process: title.into(),
+                        element_type: bpmn.element_type()?,
+                        id: bpmn.id()?.into(),
+                        name: bpmn.name().map(Into::into),
+                        hits: 0,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    // Stubs for the tasks and gateways without a registered function
+    pub(crate) fn stubs(
+        &self,
+        is_registered: impl Fn(HandlerType, &str) -> bool,
+        task_types: bool,
+    ) -> Vec<Stub> {
+        let mut seen = HashSet::new();
+        let mut stubs = Vec::new();
+        for process in self.data.iter().filter(|data| !data.non_executable) {
+            for bpmn in process.iter() {
+                let (handler_type, name_or_id) = match bpmn {
+                    Bpmn::Activity(Activity {
+                        activity_type:
+                            ActivityType::Task
+                            | ActivityType::ScriptTask
+                            | ActivityType::UserTask
+                            | ActivityType::ServiceTask
+                            | ActivityType::CallActivity
+                            | ActivityType::ReceiveTask
+                            | ActivityType::SendTask
+                            | ActivityType::ManualTask
+                            | ActivityType::BusinessRuleTask,
+                        id,
+                        name,
+                        task_type,
+                        ..
+                    }) => match task_type {
+                        Some(task_type) if task_types => (HandlerType::Task, task_type.as_str()),
+                        _ => (HandlerType::Task, name.as_deref().unwrap_or(id.bpmn())),
+                    },
+                    Bpmn::Gateway(Gateway {
+                        gateway_type: GatewayType::Exclusive,
+                        name,
+                        id,
+                        outputs,
+                        ..
+                    }) if outputs.len() > 1 => {
+                        (HandlerType::Exclusive, name.as_deref().unwrap_or(id.bpmn()))
+                    }
+                    Bpmn::Gateway(Gateway {
+                        gateway_type: GatewayType::Inclusive,
+                        name,
+                        id,
+                        outputs,
+                        ..
+                    }) if outputs.len() > 1 => {
+                        (HandlerType::Inclusive, name.as_deref().unwrap_or(id.bpmn()))
+                    }
+                    Bpmn::Gateway(Gateway {
+                        gateway_type: GatewayType::EventBased,
+                        name,
+                        id,
+                        outputs,
+                        ..
+                    }) if outputs.len() > 1 => (
+                        HandlerType::EventBased,
+                        name.as_deref().unwrap_or(id.bpmn()),
+                    ),
+                    _ => continue,
+                };
+                // Tasks can run the connector in their properties
+                let connector = bpmn
+                    .local_id()
+                    .filter(|_| handler_type == HandlerType::Task)
+                    .and_then(|index| process.properties.get(index))
+                    .and_then(|properties| properties.get(PROPERTY_CONNECTOR));
+                if is_registered(handler_type, name_or_id)
+                    || connector
+                        .is_some_and(|connector| is_registered(HandlerType::Connector, connector))
+                    || !seen.insert((handler_type, name_or_id))
+                {
+                    continue;
+                }
+
+                // The default flow is taken when there is one, otherwise the first outgoing flow
+                let first = bpmn
+                    .outputs()
+                    .and_then(|outputs| outputs.first())
+                    .and_then(|index| process.get(*index));
+                let flow = match bpmn {
+                    Bpmn::Gateway(Gateway {
+                        default: Some(_), ..
+                    }) => None,
+                    _ => first.and_then(Bpmn::name_or_id).map(Into::into),
+                };
+                let name = name_or_id.to_string();
+                stubs.push(match handler_type {
+                    HandlerType::Task => Stub::Task(name),
+                    HandlerType::Exclusive => Stub::Exclusive(name, flow),
+                    HandlerType::Inclusive => Stub::Inclusive(name, flow),
+                    HandlerType::EventBased => {
+                        // The event the first outgoing flow leads to
+                        let Some(Bpmn::Event(Event {
+                            name: Some(event),
+                            symbol: Some(symbol),
+                            ..
+                        })) = first.and_then(|flow| match flow {
+                            Bpmn::SequenceFlow { target_ref, .. } => {
+                                process.get(*target_ref.local())
+                            }
+                            _ => None,
+                        })
+                        else {
+                            continue;
+                        };
+                        Stub::EventBased(
+                            name,
+                            IntermediateEvent(event.to_string().into(), symbol.clone()),
+                        )
+                    }
+                    _ => continue,
+                });
+            }
+        }
+        stubs
+    }
+
+    // Flows that are not outgoing flows of the gateways with the name or id
+    pub(crate) fn unknown_flows(&self, gateway: &str, flows: &[&str]) -> Vec<String> {
+        self.data
+            .iter()
+            .flat_map(|process_data| {
+                process_data.iter().filter_map(move |bpmn| match bpmn {
+                    Bpmn::Gateway(value @ Gateway { outputs, .. })
+                        if bpmn.name_or_id() == Some(gateway) || bpmn.id() == Some(gateway) =>
+                    {
+                        Some((process_data, value, outputs))
+                    }
+                    _ => None,
+                })
+            })
+            .flat_map(|(process_data, value, outputs)| {
+                flows
+                    .iter()
+                    .filter(|flow| process_data.find_by_name_or_id(flow, outputs).is_none())
+                    .map(move |flow| format!("{value}: {flow}"))
+            })
+            .collect()
+    }
+
+    // Build lookup tables for faster execution. Called by build after the functions are installed.
+    pub(crate) fn compile(&mut self) {
+        self.data.iter_mut().for_each(ProcessData::compile);
+    }
+
+    pub fn install_and_check(&mut self, handler_map: &HandlerMap, task_types: bool) -> BuildReport {
+        let mut missing = BTreeSet::new();
+        let mut used = HashSet::new();
+        for process_data in self.data.iter_mut().filter(|data| !data.non_executable) {
             for bpmn in &mut process_data.data {
                 match bpmn {
                     Bpmn::Activity(Activity {
                         id,
                         name,
                         func_idx,
+                        task_type,
                         activity_type:
                             activity_type @ (ActivityType::Task
                             | ActivityType::ScriptTask
@@ -60,9 +336,24 @@ impl Diagram {
                             | ActivityType::BusinessRuleTask),
                         ..
                     }) => {
-                        let name_or_id = name.as_deref().unwrap_or(id.bpmn());
-                        if let Some(id) = handler_map.get(HandlerType::Task, name_or_id) {
+                        let name_or_id = match task_type {
+                            Some(task_type) if task_types => task_type,
+                            _ => name.as_deref().unwrap_or(id.bpmn()),
+                        };
+                        // A registered function is used instead of the connector of the task
+                        if let Some(id) =
+                            handler_map.get(HandlerType::Task, name_or_id).or_else(|| {
+                                handler_map.get(
+                                    HandlerType::Connector,
+                                    process_data
+                                        .properties
+                                        .get(id.local())?
+                                        .get(PROPERTY_CONNECTOR)?,
+                                )
+                            })
+                        {
                             func_idx.replace(*id);
+                            used.insert(*id);
                         } else {
                             missing.insert(format!("{activity_type}: {name_or_id}"));
                         }
@@ -88,29 +379,160 @@ impl Diagram {
                         let name_or_id = name.as_deref().unwrap_or(id.bpmn());
                         if let Some(id) = handler_map.get(handler_type, name_or_id) {
                             func_idx.replace(*id);
+                            used.insert(*id);
                         } else {
                             missing.insert(format!("{gateway_type}: {name_or_id}"));
                         }
                     }
+                    // Catch event and message or signal throw event functions are optional and registered like tasks
+                    Bpmn::Event(Event {
+                        event_type: EventType::IntermediateCatch,
+                        id,
+                        name,
+                        func_idx,
+                        ..
+                    })
+                    | Bpmn::Event(Event {
+                        event_type: EventType::IntermediateThrow | EventType::End,
+                        symbol: Some(Symbol::Message | Symbol::Signal),
+                        id,
+                        name,
+                        func_idx,
+                        ..
+                    }) => {
+                        let name_or_id = name.as_deref().unwrap_or(id.bpmn());
+                        if let Some(id) = handler_map.get(HandlerType::Task, name_or_id) {
+                            func_idx.replace(*id);
+                            used.insert(*id);
+                        }
+                    }
+                    // Boundary functions are optional
+                    Bpmn::Event(Event {
+                        event_type: EventType::Boundary,
+                        id,
+                        name,
+                        func_idx,
+                        ..
+                    }) => {
+                        let name_or_id = name.as_deref().unwrap_or(id.bpmn());
+                        if let Some(id) = handler_map.get(HandlerType::Boundary, name_or_id) {
+                            func_idx.replace(*id);
+                            used.insert(*id);
+                        }
+                    }
                     _ => {}
                 }
             }
         }
-        missing
+        let mut unused: Vec<String> = handler_map
+            .iter()
+            .filter(|(_, _, index)| !used.contains(index))
+            .map(|(handler_type, name, _)| format!("{handler_type}: {name}"))
+            .collect();
+        unused.sort_unstable();
+        BuildReport {
+            missing: missing.into_iter().collect(),
+            unused,
+        }
     }
 }
 
-#[derive(Default, Debug)]
+// Function for an element without a registered function
+pub(crate) enum Stub {
+    Task(String),
+    // Gateway name or id and the flow to take. The default flow is taken with None.
+    Exclusive(String, Option<String>),
+    Inclusive(String, Option<String>),
+    EventBased(String, IntermediateEvent),
+}
+
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProcessData {
     // Start event in the process
     start: Option<usize>,
     data: Vec<Bpmn>,
     boundaries: HashMap<usize, Vec<usize>>,
     catch_event_links: HashMap<String, usize>,
+    // Vendor properties from extensionElements by local id
+    properties: HashMap<usize, BTreeMap<String, String>>,
+    // Documentation text by local id
+    documentation: HashMap<usize, String>,
+    // Part of a process with isExecutable="false". Functions are not installed.
+    non_executable: bool,
+    // Data associations by local id of the activity
+    data_associations: HashMap<usize, DataAssociations>,
+    // Input and output parameters by local id of the activity
+    io_mappings: HashMap<usize, IoMapping>,
+    // Name of the message, signal or escalation by local id of the task or event
+    messages: HashMap<usize, String>,
+    // Boundary events by activity local id and symbol. Filled by compile.
+    boundary_lookup: HashMap<(usize, Symbol), BoundaryLookup>,
+}
+
+// Boundary events of an activity with the same symbol, by name
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct BoundaryLookup {
+    unnamed: Option<usize>,
+    named: HashMap<String, usize>,
+}
+
+// Input and output parameters of a task from camunda:inputOutput, zeebe:ioMapping or ioSpecification.
+// Inputs are set in the scope of the task from the process variables and outputs are set in the
+// process variables from the scope of the task.
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "variables"), allow(dead_code))]
+pub(crate) struct IoMapping {
+    pub(crate) inputs: Vec<Parameter>,
+    pub(crate) outputs: Vec<Parameter>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "variables"), allow(dead_code))]
+pub(crate) struct Parameter {
+    // Variable set in the target scope
+    pub(crate) target: String,
+    pub(crate) source: Source,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(not(feature = "variables"), allow(dead_code))]
+pub(crate) enum Source {
+    // Variable path in the source scope, like order.id
+    Variable(String),
+    Literal(String),
+}
+
+impl Source {
+    // Camunda uses ${order.id} and Zeebe =order.id for a variable, anything else is a literal
+    pub(crate) fn parse(value: &str) -> Self {
+        let value = value.trim();
+        match value
+            .strip_prefix("${")
+            .and_then(|value| value.strip_suffix('}'))
+            .or_else(|| value.strip_prefix('='))
+        {
+            Some(path) => Self::Variable(path.trim().into()),
+            None => Self::Literal(value.into()),
+        }
+    }
+}
+
+// Data objects and data stores read and written by an activity
+#[derive(Default, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct DataAssociations {
+    pub(crate) inputs: Vec<DataReference>,
+    pub(crate) outputs: Vec<DataReference>,
 }
 
 impl ProcessData {
-    fn add(&mut self, mut bpmn: Bpmn) -> Result<(), Error> {
+    // Add the element and return its local id
+    fn add(&mut self, mut bpmn: Bpmn) -> Result<usize, Error> {
         let len = self.data.len();
         if let Bpmn::Event(Event {
             event_type: EventType::Start,
@@ -124,17 +546,33 @@ impl ProcessData {
 
         bpmn.update_local_id(len);
         self.data.push(bpmn);
-        Ok(())
+        Ok(len)
     }
 
     // Everything in the process has been collected. Update local IDs with correct index.
     fn finalize(&mut self) {
-        // Collect Bpmn id to index in array
-        let bpmn_index: HashMap<String, usize> = self
+        // A single message, signal or timer start event is used when there is no start event of type none
+        if self.start.is_none() {
+            let mut starts = self.data.iter().enumerate().filter(|(_, bpmn)| {
+                matches!(
+                    bpmn,
+                    Bpmn::Event(Event {
+                        event_type: EventType::Start,
+                        ..
+                    })
+                )
+            });
+            if let (Some((index, _)), None) = (starts.next(), starts.next()) {
+                self.start = Some(index);
+            }
+        }
+
+        // Collect Bpmn id to index in array. References to an element share the string of its id.
+        let bpmn_index: HashMap<Arc<str>, usize> = self
             .data
             .iter()
             .enumerate()
-            .filter_map(|(index, bpmn)| bpmn.id().map(|id| (id.into(), index)))
+            .filter_map(|(index, bpmn)| bpmn.element_id().map(|id| (id.bpmn_arc(), index)))
             .collect();
 
         self.data.iter_mut().for_each(|bpmn| match bpmn {
@@ -163,7 +601,7 @@ impl ProcessData {
                     && let Some(Symbol::Link) = symbol
                     && EventType::IntermediateCatch == *event_type
                 {
-                    self.catch_event_links.insert(name.clone(), *id.local());
+                    self.catch_event_links.insert(name.to_string(), *id.local());
                 }
             }
             Bpmn::Gateway(Gateway {
@@ -179,10 +617,80 @@ impl ProcessData {
         });
     }
 
+    // Build the lookup tables used while running. Flows are found by name or id and boundary events
+    // by activity, symbol and name without scanning.
+    fn compile(&mut self) {
+        let names: Vec<Option<HashMap<String, usize>>> = self
+            .data
+            .iter()
+            .map(|bpmn| bpmn.outputs().map(|outputs| self.output_names(outputs)))
+            .collect();
+        for (bpmn, names) in self.data.iter_mut().zip(names) {
+            if let Bpmn::Activity(Activity { outputs, .. })
+            | Bpmn::Event(Event { outputs, .. })
+            | Bpmn::Gateway(Gateway { outputs, .. }) = bpmn
+            {
+                outputs.names = names;
+            }
+        }
+
+        let mut boundary_lookup: HashMap<(usize, Symbol), BoundaryLookup> = HashMap::new();
+        for (activity, boundaries) in &self.boundaries {
+            for index in boundaries {
+                if let Some(Bpmn::Event(Event {
+                    symbol: Some(symbol),
+                    name,
+                    ..
+                })) = self.data.get(*index)
+                {
+                    // The first boundary in the diagram wins, like the search does
+                    let lookup = boundary_lookup
+                        .entry((*activity, symbol.clone()))
+                        .or_default();
+                    match name {
+                        Some(name) => {
+                            lookup.named.entry(name.to_string()).or_insert(*index);
+                        }
+                        None => {
+                            lookup.unnamed.get_or_insert(*index);
+                        }
+                    }
+                }
+            }
+        }
+        self.boundary_lookup = boundary_lookup;
+    }
+
+    fn output_names(&self, outputs: &Outputs) -> HashMap<String, usize> {
+        let mut names = HashMap::new();
+        for (position, index) in outputs.iter().enumerate() {
+            if let Some(Bpmn::SequenceFlow { id, name, .. }) = self.get(*index) {
+                if let Some(name) = name {
+                    names.entry(name.to_string()).or_insert(position);
+                }
+                names.entry(id.bpmn().to_string()).or_insert(position);
+            }
+        }
+        names
+    }
+
     pub fn start(&self) -> Option<usize> {
         self.start
     }
 
+    // Start event of any type with the name or id
+    pub fn find_start(&self, name_or_id: &str) -> Option<usize> {
+        self.data.iter().position(|bpmn| {
+            matches!(
+                bpmn,
+                Bpmn::Event(Event {
+                    event_type: EventType::Start,
+                    ..
+                })
+            ) && (bpmn.name() == Some(name_or_id) || bpmn.id() == Some(name_or_id))
+        })
+    }
+
     pub fn get(&self, index: usize) -> Option<&Bpmn> {
         self.data.get(index)
     }
@@ -191,6 +699,27 @@ impl ProcessData {
         self.data.iter()
     }
 
+    pub fn properties(&self, index: usize) -> Option<&BTreeMap<String, String>> {
+        self.properties.get(&index)
+    }
+
+    #[cfg(feature = "variables")]
+    pub fn io_mapping(&self, index: usize) -> Option<&IoMapping> {
+        self.io_mappings.get(&index)
+    }
+
+    pub fn message(&self, index: usize) -> Option<&str> {
+        self.messages.get(&index).map(String::as_str)
+    }
+
+    pub fn data_associations(&self, index: usize) -> Option<&DataAssociations> {
+        self.data_associations.get(&index)
+    }
+
+    pub fn documentation(&self, index: usize) -> Option<&str> {
+        self.documentation.get(&index).map(String::as_str)
+    }
+
     pub fn activity_boundaries(&self, id: &Id) -> Option<&Vec<usize>> {
         self.boundaries.get(id.local())
     }
@@ -201,6 +730,16 @@ impl ProcessData {
         search_name: Option<&str>,
         search_symbol: &Symbol,
     ) -> Option<&'a usize> {
+        if !self.boundary_lookup.is_empty() {
+            let lookup = self
+                .boundary_lookup
+                .get(&(*activity_id.local(), search_symbol.clone()))?;
+            return match search_name {
+                Some(name) => lookup.named.get(name),
+                None => lookup.unnamed.as_ref(),
+            };
+        }
+
         self.activity_boundaries(activity_id)?
             .iter()
             .filter_map(|index| self.data.get(*index))
@@ -215,6 +754,65 @@ impl ProcessData {
             })
     }
 
+    // Error or escalation boundary event of the activity that catches the end event. A boundary that references
+    // an error or escalation catches the same code, otherwise a boundary with the same name as the end event catches
+    // it. A boundary without reference and name catches every end event of its symbol when no other boundary does.
+    pub fn find_catch_boundary(&self, activity_id: &Id, end: &Event) -> Option<&Event> {
+        let mut catch_all = None;
+        for event in self
+            .activity_boundaries(activity_id)?
+            .iter()
+            .filter_map(|index| match self.data.get(*index) {
+                Some(Bpmn::Event(event)) if event.symbol == end.symbol => Some(event),
+                _ => None,
+            })
+        {
+            match (&event.code, &event.name) {
+                (Some(code), _) if end.code.as_ref() == Some(code) => return Some(event),
+                (None, Some(name)) if end.name.as_ref() == Some(name) => return Some(event),
+                (None, None) => catch_all = catch_all.or(Some(event)),
+                _ => {}
+            }
+        }
+        catch_all
+    }
+
+    // Indexes of all elements reachable from the given elements, including the elements.
+    pub fn reachable_from(&self, start: impl IntoIterator<Item = usize>) -> HashSet<usize> {
+        let mut stack: Vec<usize> = start.into_iter().collect();
+        let mut visited = HashSet::new();
+        while let Some(index) = stack.pop() {
+            if !visited.insert(index) {
+                continue;
+            }
+
+            match self.data.get(index) {
+                Some(Bpmn::Event(Event {
+                    event_type: EventType::IntermediateThrow,
+                    symbol: Some(Symbol::Link),
+                    name: Some(name),
+                    ..
+                })) => {
+                    if let Ok(catch) = self.catch_event_link(name) {
+                        stack.push(*catch);
+                    }
+                }
+                Some(
+                    Bpmn::Event(Event { outputs, .. }) | Bpmn::Gateway(Gateway { outputs, .. }),
+                ) => stack.extend(outputs.iter()),
+                Some(Bpmn::Activity(activity)) => {
+                    stack.extend(activity.outputs.iter());
+                    if let Some(boundaries) = self.activity_boundaries(&activity.id) {
+                        stack.extend(boundaries.iter());
+                    }
+                }
+                Some(Bpmn::SequenceFlow { target_ref, .. }) => stack.push(*target_ref.local()),
+                _ => {}
+            }
+        }
+        visited
+    }
+
     pub fn catch_event_link(&self, throw_event_name: &str) -> Result<&usize, Error> {
         self.catch_event_links.get(throw_event_name).ok_or_else(|| {
             Error::MissingIntermediateCatchEvent(Symbol::Link.to_string(), throw_event_name.into())
@@ -226,6 +824,12 @@ impl ProcessData {
         search: impl AsRef<str>,
         outputs: &'a Outputs,
     ) -> Option<&'a usize> {
+        if let Some(names) = &outputs.names {
+            return names
+                .get(search.as_ref())
+                .and_then(|position| outputs.local_ids.get(*position));
+        }
+
         outputs.iter().find(|index| {
             if let Some(Bpmn::SequenceFlow { id, name, .. }) = self.get(**index) {
                 return name.as_deref().is_some_and(|name| name == search.as_ref())
@@ -250,7 +854,7 @@ impl ProcessData {
                         activity_type: ActivityType::ReceiveTask,
                         name: Some(name),
                         ..
-                    }) => search.1 == Symbol::Message && name.as_str() == search.0,
+                    }) => search.1 == Symbol::Message && **name == search.0,
                     Bpmn::Event(Event {
                         symbol:
                             Some(
@@ -261,7 +865,7 @@ impl ProcessData {
                             ),
                         name: Some(name),
                         ..
-                    }) => symbol == &search.1 && name.as_str() == search.0,
+                    }) => symbol == &search.1 && **name == search.0,
                     _ => false,
                 };
             }
@@ -270,10 +874,13 @@ impl ProcessData {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Outputs {
-    bpmn_ids: Vec<String>,
+    bpmn_ids: Vec<Arc<str>>,
     local_ids: Vec<usize>,
+    // Flow name or id to position in local_ids. Filled by compile.
+    names: Option<HashMap<String, usize>>,
 }
 
 impl Display for Outputs {
@@ -283,7 +890,7 @@ impl Display for Outputs {
 }
 
 impl Outputs {
-    fn add(&mut self, output_id: impl Into<String>) {
+    fn add(&mut self, output_id: impl Into<Arc<str>>) {
         self.bpmn_ids.push(output_id.into());
         self.local_ids.push(0);
     }
@@ -304,18 +911,20 @@ impl Outputs {
         self.local_ids.first()
     }
 
-    fn update_local_ids(&mut self, bpmn_index: &HashMap<String, usize>) {
-        for (idx, value) in self.bpmn_ids.iter().enumerate() {
-            if let Some(index) = bpmn_index.get(value) {
+    fn update_local_ids(&mut self, bpmn_index: &HashMap<Arc<str>, usize>) {
+        for (idx, value) in self.bpmn_ids.iter_mut().enumerate() {
+            if let Some((id, index)) = bpmn_index.get_key_value(&**value) {
+                *value = Arc::clone(id);
                 self.local_ids[idx] = *index;
             }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct Id {
-    bpmn_id: String,
+    bpmn_id: Arc<str>,
     local_id: usize,
 }
 
@@ -330,12 +939,18 @@ impl Id {
         &self.bpmn_id
     }
 
+    // Shared BPMN id without copying the string
+    pub(crate) fn bpmn_arc(&self) -> Arc<str> {
+        Arc::clone(&self.bpmn_id)
+    }
+
     pub(crate) fn local(&self) -> &usize {
         &self.local_id
     }
 
-    fn update_local_id(&mut self, map: &HashMap<String, usize>) {
-        if let Some(index) = map.get(&self.bpmn_id) {
+    fn update_local_id(&mut self, map: &HashMap<Arc<str>, usize>) {
+        if let Some((id, index)) = map.get_key_value(&*self.bpmn_id) {
+            self.bpmn_id = Arc::clone(id);
             self.local_id = *index;
         }
     }
@@ -344,21 +959,53 @@ impl Id {
 impl From<String> for Id {
     fn from(bpmn_id: String) -> Self {
         Self {
-            bpmn_id,
+            bpmn_id: bpmn_id.into(),
             local_id: 0,
         }
     }
 }
 
 impl Bpmn {
-    fn id(&self) -> Option<&str> {
+    fn element_id(&self) -> Option<&Id> {
         match self {
             Bpmn::Event(Event { id, .. })
             | Bpmn::SequenceFlow { id, .. }
             | Bpmn::Activity(Activity { id, .. })
             | Bpmn::Definitions { id, .. }
             | Bpmn::Gateway(Gateway { id, .. })
-            | Bpmn::Process { id, .. } => Some(id.bpmn()),
+            | Bpmn::Process { id, .. } => Some(id),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn id(&self) -> Option<&str> {
+        self.element_id().map(Id::bpmn)
+    }
+
+    pub(crate) fn local_id(&self) -> Option<&usize> {
+        self.element_id().map(Id::local)
+    }
+
+    pub(crate) fn name(&self) -> Option<&str> {
+        match self {
+            Bpmn::Event(Event { name, .. })
+            | Bpmn::SequenceFlow { name, .. }
+            | Bpmn::Activity(Activity { name, .. })
+            | Bpmn::Gateway(Gateway { name, .. })
+            | Bpmn::Process { name, .. } => name.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn name_or_id(&self) -> Option<&str> {
+        self.name().or_else(|| self.id())
+    }
+
+    pub(crate) fn outputs(&self) -> Option<&Outputs> {
+        match self {
+            Bpmn::Event(Event { outputs, .. })
+            | Bpmn::Activity(Activity { outputs, .. })
+            | Bpmn::Gateway(Gateway { outputs, .. }) => Some(outputs),
             _ => None,
         }
     }
@@ -0,0 +1,38 @@
+use super::Diagram;
+use crate::error::{COMPILED_FORMAT_ERROR_MSG, Error};
+use bincode::Options;
+use std::io::{Read, Write};
+
+// File starts with the magic bytes and the format version. Bump the version when the layout of the
+// diagram changes, so a file written with another layout is rejected.
+const MAGIC: &[u8; 6] = b"SNURR\0";
+const FORMAT_VERSION: u32 = 1;
+// Upper bound of the bytes read for a diagram. Far above any real diagram, but a truncated or
+// hostile file can't make the reader allocate without bound.
+const SIZE_LIMIT: u64 = 64 * 1024 * 1024;
+
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().with_limit(SIZE_LIMIT)
+}
+
+impl Diagram {
+    // Write the parsed diagram in the compact binary format
+    pub(crate) fn write_compiled(&self, mut writer: impl Write) -> Result<(), Error> {
+        writer.write_all(MAGIC)?;
+        options().serialize_into(&mut writer, &FORMAT_VERSION)?;
+        options().serialize_into(&mut writer, self)?;
+        Ok(())
+    }
+
+    // Read a diagram written by write_compiled
+    pub(crate) fn read_compiled(mut reader: impl Read) -> Result<Self, Error> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC || options().deserialize_from::<_, u32>(&mut reader)? != FORMAT_VERSION {
+            return Err(Error::Compiled(Box::new(bincode::ErrorKind::Custom(
+                COMPILED_FORMAT_ERROR_MSG.into(),
+            ))));
+        }
+        Ok(options().deserialize_from(reader)?)
+    }
+}
@@ -0,0 +1,142 @@
+use super::{Diagram, ProcessData};
+use crate::{
+    api::ElementType,
+    bpmn::{Activity, ActivityType, Bpmn, Event, Symbol},
+};
+use std::collections::HashMap;
+
+/// Read-only view of an element in the parsed diagram, returned by `Process::elements`.
+///
+/// For a sequence flow, `incoming` has the source element and `outgoing` the target element.
+/// For other elements, they have the sequence flows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementInfo<'a> {
+    /// BPMN id of the process or sub process that contains the element
+    pub process: &'a str,
+    /// The BPMN ID of the element
+    pub id: &'a str,
+    /// The name of the element (if it has one)
+    pub name: Option<&'a str>,
+    pub kind: ElementType,
+    /// Symbol of an event with an event definition
+    pub symbol: Option<&'a Symbol>,
+    /// BPMN ids of the incoming sequence flows
+    pub incoming: Vec<&'a str>,
+    /// BPMN ids of the outgoing sequence flows
+    pub outgoing: Vec<&'a str>,
+    /// BPMN ids of the boundary events attached to an activity
+    pub boundaries: Vec<&'a str>,
+    /// BPMN id of the activity a boundary event is attached to
+    pub attached_to: Option<&'a str>,
+}
+
+impl Diagram {
+    // Elements and sequence flows of every process and sub process, in diagram order
+    pub(crate) fn elements(&self) -> Vec<ElementInfo<'_>> {
+        let process_ids = self.process_ids();
+        // Skip the definitions that is always last
+        self.data
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(1)
+            .rev()
+            .flat_map(|(index, process)| {
+                let process_id = process_ids.get(&index).copied().unwrap_or_default();
+                elements(process_id, process)
+            })
+            .collect()
+    }
+
+    // BPMN id of the process or sub process by index of its data
+    fn process_ids(&self) -> HashMap<usize, &str> {
+        self.data
+            .iter()
+            .flat_map(ProcessData::iter)
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    data_index: Some(index),
+                    ..
+                }
+                | Bpmn::Activity(Activity {
+                    activity_type:
+                        ActivityType::SubProcess {
+                            data_index: Some(index),
+                        },
+                    ..
+                }) => Some((*index, bpmn.id()?)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn elements<'a>(process_id: &'a str, process: &'a ProcessData) -> Vec<ElementInfo<'a>> {
+    // Incoming sequence flows and source element by local id of the target
+    let mut incoming: HashMap<usize, Vec<&str>> = HashMap::new();
+    for bpmn in process.iter() {
+        for index in bpmn
+            .outputs()
+            .into_iter()
+            .flat_map(|outputs| outputs.iter())
+        {
+            if let Some(source) = bpmn.id() {
+                incoming.entry(*index).or_default().push(source);
+            }
+            if let Some(Bpmn::SequenceFlow { id, target_ref, .. }) = process.get(*index) {
+                incoming
+                    .entry(*target_ref.local())
+                    .or_default()
+                    .push(id.bpmn());
+            }
+        }
+    }
+
+    let ids = |indexes: &[usize]| -> Vec<&'a str> {
+        indexes
+            .iter()
+            .filter_map(|index| process.get(*index)?.id())
+            .collect()
+    };
+    process
+        .iter()
+        .filter_map(|bpmn| {
+            let local_id = bpmn.local_id()?;
+            let (symbol, attached_to) = match bpmn {
+                Bpmn::Event(Event {
+                    symbol,
+                    attached_to_ref,
+                    ..
+                }) => (
+                    symbol.as_ref(),
+                    attached_to_ref.as_ref().map(|id| id.bpmn()),
+                ),
+                _ => (None, None),
+            };
+            let outgoing = match bpmn {
+                Bpmn::SequenceFlow { target_ref, .. } => ids(&[*target_ref.local()]),
+                _ => bpmn
+                    .outputs()
+                    .map(|outputs| ids(outputs.ids()))
+                    .unwrap_or_default(),
+            };
+            Some(ElementInfo {
+                process: process_id,
+                id: bpmn.id()?,
+                name: bpmn.name(),
+                kind: bpmn.element_type()?,
+                symbol,
+                incoming: incoming.get(local_id).cloned().unwrap_or_default(),
+                outgoing,
+                boundaries: match bpmn {
+                    Bpmn::Activity(activity) => process
+                        .activity_boundaries(&activity.id)
+                        .map(|boundaries| ids(boundaries))
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                },
+                attached_to,
+            })
+        })
+        .collect()
+}
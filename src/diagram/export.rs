@@ -0,0 +1,548 @@
+use super::{Diagram, ProcessData};
+use crate::{
+    bpmn::{Activity, ActivityType, Bpmn, Event, EventType, Gateway, GatewayType},
+    error::Error,
+};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write,
+    path::Path,
+};
+
+/// Documentation page of a top level process, generated with `Process::handbook`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandbookPage {
+    /// BPMN id of the process
+    pub id: String,
+    pub name: Option<String>,
+    /// Graphviz DOT of the process and its sub processes with a legend
+    pub dot: String,
+    /// Markdown with the process documentation and the elements with their documentation
+    pub markdown: String,
+}
+
+impl HandbookPage {
+    /// Write `{id}.dot` and `{id}.md` to the directory. The markdown refers to `{id}.svg`,
+    /// rendered with `dot -Tsvg {id}.dot -o {id}.svg`.
+    pub fn save(&self, dir: impl AsRef<Path>) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{}.dot", self.id)), &self.dot)?;
+        std::fs::write(dir.join(format!("{}.md", self.id)), &self.markdown)?;
+        Ok(())
+    }
+}
+
+impl Diagram {
+    // Convert the parsed diagram to Graphviz DOT. Elements and sequence flows with an id in the trace are highlighted.
+    pub fn to_dot(&self, trace: &HashSet<&str>) -> String {
+        let mut dot =
+            String::from("digraph bpmn {\n  rankdir=LR;\n  node [fontname=\"Helvetica\"];\n");
+        let titles = self.process_titles();
+        // Skip the definitions that is always last
+        for (index, process) in self.data.iter().enumerate().rev().skip(1).rev() {
+            let title = titles.get(&index).copied().unwrap_or_default();
+            let _ = writeln!(
+                dot,
+                "  subgraph cluster_{index} {{\n    label=\"{}\";",
+                escape(title)
+            );
+            for bpmn in process.iter() {
+                write_node(&mut dot, bpmn, trace);
+            }
+            dot.push_str("  }\n");
+            for bpmn in process.iter() {
+                write_edges(&mut dot, process, bpmn, trace);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // Convert the parsed diagram to a Mermaid flowchart. Elements and sequence flows with an id in the trace are highlighted.
+    pub fn to_mermaid(&self, trace: &HashSet<&str>) -> String {
+        let mut mermaid = String::from("flowchart LR\n");
+        let mut edges = Vec::new();
+        let mut traced = Vec::new();
+        let titles = self.process_titles();
+        // Skip the definitions that is always last
+        for (index, process) in self.data.iter().enumerate().rev().skip(1).rev() {
+            let title = titles.get(&index).copied().unwrap_or_default();
+            let _ = writeln!(
+                mermaid,
+                "  subgraph process_{index} [\"{}\"]",
+                mermaid_escape(title)
+            );
+            for bpmn in process.iter() {
+                write_mermaid_node(&mut mermaid, bpmn);
+                if let Some(id) = bpmn.id().filter(|id| trace.contains(id)) {
+                    traced.push(mermaid_id(id));
+                }
+            }
+            mermaid.push_str("  end\n");
+            for bpmn in process.iter() {
+                mermaid_edges(&mut edges, process, bpmn, trace);
+            }
+        }
+
+        for (edge, _) in &edges {
+            let _ = writeln!(mermaid, "  {edge}");
+        }
+        // Links are styled by their position
+        let traced_edges = edges
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, traced))| *traced)
+            .map(|(index, _)| index.to_string())
+            .collect::<Vec<_>>();
+        if !traced_edges.is_empty() {
+            let _ = writeln!(
+                mermaid,
+                "  linkStyle {} stroke:red,stroke-width:2px",
+                traced_edges.join(",")
+            );
+        }
+        if !traced.is_empty() {
+            mermaid.push_str("  classDef trace stroke:red,stroke-width:2px\n");
+            let _ = writeln!(mermaid, "  class {} trace", traced.join(","));
+        }
+        mermaid
+    }
+
+    // One documentation page for every top level process
+    pub(crate) fn handbook(&self) -> Vec<HandbookPage> {
+        let Some(definitions) = self.get_definition() else {
+            return Vec::new();
+        };
+
+        let titles = self.process_titles();
+        definitions
+            .iter()
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    id,
+                    name,
+                    data_index: Some(index),
+                    ..
+                } => Some(self.handbook_page(
+                    id.bpmn(),
+                    name.as_deref(),
+                    definitions.documentation(*id.local()),
+                    *index,
+                    &titles,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn handbook_page(
+        &self,
+        id: &str,
+        name: Option<&str>,
+        documentation: Option<&str>,
+        index: usize,
+        titles: &HashMap<usize, &str>,
+    ) -> HandbookPage {
+        let title = name.unwrap_or(id);
+        let indexes = self.nested_processes(index);
+
+        let mut dot = format!(
+            "digraph \"{}\" {{\n  rankdir=LR;\n  node [fontname=\"Helvetica\"];\n",
+            escape(id)
+        );
+        let mut legend = BTreeSet::new();
+        let mut rows = String::new();
+        for index in &indexes {
+            let Some(process) = self.get_process(*index) else {
+                continue;
+            };
+            let _ = writeln!(
+                dot,
+                "  subgraph cluster_{index} {{\n    label=\"{}\";",
+                escape(titles.get(index).copied().unwrap_or_default())
+            );
+            for bpmn in process.iter() {
+                write_node(&mut dot, bpmn, &HashSet::new());
+            }
+            dot.push_str("  }\n");
+            for (local, bpmn) in process.iter().enumerate() {
+                write_edges(&mut dot, process, bpmn, &HashSet::new());
+                legend.extend(legend_entries(bpmn));
+                write_row(&mut rows, bpmn, process.documentation(local));
+            }
+        }
+
+        if !legend.is_empty() {
+            let _ = writeln!(
+                dot,
+                "  subgraph cluster_legend {{\n    label=\"Legend\";\n    legend [shape=note, label=\"{}\\l\"];\n  }}",
+                legend.into_iter().collect::<Vec<_>>().join("\\l")
+            );
+        }
+        dot.push_str("}\n");
+
+        let mut markdown = format!("# {title}\n\n");
+        if let Some(documentation) = documentation {
+            let _ = writeln!(markdown, "{}\n", documentation.trim());
+        }
+        let _ = writeln!(markdown, "![{title}]({id}.svg)\n");
+        if !rows.is_empty() {
+            markdown.push_str("| Element | Type | Documentation |\n| --- | --- | --- |\n");
+            markdown.push_str(&rows);
+        }
+
+        HandbookPage {
+            id: id.into(),
+            name: name.map(Into::into),
+            dot,
+            markdown,
+        }
+    }
+
+    // Data index of the process and all its sub processes
+    fn nested_processes(&self, index: usize) -> Vec<usize> {
+        let mut indexes = vec![index];
+        let mut position = 0;
+        while let Some(process) = indexes
+            .get(position)
+            .and_then(|index| self.get_process(*index))
+        {
+            indexes.extend(process.iter().filter_map(|bpmn| match bpmn {
+                Bpmn::Activity(Activity {
+                    activity_type:
+                        ActivityType::SubProcess {
+                            data_index: Some(index),
+                        },
+                    ..
+                }) => Some(*index),
+                _ => None,
+            }));
+            position += 1;
+        }
+        indexes
+    }
+
+    // Title of every process and sub process by data index
+    pub(crate) fn process_titles(&self) -> HashMap<usize, &str> {
+        self.data
+            .iter()
+            .flat_map(ProcessData::iter)
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    data_index: Some(index),
+                    ..
+                }
+                | Bpmn::Activity(Activity {
+                    activity_type:
+                        ActivityType::SubProcess {
+                            data_index: Some(index),
+                        },
+                    ..
+                }) => Some((*index, bpmn.name_or_id()?)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn write_node(dot: &mut String, bpmn: &Bpmn, trace: &HashSet<&str>) {
+    let (Some(id), Some(label)) = (bpmn.id(), bpmn.name_or_id()) else {
+        return;
+    };
+
+    let shape = match bpmn {
+        Bpmn::Event(Event {
+            event_type: EventType::End,
+            ..
+        }) => "doublecircle",
+        Bpmn::Event(_) => "circle",
+        Bpmn::Activity(_) => "box, style=rounded",
+        Bpmn::Gateway(_) => "diamond",
+        _ => return,
+    };
+
+    let label = node_label(bpmn, label);
+
+    let highlight = if trace.contains(id) {
+        ", color=red, penwidth=2"
+    } else {
+        ""
+    };
+    let _ = writeln!(
+        dot,
+        "    \"{}\" [label=\"{}\", shape={shape}{highlight}];",
+        escape(id),
+        escape(&label)
+    );
+}
+
+// Label with the event symbol or the gateway marker
+fn node_label(bpmn: &Bpmn, label: &str) -> String {
+    match bpmn {
+        Bpmn::Event(Event {
+            symbol: Some(symbol),
+            ..
+        }) => format!("{label}\n({symbol})"),
+        Bpmn::Gateway(Gateway { gateway_type, .. }) => {
+            let marker = match gateway_type {
+                GatewayType::Exclusive => "X",
+                GatewayType::Inclusive => "O",
+                GatewayType::Parallel => "+",
+                GatewayType::EventBased => "E",
+            };
+            format!("{marker} {label}")
+        }
+        _ => label.to_string(),
+    }
+}
+
+fn write_edges(dot: &mut String, process: &ProcessData, bpmn: &Bpmn, trace: &HashSet<&str>) {
+    let (source, outputs, default) = match bpmn {
+        Bpmn::Event(Event {
+            id,
+            outputs,
+            attached_to_ref,
+            ..
+        }) => {
+            // Dashed edge from the activity to the boundary
+            if let Some(activity) = attached_to_ref
+                .as_ref()
+                .and_then(|id| process.get(*id.local()))
+                .and_then(Bpmn::id)
+            {
+                let _ = writeln!(
+                    dot,
+                    "  \"{}\" -> \"{}\" [style=dashed, arrowhead=none];",
+                    escape(activity),
+                    escape(id.bpmn())
+                );
+            }
+            (id, outputs, None)
+        }
+        Bpmn::Activity(Activity { id, outputs, .. }) => (id, outputs, None),
+        Bpmn::Gateway(Gateway {
+            id,
+            outputs,
+            default,
+            ..
+        }) => (id, outputs, default.as_ref()),
+        _ => return,
+    };
+
+    for index in outputs.iter() {
+        let Some(Bpmn::SequenceFlow {
+            id,
+            name,
+            target_ref,
+        }) = process.get(*index)
+        else {
+            continue;
+        };
+
+        let Some(target) = process.get(*target_ref.local()).and_then(Bpmn::id) else {
+            continue;
+        };
+
+        let mut attributes = Vec::new();
+        if let Some(name) = name {
+            attributes.push(format!("label=\"{}\"", escape(name)));
+        }
+        if default.is_some_and(|default| default.bpmn() == id.bpmn()) {
+            attributes.push("arrowtail=odiamond, dir=both".into());
+        }
+        if trace.contains(id.bpmn()) {
+            attributes.push("color=red, penwidth=2".into());
+        }
+
+        let _ = writeln!(
+            dot,
+            "  \"{}\" -> \"{}\" [{}];",
+            escape(source.bpmn()),
+            escape(target),
+            attributes.join(", ")
+        );
+    }
+}
+
+fn write_mermaid_node(mermaid: &mut String, bpmn: &Bpmn) {
+    let (Some(id), Some(label)) = (bpmn.id(), bpmn.name_or_id()) else {
+        return;
+    };
+
+    let (open, close) = match bpmn {
+        Bpmn::Event(Event {
+            event_type: EventType::End,
+            ..
+        }) => ("(((", ")))"),
+        Bpmn::Event(_) => ("((", "))"),
+        Bpmn::Activity(_) => ("(", ")"),
+        Bpmn::Gateway(_) => ("{", "}"),
+        _ => return,
+    };
+
+    let _ = writeln!(
+        mermaid,
+        "    {}{open}\"{}\"{close}",
+        mermaid_id(id),
+        mermaid_escape(&node_label(bpmn, label))
+    );
+}
+
+// Edges with a flag if the sequence flow is in the trace
+fn mermaid_edges(
+    edges: &mut Vec<(String, bool)>,
+    process: &ProcessData,
+    bpmn: &Bpmn,
+    trace: &HashSet<&str>,
+) {
+    let (source, outputs, default) = match bpmn {
+        Bpmn::Event(Event {
+            id,
+            outputs,
+            attached_to_ref,
+            ..
+        }) => {
+            // Dotted link from the activity to the boundary
+            if let Some(activity) = attached_to_ref
+                .as_ref()
+                .and_then(|id| process.get(*id.local()))
+                .and_then(Bpmn::id)
+            {
+                edges.push((
+                    format!("{} -.- {}", mermaid_id(activity), mermaid_id(id.bpmn())),
+                    false,
+                ));
+            }
+            (id, outputs, None)
+        }
+        Bpmn::Activity(Activity { id, outputs, .. }) => (id, outputs, None),
+        Bpmn::Gateway(Gateway {
+            id,
+            outputs,
+            default,
+            ..
+        }) => (id, outputs, default.as_ref()),
+        _ => return,
+    };
+
+    for index in outputs.iter() {
+        let Some(Bpmn::SequenceFlow {
+            id,
+            name,
+            target_ref,
+        }) = process.get(*index)
+        else {
+            continue;
+        };
+
+        let Some(target) = process.get(*target_ref.local()).and_then(Bpmn::id) else {
+            continue;
+        };
+
+        // A slash marks the default flow like in the modeler
+        let label = match (
+            name,
+            default.is_some_and(|default| default.bpmn() == id.bpmn()),
+        ) {
+            (Some(name), true) => Some(format!("/ {name}")),
+            (None, true) => Some("/".into()),
+            (name, false) => name.as_deref().map(Into::into),
+        };
+        let arrow = match label {
+            Some(label) => format!("-->|\"{}\"|", mermaid_escape(&label)),
+            None => "-->".into(),
+        };
+        edges.push((
+            format!(
+                "{} {arrow} {}",
+                mermaid_id(source.bpmn()),
+                mermaid_id(target)
+            ),
+            trace.contains(id.bpmn()),
+        ));
+    }
+}
+
+// Mermaid node ids are alphanumeric and `end` is a keyword
+fn mermaid_id(id: &str) -> String {
+    let mut mermaid_id: String = id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if mermaid_id.eq_ignore_ascii_case("end") {
+        mermaid_id.push('_');
+    }
+    mermaid_id
+}
+
+fn mermaid_escape(value: &str) -> String {
+    value.replace('"', "#quot;").replace('\n', "<br>")
+}
+
+// Legend lines for the notation used by the element
+fn legend_entries(bpmn: &Bpmn) -> Vec<String> {
+    match bpmn {
+        Bpmn::Event(Event {
+            event_type,
+            symbol,
+            attached_to_ref,
+            ..
+        }) => {
+            let mut entries = vec![match event_type {
+                EventType::End => "double circle: end event".to_string(),
+                _ => "circle: start, intermediate or boundary event".to_string(),
+            }];
+            if let Some(symbol) = symbol {
+                entries.push(format!("({symbol}): {symbol} event"));
+            }
+            if attached_to_ref.is_some() {
+                entries.push("dashed line: boundary event attached to an activity".into());
+            }
+            entries
+        }
+        Bpmn::Gateway(Gateway {
+            gateway_type,
+            default,
+            ..
+        }) => {
+            let marker = match gateway_type {
+                GatewayType::Exclusive => "X",
+                GatewayType::Inclusive => "O",
+                GatewayType::Parallel => "+",
+                GatewayType::EventBased => "E",
+            };
+            let mut entries = vec![format!("{marker}: {gateway_type} gateway")];
+            if default.is_some() {
+                entries.push("diamond tail: default flow".into());
+            }
+            entries
+        }
+        _ => Vec::new(),
+    }
+}
+
+// Markdown table row. Sequence flows are only listed when documented.
+fn write_row(markdown: &mut String, bpmn: &Bpmn, documentation: Option<&str>) {
+    let (Some(name_or_id), Some(element_type)) = (bpmn.name_or_id(), bpmn.element_type()) else {
+        return;
+    };
+    if matches!(bpmn, Bpmn::SequenceFlow { .. }) && documentation.is_none() {
+        return;
+    }
+
+    let cell = |value: &str| value.trim().replace('|', "\\|").replace('\n', "<br>");
+    let _ = writeln!(
+        markdown,
+        "| {} | {element_type} | {} |",
+        cell(name_or_id),
+        cell(documentation.unwrap_or_default())
+    );
+}
+
+pub(crate) fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -1,120 +1,426 @@
 mod builder;
+mod program;
 
-use super::Diagram;
+use super::{Diagram, ParseWarningKind};
+use crate::api::DataKind;
 use crate::bpmn::*;
 use crate::error::Error;
+use crate::process::options::ParseOptions;
 use builder::DataBuilder;
 use log::error;
 use quick_xml::events::Event;
+use quick_xml::name::QName;
 use quick_xml::reader::Reader;
 use std::collections::HashMap;
-use std::io::BufRead;
+use std::io::{BufRead, Read};
+
+pub use program::DiagramBuilder;
 
 // Read BPMN content and return the Diagram
-pub fn read_bpmn<R: BufRead>(mut reader: Reader<R>) -> Result<Diagram, Error> {
-    let mut builder = DataBuilder::default();
+pub fn read_bpmn<R: BufRead>(source: R) -> Result<Diagram, Error> {
+    read_bpmn_with(source, ParseOptions::default())
+}
+
+// Malformed XML returns `Error::Parse` with the line and column. Other errors are logged with the line and column
+// of the XML element and the id of the enclosing element.
+pub fn read_bpmn_with<R: BufRead>(source: R, options: ParseOptions) -> Result<Diagram, Error> {
+    let mut reader = Reader::from_reader(LineCounter::new(source));
+    let mut builder = DataBuilder::new(options.mode);
+    let mut state = ReadState::default();
     let mut buf = Vec::new();
     loop {
-        match reader.read_event_into(&mut buf) {
-            Err(e) => error!("Error at position {}: {:?}", reader.buffer_position(), e),
+        let position = reader.buffer_position();
+        let result = match reader.read_event_into(&mut buf) {
             Ok(Event::Eof) => break,
-            Ok(Event::Start(bs)) => match bs.local_name().as_ref() {
-                bpmn_type @ (START_EVENT
-                | END_EVENT
-                | BOUNDARY_EVENT
-                | INTERMEDIATE_CATCH_EVENT
-                | INTERMEDIATE_THROW_EVENT
-                | TASK
-                | SCRIPT_TASK
-                | USER_TASK
-                | SERVICE_TASK
-                | CALL_ACTIVITY
-                | RECEIVE_TASK
-                | SEND_TASK
-                | MANUAL_TASK
-                | BUSINESS_RULE_TASK
-                | OUTGOING
-                | INCOMING
-                | EXCLUSIVE_GATEWAY
-                | PARALLEL_GATEWAY
-                | INCLUSIVE_GATEWAY
-                | EVENT_BASED_GATEWAY
-                | SEQUENCE_FLOW) => {
-                    builder.add(Bpmn::try_from((bpmn_type, collect_attributes(&bs)))?)
+            // Diagram interchange has the shapes and edges drawn by the modeler and is often most of the file.
+            // Skip to its end without decoding the elements.
+            Ok(Event::Start(bs)) if bs.local_name().as_ref() == BPMN_DIAGRAM => {
+                let end = bs.name().as_ref().to_vec();
+                reader
+                    .read_to_end_into(QName(&end), &mut buf)
+                    .map(|_| ())
+                    .map_err(Into::into)
+            }
+            Ok(event) => read_event(event, &mut builder, &mut state),
+            Err(error) => Err(error.into()),
+        };
+        if let Err(error) = result {
+            let position = match error {
+                Error::File(_) => reader.error_position(),
+                _ => position,
+            };
+            let (line, column) = reader.get_ref().line_column(position);
+            let element = builder.current_id().map(String::from);
+            return Err(match error {
+                // Malformed XML has no error of its own, the location is part of it
+                Error::File(source) => Error::Parse {
+                    line,
+                    column,
+                    element,
+                    source,
+                },
+                // Other errors keep their variant to match on
+                error => {
+                    error!(
+                        "{error} at line {line}, column {column}{}",
+                        element.map(|id| format!(" in {id}")).unwrap_or_default()
+                    );
+                    error
+                }
+            });
+        }
+        buf.clear();
+    }
+    Ok(builder.into())
+}
+
+#[derive(Default)]
+struct ReadState {
+    in_extensions: bool,
+    in_documentation: bool,
+    // Inside a data association. True for a data input association.
+    data_association: Option<bool>,
+    // Inside the element of the data association that refers to the data object or data store
+    in_data_ref: bool,
+    // Inside a camunda inputParameter or outputParameter with the value as text
+    in_parameter: bool,
+    // Inside a camunda formField with values and constraints
+    in_form_field: bool,
+}
+
+fn read_event(event: Event, builder: &mut DataBuilder, state: &mut ReadState) -> Result<(), Error> {
+    match event {
+        Event::End(be) if be.local_name().as_ref() == EXTENSION_ELEMENTS => {
+            state.in_extensions = false;
+        }
+        Event::End(be) if be.local_name().as_ref() == DOCUMENTATION => {
+            state.in_documentation = false;
+        }
+        Event::Text(bt) if state.in_documentation => {
+            builder.add_documentation(&bt.decode().map_err(quick_xml::Error::from)?);
+        }
+        Event::Text(bt) if state.in_parameter => {
+            builder.add_parameter_text(&bt.decode().map_err(quick_xml::Error::from)?);
+        }
+        Event::End(be)
+            if state.in_parameter
+                && matches!(be.local_name().as_ref(), INPUT_PARAMETER | OUTPUT_PARAMETER) =>
+        {
+            state.in_parameter = false;
+            builder.end_parameter();
+        }
+        Event::End(be) if state.in_form_field && be.local_name().as_ref() == FORM_FIELD => {
+            state.in_form_field = false;
+        }
+        // Values and constraints of a form field. Other elements inside it, like properties, are skipped.
+        Event::Start(bs) | Event::Empty(bs) if state.in_form_field => {
+            builder.add_form_field_item(bs.local_name().as_ref(), collect_attributes(&bs))
+        }
+        Event::Text(bt) if state.in_data_ref => {
+            if let Some(input) = state.data_association {
+                builder.add_data_association(input, &bt.decode().map_err(quick_xml::Error::from)?);
+            }
+        }
+        // Input and output parameters inside extensionElements
+        Event::Start(bs) if state.in_extensions && !state.in_parameter => {
+            match bs.local_name().as_ref() {
+                parameter @ (INPUT_PARAMETER | OUTPUT_PARAMETER) => {
+                    state.in_parameter = true;
+                    builder.start_parameter(parameter == INPUT_PARAMETER, collect_attributes(&bs));
                 }
-                bpmn_type @ (DEFINITIONS | PROCESS | SUB_PROCESS | TRANSACTION) => {
-                    builder.add_new_process(Bpmn::try_from((bpmn_type, collect_attributes(&bs)))?)
+                parameter @ (INPUT | OUTPUT) => {
+                    builder.add_parameter(parameter == INPUT, collect_attributes(&bs))
                 }
-                _ => {}
-            },
-            Ok(Event::Empty(bs)) => {
-                match bs.local_name().as_ref() {
-                    // Attach symbol to parent
-                    bpmn_type @ (CANCEL_EVENT_DEFINITION
-                    | COMPENSATE_EVENT_DEFINITION
-                    | CONDITIONAL_EVENT_DEFINITION
-                    | ERROR_EVENT_DEFINITION
-                    | ESCALATION_EVENT_DEFINITION
-                    | MESSAGE_EVENT_DEFINITION
-                    | LINK_EVENT_DEFINITION
-                    | SIGNAL_EVENT_DEFINITION
-                    | TERMINATE_EVENT_DEFINITION
-                    | TIMER_EVENT_DEFINITION) => {
-                        builder.update_symbol(bpmn_type);
-                    }
-                    bpmn_type @ SEQUENCE_FLOW => {
-                        builder.add_to_process(Bpmn::try_from((
-                            bpmn_type,
-                            collect_attributes(&bs),
-                        ))?)?;
-                    }
-                    _ => {}
+                FORM_FIELD => {
+                    state.in_form_field = true;
+                    builder.start_form_field(collect_attributes(&bs));
+                }
+                property => builder.add_property(property, collect_attributes(&bs))?,
+            }
+        }
+        Event::Empty(bs) if state.in_extensions && !state.in_parameter => {
+            match bs.local_name().as_ref() {
+                parameter @ (INPUT_PARAMETER | OUTPUT_PARAMETER) => {
+                    builder.start_parameter(parameter == INPUT_PARAMETER, collect_attributes(&bs));
+                    builder.end_parameter();
                 }
+                parameter @ (INPUT | OUTPUT) => {
+                    builder.add_parameter(parameter == INPUT, collect_attributes(&bs))
+                }
+                FORM_FIELD => builder.start_form_field(collect_attributes(&bs)),
+                property => builder.add_property(property, collect_attributes(&bs))?,
+            }
+        }
+        // Vendor elements inside extensionElements
+        Event::Start(bs) | Event::Empty(bs) if state.in_extensions => {
+            builder.add_property(bs.local_name().as_ref(), collect_attributes(&bs))?
+        }
+        Event::Start(bs) => match bs.local_name().as_ref() {
+            bpmn_type @ (START_EVENT
+            | END_EVENT
+            | BOUNDARY_EVENT
+            | INTERMEDIATE_CATCH_EVENT
+            | INTERMEDIATE_THROW_EVENT
+            | TASK
+            | SCRIPT_TASK
+            | USER_TASK
+            | SERVICE_TASK
+            | CALL_ACTIVITY
+            | RECEIVE_TASK
+            | SEND_TASK
+            | MANUAL_TASK
+            | BUSINESS_RULE_TASK
+            | OUTGOING
+            | INCOMING
+            | EXCLUSIVE_GATEWAY
+            | PARALLEL_GATEWAY
+            | INCLUSIVE_GATEWAY
+            | EVENT_BASED_GATEWAY
+            | SEQUENCE_FLOW) => {
+                let attributes = collect_attributes(&bs);
+                // Send and receive tasks reference a message
+                let message_ref = attributes
+                    .contains_key(ATTRIB_MESSAGE_REF)
+                    .then(|| attributes.clone());
+                builder.add(Bpmn::try_from((bpmn_type, attributes))?);
+                if let Some(attributes) = message_ref {
+                    builder.add_message_ref(&attributes);
+                }
+            }
+            bpmn_type @ (DEFINITIONS | PROCESS | SUB_PROCESS | TRANSACTION) => {
+                builder.add_new_process(Bpmn::try_from((bpmn_type, collect_attributes(&bs)))?)
+            }
+            bpmn_type @ (COMPLEX_GATEWAY
+            | AD_HOC_SUB_PROCESS
+            | STANDARD_LOOP_CHARACTERISTICS
+            | MULTI_INSTANCE_LOOP_CHARACTERISTICS) => builder.add_warning(
+                ParseWarningKind::Unsupported,
+                bpmn_type,
+                collect_attributes(&bs),
+            )?,
+            bpmn_type @ (TEXT_ANNOTATION | ASSOCIATION | GROUP | MESSAGE_FLOW) => builder
+                .add_warning(
+                    ParseWarningKind::Ignored,
+                    bpmn_type,
+                    collect_attributes(&bs),
+                )?,
+            DATA_OBJECT => builder.add_data_object(collect_attributes(&bs)),
+            bpmn_type @ DATA_OBJECT_REFERENCE => {
+                builder.add_data_reference(DataKind::Object, bpmn_type, collect_attributes(&bs))?
+            }
+            bpmn_type @ DATA_STORE_REFERENCE => {
+                builder.add_data_reference(DataKind::Store, bpmn_type, collect_attributes(&bs))?
             }
-            Ok(Event::End(be)) => match be.local_name().as_ref() {
-                direction @ (OUTGOING | INCOMING) => builder.add_direction(direction),
-                START_EVENT
-                | END_EVENT
-                | BOUNDARY_EVENT
-                | INTERMEDIATE_CATCH_EVENT
-                | INTERMEDIATE_THROW_EVENT
-                | TASK
-                | SCRIPT_TASK
-                | USER_TASK
-                | SERVICE_TASK
-                | CALL_ACTIVITY
-                | RECEIVE_TASK
-                | SEND_TASK
-                | MANUAL_TASK
-                | BUSINESS_RULE_TASK
-                | EXCLUSIVE_GATEWAY
-                | PARALLEL_GATEWAY
-                | INCLUSIVE_GATEWAY
-                | EVENT_BASED_GATEWAY
-                | SEQUENCE_FLOW => builder.end()?,
-                DEFINITIONS | PROCESS | SUB_PROCESS | TRANSACTION => builder.end_process()?,
+            // Data inputs and outputs of an ioSpecification map the variable with the same name
+            parameter @ (DATA_INPUT | DATA_OUTPUT) => {
+                builder.add_parameter(parameter == DATA_INPUT, collect_attributes(&bs))
+            }
+            DATA_INPUT_ASSOCIATION => state.data_association = Some(true),
+            DATA_OUTPUT_ASSOCIATION => state.data_association = Some(false),
+            // Input associations read from the source and output associations write to the target
+            SOURCE_REF => state.in_data_ref = state.data_association == Some(true),
+            TARGET_REF => state.in_data_ref = state.data_association == Some(false),
+            // Event definition with content like a timer duration. Only the symbol is used.
+            bpmn_type @ (CANCEL_EVENT_DEFINITION
+            | COMPENSATE_EVENT_DEFINITION
+            | CONDITIONAL_EVENT_DEFINITION
+            | ERROR_EVENT_DEFINITION
+            | ESCALATION_EVENT_DEFINITION
+            | MESSAGE_EVENT_DEFINITION
+            | LINK_EVENT_DEFINITION
+            | SIGNAL_EVENT_DEFINITION
+            | TERMINATE_EVENT_DEFINITION
+            | TIMER_EVENT_DEFINITION) => {
+                update_symbol(builder, bpmn_type)?;
+                builder.add_message_ref(&collect_attributes(&bs));
+                builder.add_warning(
+                    ParseWarningKind::EventDefinitionContent,
+                    bpmn_type,
+                    collect_attributes(&bs),
+                )?
+            }
+            MESSAGE | SIGNAL => builder.add_message(collect_attributes(&bs)),
+            ESCALATION => {
+                builder.add_code_definition(ATTRIB_ESCALATION_CODE, collect_attributes(&bs))
+            }
+            ERROR => builder.add_code_definition(ATTRIB_ERROR_CODE, collect_attributes(&bs)),
+            EXTENSION_ELEMENTS => state.in_extensions = true,
+            DOCUMENTATION => state.in_documentation = true,
+            _ => {}
+        },
+        Event::Empty(bs) => {
+            match bs.local_name().as_ref() {
+                // Attach symbol to parent
+                bpmn_type @ (CANCEL_EVENT_DEFINITION
+                | COMPENSATE_EVENT_DEFINITION
+                | CONDITIONAL_EVENT_DEFINITION
+                | ERROR_EVENT_DEFINITION
+                | ESCALATION_EVENT_DEFINITION
+                | MESSAGE_EVENT_DEFINITION
+                | LINK_EVENT_DEFINITION
+                | SIGNAL_EVENT_DEFINITION
+                | TERMINATE_EVENT_DEFINITION
+                | TIMER_EVENT_DEFINITION) => {
+                    update_symbol(builder, bpmn_type)?;
+                    builder.add_message_ref(&collect_attributes(&bs));
+                }
+                MESSAGE | SIGNAL => builder.add_message(collect_attributes(&bs)),
+                ESCALATION => {
+                    builder.add_code_definition(ATTRIB_ESCALATION_CODE, collect_attributes(&bs))
+                }
+                ERROR => builder.add_code_definition(ATTRIB_ERROR_CODE, collect_attributes(&bs)),
+                bpmn_type @ SEQUENCE_FLOW => {
+                    builder.add_to_process(
+                        Bpmn::try_from((bpmn_type, collect_attributes(&bs)))?,
+                        None,
+                        None,
+                    )?;
+                }
+                bpmn_type @ (COMPLEX_GATEWAY
+                | STANDARD_LOOP_CHARACTERISTICS
+                | MULTI_INSTANCE_LOOP_CHARACTERISTICS) => builder.add_warning(
+                    ParseWarningKind::Unsupported,
+                    bpmn_type,
+                    collect_attributes(&bs),
+                )?,
+                bpmn_type @ (TEXT_ANNOTATION | ASSOCIATION | GROUP | MESSAGE_FLOW) => builder
+                    .add_warning(
+                        ParseWarningKind::Ignored,
+                        bpmn_type,
+                        collect_attributes(&bs),
+                    )?,
+                DATA_OBJECT => builder.add_data_object(collect_attributes(&bs)),
+                parameter @ (DATA_INPUT | DATA_OUTPUT) => {
+                    builder.add_parameter(parameter == DATA_INPUT, collect_attributes(&bs))
+                }
+                bpmn_type @ DATA_OBJECT_REFERENCE => builder.add_data_reference(
+                    DataKind::Object,
+                    bpmn_type,
+                    collect_attributes(&bs),
+                )?,
+                bpmn_type @ DATA_STORE_REFERENCE => builder.add_data_reference(
+                    DataKind::Store,
+                    bpmn_type,
+                    collect_attributes(&bs),
+                )?,
                 _ => {}
-            },
-            Ok(Event::Text(bt)) => {
-                builder.add_text(bt.decode().map_err(quick_xml::Error::from)?.into_owned());
             }
+        }
+        Event::End(be) => match be.local_name().as_ref() {
+            direction @ (OUTGOING | INCOMING) => builder.add_direction(direction),
+            START_EVENT
+            | END_EVENT
+            | BOUNDARY_EVENT
+            | INTERMEDIATE_CATCH_EVENT
+            | INTERMEDIATE_THROW_EVENT
+            | TASK
+            | SCRIPT_TASK
+            | USER_TASK
+            | SERVICE_TASK
+            | CALL_ACTIVITY
+            | RECEIVE_TASK
+            | SEND_TASK
+            | MANUAL_TASK
+            | BUSINESS_RULE_TASK
+            | EXCLUSIVE_GATEWAY
+            | PARALLEL_GATEWAY
+            | INCLUSIVE_GATEWAY
+            | EVENT_BASED_GATEWAY
+            | SEQUENCE_FLOW => builder.end()?,
+            DEFINITIONS | PROCESS | SUB_PROCESS | TRANSACTION => builder.end_process()?,
+            DATA_INPUT_ASSOCIATION | DATA_OUTPUT_ASSOCIATION => state.data_association = None,
+            SOURCE_REF | TARGET_REF => state.in_data_ref = false,
+            _ => {}
+        },
+        Event::Text(bt) if builder.in_direction() => {
+            builder.add_text(bt.decode().map_err(quick_xml::Error::from)?.into_owned());
+        }
 
-            // Ignore other XML events
-            _ => (),
+        // Ignore other XML events
+        _ => (),
+    }
+    Ok(())
+}
+
+// Counts the lines of the consumed content to give the line and column of a byte position
+struct LineCounter<R> {
+    inner: R,
+    consumed: u64,
+    // Byte position of every newline
+    newlines: Vec<u64>,
+}
+
+impl<R> LineCounter<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            consumed: 0,
+            newlines: Vec::new(),
         }
-        buf.clear();
     }
-    Ok(builder.into())
+
+    // Line and column starting at 1
+    fn line_column(&self, position: u64) -> (usize, usize) {
+        let line = self.newlines.partition_point(|newline| *newline < position);
+        let start = line
+            .checked_sub(1)
+            .map_or(0, |index| self.newlines[index] + 1);
+        (line + 1, (position - start) as usize + 1)
+    }
+}
+
+impl<R: BufRead> Read for LineCounter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+        buf[..amount].copy_from_slice(&available[..amount]);
+        self.consume(amount);
+        Ok(amount)
+    }
+}
+
+impl<R: BufRead> BufRead for LineCounter<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            let consumed = self.consumed;
+            self.newlines.extend(
+                buf[..amount.min(buf.len())]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, byte)| **byte == b'\n')
+                    .map(|(index, _)| consumed + index as u64),
+            );
+        }
+        self.consumed += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+// Attach symbol to parent event
+fn update_symbol(builder: &mut DataBuilder, bpmn_type: &[u8]) -> Result<(), Error> {
+    if !builder.update_symbol(bpmn_type) {
+        builder.add_warning(
+            ParseWarningKind::MultipleEventDefinitions,
+            bpmn_type,
+            HashMap::new(),
+        )?;
+    }
+    Ok(())
 }
 
 fn collect_attributes<'a>(bs: &'a quick_xml::events::BytesStart<'_>) -> HashMap<&'a [u8], String> {
     bs.attributes()
         .filter_map(Result::ok)
         .filter_map(|attribute| {
-            std::str::from_utf8(&attribute.value)
+            attribute
+                .unescape_value()
                 .ok()
                 .filter(|value| !value.is_empty())
-                .map(|value| (attribute.key.local_name().into_inner(), value.into()))
+                .map(|value| (attribute.key.local_name().into_inner(), value.into_owned()))
         })
         .collect::<HashMap<&'a [u8], String>>()
 }
@@ -127,8 +433,32 @@ mod tests {
     fn load_file() -> Result<(), Box<dyn std::error::Error>> {
         println!(
             "{:#?}",
-            read_bpmn(quick_xml::Reader::from_file("examples/example.bpmn")?)
+            read_bpmn(std::io::BufReader::new(std::fs::File::open(
+                "examples/example.bpmn"
+            )?))
         );
         Ok(())
     }
+
+    #[test]
+    fn skip_diagram_interchange() -> Result<(), Box<dyn std::error::Error>> {
+        // Elements in the diagram interchange are never read, so the task without id is no error
+        let diagram = read_bpmn(
+            r#"<definitions id="Definitions_1">
+                <process id="Process_1">
+                    <startEvent id="Start"><outgoing>Flow_1</outgoing></startEvent>
+                    <sequenceFlow id="Flow_1" sourceRef="Start" targetRef="End" />
+                    <endEvent id="End"><incoming>Flow_1</incoming></endEvent>
+                </process>
+                <bpmndi:BPMNDiagram id="BPMNDiagram_1">
+                    <bpmndi:BPMNPlane id="BPMNPlane_1" bpmnElement="Process_1">
+                        <task><incoming>Flow_1</incoming></task>
+                    </bpmndi:BPMNPlane>
+                </bpmndi:BPMNDiagram>
+            </definitions>"#
+                .as_bytes(),
+        )?;
+        assert_eq!(diagram.data().len(), 2);
+        Ok(())
+    }
 }
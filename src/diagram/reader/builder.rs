@@ -1,8 +1,14 @@
 use crate::{
+    api::{DataKind, DataReference},
     bpmn::{Event, *},
-    diagram::{Diagram, ProcessData},
+    diagram::{
+        DataAssociations, Diagram, IoMapping, Parameter, ParseWarning, ParseWarningKind,
+        ProcessData, Source,
+    },
     error::{BUILD_PROCESS_ERROR_MSG, Error},
+    process::options::ParseMode,
 };
+use std::collections::{BTreeMap, HashMap};
 
 //
 // data: [
@@ -27,29 +33,390 @@ pub(super) struct DataBuilder {
     data: Vec<ProcessData>,
     process_stack: Vec<ProcessData>,
     stack: Vec<Bpmn>,
+    warnings: Vec<ParseWarning>,
+    // Properties from the extensionElements of the element at the stack depth
+    properties: HashMap<usize, BTreeMap<String, String>>,
+    // Documentation text of the element at the stack depth
+    documentation: HashMap<usize, String>,
+    // Data object and data store references by BPMN id, with the id of the referenced data object
+    data_references: HashMap<String, (DataReference, Option<String>)>,
+    // Names of the data objects by BPMN id
+    data_objects: HashMap<String, String>,
+    // Referenced ids of the data input and output associations by activity BPMN id
+    associations: HashMap<String, (Vec<String>, Vec<String>)>,
+    // Input and output parameters of the element at the stack depth
+    io_mappings: HashMap<usize, IoMapping>,
+    // Camunda parameter being read. True for an input parameter, the name and the text.
+    parameter: Option<(bool, String, String)>,
+    // Names of the messages, signals and escalations by BPMN id
+    messages: HashMap<String, String>,
+    // Codes of the escalations by BPMN id
+    codes: HashMap<String, String>,
+    // Referenced message or signal id by BPMN id of the task or event
+    message_refs: HashMap<String, String>,
+    // Camunda form fields of the element at the stack depth
+    form_fields: HashMap<usize, Vec<FormField>>,
+    mode: ParseMode,
 }
 
 impl DataBuilder {
+    pub(super) fn new(mode: ParseMode) -> Self {
+        Self {
+            mode,
+            ..Default::default()
+        }
+    }
+
     pub(super) fn add(&mut self, bpmn: Bpmn) {
         self.stack.push(bpmn);
     }
 
     pub(super) fn add_new_process(&mut self, bpmn: Bpmn) {
-        self.process_stack.push(Default::default());
+        // Sub processes in a non executable process are not executable either
+        let non_executable = matches!(
+            bpmn,
+            Bpmn::Process {
+                executable: false,
+                ..
+            }
+        ) || self
+            .process_stack
+            .last()
+            .is_some_and(|process_data| process_data.non_executable);
+        self.process_stack.push(ProcessData {
+            non_executable,
+            ..Default::default()
+        });
         self.add(bpmn);
     }
 
-    pub(super) fn add_to_process(&mut self, bpmn: Bpmn) -> Result<(), Error> {
-        if let Some(process_data) = self.process_stack.last_mut() {
-            process_data.add(bpmn)?;
+    pub(super) fn add_to_process(
+        &mut self,
+        bpmn: Bpmn,
+        properties: Option<BTreeMap<String, String>>,
+        documentation: Option<String>,
+    ) -> Result<Option<usize>, Error> {
+        let Some(process_data) = self.process_stack.last_mut() else {
+            return Ok(None);
+        };
+        let index = process_data.add(bpmn)?;
+        if let Some(properties) = properties {
+            process_data.properties.insert(index, properties);
+        }
+        if let Some(documentation) = documentation {
+            process_data.documentation.insert(index, documentation);
+        }
+        Ok(Some(index))
+    }
+
+    // Parameter of zeebe:ioMapping with source and target attributes, or a dataInput or dataOutput of
+    // an ioSpecification that maps the variable with the same name
+    pub(super) fn add_parameter(&mut self, input: bool, attributes: HashMap<&[u8], String>) {
+        let (target, source) = match (attributes.get(ATTRIB_TARGET), attributes.get(ATTRIB_SOURCE))
+        {
+            (Some(target), Some(source)) => (target.clone(), Source::parse(source)),
+            _ => match attributes.get(ATTRIB_NAME) {
+                Some(name) => (name.clone(), Source::Variable(name.clone())),
+                None => return,
+            },
+        };
+        let io_mapping = self.io_mappings.entry(self.stack.len()).or_default();
+        let parameters = if input {
+            &mut io_mapping.inputs
+        } else {
+            &mut io_mapping.outputs
+        };
+        parameters.push(Parameter { target, source });
+    }
+
+    // Camunda inputParameter or outputParameter with the name attribute and the value as text
+    pub(super) fn start_parameter(&mut self, input: bool, mut attributes: HashMap<&[u8], String>) {
+        if let Some(name) = attributes.remove(ATTRIB_NAME) {
+            self.parameter = Some((input, name, String::new()));
+        }
+    }
+
+    pub(super) fn add_parameter_text(&mut self, text: &str) -> bool {
+        if let Some((_, _, value)) = &mut self.parameter {
+            value.push_str(text);
+            return true;
+        }
+        false
+    }
+
+    pub(super) fn end_parameter(&mut self) {
+        if let Some((input, target, value)) = self.parameter.take() {
+            let io_mapping = self.io_mappings.entry(self.stack.len()).or_default();
+            let parameters = if input {
+                &mut io_mapping.inputs
+            } else {
+                &mut io_mapping.outputs
+            };
+            parameters.push(Parameter {
+                target,
+                source: Source::parse(&value),
+            });
+        }
+    }
+
+    // Camunda formField with the id, label, type and defaultValue attributes
+    pub(super) fn start_form_field(&mut self, attributes: HashMap<&[u8], String>) {
+        if let Some(field) = FormField::new(attributes) {
+            self.form_fields
+                .entry(self.stack.len())
+                .or_default()
+                .push(field);
+        }
+    }
+
+    // Camunda value of an enum field or constraint of the last form field
+    pub(super) fn add_form_field_item(
+        &mut self,
+        item: &[u8],
+        mut attributes: HashMap<&[u8], String>,
+    ) {
+        let Some(field) = self
+            .form_fields
+            .get_mut(&self.stack.len())
+            .and_then(|fields| fields.last_mut())
+        else {
+            return;
+        };
+        match (item, &mut field.field_type) {
+            (FORM_VALUE, FormFieldType::Enum(values)) => {
+                if let Some(id) = attributes.remove(ATTRIB_ID) {
+                    let name = attributes.remove(ATTRIB_NAME).unwrap_or_else(|| id.clone());
+                    values.push((id, name));
+                }
+            }
+            (FORM_CONSTRAINT, _) => {
+                if let Some(name) = attributes.remove(ATTRIB_NAME) {
+                    field.constraints.push(FormConstraint {
+                        name,
+                        config: attributes.remove(ATTRIB_CONFIG),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Text can be split in several events, for example around entities
+    pub(super) fn add_documentation(&mut self, text: &str) {
+        self.documentation
+            .entry(self.stack.len())
+            .or_default()
+            .push_str(text);
+    }
+
+    // A vendor element inside extensionElements. Elements with a name or key and a value attribute,
+    // like camunda:property and zeebe:header, add the pair. Other elements add every attribute
+    // prefixed with the element name, like taskDefinition.type for zeebe:taskDefinition.
+    pub(super) fn add_property(
+        &mut self,
+        element: &[u8],
+        attributes: HashMap<&[u8], String>,
+    ) -> Result<(), Error> {
+        if attributes.is_empty() {
+            return Ok(());
+        }
+
+        let properties = self.properties.entry(self.stack.len()).or_default();
+        if let Some(key) = attributes
+            .get(ATTRIB_NAME)
+            .or_else(|| attributes.get(ATTRIB_KEY))
+            && let Some(value) = attributes.get(ATTRIB_VALUE)
+        {
+            properties.insert(key.clone(), value.clone());
+            return Ok(());
+        }
+
+        let element = std::str::from_utf8(element)?;
+        for (attribute, value) in attributes {
+            properties.insert(
+                format!("{element}.{}", std::str::from_utf8(attribute)?),
+                value,
+            );
+        }
+        Ok(())
+    }
+
+    // Element is skipped or not honored. Markers like loop characteristics and event definitions
+    // belong to the parent element.
+    pub(super) fn add_warning(
+        &mut self,
+        kind: ParseWarningKind,
+        bpmn_type: &[u8],
+        mut attributes: HashMap<&[u8], String>,
+    ) -> Result<(), Error> {
+        let element = std::str::from_utf8(bpmn_type)?.to_string();
+        let (id, name) = match self.stack.last() {
+            Some(parent @ (Bpmn::Activity(_) | Bpmn::Event(_)))
+                if matches!(
+                    kind,
+                    ParseWarningKind::EventDefinitionContent
+                        | ParseWarningKind::MultipleEventDefinitions
+                ) || matches!(
+                    bpmn_type,
+                    STANDARD_LOOP_CHARACTERISTICS | MULTI_INSTANCE_LOOP_CHARACTERISTICS
+                ) =>
+            {
+                (parent.id().map(Into::into), parent.name().map(Into::into))
+            }
+            _ => (attributes.remove(ATTRIB_ID), attributes.remove(ATTRIB_NAME)),
+        };
+        if kind == ParseWarningKind::Unsupported && self.mode == ParseMode::Strict {
+            return Err(Error::NotSupported(
+                match name.as_deref().or(id.as_deref()) {
+                    Some(name_or_id) => format!("{name_or_id}: {element}"),
+                    None => element,
+                },
+            ));
         }
+        self.warnings.push(ParseWarning {
+            kind,
+            element,
+            id,
+            name,
+        });
         Ok(())
     }
 
-    pub(super) fn update_symbol(&mut self, bpmn_type: &[u8]) {
+    // Id of the innermost element being read
+    pub(super) fn add_data_object(&mut self, mut attributes: HashMap<&[u8], String>) {
+        if let Some(id) = attributes.remove(ATTRIB_ID)
+            && let Some(name) = attributes.remove(ATTRIB_NAME)
+        {
+            self.data_objects.insert(id, name);
+        }
+    }
+
+    pub(super) fn add_data_reference(
+        &mut self,
+        kind: DataKind,
+        bpmn_type: &[u8],
+        mut attributes: HashMap<&[u8], String>,
+    ) -> Result<(), Error> {
+        let id = attributes
+            .remove(ATTRIB_ID)
+            .ok_or_else(|| Error::MissingId(String::from_utf8_lossy(bpmn_type).into()))?;
+        let reference = DataReference {
+            id: id.clone(),
+            name: attributes.remove(ATTRIB_NAME),
+            kind,
+        };
+        self.data_references
+            .insert(id, (reference, attributes.remove(ATTRIB_DATA_OBJECT_REF)));
+        Ok(())
+    }
+
+    // Data object or data store referenced by a data association of the activity on the stack
+    pub(super) fn add_data_association(&mut self, input: bool, reference: &str) {
+        let Some(id) = self.current_id() else {
+            return;
+        };
+        let (inputs, outputs) = self.associations.entry(id.into()).or_default();
+        if input {
+            inputs.push(reference.trim().into());
+        } else {
+            outputs.push(reference.trim().into());
+        }
+    }
+
+    // Error or escalation definition with the code in the code attribute. Events that reference the same
+    // definition have the same code.
+    pub(super) fn add_code_definition(
+        &mut self,
+        code_attribute: &[u8],
+        mut attributes: HashMap<&[u8], String>,
+    ) {
+        let code = attributes.remove(code_attribute);
+        if let Some(id) = attributes.get(ATTRIB_ID) {
+            self.codes
+                .insert(id.clone(), code.unwrap_or_else(|| id.clone()));
+        }
+        self.add_message(attributes);
+    }
+
+    // Message, signal, error or escalation definition. The name is used when it is referenced.
+    pub(super) fn add_message(&mut self, mut attributes: HashMap<&[u8], String>) {
+        if let Some(id) = attributes.remove(ATTRIB_ID) {
+            let name = attributes.remove(ATTRIB_NAME).unwrap_or_else(|| id.clone());
+            self.messages.insert(id, name);
+        }
+    }
+
+    // Message, signal, error or escalation referenced by the task or event on the stack
+    pub(super) fn add_message_ref(&mut self, attributes: &HashMap<&[u8], String>) {
+        if let Some(reference) = attributes
+            .get(ATTRIB_MESSAGE_REF)
+            .or_else(|| attributes.get(ATTRIB_SIGNAL_REF))
+            .or_else(|| attributes.get(ATTRIB_ESCALATION_REF))
+            .or_else(|| attributes.get(ATTRIB_ERROR_REF))
+            && let Some(id) = self.current_id()
+        {
+            self.message_refs.insert(id.into(), reference.clone());
+        }
+    }
+
+    // Resolve the message names when every message, signal, error and escalation has been read.
+    // An unknown reference is used as name.
+    fn resolve_messages(&mut self) {
+        for process_data in &mut self.data {
+            for (index, bpmn) in process_data.data.iter_mut().enumerate() {
+                if let Some(reference) = bpmn.id().and_then(|id| self.message_refs.get(id)) {
+                    process_data.messages.insert(
+                        index,
+                        self.messages.get(reference).unwrap_or(reference).clone(),
+                    );
+                    if let Bpmn::Event(event) = bpmn {
+                        event.code = self.codes.get(reference).cloned();
+                    }
+                }
+            }
+        }
+    }
+
+    // Resolve the data associations when every data object and reference has been read
+    fn resolve_data_associations(&mut self) {
+        let resolve = |id: &String| {
+            let (reference, data_object) = self.data_references.get(id)?;
+            let mut reference = reference.clone();
+            if reference.name.is_none() {
+                reference.name = data_object
+                    .as_ref()
+                    .and_then(|data_object| self.data_objects.get(data_object))
+                    .cloned();
+            }
+            Some(reference)
+        };
+        for process_data in &mut self.data {
+            for (index, bpmn) in process_data.data.iter().enumerate() {
+                if let Bpmn::Activity(Activity { id, .. }) = bpmn
+                    && let Some((inputs, outputs)) = self.associations.get(id.bpmn())
+                {
+                    process_data.data_associations.insert(
+                        index,
+                        DataAssociations {
+                            inputs: inputs.iter().filter_map(resolve).collect(),
+                            outputs: outputs.iter().filter_map(resolve).collect(),
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    pub(super) fn current_id(&self) -> Option<&str> {
+        self.stack.iter().rev().find_map(Bpmn::id)
+    }
+
+    // Returns false if the event already had a symbol
+    pub(super) fn update_symbol(&mut self, bpmn_type: &[u8]) -> bool {
         if let Some(Bpmn::Event(Event { symbol, .. })) = self.stack.last_mut() {
-            *symbol = bpmn_type.try_into().ok();
+            return std::mem::replace(symbol, bpmn_type.try_into().ok()).is_none();
         }
+        true
     }
 
     pub(super) fn add_direction(&mut self, direction: &[u8]) {
@@ -63,6 +430,11 @@ impl DataBuilder {
         }
     }
 
+    // Text is only used inside incoming and outgoing
+    pub(super) fn in_direction(&self) -> bool {
+        matches!(self.stack.last(), Some(Bpmn::Direction(_)))
+    }
+
     pub(super) fn add_text(&mut self, value: String) {
         if let Some(Bpmn::Direction(text)) = self.stack.last_mut() {
             text.replace(value);
@@ -70,14 +442,100 @@ impl DataBuilder {
     }
 
     pub(super) fn end(&mut self) -> Result<(), Error> {
-        if let Some(bpmn) = self.stack.pop() {
-            check_unsupported(&bpmn)?;
-            self.add_to_process(bpmn)?;
+        let properties = self.properties.remove(&self.stack.len());
+        let documentation = self.documentation.remove(&self.stack.len());
+        let io_mapping = self.io_mappings.remove(&self.stack.len());
+        let form_fields = self.form_fields.remove(&self.stack.len());
+        if let Some(mut bpmn) = self.stack.pop() {
+            // Zeebe job type takes precedence over the Camunda 7 topic
+            if let Bpmn::Activity(Activity { task_type, .. }) = &mut bpmn
+                && let Some(value) = properties
+                    .as_ref()
+                    .and_then(|properties| properties.get(PROPERTY_TASK_TYPE))
+            {
+                task_type.replace(value.clone());
+            }
+            // Zeebe assignment definition takes precedence over the Camunda 7 attributes
+            if let Bpmn::Activity(Activity { assignment, .. }) = &mut bpmn
+                && let Some(properties) = &properties
+                && let Some(value) = Assignment::new(
+                    properties.get(PROPERTY_ASSIGNEE).cloned(),
+                    properties.get(PROPERTY_CANDIDATE_USERS).map(String::as_str),
+                    properties
+                        .get(PROPERTY_CANDIDATE_GROUPS)
+                        .map(String::as_str),
+                )
+            {
+                assignment.replace(value);
+            }
+            if let Bpmn::Activity(Activity { form, .. }) = &mut bpmn {
+                if let Some(fields) = form_fields {
+                    form.get_or_insert_default().fields = fields;
+                }
+                // Zeebe form definition takes precedence over the Camunda 7 form key
+                if let Some(key) = properties.as_ref().and_then(|properties| {
+                    properties
+                        .get(PROPERTY_FORM_KEY)
+                        .or_else(|| properties.get(PROPERTY_FORM_ID))
+                }) {
+                    form.get_or_insert_default().key = Some(key.clone());
+                }
+            }
+            if let Err(error) = check_unsupported(&bpmn) {
+                if self.mode != ParseMode::Lenient {
+                    return Err(error);
+                }
+                // Condition is skipped and the flow is used like any other flow
+                self.warnings.push(ParseWarning {
+                    kind: ParseWarningKind::Unsupported,
+                    element: "conditionExpression".into(),
+                    id: bpmn.id().map(Into::into),
+                    name: bpmn.name().map(Into::into),
+                });
+            }
+            if let Some(index) = self.add_to_process(bpmn, properties, documentation)?
+                && let Some(io_mapping) = io_mapping
+                && let Some(process_data) = self.process_stack.last_mut()
+            {
+                process_data.io_mappings.insert(index, io_mapping);
+            }
         }
         Ok(())
     }
 
+    // Modelers mark a single process as not executable by default.
+    // Non executable processes are only skipped when another process is executable.
+    fn executable_fallback(&mut self) {
+        let Some(definitions) = self.data.last_mut() else {
+            return;
+        };
+        if definitions.data.iter().any(|bpmn| {
+            matches!(
+                bpmn,
+                Bpmn::Process {
+                    executable: true,
+                    ..
+                }
+            )
+        }) {
+            return;
+        }
+        for bpmn in &mut definitions.data {
+            if let Bpmn::Process { executable, .. } = bpmn {
+                *executable = true;
+            }
+        }
+        for process_data in &mut self.data {
+            process_data.non_executable = false;
+        }
+    }
+
     pub(super) fn end_process(&mut self) -> Result<(), Error> {
+        // Properties and parameters of processes are not used
+        self.properties.remove(&self.stack.len());
+        self.io_mappings.remove(&self.stack.len());
+        self.form_fields.remove(&self.stack.len());
+        let documentation = self.documentation.remove(&self.stack.len());
         let Some((mut bpmn, mut process_data)) = self.stack.pop().zip(self.process_stack.pop())
         else {
             return Err(Error::Builder(BUILD_PROCESS_ERROR_MSG.into()));
@@ -88,7 +546,12 @@ impl DataBuilder {
         if let Some(parent_process_data) = self.process_stack.last_mut() {
             // Process or sub process use index to point to data.
             bpmn.update_data_index(self.data.len());
-            parent_process_data.add(bpmn)?;
+            let index = parent_process_data.add(bpmn)?;
+            if let Some(documentation) = documentation {
+                parent_process_data
+                    .documentation
+                    .insert(index, documentation);
+            }
         }
 
         process_data.finalize();
@@ -98,8 +561,13 @@ impl DataBuilder {
 }
 
 impl From<DataBuilder> for Diagram {
-    fn from(builder: DataBuilder) -> Self {
-        Diagram::new(builder.data)
+    fn from(mut builder: DataBuilder) -> Self {
+        builder.executable_fallback();
+        builder.resolve_data_associations();
+        builder.resolve_messages();
+        let mut diagram = Diagram::new(builder.data);
+        diagram.warnings = builder.warnings;
+        diagram
     }
 }
 
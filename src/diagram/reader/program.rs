@@ -0,0 +1,260 @@
+use super::builder::DataBuilder;
+use crate::{bpmn::*, diagram::Diagram, error::Error, process::options::ParseMode};
+use std::collections::HashMap;
+
+/// Build a diagram in code instead of a BPMN file. Elements are connected in the order they are added.
+/// Continue from a named flow of a gateway with `flow`, from an element with `from` and connect to an
+/// element that is already added with `goto`. Create the process with `Process::from_builder`.
+///
+/// ```
+/// use snurr::{DiagramBuilder, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let diagram = DiagramBuilder::start()
+///         .task("Count 1")
+///         .exclusive("equal to 3", ["YES", "NO"])
+///         .flow("YES")
+///         .end()
+///         .flow("NO")
+///         .goto("Count 1");
+///
+///     let bpmn = Process::<u32>::from_builder(diagram)?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///     assert_eq!(bpmn.run(0)?.data, 3);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct DiagramBuilder {
+    elements: Vec<Element>,
+    flows: Vec<Flow>,
+    cursor: Option<Cursor>,
+    // First error. Returned when the diagram is built.
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct Element {
+    bpmn_type: &'static [u8],
+    name: Option<String>,
+}
+
+#[derive(Debug)]
+struct Flow {
+    name: Option<String>,
+    source: usize,
+    // None for a named gateway flow that is not connected yet
+    target: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cursor {
+    Element(usize),
+    Flow(usize),
+}
+
+impl DiagramBuilder {
+    /// New diagram with a start event
+    pub fn start() -> Self {
+        let mut builder = Self::default();
+        builder.add(START_EVENT, None);
+        builder
+    }
+
+    /// Add a task
+    pub fn task(mut self, name: impl Into<String>) -> Self {
+        self.add(TASK, Some(name.into()));
+        self
+    }
+
+    /// Add an exclusive gateway with the names of its outgoing flows
+    pub fn exclusive<I, S>(self, name: impl Into<String>, flows: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.gateway(EXCLUSIVE_GATEWAY, name.into(), flows)
+    }
+
+    /// Add an inclusive gateway with the names of its outgoing flows
+    pub fn inclusive<I, S>(self, name: impl Into<String>, flows: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.gateway(INCLUSIVE_GATEWAY, name.into(), flows)
+    }
+
+    /// Add a parallel gateway. Add the branches with `from` and join them with `goto`.
+    pub fn parallel(mut self, name: impl Into<String>) -> Self {
+        self.add(PARALLEL_GATEWAY, Some(name.into()));
+        self
+    }
+
+    /// Add an end event
+    pub fn end(mut self) -> Self {
+        self.add(END_EVENT, None);
+        self.cursor = None;
+        self
+    }
+
+    /// Add an end event with a name
+    pub fn end_named(mut self, name: impl Into<String>) -> Self {
+        self.add(END_EVENT, Some(name.into()));
+        self.cursor = None;
+        self
+    }
+
+    /// Continue from the named flow of the last gateway that has an unconnected flow with the name
+    pub fn flow(mut self, name: &str) -> Self {
+        match self
+            .flows
+            .iter()
+            .rposition(|flow| flow.target.is_none() && flow.name.as_deref() == Some(name))
+        {
+            Some(index) => self.cursor = Some(Cursor::Flow(index)),
+            None => self.fail(format!("no unconnected flow {name}")),
+        }
+        self
+    }
+
+    /// Continue from the element with the name
+    pub fn from(mut self, name: &str) -> Self {
+        match self.find(name) {
+            Some(index) => self.cursor = Some(Cursor::Element(index)),
+            None => self.fail(format!("no element {name}")),
+        }
+        self
+    }
+
+    /// Connect to the element with the name, that is already added. Used for loops and joins.
+    pub fn goto(mut self, name: &str) -> Self {
+        match self.find(name) {
+            Some(index) => self.connect(index),
+            None => self.fail(format!("no element {name}")),
+        }
+        self.cursor = None;
+        self
+    }
+
+    fn gateway<I, S>(mut self, bpmn_type: &'static [u8], name: String, flows: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let index = self.add(bpmn_type, Some(name));
+        self.flows.extend(flows.into_iter().map(|name| Flow {
+            name: Some(name.into()),
+            source: index,
+            target: None,
+        }));
+        self
+    }
+
+    fn add(&mut self, bpmn_type: &'static [u8], name: Option<String>) -> usize {
+        let index = self.elements.len();
+        if bpmn_type != START_EVENT {
+            if self.cursor.is_none() {
+                let name = name.as_deref().unwrap_or("element");
+                self.fail(format!("{name} has no incoming flow"));
+            }
+            self.connect(index);
+        }
+        self.elements.push(Element { bpmn_type, name });
+        self.cursor = Some(Cursor::Element(index));
+        index
+    }
+
+    fn connect(&mut self, target: usize) {
+        match self.cursor {
+            Some(Cursor::Element(source)) => self.flows.push(Flow {
+                name: None,
+                source,
+                target: Some(target),
+            }),
+            Some(Cursor::Flow(index)) => self.flows[index].target = Some(target),
+            None => {}
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<usize> {
+        self.elements
+            .iter()
+            .position(|element| element.name.as_deref() == Some(name))
+    }
+
+    fn fail(&mut self, message: String) {
+        self.error.get_or_insert(message);
+    }
+
+    // The diagram is read like a BPMN file with one process
+    pub(crate) fn build(self) -> Result<Diagram, Error> {
+        if let Some(error) = self.error {
+            return Err(Error::Builder(error));
+        }
+        if let Some(flow) = self.flows.iter().find(|flow| flow.target.is_none()) {
+            return Err(Error::Builder(format!(
+                "flow {} of {} has no target",
+                flow.name.as_deref().unwrap_or_default(),
+                self.elements[flow.source]
+                    .name
+                    .as_deref()
+                    .unwrap_or_default()
+            )));
+        }
+
+        let element_id = |index: usize| format!("Element_{index}");
+        let flow_id = |index: usize| format!("Flow_{index}");
+        let mut builder = DataBuilder::new(ParseMode::default());
+        builder.add_new_process(Bpmn::try_from((
+            DEFINITIONS,
+            HashMap::from([(ATTRIB_ID, "Definitions_1".to_string())]),
+        ))?);
+        builder.add_new_process(Bpmn::try_from((
+            PROCESS,
+            HashMap::from([(ATTRIB_ID, "Process_1".to_string())]),
+        ))?);
+        for (index, element) in self.elements.iter().enumerate() {
+            let mut attributes = HashMap::from([(ATTRIB_ID, element_id(index))]);
+            if let Some(name) = &element.name {
+                attributes.insert(ATTRIB_NAME, name.clone());
+            }
+            builder.add(Bpmn::try_from((element.bpmn_type, attributes))?);
+            for (flow_index, flow) in self.flows.iter().enumerate() {
+                if flow.target == Some(index) {
+                    builder.add(Bpmn::Direction(Some(flow_id(flow_index))));
+                    builder.add_direction(INCOMING);
+                }
+                if flow.source == index {
+                    builder.add(Bpmn::Direction(Some(flow_id(flow_index))));
+                    builder.add_direction(OUTGOING);
+                }
+            }
+            builder.end()?;
+        }
+        for (index, flow) in self.flows.iter().enumerate() {
+            let mut attributes = HashMap::from([
+                (ATTRIB_ID, flow_id(index)),
+                (
+                    ATTRIB_TARGET_REF,
+                    element_id(flow.target.unwrap_or_default()),
+                ),
+            ]);
+            if let Some(name) = &flow.name {
+                attributes.insert(ATTRIB_NAME, name.clone());
+            }
+            builder.add_to_process(Bpmn::try_from((SEQUENCE_FLOW, attributes))?, None, None)?;
+        }
+        builder.end_process()?;
+        builder.end_process()?;
+        Ok(builder.into())
+    }
+}
@@ -0,0 +1,365 @@
+use super::{Diagram, ParseWarningKind, ProcessData};
+use crate::bpmn::{Bpmn, Event, EventType, Gateway, GatewayType, Symbol};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
+
+/// Diagnostic severity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The diagram might not behave as expected
+    Warning,
+    /// The diagram will fail at runtime if this element is used
+    Error,
+}
+
+/// Diagnostic kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Element cannot be reached from any start event
+    Unreachable,
+    /// Exclusive or inclusive gateway without a default flow
+    MissingDefault,
+    /// Event based gateway with less than two outgoing flows
+    EventBasedSingleOutput,
+    /// Element, or marker on the element, that is not supported and is skipped
+    Unsupported,
+    /// Process without a start event
+    MissingStartEvent,
+    /// Parallel join that can never receive a token on all incoming sequence flows
+    JoinDeadlock,
+    /// Link throw event without a link catch event with the same name in the same process
+    UnpairedLink,
+}
+
+/// Structured diagnostic from validating a diagram
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The BPMN ID of the element (if it has one)
+    pub id: Option<String>,
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(severity: Severity, kind: DiagnosticKind, bpmn: &Bpmn, message: String) -> Self {
+        Self {
+            severity,
+            kind,
+            id: bpmn.id().map(Into::into),
+            name: bpmn.name().map(Into::into),
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} {:?}", self.severity, self.kind)?;
+        if let Some(name_or_id) = self.name.as_deref().or(self.id.as_deref()) {
+            write!(f, r#" "{name_or_id}""#)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl Diagram {
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let titles = self.process_titles();
+
+        // Skip the definitions that is always last
+        let processes = &self.data[..self.data.len().saturating_sub(1)];
+        for (index, process) in processes.iter().enumerate() {
+            let title = titles.get(&index).copied().unwrap_or_default();
+            validate_process(process, title, &mut diagnostics);
+            join_deadlocks(process, &mut diagnostics);
+            unpaired_links(process, title, processes, &titles, &mut diagnostics);
+        }
+
+        diagnostics.extend(
+            self.warnings
+                .iter()
+                .filter(|warning| warning.kind == ParseWarningKind::Unsupported)
+                .map(|unsupported| Diagnostic {
+                    severity: Severity::Error,
+                    kind: DiagnosticKind::Unsupported,
+                    id: unsupported.id.clone(),
+                    name: unsupported.name.clone(),
+                    message: format!("{} is not supported and is skipped", unsupported.element),
+                }),
+        );
+        diagnostics
+    }
+
+    // Parallel joins fed by mutually exclusive branches. These are reported as build errors.
+    pub(crate) fn deadlocks(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for process in self.data.iter().rev().skip(1) {
+            join_deadlocks(process, &mut diagnostics);
+        }
+        diagnostics
+    }
+}
+
+fn validate_process(process: &ProcessData, title: &str, diagnostics: &mut Vec<Diagnostic>) {
+    let reachable =
+        process.reachable_from(process.iter().enumerate().filter_map(|(index, bpmn)| {
+            matches!(
+                bpmn,
+                Bpmn::Event(Event {
+                    event_type: EventType::Start,
+                    ..
+                })
+            )
+            .then_some(index)
+        }));
+    if reachable.is_empty() && process.iter().any(|bpmn| bpmn.element_type().is_some()) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            kind: DiagnosticKind::MissingStartEvent,
+            id: None,
+            name: Some(title.into()),
+            message: "process has no start event".into(),
+        });
+        return;
+    }
+
+    for (index, bpmn) in process.iter().enumerate() {
+        if matches!(bpmn, Bpmn::Event(_) | Bpmn::Activity(_) | Bpmn::Gateway(_))
+            && !reachable.contains(&index)
+        {
+            diagnostics.push(Diagnostic::new(
+                Severity::Warning,
+                DiagnosticKind::Unreachable,
+                bpmn,
+                format!(r#"cannot be reached from a start event in "{title}""#),
+            ));
+        }
+
+        if let Bpmn::Gateway(Gateway {
+            gateway_type,
+            default,
+            outputs,
+            ..
+        }) = bpmn
+        {
+            match gateway_type {
+                GatewayType::Exclusive | GatewayType::Inclusive
+                    if outputs.len() > 1 && default.is_none() =>
+                {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Warning,
+                        DiagnosticKind::MissingDefault,
+                        bpmn,
+                        format!("{gateway_type} gateway has no default flow"),
+                    ));
+                }
+                GatewayType::EventBased if outputs.len() < 2 => {
+                    diagnostics.push(Diagnostic::new(
+                        Severity::Error,
+                        DiagnosticKind::EventBasedSingleOutput,
+                        bpmn,
+                        "event based gateway must have at least two outgoing sequence flows".into(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Links only connect events of the same process. Modelers that split a large diagram over pages need the pages
+// in the same process, so point out the process of a catch event that is somewhere else.
+fn unpaired_links(
+    process: &ProcessData,
+    title: &str,
+    processes: &[ProcessData],
+    titles: &HashMap<usize, &str>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for bpmn in process.iter() {
+        let Bpmn::Event(Event {
+            event_type: EventType::IntermediateThrow,
+            symbol: Some(Symbol::Link),
+            name: Some(name),
+            ..
+        }) = bpmn
+        else {
+            continue;
+        };
+        if process.catch_event_link(name).is_ok() {
+            continue;
+        }
+
+        let mut message = format!(r#"link has no catch event with the same name in "{title}""#);
+        if let Some(other) = processes
+            .iter()
+            .position(|other| other.catch_event_link(name).is_ok())
+        {
+            message.push_str(&format!(
+                r#". The catch event is in "{}", links only connect events of the same process"#,
+                titles.get(&other).copied().unwrap_or_default()
+            ));
+        }
+        diagnostics.push(Diagnostic::new(
+            Severity::Error,
+            DiagnosticKind::UnpairedLink,
+            bpmn,
+            message,
+        ));
+    }
+}
+
+// Where the token on an incoming sequence flow of a join can come from
+enum Origin {
+    // Token can come from a start event or a forking gateway
+    Concurrent,
+    // Token only comes from outgoing flows of a single exclusive or event based gateway
+    Choice(usize, HashSet<usize>),
+}
+
+fn join_deadlocks(process: &ProcessData, diagnostics: &mut Vec<Diagnostic>) {
+    // Sequence flow index to source element index and element index to incoming sequence flows
+    let mut sources: HashMap<usize, usize> = HashMap::new();
+    let mut incoming: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (index, bpmn) in process.iter().enumerate() {
+        let outputs = match bpmn {
+            Bpmn::Activity(activity) => &activity.outputs,
+            Bpmn::Event(Event { outputs, .. }) | Bpmn::Gateway(Gateway { outputs, .. }) => outputs,
+            Bpmn::SequenceFlow { target_ref, .. } => {
+                incoming.entry(*target_ref.local()).or_default().push(index);
+                continue;
+            }
+            _ => continue,
+        };
+        for flow in outputs.iter() {
+            sources.insert(*flow, index);
+        }
+    }
+
+    for bpmn in process.iter() {
+        let Bpmn::Gateway(Gateway {
+            gateway_type: GatewayType::Parallel,
+            id,
+            ..
+        }) = bpmn
+        else {
+            continue;
+        };
+        let Some(flows) = incoming.get(id.local()).filter(|flows| flows.len() > 1) else {
+            continue;
+        };
+
+        let origins: Vec<_> = flows
+            .iter()
+            .map(|flow| origin(process, &sources, &incoming, *flow))
+            .collect();
+
+        let conflict = origins.iter().enumerate().find_map(|(index, first)| {
+            origins[index + 1..]
+                .iter()
+                .find_map(|second| match (first, second) {
+                    (Origin::Choice(a, a_flows), Origin::Choice(b, b_flows))
+                        if a == b && a_flows.is_disjoint(b_flows) =>
+                    {
+                        process.get(*a)
+                    }
+                    _ => None,
+                })
+        });
+
+        if let Some(gateway) = conflict {
+            diagnostics.push(Diagnostic::new(
+                Severity::Error,
+                DiagnosticKind::JoinDeadlock,
+                bpmn,
+                format!(
+                    r#"parallel join can never receive enough tokens. Incoming flows are mutually exclusive branches of "{}""#,
+                    gateway.name_or_id().unwrap_or_default()
+                ),
+            ));
+        }
+    }
+}
+
+// Walk backwards from a sequence flow until a forking gateway, exclusive choice or start event is found.
+fn origin(
+    process: &ProcessData,
+    sources: &HashMap<usize, usize>,
+    incoming: &HashMap<usize, Vec<usize>>,
+    flow: usize,
+) -> Origin {
+    let mut choice: Option<(usize, HashSet<usize>)> = None;
+    let mut visited = HashSet::new();
+    let mut stack = vec![flow];
+    while let Some(flow) = stack.pop() {
+        let Some(source) = sources.get(&flow) else {
+            continue;
+        };
+        if !visited.insert(flow) {
+            continue;
+        }
+
+        let element = match process.get(*source) {
+            Some(Bpmn::Gateway(Gateway {
+                gateway_type: GatewayType::Exclusive | GatewayType::EventBased,
+                outputs,
+                ..
+            })) if outputs.len() > 1 => {
+                match &mut choice {
+                    Some((gateway, flows)) if gateway == source => {
+                        flows.insert(flow);
+                    }
+                    Some(_) => return Origin::Concurrent,
+                    None => choice = Some((*source, HashSet::from([flow]))),
+                }
+                continue;
+            }
+            Some(Bpmn::Gateway(Gateway { outputs, .. })) if outputs.len() > 1 => {
+                return Origin::Concurrent;
+            }
+            Some(Bpmn::Event(Event {
+                event_type: EventType::Start,
+                ..
+            })) => return Origin::Concurrent,
+            Some(Bpmn::Event(Event {
+                attached_to_ref: Some(attached_to_ref),
+                ..
+            })) => *attached_to_ref.local(),
+            _ => *source,
+        };
+
+        // Link catch events continue from the throw events with the same name
+        if let Some(Bpmn::Event(Event {
+            event_type: EventType::IntermediateCatch,
+            symbol: Some(Symbol::Link),
+            name: Some(link),
+            ..
+        })) = process.get(element)
+        {
+            for (index, bpmn) in process.iter().enumerate() {
+                if let Bpmn::Event(Event {
+                    event_type: EventType::IntermediateThrow,
+                    symbol: Some(Symbol::Link),
+                    name: Some(name),
+                    ..
+                }) = bpmn
+                    && name == link
+                {
+                    stack.extend(incoming.get(&index).into_iter().flatten());
+                }
+            }
+        }
+        stack.extend(incoming.get(&element).into_iter().flatten());
+    }
+
+    match choice {
+        Some((gateway, flows)) => Origin::Choice(gateway, flows),
+        None => Origin::Concurrent,
+    }
+}
@@ -15,6 +15,9 @@ pub enum Error {
     #[error("Missing implementations {0}")]
     MissingImplementations(String),
 
+    #[error("Unused implementations {0}")]
+    UnusedImplementations(String),
+
     #[error("{0} has no default flow")]
     MissingDefault(String),
 
@@ -33,12 +36,21 @@ pub enum Error {
     #[error("type {0} not implemented")]
     TypeNotImplemented(String),
 
+    #[error("unknown symbol {0}")]
+    UnknownSymbol(String),
+
     #[error("could not find {0} boundary symbol attached to {1}")]
     MissingBoundary(String, String),
 
     #[error("{0} could not find {1}")]
     MissingIntermediateEvent(String, String),
 
+    #[error("{0} used sequence flow {1} multiple times")]
+    DuplicateFlow(String, String),
+
+    #[error("transition from {0} to {1} was vetoed")]
+    TransitionVetoed(String, String),
+
     #[error("missing intermediate throw event name on {0}")]
     MissingIntermediateThrowEventName(String),
 
@@ -51,12 +63,58 @@ pub enum Error {
     #[error("missing start event")]
     MissingStartEvent,
 
+    #[error("missing start event with name {0}")]
+    MissingStartEventName(String),
+
     #[error("couldn't extract process result")]
     NoProcessResult,
 
+    #[error("{0} is disabled and has no fallback flow")]
+    Disabled(String),
+
+    #[error("Parallel join deadlock {0}")]
+    Deadlock(String),
+
+    #[error("{0} is already deployed")]
+    AlreadyDeployed(String),
+
+    #[error("Unknown flows {0}")]
+    UnknownFlows(String),
+
+    #[error("Missing outcomes for end events {0}")]
+    MissingOutcomes(String),
+
+    #[error("Process cancelled. Active elements: {}", .0.join(", "))]
+    Cancelled(Vec<String>),
+
+    #[error("Process timed out. Active elements: {}", .0.join(", "))]
+    Timeout(Vec<String>),
+
+    #[error("Visit limit {} exceeded. Cycling elements: {}", .0, .1.join(", "))]
+    VisitLimit(usize, Vec<String>),
+
+    #[error(
+        "Token limit {} exceeded. Tokens at: {}",
+        .0,
+        .1.iter().map(|(id, count)| format!("{id} ({count})")).collect::<Vec<_>>().join(", ")
+    )]
+    TokenLimit(usize, Vec<(String, usize)>),
+
+    #[error("{0} circuit is open")]
+    CircuitOpen(String),
+
+    #[error("{0} interrupted by a boundary event")]
+    Interrupted(String),
+
     #[error("{0} not supported")]
     NotSupported(String),
 
+    #[error("error {0} was not caught by a boundary event")]
+    UncaughtError(String),
+
+    #[error("{0} has no element in the diagram to migrate to")]
+    UnmappedElement(String),
+
     #[error("{0}")]
     BpmnRequirement(String),
 
@@ -66,6 +124,15 @@ pub enum Error {
     #[error("{0}")]
     Builder(String),
 
+    #[error("{source} at line {line}, column {column}{}", .element.as_deref().map(|id| format!(" in {id}")).unwrap_or_default())]
+    Parse {
+        line: usize,
+        column: usize,
+        /// Id of the enclosing element
+        element: Option<String>,
+        source: quick_xml::Error,
+    },
+
     #[error(transparent)]
     File(#[from] quick_xml::Error),
 
@@ -74,6 +141,27 @@ pub enum Error {
 
     #[error(transparent)]
     Utf8(#[from] std::str::Utf8Error),
+
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] ureq::Error),
+
+    #[cfg(feature = "compiled")]
+    #[error(transparent)]
+    Compiled(#[from] bincode::Error),
+
+    #[error("Missing connector property {0} in {1}")]
+    MissingConnectorProperty(String, String),
+
+    #[error("Unknown user task {0}")]
+    UnknownUserTask(u64),
+
+    #[error("User task {0} is already claimed by {1}")]
+    AlreadyClaimed(u64, String),
+
+    #[cfg(feature = "worker")]
+    #[error("External task {0} is not locked by worker {1}")]
+    NotLocked(u64, String),
 }
 
 // BpmnRequirement
@@ -84,3 +172,16 @@ pub(crate) const ONLY_ONE_START_EVENT: &str = "There can only be one start event
 // Builder
 pub(crate) const FUNC_MAP_ERROR_MSG: &str = "Handlermap has already been consumed";
 pub(crate) const BUILD_PROCESS_ERROR_MSG: &str = "Couldn't build process";
+#[cfg(feature = "compiled")]
+pub(crate) const COMPILED_FORMAT_ERROR_MSG: &str =
+    "Not a compiled diagram or compiled with another format version";
+
+// Execution
+pub(crate) const DATA_LOCK_ERROR_MSG: &str = "Data lock is poisoned";
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) const RUNNER_STOPPED_ERROR_MSG: &str = "Runner is stopped";
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) const INSTANCE_PANICKED_ERROR_MSG: &str = "Instance panicked";
+pub(crate) const CHANNEL_CLOSED_ERROR_MSG: &str = "Channel is closed";
+#[cfg(feature = "worker")]
+pub(crate) const WORKER_STOPPED_ERROR_MSG: &str = "Worker pool is stopped";
@@ -0,0 +1,301 @@
+use crate::{
+    api::{ElementType, EndNode, ExecutionEvent, ExecutionEventKind},
+    error::Error,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
+    io::ErrorKind,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+const END: &str = "end";
+const DECISION: &str = "decision";
+const TRACE: &str = "trace";
+
+/// Record the path of a run to compare it with a golden run. Register the `listener` on the process
+/// and call `take` with the end node after each run.
+///
+/// ```
+/// use snurr::{Process, RunRecorder};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let recorder = RunRecorder::default();
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .listener(recorder.listener())
+///         .build()?;
+///
+///     let result = bpmn.run(0)?;
+///     let diff = recorder.take(&result.end_node).golden("tests/golden/example.txt")?;
+///     assert!(diff.is_empty(), "{diff}");
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RunRecorder {
+    inner: Arc<Mutex<Recording>>,
+}
+
+#[derive(Debug, Default)]
+struct Recording {
+    trace: Vec<String>,
+    decisions: Vec<(String, String)>,
+    // Last element entered by a token
+    last: HashMap<usize, (String, ElementType)>,
+}
+
+impl RunRecorder {
+    /// Listener to register on the process with `.listener(...)`
+    pub fn listener(&self) -> impl Fn(&ExecutionEvent) + Sync + Send + 'static {
+        let inner = Arc::clone(&self.inner);
+        move |event| {
+            if event.kind != ExecutionEventKind::Enter {
+                return;
+            }
+
+            let Ok(mut recording) = inner.lock() else {
+                return;
+            };
+
+            // A new token continues from the element its parent token left
+            let previous = recording
+                .last
+                .get(&event.token)
+                .or_else(|| {
+                    event
+                        .parent_token
+                        .and_then(|parent| recording.last.get(&parent))
+                })
+                .cloned();
+
+            if event.element_type == ElementType::SequenceFlow
+                && let Some((
+                    gateway,
+                    ElementType::ExclusiveGateway
+                    | ElementType::InclusiveGateway
+                    | ElementType::EventBasedGateway,
+                )) = previous
+            {
+                recording.decisions.push((gateway, event.id.into()));
+            }
+
+            recording.trace.push(event.id.into());
+            recording
+                .last
+                .insert(event.token, (event.id.into(), event.element_type));
+        }
+    }
+
+    /// Take the recorded run and reset the recorder for the next run.
+    pub fn take(&self, end_node: &EndNode) -> RunRecord {
+        let recording = self
+            .inner
+            .lock()
+            .map(|mut recording| std::mem::take(&mut *recording))
+            .unwrap_or_default();
+
+        RunRecord {
+            trace: recording.trace,
+            decisions: recording.decisions,
+            end_node: end_node.id.clone(),
+        }
+    }
+}
+
+/// The path of a run. Elements entered in order, the sequence flows selected by the gateways and the end node.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunRecord {
+    /// BPMN ids of the entered elements in order
+    pub trace: Vec<String>,
+    /// Gateway BPMN id and the selected sequence flow BPMN id in order
+    pub decisions: Vec<(String, String)>,
+    /// BPMN id of the end node
+    pub end_node: String,
+}
+
+impl RunRecord {
+    /// Compare with the golden run stored in the file. If the file doesn't exist, the record is saved
+    /// as the golden run and an empty diff is returned.
+    pub fn golden(&self, path: impl AsRef<Path>) -> Result<RunDiff, Error> {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(expected) => Ok(expected.diff(self)),
+            Err(Error::Io(error)) if error.kind() == ErrorKind::NotFound => {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                self.save(path)?;
+                Ok(RunDiff::default())
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Save the record to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        Ok(std::fs::write(path, self.to_string())?)
+    }
+
+    /// Load a record saved with `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut record = Self::default();
+        for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(END), Some(id), None) => record.end_node = id.into(),
+                (Some(DECISION), Some(gateway), Some(flow)) => {
+                    record.decisions.push((gateway.into(), flow.into()))
+                }
+                (Some(TRACE), Some(id), None) => record.trace.push(id.into()),
+                (None, ..) => {}
+                _ => {
+                    return Err(std::io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("invalid run record on line {}: {line}", number + 1),
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(record)
+    }
+
+    /// Structured difference between this expected run and the actual run
+    pub fn diff(&self, actual: &RunRecord) -> RunDiff {
+        let first_divergence = (0..self.trace.len().max(actual.trace.len()))
+            .find(|index| self.trace.get(*index) != actual.trace.get(*index))
+            .map(|index| Divergence {
+                index,
+                expected: self.trace.get(index).cloned(),
+                actual: actual.trace.get(index).cloned(),
+            });
+
+        let expected = group_decisions(&self.decisions);
+        let actual_decisions = group_decisions(&actual.decisions);
+        let gateways: BTreeSet<&str> = expected
+            .keys()
+            .chain(actual_decisions.keys())
+            .copied()
+            .collect();
+        let decisions = gateways
+            .into_iter()
+            .filter_map(|gateway| {
+                let expected = expected.get(gateway).cloned().unwrap_or_default();
+                let actual = actual_decisions.get(gateway).cloned().unwrap_or_default();
+                (expected != actual).then(|| DecisionDiff {
+                    gateway: gateway.into(),
+                    expected,
+                    actual,
+                })
+            })
+            .collect();
+
+        RunDiff {
+            first_divergence,
+            decisions,
+            end_node: (self.end_node != actual.end_node)
+                .then(|| (self.end_node.clone(), actual.end_node.clone())),
+        }
+    }
+}
+
+fn group_decisions(decisions: &[(String, String)]) -> BTreeMap<&str, Vec<String>> {
+    let mut map: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (gateway, flow) in decisions {
+        map.entry(gateway).or_default().push(flow.clone());
+    }
+    map
+}
+
+impl Display for RunRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{END} {}", self.end_node)?;
+        for (gateway, flow) in &self.decisions {
+            writeln!(f, "{DECISION} {gateway} {flow}")?;
+        }
+        for id in &self.trace {
+            writeln!(f, "{TRACE} {id}")?;
+        }
+        Ok(())
+    }
+}
+
+/// First element where the actual run took another path than the expected run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position in the trace
+    pub index: usize,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+/// Gateway that selected other sequence flows than in the expected run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecisionDiff {
+    pub gateway: String,
+    pub expected: Vec<String>,
+    pub actual: Vec<String>,
+}
+
+/// Difference between an expected and an actual run
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunDiff {
+    pub first_divergence: Option<Divergence>,
+    pub decisions: Vec<DecisionDiff>,
+    /// Expected and actual end node if they differ
+    pub end_node: Option<(String, String)>,
+}
+
+impl RunDiff {
+    pub fn is_empty(&self) -> bool {
+        self.first_divergence.is_none() && self.decisions.is_empty() && self.end_node.is_none()
+    }
+}
+
+impl Display for RunDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "runs are equal");
+        }
+
+        if let Some(Divergence {
+            index,
+            expected,
+            actual,
+        }) = &self.first_divergence
+        {
+            writeln!(
+                f,
+                "first divergence at {index}: expected {}, actual {}",
+                expected.as_deref().unwrap_or("<none>"),
+                actual.as_deref().unwrap_or("<none>")
+            )?;
+        }
+        for DecisionDiff {
+            gateway,
+            expected,
+            actual,
+        } in &self.decisions
+        {
+            writeln!(
+                f,
+                "decision {gateway}: expected [{}], actual [{}]",
+                expected.join(", "),
+                actual.join(", ")
+            )?;
+        }
+        if let Some((expected, actual)) = &self.end_node {
+            writeln!(f, "end node: expected {expected}, actual {actual}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use crate::api::{EndNode, EngineEvent};
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// Receives the history of every completed run of a process. Install it with `Process::history`.
+/// Implement it to store the history somewhere else, like a database, or use the in-memory `History`.
+pub trait HistorySink: Send + Sync {
+    fn record(&self, run: RunHistory);
+}
+
+impl<F> HistorySink for F
+where
+    F: Fn(RunHistory) + Send + Sync,
+{
+    fn record(&self, run: RunHistory) {
+        self(run)
+    }
+}
+
+/// Audit history of a single run
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunHistory {
+    /// Correlation id given to the run with `RunOptions::correlation_id`
+    pub correlation_id: Option<String>,
+    /// Metadata given to the run with `RunOptions::metadata`
+    pub metadata: BTreeMap<String, String>,
+    /// Time since the Unix epoch when the run started
+    pub started: Duration,
+    /// Time the run took
+    pub duration: Duration,
+    /// Elements entered and left, gateway decisions, boundary events and ended processes in the order they happened
+    pub entries: Vec<HistoryEntry>,
+    /// End event of the run, or the error that stopped it
+    pub result: Result<EndNode, String>,
+}
+
+/// Event of a run and when it happened
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistoryEntry {
+    /// Time since the run started
+    pub elapsed: Duration,
+    pub event: EngineEvent,
+}
+
+impl RunHistory {
+    pub fn is_failed(&self) -> bool {
+        self.result.is_err()
+    }
+
+    /// BPMN ids of the entered elements and sequence flows in the order they were entered
+    pub fn path(&self) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                EngineEvent::ElementEntered { id, .. } => Some(&**id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// BPMN ids of the sequence flows selected by the gateway with the name or BPMN id, for every visit
+    pub fn decisions(&self, name_or_id: &str) -> Vec<Vec<&str>> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                EngineEvent::GatewayDecision {
+                    id, name, flows, ..
+                } if &**id == name_or_id || name.as_deref() == Some(name_or_id) => {
+                    Some(flows.iter().map(|flow| &**flow).collect())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// In-memory history of the completed runs. Clones share the same runs.
+///
+/// ```
+/// use snurr::{History, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let history = History::default();
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .history(history.clone())
+///         .build()?;
+///
+///     bpmn.run(0)?;
+///     assert_eq!(history.runs()[0].decisions("equal to 3").len(), 3);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    runs: Arc<Mutex<Vec<RunHistory>>>,
+}
+
+impl HistorySink for History {
+    fn record(&self, run: RunHistory) {
+        self.runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(run);
+    }
+}
+
+impl History {
+    /// Every recorded run in the order they completed
+    pub fn runs(&self) -> Vec<RunHistory> {
+        self.filter(|_| true)
+    }
+
+    /// Runs with the correlation id
+    pub fn by_correlation_id(&self, correlation_id: &str) -> Vec<RunHistory> {
+        self.filter(|run| run.correlation_id.as_deref() == Some(correlation_id))
+    }
+
+    /// Runs stopped by an error
+    pub fn failed(&self) -> Vec<RunHistory> {
+        self.filter(RunHistory::is_failed)
+    }
+
+    /// Runs matching the predicate
+    pub fn filter(&self, predicate: impl Fn(&RunHistory) -> bool) -> Vec<RunHistory> {
+        self.runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|run| predicate(run))
+            .cloned()
+            .collect()
+    }
+
+    /// Take the recorded runs, for example to export them, and start over
+    pub fn take(&self) -> Vec<RunHistory> {
+        std::mem::take(&mut *self.runs.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
@@ -66,11 +66,80 @@
 
 mod api;
 mod bpmn;
+mod catalog;
+mod coverage;
+mod debugger;
 mod diagram;
 mod error;
+mod golden;
+mod history;
 mod process;
+mod registry;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+mod runner;
+pub mod testing;
+mod time;
+mod user_task;
+#[cfg(feature = "variables")]
+mod variables;
+#[cfg(feature = "worker")]
+pub mod worker;
 
-pub use api::{Boundary, Data, EndNode, IntermediateEvent, ProcessOutput, TaskResult, With};
-pub use bpmn::Symbol;
+#[cfg(not(feature = "parallel"))]
+pub use api::Sequential;
+pub use api::{
+    Boundary, BuildReport, Context, Data, DataKind, DataReference, DuplicateFlows, ElementStats,
+    ElementType, EndNode, EngineEvent, ErrorDecision, ExecutionEvent, ExecutionEventKind, FlowName,
+    IntermediateEvent, MessageMeta, Milestone, Outcome, ProcessOutput, SharedData, Snapshot, Stats,
+    TaskResult, TraceContext, Transition, TransitionDecision, With,
+};
+pub use bpmn::{Assignment, EventPosition, Form, FormConstraint, FormField, FormFieldType, Symbol};
+pub use catalog::{CatalogDiagnostic, DiagramCache, ProcessCatalog};
+pub use coverage::{Coverage, CoverageItem, CoverageReport};
+pub use debugger::{DebugRecorder, DebugStep, Debugger};
+pub use diagram::{
+    ParseWarning, ParseWarningKind,
+    elements::ElementInfo,
+    export::HandbookPage,
+    reader::DiagramBuilder,
+    validate::{Diagnostic, DiagnosticKind, Severity},
+};
 pub use error::{Error, Result};
-pub use process::{Build, Process, Run};
+pub use golden::{DecisionDiff, Divergence, RunDiff, RunRecord, RunRecorder};
+pub use history::{History, HistoryEntry, HistorySink, RunHistory};
+pub use process::{
+    Build, Process, Run,
+    breaker::CircuitBreaker,
+    cache::DecisionCache,
+    connector::{Connector, ConnectorConfig},
+    flags::FeatureFlags,
+    handlers::ProcessHandlers,
+    options::{
+        ActiveElement, BoundaryTrigger, CancelToken, ParseOptions, RunHandle, RunOptions, RunStatus,
+    },
+    receive::MessageReceiver,
+    retry::{Backoff, RetryPolicy},
+    schedule::Workload,
+};
+pub use registry::Registry;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub use runner::{InstanceOutput, Runner};
+pub use user_task::{UserTask, UserTasks};
+
+#[cfg(all(feature = "http", feature = "variables"))]
+pub use process::connector::http::HttpConnector;
+#[cfg(feature = "parallel")]
+pub use rayon;
+#[cfg(feature = "variables")]
+pub use serde_json;
+#[cfg(feature = "variables")]
+pub use variables::Variables;
+
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub use process::collect as __private;
+#[cfg(feature = "macros")]
+pub use snurr_macros::{event_based, exclusive, inclusive, task};
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use time::Instant;
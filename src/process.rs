@@ -1,112 +1,1131 @@
+pub(crate) mod breaker;
+pub(crate) mod cache;
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod collect;
+pub(crate) mod connector;
 mod engine;
+pub(crate) mod flags;
 pub mod handler;
+pub(crate) mod handlers;
+pub(crate) mod options;
+#[cfg(feature = "otel")]
+mod otel;
+pub(crate) mod receive;
+pub(crate) mod retry;
 mod scaffold;
+pub(crate) mod schedule;
 
+#[cfg(not(feature = "parallel"))]
+use crate::api::Sequential;
 use crate::{
-    api::{Data, EndNode, IntermediateEvent, ProcessOutput, TaskResult, With},
-    bpmn::{Bpmn, Symbol},
-    diagram::{Diagram, reader::read_bpmn},
-    error::Error,
-    process::handler::Callback,
+    api::{
+        BuildReport, Context, DuplicateFlows, EndNode, EngineEvent, ErrorDecision, ExecutionEvent,
+        FlowName, IntermediateEvent, MessageMeta, Outcome, Position, ProcessOutput, SharedData,
+        Snapshot, TaskResult, Transition, TransitionDecision, With,
+    },
+    bpmn::{ActivityType, Bpmn, Event, Symbol},
+    coverage::CoverageItem,
+    diagram::{
+        Diagram, ParseWarning, Stub,
+        elements::ElementInfo,
+        export::HandbookPage,
+        reader::{DiagramBuilder, read_bpmn, read_bpmn_with},
+        validate::Diagnostic,
+    },
+    error::{DATA_LOCK_ERROR_MSG, Error},
+    history::HistorySink,
+    process::handler::{Callback, HandlerMap},
 };
-use engine::ExecuteInput;
+use breaker::CircuitBreaker;
+use cache::{Decision, DecisionCache};
+use connector::{Connector, ConnectorConfig};
+use engine::{ExecuteInput, RunState};
+use flags::FeatureFlags;
 use handler::Handler;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use options::RunHandle;
+use options::{ParseOptions, RunOptions};
+use receive::MessageReceiver;
+use retry::RetryPolicy;
+use schedule::Workload;
 use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fs::File,
+    hash::Hash,
+    io::{BufRead, BufReader},
     marker::PhantomData,
     path::Path,
     str::FromStr,
-    sync::{Arc, Mutex},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
 };
 
+// Run handle and thread of a spawned run
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+type Spawned<T, O> = (
+    RunHandle,
+    std::thread::JoinHandle<Result<ProcessOutput<T, O>, Error>>,
+);
+
 /// Process that contains information from the BPMN file
-pub struct Process<T, S = Build>
+pub struct Process<T, S = Build, L = Mutex<T>>
 where
     Self: Sync + Send,
 {
     diagram: Diagram,
-    handler: Handler<T>,
+    handler: Handler<T, L>,
     _marker: PhantomData<S>,
 }
 
-/// Process Build state
-pub struct Build;
+/// Process Build state. `O` is the `Outcome` of a run, see `Process::outcomes`.
+pub struct Build<O = ()>(PhantomData<fn() -> O>);
+
+/// Process Run state. `O` is the `Outcome` of a run, see `Process::outcomes`.
+pub struct Run<O = ()>(PhantomData<fn() -> O>);
+
+impl<T> Process<T> {
+    /// Create new process and initialize it from the BPMN file path.
+    /// ```
+    /// use snurr::{Build, Process};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn: Process<()> = Process::new("examples/example.bpmn")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::from_diagram(read_bpmn(BufReader::new(File::open(
+            path,
+        )?))?))
+    }
+
+    /// Create new process and initialize it from a reader, for example a file or a response body.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let file = std::fs::File::open("examples/example.bpmn")?;
+    ///     let bpmn: Process<()> = Process::from_reader(std::io::BufReader::new(file))?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, Error> {
+        Ok(Self::from_diagram(read_bpmn(reader)?))
+    }
+
+    /// Create new process and initialize it from BPMN bytes, for example an embedded asset or a database blob.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// static BPMN_DATA: &[u8] = include_bytes!("../examples/example.bpmn");
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn: Process<()> = Process::from_bytes(BPMN_DATA)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_reader(bytes)
+    }
+
+    /// Create new process and initialize it from a BPMN file downloaded with a GET request. Requires the `http` feature.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn: Process<()> = Process::from_url("https://example.com/diagrams/example.bpmn")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let mut response = ureq::get(url).call()?;
+        Self::from_reader(BufReader::new(response.body_mut().as_reader()))
+    }
+
+    /// Create new process from the BPMN file path with options for unsupported elements. See `ParseOptions`.
+    pub fn new_with_options(path: impl AsRef<Path>, options: ParseOptions) -> Result<Self, Error> {
+        let mut process =
+            Self::from_diagram(read_bpmn_with(BufReader::new(File::open(path)?), options)?);
+        process.handler.set_parse_options(options);
+        Ok(process)
+    }
+
+    /// Create new process from a diagram written by `compile_to`. The XML is not parsed. Requires the `compiled` feature.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     Process::<()>::new("examples/example.bpmn")?.compile_to("example.snurr")?;
+    ///     let bpmn: Process<()> = Process::from_compiled("example.snurr")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(feature = "compiled")]
+    pub fn from_compiled(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::from_diagram(Diagram::read_compiled(BufReader::new(
+            File::open(path)?,
+        ))?))
+    }
+
+    /// Write the parsed diagram to a compact binary file that `from_compiled` reads. The file is only
+    /// read by versions of snurr with the same format. Registered functions are not written. Requires the `compiled` feature.
+    #[cfg(feature = "compiled")]
+    pub fn compile_to(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut writer = std::io::BufWriter::new(File::create(path)?);
+        self.diagram.write_compiled(&mut writer)?;
+        std::io::Write::flush(&mut writer)?;
+        Ok(())
+    }
+
+    /// Create new process from a diagram built in code. See `DiagramBuilder`.
+    pub fn from_builder(builder: DiagramBuilder) -> Result<Self, Error> {
+        Ok(Self::from_diagram(builder.build()?))
+    }
+
+    /// Put the data of the runs behind another synchronization primitive than the default `Mutex<T>`. The
+    /// functions get the data as `Arc<L>`. With a `RwLock`, functions that only `read` the data don't wait for
+    /// each other in parallel branches. Call it before registering functions, the functions registered before
+    /// are dropped. Implement `SharedData` to plug in your own primitive.
+    ///
+    /// ```
+    /// use snurr::Process;
+    /// use std::sync::RwLock;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+    ///         .data_lock::<RwLock<u32>>()
+    ///         .task("Count 1", |input| {
+    ///             *input.write().unwrap() += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.read().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///     bpmn.run(0)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn data_lock<L: SharedData<T>>(self) -> Process<T, Build, L> {
+        Process {
+            diagram: self.diagram,
+            handler: self.handler.with_lock(),
+            _marker: Default::default(),
+        }
+    }
+
+    /// Run the process without a lock around the data. Functions registered with `task_mut`, `exclusive_mut`,
+    /// `inclusive_mut` and `event_based_mut` get the data as `&mut T`. Only available without the `parallel` feature,
+    /// where a single function runs at a time. See `Sequential`.
+    ///
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+    ///         .sequential()
+    ///         .task_mut("Count 1", |count| {
+    ///             *count += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive_mut("equal to 3", |count| match count {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///     bpmn.run(0)?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(not(feature = "parallel"))]
+    pub fn sequential(self) -> Process<T, Build, Sequential<T>> {
+        self.data_lock()
+    }
+
+    pub(crate) fn from_diagram(diagram: Diagram) -> Self {
+        Self {
+            diagram,
+            handler: Default::default(),
+            _marker: Default::default(),
+        }
+    }
+}
+
+impl<T, O: Outcome, L: SharedData<T>> Process<T, Build<O>, L> {
+    /// Register a task function with name or bpmn id
+    pub fn task<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>) -> Result<TaskResult, Error> + 'static + Sync + Send,
+    {
+        self.task_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register a task function with name or bpmn id. The function also receives the `Context`.
+    pub fn task_with_context<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<TaskResult, Error> + 'static + Sync + Send,
+    {
+        self.handler
+            .add_callback(name, Callback::Task(Arc::new(func)));
+        self
+    }
+
+    /// Register a receive task or intermediate catch event with name or bpmn id that waits for a message from the
+    /// receiver. `func` updates the data with the message. The token blocks until a message arrives, the channel
+    /// is closed or the run is cancelled. Tokens waiting on the same receiver get one message each.
+    /// A boundary returned for a catch event is ignored.
+    ///
+    /// ```
+    /// use snurr::Process;
+    /// use std::sync::mpsc;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (sender, receiver) = mpsc::channel();
+    ///     let bpmn = Process::<u32>::new("tests/files/one_task.bpmn")?
+    ///         .receive("Count 1", receiver, |input, amount: u32| {
+    ///             *input.lock().unwrap() += amount;
+    ///             Ok(None)
+    ///         })
+    ///         .build()?;
+    ///     sender.send(3)?;
+    ///     assert_eq!(bpmn.run(0)?.data, 3);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn receive<M, R, F>(self, name: impl Into<String>, receiver: R, func: F) -> Self
+    where
+        R: MessageReceiver<M> + 'static,
+        F: Fn(Arc<L>, M) -> Result<TaskResult, Error> + 'static + Sync + Send,
+    {
+        let receiver = Mutex::new(receiver);
+        self.task_with_context(name, move |ctx, data| {
+            let message = {
+                let mut receiver = receiver.lock().unwrap_or_else(PoisonError::into_inner);
+                ctx.wait_for_message(|timeout| receiver.recv_timeout(timeout))?
+            };
+            func(data, message)
+        })
+    }
+
+    /// Register the delivery of a send task or a message or signal throw event with name or bpmn id,
+    /// like sending an email or publishing to a queue. The function gets the name of the referenced message or
+    /// signal and the token continues when it returns.
+    pub fn sender<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>, &MessageMeta) -> Result<(), Error> + 'static + Sync + Send,
+    {
+        self.task_with_context(name, move |ctx, data| {
+            let meta = MessageMeta {
+                name: ctx.message().or(ctx.name()).unwrap_or(ctx.id()),
+                symbol: ctx.symbol().unwrap_or(&Symbol::Message),
+                element_id: ctx.id(),
+                correlation_id: ctx.correlation_id(),
+            };
+            func(data, &meta).map(|_| None)
+        })
+    }
+
+    /// Register a connector with the name used in the `connector` property of the tasks. Tasks with the property
+    /// and no registered function run the connector with their properties as configuration.
+    ///
+    /// ```
+    /// use snurr::{ConnectorConfig, Data, Error, Process};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("tests/files/connector.bpmn")?
+    ///         .connector("add", |config: &ConnectorConfig, input: Data<u32>| {
+    ///             let amount = config.require("amount")?;
+    ///             *input.lock().unwrap() += amount
+    ///                 .parse::<u32>()
+    ///                 .map_err(|error| Error::ProcessExecution(error.into()))?;
+    ///             Ok(None)
+    ///         })
+    ///         .build()?;
+    ///     assert_eq!(bpmn.run(0)?.data, 3);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn connector(
+        mut self,
+        name: impl Into<String>,
+        connector: impl Connector<T, L> + 'static,
+    ) -> Self {
+        self.handler.add_callback(
+            name,
+            Callback::Connector(Arc::new(move |context, data| {
+                connector.execute(&ConnectorConfig { context }, data)
+            })),
+        );
+        self
+    }
+
+    /// Register a call activity with name or bpmn id that runs the child process on its own data.
+    /// `input` creates the data of the child from the data of the process and `output` copies the result
+    /// back. `output` can return a boundary, for example when the child ended in an error end event.
+    /// The services, trace context and cancel token of the run are given to the child.
+    ///
+    /// ```
+    /// use snurr::{Process, Symbol};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let child = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task("Count 1", |input| {
+    ///             *input.lock().unwrap() += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///
+    ///     let bpmn = Process::<(u32, String)>::new("tests/files/one_task.bpmn")?
+    ///         .call_activity(
+    ///             "Count 1",
+    ///             child,
+    ///             |(start, _)| *start,
+    ///             |(_, result), output| {
+    ///                 *result = format!("counted to {}", output.data);
+    ///                 (output.end_node.symbol == Symbol::Error).then_some(Symbol::Error.into())
+    ///             },
+    ///         )
+    ///         .build()?;
+    ///     bpmn.run((0, String::new()))?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn call_activity<C, CO, I, M>(
+        self,
+        name: impl Into<String>,
+        child: Process<C, Run<CO>>,
+        input: I,
+        output: M,
+    ) -> Self
+    where
+        C: Send + 'static,
+        CO: Outcome + 'static,
+        I: Fn(&T) -> C + 'static + Sync + Send,
+        M: Fn(&mut T, ProcessOutput<C, CO>) -> TaskResult + 'static + Sync + Send,
+    {
+        self.task_with_context(name, move |ctx, data| {
+            let child_data = input(
+                &*data
+                    .read()
+                    .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?,
+            );
+            let result = child.run_with(
+                child_data,
+                RunOptions {
+                    services: ctx.services,
+                    trace: ctx.trace,
+                    cancel: ctx.cancel.cloned(),
+                    ..Default::default()
+                },
+            )?;
+            let mut data = data
+                .lock()
+                .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?;
+            Ok(output(&mut data, result))
+        })
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id
+    pub fn exclusive<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>) -> Result<Option<&'static str>, Error> + 'static + Sync + Send,
+    {
+        self.exclusive_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that returns the name or id of the
+    /// outgoing flow as a `String` or another owned name, for flow names computed at runtime.
+    pub fn exclusive_owned<F, N>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>) -> Result<Option<N>, Error> + 'static + Sync + Send,
+        N: Into<Cow<'static, str>>,
+    {
+        self.exclusive_owned_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an exclusive gateway with name or bpmn id that takes the outgoing sequence flow with the name or id
+    /// in the string variable. The default flow is taken if the variable is missing or not a string.
+    #[cfg(feature = "variables")]
+    pub fn exclusive_by_variable(
+        self,
+        name: impl Into<String>,
+        variable: impl Into<String>,
+    ) -> Self {
+        let variable = variable.into();
+        self.exclusive_owned_with_context(name, move |ctx, _| {
+            Ok(ctx
+                .variables()
+                .get(&variable)
+                .and_then(|value| value.as_str().map(String::from)))
+        })
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id. The function also receives the `Context`.
+    pub fn exclusive_with_context<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<Option<&'static str>, Error> + 'static + Sync + Send,
+    {
+        self.exclusive_owned_with_context(name, func)
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that returns an owned flow name.
+    /// The function also receives the `Context`.
+    pub fn exclusive_owned_with_context<F, N>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<Option<N>, Error> + 'static + Sync + Send,
+        N: Into<Cow<'static, str>>,
+    {
+        self.handler.add_callback(
+            name,
+            Callback::Exclusive(Arc::new(move |context, data| {
+                func(context, data).map(|flow| flow.map(Into::into))
+            })),
+        );
+        self
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that selects an outgoing flow by index.
+    /// The function receives the names, or ids if unnamed, of the outgoing flows in diagram order.
+    /// `None` selects the default flow.
+    pub fn exclusive_index<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>, &[&str]) -> Result<Option<usize>, Error> + 'static + Sync + Send,
+    {
+        self.exclusive_index_with_context(name, move |_, data, flows| func(data, flows))
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that selects an outgoing flow by index.
+    /// The function also receives the `Context`.
+    pub fn exclusive_index_with_context<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>, &[&str]) -> Result<Option<usize>, Error> + 'static + Sync + Send,
+    {
+        self.handler
+            .add_callback(name, Callback::ExclusiveIndex(Arc::new(func)));
+        self
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that selects a typed flow declared with
+    /// `flow_names!`. `None` selects the default flow. `build` fails if a flow is not an outgoing flow of the gateway.
+    pub fn exclusive_flow<E, F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        E: FlowName,
+        F: Fn(Arc<L>) -> Result<Option<E>, Error> + 'static + Sync + Send,
+    {
+        self.exclusive_flow_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an exclusive gateway function with name or bpmn id that selects a typed flow.
+    /// The function also receives the `Context`.
+    pub fn exclusive_flow_with_context<E, F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        E: FlowName,
+        F: Fn(&Context, Arc<L>) -> Result<Option<E>, Error> + 'static + Sync + Send,
+    {
+        let name = name.into();
+        self.handler.add_flow_names(name.clone(), E::NAMES);
+        self.exclusive_with_context(name, move |context, data| {
+            func(context, data).map(|flow| flow.as_ref().map(E::flow_name))
+        })
+    }
+
+    /// Register an inclusive gateway function with name or bpmn id
+    pub fn inclusive<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>) -> Result<With, Error> + 'static + Sync + Send,
+    {
+        self.inclusive_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an inclusive gateway function with name or bpmn id. The function also receives the `Context`.
+    pub fn inclusive_with_context<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<With, Error> + 'static + Sync + Send,
+    {
+        self.handler
+            .add_callback(name, Callback::Inclusive(Arc::new(func)));
+        self
+    }
+
+    /// Register an inclusive gateway function with name or bpmn id that selects typed flows declared with
+    /// `flow_names!`. An empty `Vec` selects the default flow. `build` fails if a flow is not an outgoing flow of the gateway.
+    pub fn inclusive_flow<E, F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        E: FlowName,
+        F: Fn(Arc<L>) -> Result<Vec<E>, Error> + 'static + Sync + Send,
+    {
+        self.inclusive_flow_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an inclusive gateway function with name or bpmn id that selects typed flows.
+    /// The function also receives the `Context`.
+    pub fn inclusive_flow_with_context<E, F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        E: FlowName,
+        F: Fn(&Context, Arc<L>) -> Result<Vec<E>, Error> + 'static + Sync + Send,
+    {
+        let name = name.into();
+        self.handler.add_flow_names(name.clone(), E::NAMES);
+        self.inclusive_with_context(name, move |context, data| {
+            func(context, data).map(|flows| match flows.as_slice() {
+                [] => With::Default,
+                [flow] => With::Flow(flow.flow_name().into()),
+                flows => With::Fork(flows.iter().map(|flow| flow.flow_name().into()).collect()),
+            })
+        })
+    }
+
+    /// Register an event based gateway function with name or bpmn id
+    pub fn event_based<F>(self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(Arc<L>) -> Result<IntermediateEvent, Error> + 'static + Sync + Send,
+    {
+        self.event_based_with_context(name, move |_, data| func(data))
+    }
+
+    /// Register an event based gateway function with name or bpmn id. The function also receives the `Context`.
+    pub fn event_based_with_context<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<IntermediateEvent, Error> + 'static + Sync + Send,
+    {
+        self.handler
+            .add_callback(name, Callback::EventBased(Arc::new(func)));
+        self
+    }
+
+    /// Register a boundary event function with name or bpmn id. It is invoked when the boundary is triggered,
+    /// before the boundary outgoing flow is followed. Boundary functions are optional.
+    pub fn boundary<F>(mut self, name: impl Into<String>, func: F) -> Self
+    where
+        F: Fn(&Context, Arc<L>) -> Result<(), Error> + 'static + Sync + Send,
+    {
+        self.handler
+            .add_callback(name, Callback::Boundary(Arc::new(func)));
+        self
+    }
+
+    /// Register a listener that is called when any element is entered or left during a run.
+    /// Multiple listeners can be registered and they are called in registration order.
+    pub fn listener<F>(mut self, func: F) -> Self
+    where
+        F: Fn(&ExecutionEvent) + 'static + Sync + Send,
+    {
+        self.handler.add_listener(Arc::new(func));
+        self
+    }
+
+    /// Register a listener that also gets the data when any element is entered or left during a run.
+    /// The data is locked while the listener is called. Idle events are only given to `listener`.
+    pub fn listener_with_data<F>(mut self, func: F) -> Self
+    where
+        F: Fn(&ExecutionEvent, &T) + 'static + Sync + Send,
+    {
+        self.handler.add_data_listener(Arc::new(func));
+        self
+    }
+
+    /// Register a hook that is called when a registered function returns an error. The hook decides to abort the run,
+    /// call the function again or continue with a boundary event of the task. Without a hook the run is aborted.
+    pub fn on_error<F>(mut self, func: F) -> Self
+    where
+        F: Fn(&Context, &Error) -> ErrorDecision + 'static + Sync + Send,
+    {
+        self.handler.set_on_error(Arc::new(func));
+        self
+    }
+
+    /// Register a hook that is called before a token follows an outgoing sequence flow of an element.
+    /// The hook can let the token continue, veto the transition or redirect it to another outgoing
+    /// sequence flow of the same element. Flows forked by a gateway are passed one at a time.
+    pub fn on_transition<F>(mut self, func: F) -> Self
+    where
+        F: Fn(&Transition) -> TransitionDecision + 'static + Sync + Send,
+    {
+        self.handler.set_on_transition(Arc::new(func));
+        self
+    }
+
+    /// Give every parallel branch its own data instead of sharing one `Mutex` between the tokens.
+    /// When a gateway forks, `split` creates the data of each branch from the data of the forking token.
+    /// When the branches arrive at a parallel or inclusive join, `merge` is called with the data the branch
+    /// was forked from and the data of each branch, and the joined token continues with the merged data.
+    /// A branch that ends without a join is merged when it ends.
+    ///
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// #[derive(Default, Clone)]
+    /// struct Counter {
+    ///     count: u32,
+    /// }
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<Counter>::new("examples/example.bpmn")?
+    ///         .branch_data(
+    ///             |_| Counter::default(),
+    ///             |joined, branch| joined.count += branch.count,
+    ///         );
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn branch_data<S, M>(mut self, split: S, merge: M) -> Self
+    where
+        S: Fn(&T) -> T + 'static + Sync + Send,
+        M: Fn(&mut T, &T) + 'static + Sync + Send,
+    {
+        self.handler
+            .set_branch_data(Arc::new(split), Arc::new(merge));
+        self
+    }
+
+    /// Register an exclusive gateway function that is pure over the key extracted from the data.
+    /// The decision is cached per key and reused instead of calling the function again.
+    pub fn exclusive_cached<K, KF, F>(self, name: impl Into<String>, key: KF, func: F) -> Self
+    where
+        K: Hash + Eq + Send + 'static,
+        KF: Fn(&T) -> K + 'static + Sync + Send,
+        F: Fn(Arc<L>) -> Result<Option<&'static str>, Error> + 'static + Sync + Send,
+    {
+        let function = cache::next_function();
+        self.exclusive_owned_with_context(name, move |ctx, data| {
+            let key = cache_key(&key, &*data)?;
+            if let Some(Decision::Exclusive(value)) = ctx.decisions.get(function, ctx.id, &key) {
+                return Ok(value);
+            }
+            let value = func(data)?.map(Into::into);
+            ctx.decisions
+                .insert(function, ctx.id, key, Decision::Exclusive(value.clone()));
+            Ok(value)
+        })
+    }
+
+    /// Register an inclusive gateway function that is pure over the key extracted from the data.
+    /// The decision is cached per key and reused instead of calling the function again.
+    pub fn inclusive_cached<K, KF, F>(self, name: impl Into<String>, key: KF, func: F) -> Self
+    where
+        K: Hash + Eq + Send + 'static,
+        KF: Fn(&T) -> K + 'static + Sync + Send,
+        F: Fn(Arc<L>) -> Result<With, Error> + 'static + Sync + Send,
+    {
+        let function = cache::next_function();
+        self.inclusive_with_context(name, move |ctx, data| {
+            let key = cache_key(&key, &*data)?;
+            if let Some(Decision::Inclusive(value)) = ctx.decisions.get(function, ctx.id, &key) {
+                return Ok(value);
+            }
+            let value = func(data)?;
+            ctx.decisions
+                .insert(function, ctx.id, key, Decision::Inclusive(value.clone()));
+            Ok(value)
+        })
+    }
+
+    /// Register an event based gateway function that is pure over the key extracted from the data.
+    /// The decision is cached per key and reused instead of calling the function again.
+    pub fn event_based_cached<K, KF, F>(self, name: impl Into<String>, key: KF, func: F) -> Self
+    where
+        K: Hash + Eq + Send + 'static,
+        KF: Fn(&T) -> K + 'static + Sync + Send,
+        F: Fn(Arc<L>) -> Result<IntermediateEvent, Error> + 'static + Sync + Send,
+    {
+        let function = cache::next_function();
+        self.event_based_with_context(name, move |ctx, data| {
+            let key = cache_key(&key, &*data)?;
+            if let Some(Decision::EventBased(value)) = ctx.decisions.get(function, ctx.id, &key) {
+                return Ok(value);
+            }
+            let value = func(data)?;
+            ctx.decisions
+                .insert(function, ctx.id, key, Decision::EventBased(value.clone()));
+            Ok(value)
+        })
+    }
+
+    /// Use a shared cache for the decisions of cached gateway functions. Without it, decisions are only kept within a run.
+    pub fn decision_cache(mut self, cache: Arc<DecisionCache>) -> Self {
+        self.handler.set_decision_cache(cache);
+        self
+    }
+
+    /// Install feature flags that can disable tasks and sequence flows at runtime.
+    pub fn feature_flags(mut self, flags: impl FeatureFlags + 'static) -> Self {
+        self.handler.set_feature_flags(Arc::new(flags));
+        self
+    }
+
+    /// Install a history sink that gets the audit history of every completed run, with the elements entered and
+    /// left, the gateway decisions, the boundary events and the end result. See `History`.
+    pub fn history(mut self, sink: impl HistorySink + 'static) -> Self {
+        self.handler.set_history(Arc::new(sink));
+        self
+    }
+
+    /// Declare the fallback flow, by name or id, used when the task or sequence flow is disabled by the feature flags.
+    /// For a task it is one of the task outgoing flows. For a sequence flow it is another outgoing flow of the same gateway.
+    pub fn fallback(mut self, name: impl Into<String>, flow: impl Into<String>) -> Self {
+        self.handler.add_fallback(name.into(), flow.into());
+        self
+    }
+
+    /// Set the scheduling hint, by name or id, for a task. With the `parallel` feature, `Workload::Io` tasks
+    /// run on a separate pool of blocking threads so they don't starve the CPU bound tasks on the rayon pool.
+    pub fn workload(mut self, name: impl Into<String>, workload: Workload) -> Self {
+        self.handler.add_workload(name.into(), workload);
+        self
+    }
+
+    /// Number of threads for the `Workload::Io` tasks. Default is 16. An IO task waits for a free thread when
+    /// all are busy. The threads are started by the first IO task and shared by every run of the process.
+    #[cfg(feature = "parallel")]
+    pub fn io_threads(mut self, threads: usize) -> Self {
+        self.handler.set_io_threads(threads);
+        self
+    }
+
+    /// Run the tokens one after the other when fewer than `tokens` are ready to run at the same time.
+    /// Dispatching a couple of short branches to the rayon pool costs more than running them in order.
+    /// By default every fork runs in parallel.
+    #[cfg(feature = "parallel")]
+    pub fn parallel_threshold(mut self, tokens: usize) -> Self {
+        self.handler.set_parallel_threshold(tokens);
+        self
+    }
+
+    /// Retry a task, by name or id, according to the policy when its function returns an error.
+    pub fn retry(mut self, name: impl Into<String>, policy: RetryPolicy) -> Self {
+        self.handler.add_retry(name.into(), policy);
+        self
+    }
+
+    /// Limit how many times a task, by name or id, runs at the same time. The limit is shared by all tokens and
+    /// all runs of the process, so a fork doesn't send more requests than a rate limited service accepts.
+    /// A token waits at the task until another token completes it.
+    pub fn concurrency(mut self, name: impl Into<String>, limit: usize) -> Self {
+        self.handler.add_limit(name.into(), limit);
+        self
+    }
 
-/// Process Run state
-pub struct Run;
+    /// Guard a task, by name or id, with a circuit breaker. While the circuit is open the function isn't called.
+    pub fn circuit_breaker(mut self, name: impl Into<String>, breaker: CircuitBreaker) -> Self {
+        self.handler.add_breaker(name.into(), breaker);
+        self
+    }
 
-impl<T> Process<T> {
-    /// Create new process and initialize it from the BPMN file path.
-    /// ```
-    /// use snurr::{Build, Process};
-    ///
-    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let bpmn: Process<()> = Process::new("examples/example.bpmn")?;
-    ///     Ok(())
-    /// }
-    /// ```
-    pub fn new(path: impl AsRef<Path>) -> Result<Self, Error> {
-        Ok(Self {
-            diagram: read_bpmn(quick_xml::Reader::from_file(path)?)?,
-            handler: Default::default(),
+    /// Register the condition of a conditional intermediate catch event or boundary event by name or id.
+    /// A token at the catch event waits until the condition is true. It is parked while other tokens run and
+    /// change the data. A conditional boundary event interrupts its task when the condition is true
+    /// as the token arrives at the task or when the task completes. It is not checked while the task runs.
+    pub fn condition<F>(mut self, name: impl Into<String>, condition: F) -> Self
+    where
+        F: Fn(&T) -> bool + 'static + Sync + Send,
+    {
+        self.handler.add_condition(name.into(), Arc::new(condition));
+        self
+    }
+
+    /// Emit an `ExecutionEventKind::Idle` event to the listeners when a token has waited longer than the threshold
+    /// at a user task, receive task or intermediate catch event. The event is emitted once per visit.
+    pub fn idle_threshold(mut self, threshold: Duration) -> Self {
+        self.handler.set_idle_threshold(threshold);
+        self
+    }
+
+    /// Continue with the escalation boundary event of a user task or receive task that completed after the idle threshold,
+    /// instead of its outgoing flow. Tasks without an escalation boundary continue as usual.
+    pub fn escalate_idle(mut self) -> Self {
+        self.handler.set_idle_escalation();
+        self
+    }
+
+    /// Catch panics in the registered functions and return them as `Error::ProcessExecution` with the element name,
+    /// instead of unwinding through the run. The panic is given to the error hook like any other error.
+    pub fn catch_panics(mut self) -> Self {
+        self.handler.set_catch_panics();
+        self
+    }
+
+    /// Continue with the outgoing flow of a task that returns a boundary the task doesn't have, instead of failing
+    /// with `Error::MissingBoundary`. The missing boundary is logged as a warning. Useful while the diagram evolves.
+    pub fn ignore_missing_boundaries(mut self) -> Self {
+        self.handler.set_ignore_missing_boundaries();
+        self
+    }
+
+    /// Look up task functions by the `zeebe:taskDefinition` type or the `camunda:topic` attribute instead of the
+    /// name, so diagrams authored for Camunda can run without renaming the tasks. Tasks without a type use the name or id.
+    pub fn task_types(mut self) -> Self {
+        self.handler.set_task_types();
+        self
+    }
+
+    /// Fail `build` with `Error::UnusedImplementations` when a registered function matches no element in the diagram,
+    /// for example after renaming an element. Unused functions are only logged as warnings by default.
+    pub fn strict(mut self) -> Self {
+        self.handler.set_strict();
+        self
+    }
+
+    // Register a function for every task and gateway without one. Tasks do nothing and gateways take
+    // the default flow, or the first outgoing flow.
+    pub(crate) fn stub_unregistered(mut self) -> Self {
+        let stubs = self.diagram.stubs(
+            |handler_type, name| self.handler.is_registered(handler_type, name),
+            self.handler.task_types(),
+        );
+        for stub in stubs {
+            self = match stub {
+                Stub::Task(name) => self.task(name, |_| Ok(None)),
+                Stub::Exclusive(name, flow) => {
+                    self.exclusive_owned(name, move |_| Ok(flow.clone()))
+                }
+                Stub::Inclusive(name, flow) => self.inclusive(name, move |_| {
+                    Ok(flow.clone().map_or(With::Default, With::from))
+                }),
+                Stub::EventBased(name, event) => self.event_based(name, move |_| Ok(event.clone())),
+            };
+        }
+        self
+    }
+
+    /// Check the registered functions against the diagram without building. Lists the elements without a function
+    /// and the functions with a name or id that matches no element.
+    pub fn build_report(&self) -> BuildReport {
+        self.handler
+            .handler_map()
+            .map(|handler_map| {
+                self.diagram
+                    .clone()
+                    .install_and_check(handler_map, self.handler.task_types())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Policy when a gateway selects the same outgoing sequence flow more than once. Default is `DuplicateFlows::Warn`
+    /// that discards the duplicates. Use `DuplicateFlows::Allow` if the model intends multiple tokens on the same flow.
+    pub fn duplicate_flows(mut self, policy: DuplicateFlows) -> Self {
+        self.handler.set_duplicate_flows(policy);
+        self
+    }
+
+    /// Map the end events to the `Outcome` type `P`. The run returns `ProcessOutput<T, P>` with the outcome,
+    /// and `build` fails with the end events that have no outcome.
+    pub fn outcomes<P: Outcome>(self) -> Process<T, Build<P>, L> {
+        Process {
+            diagram: self.diagram,
+            handler: self.handler,
+            _marker: Default::default(),
+        }
+    }
+
+    /// Build like `build`, but tasks without a registered function do nothing and gateways without one take the
+    /// default flow, or the first outgoing flow. Smoke run a diagram right after modeling, before the functions exist.
+    /// The replaced functions are logged as warnings.
+    pub fn build_lenient(mut self) -> Result<Process<T, Run<O>, L>, Error> {
+        for missing in self.build_report().missing {
+            log::warn!("{missing} has no function and does nothing");
+        }
+        self.handler.set_lenient();
+        self.stub_unregistered().build()
+    }
+
+    /// Install and check that all required functions have been registered. You cannot run a process before `build` is called.
+    /// If `build` returns an error, it contains the missing functions, the parallel joins that can never receive enough tokens
+    /// or the end events without an outcome.
+    pub fn build(mut self) -> Result<Process<T, Run<O>, L>, Error> {
+        let handler_map = self.handler.build()?;
+        install::<T, O, L>(&mut self.diagram, &self.handler, &handler_map)?;
+        self.handler.set_installed_map(handler_map);
+        Ok(Process {
+            diagram: self.diagram,
+            handler: self.handler,
             _marker: Default::default(),
         })
     }
+}
 
-    /// Register a task function with name or bpmn id
-    pub fn task<F>(mut self, name: impl Into<String>, func: F) -> Self
+#[cfg(not(feature = "parallel"))]
+impl<T, O: Outcome> Process<T, Build<O>, Sequential<T>> {
+    /// Register a task function with name or bpmn id that receives the data as `&mut T`. See `sequential`.
+    pub fn task_mut<F>(self, name: impl Into<String>, func: F) -> Self
     where
-        F: Fn(Data<T>) -> Result<TaskResult, Error> + 'static + Sync + Send,
+        F: Fn(&mut T) -> Result<TaskResult, Error> + 'static + Sync + Send,
     {
-        self.handler
-            .add_callback(name, Callback::Task(Box::new(func)));
-        self
+        self.task_with_context(name, move |_, data| data.with_mut(&func))
     }
 
-    /// Register an exclusive gateway function with name or bpmn id
-    pub fn exclusive<F>(mut self, name: impl Into<String>, func: F) -> Self
+    /// Register an exclusive gateway function with name or bpmn id that receives the data as `&mut T`.
+    /// See `sequential`.
+    pub fn exclusive_mut<F, N>(self, name: impl Into<String>, func: F) -> Self
     where
-        F: Fn(Data<T>) -> Result<Option<&'static str>, Error> + 'static + Sync + Send,
+        F: Fn(&mut T) -> Result<Option<N>, Error> + 'static + Sync + Send,
+        N: Into<Cow<'static, str>>,
     {
-        self.handler
-            .add_callback(name, Callback::Exclusive(Box::new(func)));
-        self
+        self.exclusive_owned_with_context(name, move |_, data| data.with_mut(&func))
     }
 
-    /// Register an inclusive gateway function with name or bpmn id
-    pub fn inclusive<F>(mut self, name: impl Into<String>, func: F) -> Self
+    /// Register an inclusive gateway function with name or bpmn id that receives the data as `&mut T`.
+    /// See `sequential`.
+    pub fn inclusive_mut<F>(self, name: impl Into<String>, func: F) -> Self
     where
-        F: Fn(Data<T>) -> Result<With, Error> + 'static + Sync + Send,
+        F: Fn(&mut T) -> Result<With, Error> + 'static + Sync + Send,
     {
-        self.handler
-            .add_callback(name, Callback::Inclusive(Box::new(func)));
-        self
+        self.inclusive_with_context(name, move |_, data| data.with_mut(&func))
     }
 
-    /// Register an event based gateway function with name or bpmn id
-    pub fn event_based<F>(mut self, name: impl Into<String>, func: F) -> Self
+    /// Register an event based gateway function with name or bpmn id that receives the data as `&mut T`.
+    /// See `sequential`.
+    pub fn event_based_mut<F>(self, name: impl Into<String>, func: F) -> Self
     where
-        F: Fn(Data<T>) -> Result<IntermediateEvent, Error> + 'static + Sync + Send,
+        F: Fn(&mut T) -> Result<IntermediateEvent, Error> + 'static + Sync + Send,
     {
-        self.handler
-            .add_callback(name, Callback::EventBased(Box::new(func)));
-        self
+        self.event_based_with_context(name, move |_, data| data.with_mut(&func))
     }
+}
 
-    /// Install and check that all required functions have been registered. You cannot run a process before `build` is called.
-    /// If `build` returns an error, it contains the missing functions.
-    pub fn build(mut self) -> Result<Process<T, Run>, Error> {
-        let result = self.diagram.install_and_check(self.handler.build()?);
-        if result.is_empty() {
-            Ok(Process {
-                diagram: self.diagram,
-                handler: self.handler,
-                _marker: Default::default(),
-            })
-        } else {
-            Err(Error::MissingImplementations(
-                result.into_iter().collect::<Vec<_>>().join(", "),
-            ))
+// Install the registered functions in the diagram and check that it can run
+fn install<T, O: Outcome, L>(
+    diagram: &mut Diagram,
+    handler: &Handler<T, L>,
+    handler_map: &HandlerMap,
+) -> Result<(), Error> {
+    let report = diagram.install_and_check(handler_map, handler.task_types());
+    if !report.missing.is_empty() {
+        return Err(Error::MissingImplementations(report.missing.join(", ")));
+    }
+    if !report.unused.is_empty() {
+        if handler.strict() {
+            return Err(Error::UnusedImplementations(report.unused.join(", ")));
         }
+        for unused in &report.unused {
+            log::warn!("{unused} matches no element in the diagram");
+        }
+    }
+
+    let unknown: Vec<String> = handler
+        .flow_names()
+        .iter()
+        .flat_map(|(gateway, flows)| diagram.unknown_flows(gateway, flows))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(Error::UnknownFlows(unknown.join(", ")));
+    }
+
+    let deadlocks = diagram.deadlocks();
+    if !deadlocks.is_empty() {
+        return Err(Error::Deadlock(
+            deadlocks
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+        ));
+    }
+
+    let missing: Vec<String> = diagram
+        .end_events()
+        .filter(|event| O::from_end_node(&EndNode::from(Some(*event))).is_none())
+        .map(ToString::to_string)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingOutcomes(missing.join(", ")));
+    }
+
+    diagram.compile();
+    Ok(())
+}
+
+fn cache_key<T, K>(key: impl Fn(&T) -> K, data: &impl SharedData<T>) -> Result<K, Error> {
+    let data = data
+        .read()
+        .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?;
+    Ok(key(&data))
+}
+
+impl<T, S: Sync + Send, L> Process<T, S, L> {
+    /// Validate the diagram and return structured diagnostics, for example unreachable elements,
+    /// gateways without default flow and unsupported elements. An empty result means no problems was found.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.diagram.validate()
+    }
+
+    /// BPMN ids of the executable processes in the definitions, in the order `run` executes them
+    pub fn process_ids(&self) -> Vec<&str> {
+        self.diagram
+            .get_definition()
+            .into_iter()
+            .flat_map(|definition| definition.iter())
+            .filter_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    id,
+                    executable: true,
+                    ..
+                } => Some(id.bpmn()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Parts of the diagram that was skipped or not honored when it was read, like text annotations,
+    /// unsupported elements and the content of event definitions.
+    pub fn parse_warnings(&self) -> &[ParseWarning] {
+        self.diagram.warnings()
+    }
+
+    /// Elements and sequence flows of every process and sub process in the parsed diagram, with their
+    /// connections. For tooling like documentation generators and custom validators.
+    pub fn elements(&self) -> Vec<ElementInfo<'_>> {
+        self.diagram.elements()
+    }
+
+    pub(crate) fn coverage_items(&self) -> Vec<CoverageItem> {
+        self.diagram.coverage_items()
+    }
+
+    /// Convert the parsed diagram to Graphviz DOT. Useful to compare what is parsed with what the modeler shows.
+    pub fn to_dot(&self) -> String {
+        self.diagram.to_dot(&HashSet::new())
+    }
+
+    /// Documentation page for every top level process with a DOT graph including a legend, and markdown with the
+    /// `bpmn:documentation` of the process and its elements. Save the pages to generate a process handbook.
+    pub fn handbook(&self) -> Vec<HandbookPage> {
+        self.diagram.handbook()
+    }
+
+    /// Convert the parsed diagram to Graphviz DOT and highlight the elements and sequence flows in the trace.
+    /// The trace contains BPMN ids, for example collected with a `listener`.
+    pub fn to_dot_with_trace<I>(&self, trace: I) -> String
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let trace = trace.into_iter().collect::<Vec<_>>();
+        self.diagram
+            .to_dot(&trace.iter().map(AsRef::as_ref).collect())
+    }
+
+    /// Convert the parsed diagram to a Mermaid flowchart that can be embedded in markdown.
+    pub fn to_mermaid(&self) -> String {
+        self.diagram.to_mermaid(&HashSet::new())
+    }
+
+    /// Convert the parsed diagram to a Mermaid flowchart and highlight the elements and sequence flows in the trace.
+    pub fn to_mermaid_with_trace<I>(&self, trace: I) -> String
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let trace = trace.into_iter().collect::<Vec<_>>();
+        self.diagram
+            .to_mermaid(&trace.iter().map(AsRef::as_ref).collect())
     }
 }
 
@@ -125,15 +1144,41 @@ impl<T> FromStr for Process<T> {
     /// }
     /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        Ok(Self {
-            diagram: read_bpmn(quick_xml::Reader::from_str(s))?,
-            handler: Default::default(),
-            _marker: Default::default(),
-        })
+        Ok(Self::from_diagram(read_bpmn(s.as_bytes())?))
     }
 }
 
-impl<T> Process<T, Run> {
+impl<T, O: Outcome, L: SharedData<T> + Send + Sync> Process<T, Run<O>, L> {
+    /// Parse the BPMN file again with the `ParseOptions` of the process and return a new process with the registered
+    /// functions. The same checks as `build` are made, and the error contains the missing functions. A process built
+    /// with `build_lenient` gets stubs for the new elements. This process is not changed, so a service can swap the
+    /// process it shares between threads once the reload succeeds. Both processes share the state of limits,
+    /// circuit breakers and caches.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<()>::new("examples/example.bpmn")?.build_lenient()?;
+    ///     let bpmn = bpmn.reload("examples/example.bpmn")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn reload(&self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let mut process = Process::<T, Build<O>, L> {
+            diagram: read_bpmn_with(
+                BufReader::new(File::open(path)?),
+                self.handler.parse_options(),
+            )?,
+            handler: self.handler.clone(),
+            _marker: Default::default(),
+        };
+        if process.handler.reopen() {
+            process.build_lenient()
+        } else {
+            process.build()
+        }
+    }
+
     /// Run the process and return the `ProcessOutput<T>` containing the final data and end node information, or an `Error`.
     ///
     /// Registered functions can return `Err(Error)` to stop execution immediately.
@@ -176,16 +1221,419 @@ impl<T> Process<T, Run> {
     ///     Ok(())
     /// }
     /// ```
-    pub fn run(&self, data: T) -> Result<ProcessOutput<T>, Error>
+    pub fn run(&self, data: T) -> Result<ProcessOutput<T, O>, Error>
+    where
+        T: Send,
+    {
+        self.run_with(data, RunOptions::default())
+    }
+
+    /// Run the process on a new thread. The `RunHandle` follows the status and the active elements of the run
+    /// while the thread runs, and joining the thread returns the result.
+    ///
+    /// ```
+    /// use snurr::{Process, RunOptions};
+    /// use std::sync::Arc;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task("Count 1", |input| {
+    ///             *input.lock().unwrap() += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///
+    ///     let (handle, thread) = Arc::new(bpmn).spawn(0, RunOptions::default());
+    ///     println!("{:?} at {:?}", handle.status(), handle.active_elements());
+    ///     let result = thread.join().unwrap()?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn spawn(self: &Arc<Self>, data: T, options: RunOptions<'static>) -> Spawned<T, O>
+    where
+        T: Send + 'static,
+        O: Send + 'static,
+        L: 'static,
+    {
+        let handle = options.run_handle.clone().unwrap_or_default();
+        // Running before the thread starts, so the caller never sees a finished run as not started
+        handle.started();
+        let process = Arc::clone(self);
+        let options = options.run_handle(handle.clone());
+        (
+            handle,
+            std::thread::spawn(move || process.run_with(data, options)),
+        )
+    }
+
+    /// Run the process once for every input and return the results in input order.
+    /// With the `parallel` feature the inputs run in parallel on the rayon thread pool.
+    /// Use `runner` to collect the results while the inputs are still running.
+    pub fn run_batch<I>(&self, inputs: I) -> Vec<Result<ProcessOutput<T, O>, Error>>
+    where
+        I: IntoIterator<Item = T>,
+        T: Send,
+        O: Send,
+    {
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::iter::{IntoParallelIterator, ParallelIterator};
+            inputs
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|data| self.run(data))
+                .collect()
+        }
+        #[cfg(not(feature = "parallel"))]
+        inputs.into_iter().map(|data| self.run(data)).collect()
+    }
+
+    /// Run a single process with the BPMN id or name from a definitions file with several processes.
+    /// `run` executes every process in order. Returns `Error::MissingProcessData` if there is no such process.
+    pub fn run_process(&self, id_or_name: &str, data: T) -> Result<ProcessOutput<T, O>, Error>
+    where
+        T: Send,
+    {
+        self.run_with(data, RunOptions::default().process(id_or_name))
+    }
+
+    /// Run the process from the start event with the name or BPMN id, like a message, signal or timer start event.
+    /// Returns `Error::MissingStartEventName` if there is no such start event.
+    pub fn run_from_event(&self, name_or_id: &str, data: T) -> Result<ProcessOutput<T, O>, Error>
+    where
+        T: Send,
+    {
+        self.run_with(data, RunOptions::default().start_event(name_or_id))
+    }
+
+    /// Run the process with `RunOptions`. Services, trace context, a `CancelToken` and a timeout can be given to the run.
+    /// A cancelled run returns `Error::Cancelled` and a run exceeding the timeout returns `Error::Timeout`,
+    /// both with the BPMN ids of the elements that were active.
+    pub fn run_with(&self, data: T, options: RunOptions) -> Result<ProcessOutput<T, O>, Error>
+    where
+        T: Send,
+    {
+        self.run_data(Arc::new(L::new(data)), options, None)
+            .map_err(|snapshot| snapshot.error)
+    }
+
+    /// Run the process with `RunOptions` and return a `Snapshot` when the run fails. After correcting the data,
+    /// or deploying a fix, `resume` continues the run from the failed task instead of running the process again.
+    pub fn run_resumable(
+        &self,
+        data: T,
+        options: RunOptions,
+    ) -> Result<ProcessOutput<T, O>, Snapshot<T, L>>
+    where
+        T: Send,
+    {
+        self.run_data(Arc::new(L::new(data)), options, None)
+    }
+
+    /// Resume a failed run from the task that failed, with the data of the snapshot. Elements before the task
+    /// are not run again.
+    ///
+    /// Only a run where the failed task was the only live token can continue from the task, see
+    /// `Snapshot::is_resumable`. A run that didn't fail in a task, or failed while other tokens were live in
+    /// parallel branches, is run again from the start.
+    ///
+    /// ```
+    /// use snurr::{Error, Process, RunOptions};
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task("Count 1", |input| {
+    ///             let mut count = input.lock().unwrap();
+    ///             if *count == 100 {
+    ///                 return Err(Error::ProcessExecution("count too high".into()));
+    ///             }
+    ///             *count += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///
+    ///     if let Err(snapshot) = bpmn.run_resumable(100, RunOptions::default()) {
+    ///         *snapshot.data.lock().unwrap() = 0;
+    ///         let result = bpmn.resume(snapshot, RunOptions::default()).map_err(|snapshot| snapshot.error)?;
+    ///         assert_eq!(result.data, 3);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn resume(
+        &self,
+        snapshot: Snapshot<T, L>,
+        options: RunOptions,
+    ) -> Result<ProcessOutput<T, O>, Snapshot<T, L>>
+    where
+        T: Send,
+    {
+        self.run_data(snapshot.data, options, snapshot.position)
+    }
+
+    /// Migrate a snapshot of a failed run of another version of the diagram to this process, so `resume`
+    /// continues the run here. The failed task, its enclosing subprocesses and the process are found by BPMN id.
+    /// `remap` maps the ids of elements that were renamed from the old diagram to this one.
+    ///
+    /// Returns `Error::UnmappedElement` with the old id of the first element that isn't in this diagram,
+    /// and leaves the snapshot unchanged. A snapshot that isn't resumable has nothing to migrate.
+    ///
+    /// ```
+    /// use snurr::{Error, Process, RunOptions};
+    /// use std::collections::HashMap;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let v1 = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task("Count 1", |_| Err(Error::ProcessExecution("not deployed".into())))
+    ///         .exclusive("equal to 3", |_| Ok(Some("YES")))
+    ///         .build()?;
+    ///     let v2 = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task("Count 1", |input| {
+    ///             *input.lock().unwrap() += 1;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///
+    ///     if let Err(mut snapshot) = v1.run_resumable(0, RunOptions::default()) {
+    ///         v2.migrate(&mut snapshot, &HashMap::new())?;
+    ///         let result = v2.resume(snapshot, RunOptions::default()).map_err(|snapshot| snapshot.error)?;
+    ///         assert_eq!(result.data, 3);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn migrate(
+        &self,
+        snapshot: &mut Snapshot<T, L>,
+        remap: &HashMap<&str, &str>,
+    ) -> Result<(), Error> {
+        let Some(position) = snapshot.position.as_deref() else {
+            return Ok(());
+        };
+        let position = self.migrate_position(position, remap)?;
+        snapshot.element = position.ids.last().cloned();
+        snapshot.position = Some(Box::new(position));
+        Ok(())
+    }
+
+    // Find the process and the path of the position in this diagram by BPMN id
+    fn migrate_position(
+        &self,
+        position: &Position,
+        remap: &HashMap<&str, &str>,
+    ) -> Result<Position, Error> {
+        let lookup = |id: &str| remap.get(id).copied().unwrap_or(id).to_string();
+        let unmapped = |id: &str| Error::UnmappedElement(id.into());
+
+        let old_process = position.process.as_deref().unwrap_or_default();
+        let process = lookup(old_process);
+        let mut data_index = self
+            .diagram
+            .get_definition()
+            .into_iter()
+            .flat_map(|definition| definition.iter())
+            .find_map(|bpmn| match bpmn {
+                Bpmn::Process {
+                    id,
+                    data_index: Some(index),
+                    ..
+                } if id.bpmn() == process => Some(*index),
+                _ => None,
+            })
+            .ok_or_else(|| unmapped(old_process))?;
+
+        let mut path = Vec::with_capacity(position.ids.len());
+        let mut ids = Vec::with_capacity(position.ids.len());
+        for (index, old_id) in position.ids.iter().enumerate() {
+            let id = lookup(old_id);
+            let activity = self
+                .diagram
+                .get_process(data_index)
+                .into_iter()
+                .flat_map(|process_data| process_data.iter())
+                .find_map(|bpmn| match bpmn {
+                    Bpmn::Activity(activity) if activity.id.bpmn() == id => Some(activity),
+                    _ => None,
+                })
+                .ok_or_else(|| unmapped(old_id))?;
+
+            // Every element but the failed task is a subprocess enclosing the next one
+            if index + 1 < position.ids.len() {
+                let ActivityType::SubProcess {
+                    data_index: Some(index),
+                } = activity.activity_type
+                else {
+                    return Err(unmapped(old_id));
+                };
+                data_index = index;
+            }
+            path.push(*activity.id.local());
+            ids.push(activity.id.bpmn_arc());
+        }
+
+        Ok(Position {
+            process: Some(process.into()),
+            path,
+            ids,
+        })
+    }
+
+    fn run_data(
+        &self,
+        data: Arc<L>,
+        options: RunOptions,
+        position: Option<Box<Position>>,
+    ) -> Result<ProcessOutput<T, O>, Snapshot<T, L>>
     where
         T: Send,
     {
-        let data = Arc::new(Mutex::new(data));
-        let mut end_node_name = None;
-        let mut end_node_id = String::new();
-        let mut end_event_symbol = Symbol::None;
+        let failed = |error, data| Snapshot {
+            error,
+            data,
+            element: None,
+            position: None,
+            _marker: PhantomData,
+        };
+        let (process, start_event) = (options.process, options.start_event);
+        #[cfg(feature = "parallel")]
+        let owned_pool;
+        #[cfg(feature = "parallel")]
+        let pool = match (options.thread_pool, options.max_concurrency) {
+            (Some(pool), _) => Some(pool),
+            (None, Some(threads)) => {
+                owned_pool = rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .map_err(|error| failed(Error::ProcessExecution(error.into()), data.clone()))?;
+                Some(&owned_pool)
+            }
+            (None, None) => None,
+        };
+        let mut state = RunState::new(options);
+        if let Some(handle) = &state.run_handle {
+            handle.started();
+        }
+        // The first start is the top level process, the rest are the enclosing subprocesses of the task
+        let resume = position.and_then(|position| {
+            let (&start, subprocesses) = position.path.split_first()?;
+            state.resume = Mutex::new(subprocesses.iter().rev().copied().collect());
+            Some((position.process?, start))
+        });
+        state.decisions = self.handler.decision_cache();
+        state.history = self.handler.history().map(|_| Default::default());
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "process",
+            trace_id = state.trace_id().map(tracing::field::display),
+            correlation_id = state.correlation_id().map(tracing::field::display)
+        );
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        let run_processes = || {
+            #[cfg(feature = "parallel")]
+            if let Some(pool) = pool {
+                return pool.install(|| {
+                    self.run_processes(&data, &state, process, start_event, resume.clone())
+                });
+            }
+            self.run_processes(&data, &state, process, start_event, resume.clone())
+        };
+
+        let end_event = match self.handler.idle_threshold() {
+            // No threads to watch from
+            #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+            Some(_) => {
+                log::warn!("Idle detection is not available on this target");
+                run_processes()
+            }
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            Some(threshold) => std::thread::scope(|scope| {
+                let (stop, receiver) = std::sync::mpsc::channel();
+                scope.spawn(|| self.watch_idle(&state, threshold, receiver));
+                let result = run_processes();
+                drop(stop);
+                result
+            }),
+            None => run_processes(),
+        };
+        if let Some(handle) = &state.run_handle {
+            handle.finished(&end_event);
+        }
+        if let Some(history) = self.handler.history() {
+            history.record(state.take_history(match &end_event {
+                Ok(end_event) => Ok(EndNode::from(*end_event)),
+                Err(error) => Err(error.to_string()),
+            }));
+        }
+        let end_event = end_event.map_err(|error| {
+            let (element, position) = state
+                .take_failed()
+                .map(|(element, position)| (Some(element), position.map(Box::new)))
+                .unwrap_or_default();
+            Snapshot {
+                error,
+                data: Arc::clone(&data),
+                element,
+                position,
+                _marker: PhantomData,
+            }
+        })?;
+
+        let end_node = EndNode::from(end_event);
+        let Some(outcome) = O::from_end_node(&end_node) else {
+            return Err(failed(Error::MissingOutcomes(end_node.id.clone()), data));
+        };
+
+        let data = Arc::try_unwrap(data)
+            .map_err(|data| failed(Error::NoProcessResult, data))?
+            .into_inner()
+            .map_err(|error| {
+                failed(Error::NoProcessResult, Arc::new(L::new(error.into_inner())))
+            })?;
 
-        // Run every process specified in the diagram
+        Ok(ProcessOutput {
+            data,
+            end_node,
+            end_nodes: state.take_end_nodes(),
+            milestones: state.take_milestones(),
+            #[cfg(feature = "variables")]
+            variables: state.take_variables(),
+            outcome,
+            correlation_id: state.correlation_id().map(Into::into),
+            metadata: state.metadata().clone(),
+            stats: state.take_stats(),
+        })
+    }
+
+    // Run every process specified in the diagram, or only the given process, and return the end event of the last
+    fn run_processes<'a>(
+        &'a self,
+        data: &Arc<L>,
+        state: &'a RunState<'a>,
+        process: Option<&str>,
+        start_event: Option<&str>,
+        mut resume: Option<(Arc<str>, usize)>,
+    ) -> Result<Option<&'a Event>, Error>
+    where
+        T: Send,
+    {
+        let mut end_event = None;
         for bpmn in self
             .diagram
             .get_definition()
@@ -194,34 +1642,61 @@ impl<T> Process<T, Run> {
         {
             if let Bpmn::Process {
                 id,
+                name,
                 data_index: Some(index),
-                ..
+                executable: true,
             } = bpmn
+                && process
+                    .is_none_or(|process| id.bpmn() == process || name.as_deref() == Some(process))
             {
+                // The processes before the process of the failed task completed in the failed run
+                if resume
+                    .as_ref()
+                    .is_some_and(|(process, _)| **process != *id.bpmn())
+                {
+                    continue;
+                }
                 let process_data = self
                     .diagram
                     .get_process(*index)
                     .ok_or_else(|| Error::MissingProcessData(id.bpmn().into()))?;
-                let end_event = self.execute(ExecuteInput::new(process_data, Arc::clone(&data)))?;
-                end_node_name = end_event.name.clone();
-                end_node_id = end_event.id.bpmn().to_string();
-                end_event_symbol = end_event.symbol.clone().unwrap_or(Symbol::None);
+                // Started before the input, so the tokens of the process are its children
+                #[cfg(feature = "otel")]
+                let span = state.telemetry.process(
+                    id.bpmn(),
+                    name.as_deref(),
+                    state.correlation_id(),
+                    state.metadata(),
+                );
+                let mut input = ExecuteInput::new(process_data, Arc::clone(data), state);
+                if let Some((_, start)) = resume.take() {
+                    input = input.with_start(start);
+                } else if let Some(name_or_id) = start_event {
+                    // Processes without the start event are not started
+                    let Some(start) = process_data.find_start(name_or_id) else {
+                        continue;
+                    };
+                    input = input.with_start(start);
+                }
+                let end = self.execute(input);
+                #[cfg(feature = "otel")]
+                span.end(&end);
+                let end = end.inspect_err(|_| state.failed_in(id.bpmn()))?;
+                state.emit(|| EngineEvent::ProcessEnded {
+                    process: id.bpmn().into(),
+                    end_node: EndNode::from(Some(end)),
+                });
+                end_event = Some(end);
             }
         }
-
-        let data = Arc::into_inner(data)
-            .ok_or(Error::NoProcessResult)?
-            .into_inner()
-            .map_err(|_| Error::NoProcessResult)?;
-
-        Ok(ProcessOutput {
-            data,
-            end_node: EndNode {
-                id: end_node_id,
-                name: end_node_name,
-                symbol: end_event_symbol,
-            },
-        })
+        if let Some((process, _)) = resume {
+            return Err(Error::MissingProcessData(process.to_string()));
+        }
+        match (end_event, start_event, process) {
+            (None, Some(start_event), _) => Err(Error::MissingStartEventName(start_event.into())),
+            (None, None, Some(process)) => Err(Error::MissingProcessData(process.into())),
+            (end_event, ..) => Ok(end_event),
+        }
     }
 }
 
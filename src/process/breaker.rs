@@ -0,0 +1,127 @@
+use crate::{
+    Context, Error,
+    api::{Boundary, TaskResult},
+    time::Instant,
+};
+use log::warn;
+use std::{
+    sync::{Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    // Consecutive failures
+    Closed(u32),
+    Open(Instant),
+    // One call is let through to test if the task works again
+    HalfOpen,
+}
+
+/// Circuit breaker for a task. Register it with `Process::circuit_breaker`.
+///
+/// The circuit opens when the task fails `threshold` times in a row. While it is open the function isn't called,
+/// the token continues with the boundary of the breaker or the run fails with `Error::CircuitOpen`.
+/// After the cool-down one call is let through. The circuit closes if it succeeds and opens again if it fails.
+/// The state is shared by all tokens and all runs of the process.
+///
+/// ```
+/// use snurr::{CircuitBreaker, Symbol};
+/// use std::time::Duration;
+///
+/// let breaker = CircuitBreaker::new(5, Duration::from_secs(30)).boundary(("Unavailable", Symbol::Error));
+/// ```
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cool_down: Duration,
+    boundary: Option<Boundary>,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    /// Open the circuit after `threshold` failures in a row and keep it open for `cool_down`.
+    pub fn new(threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            threshold,
+            cool_down,
+            boundary: None,
+            state: Mutex::new(State::Closed(0)),
+        }
+    }
+
+    /// Continue with the boundary of the task while the circuit is open instead of failing the run.
+    pub fn boundary(mut self, boundary: impl Into<Boundary>) -> Self {
+        self.boundary = Some(boundary.into());
+        self
+    }
+
+    // The function can be called. Moves an open circuit past its cool-down to half open.
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match *state {
+            State::Closed(_) => true,
+            State::Open(since) if since.elapsed() >= self.cool_down => {
+                *state = State::HalfOpen;
+                true
+            }
+            State::Open(_) | State::HalfOpen => false,
+        }
+    }
+
+    // Returns true if the failure opened the circuit
+    fn record(&self, success: bool) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let next = match *state {
+            _ if success => State::Closed(0),
+            State::Closed(failures) if failures + 1 < self.threshold => State::Closed(failures + 1),
+            _ => State::Open(Instant::now()),
+        };
+        let opened = matches!(next, State::Open(_)) && !matches!(*state, State::Open(_));
+        *state = next;
+        opened
+    }
+}
+
+// Records a failure when the function panics, so a panicking call through a half open circuit opens it again
+struct PanicGuard<'a>(&'a CircuitBreaker);
+
+impl Drop for PanicGuard<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.record(false);
+        }
+    }
+}
+
+// Run the task through the breaker. An open circuit skips the function.
+pub(super) fn run(
+    breaker: Option<&CircuitBreaker>,
+    context: &mut Context,
+    func: impl FnOnce(&mut Context) -> Result<TaskResult, Error>,
+) -> Result<TaskResult, Error> {
+    let Some(breaker) = breaker else {
+        return func(context);
+    };
+
+    let name = context.name.unwrap_or(context.id);
+    if !breaker.allow() {
+        warn!("{}{name} circuit is open", context.log_prefix);
+        return match &breaker.boundary {
+            Some(boundary) => Ok(Some(boundary.clone())),
+            None => Err(Error::CircuitOpen(name.into())),
+        };
+    }
+
+    let guard = PanicGuard(breaker);
+    let result = func(context);
+    drop(guard);
+    if breaker.record(result.is_ok()) {
+        warn!(
+            "{}{name} failed. Open the circuit for {:?}",
+            context.log_prefix, breaker.cool_down
+        );
+    }
+    result
+}
@@ -0,0 +1,180 @@
+use crate::api::{IntermediateEvent, With};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+// Decisions kept by a cache created with `Default`
+const DEFAULT_CAPACITY: usize = 10_000;
+
+// Id of the next function registered as pure
+static NEXT_FUNCTION: AtomicU64 = AtomicU64::new(0);
+
+/// Cache with gateway decisions from functions registered as pure.
+///
+/// A new cache is used for every run. Install a shared cache with `Process::decision_cache`
+/// to keep the decisions across runs. Decisions are stored per registered function, gateway id and key,
+/// so processes can share a cache. When the cache is full the oldest decision is removed.
+pub struct DecisionCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+#[derive(Default)]
+struct Entries {
+    // Decisions by gateway id, function and hash of the key. Keys with the same hash share the bucket.
+    decisions: HashMap<String, HashMap<(u64, u64), Vec<Entry>>>,
+    // Bucket of every decision, oldest first
+    order: VecDeque<(String, u64, u64)>,
+}
+
+// The key is compared on a hit, because different keys can have the same hash
+struct Entry {
+    key: Box<dyn Any + Send>,
+    decision: Decision,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Decision {
+    Exclusive(Option<Cow<'static, str>>),
+    Inclusive(With),
+    EventBased(IntermediateEvent),
+}
+
+impl Default for DecisionCache {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+}
+
+impl Debug for DecisionCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DecisionCache")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl DecisionCache {
+    /// Cache that keeps at most `capacity` decisions. `Default` keeps 10 000.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Default::default(),
+        }
+    }
+
+    /// Number of cached decisions
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .map(|entries| entries.order.len())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if no decisions are cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all cached decisions
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.decisions.clear();
+            entries.order.clear();
+        }
+    }
+
+    pub(crate) fn get<K: Hash + Eq + 'static>(
+        &self,
+        function: u64,
+        gateway_id: &str,
+        key: &K,
+    ) -> Option<Decision> {
+        self.entries
+            .lock()
+            .ok()?
+            .decisions
+            .get(gateway_id)?
+            .get(&(function, hash_key(key)))?
+            .iter()
+            .find(|entry| entry.key.downcast_ref::<K>() == Some(key))
+            .map(|entry| entry.decision.clone())
+    }
+
+    pub(crate) fn insert<K: Hash + Eq + Send + 'static>(
+        &self,
+        function: u64,
+        gateway_id: &str,
+        key: K,
+        decision: Decision,
+    ) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let hash = hash_key(&key);
+        let bucket = entries
+            .decisions
+            .entry(gateway_id.into())
+            .or_default()
+            .entry((function, hash))
+            .or_default();
+        // Another token made the same decision first
+        if let Some(entry) = bucket
+            .iter_mut()
+            .find(|entry| entry.key.downcast_ref::<K>() == Some(&key))
+        {
+            entry.decision = decision;
+            return;
+        }
+        bucket.push(Entry {
+            key: Box::new(key),
+            decision,
+        });
+        entries.order.push_back((gateway_id.into(), function, hash));
+        while entries.order.len() > self.capacity {
+            entries.remove_oldest();
+        }
+    }
+}
+
+impl Entries {
+    // The oldest decision is the first in its bucket
+    fn remove_oldest(&mut self) {
+        let Some((gateway_id, function, hash)) = self.order.pop_front() else {
+            return;
+        };
+        let Some(buckets) = self.decisions.get_mut(&gateway_id) else {
+            return;
+        };
+        if let Some(bucket) = buckets.get_mut(&(function, hash)) {
+            bucket.remove(0);
+            if bucket.is_empty() {
+                buckets.remove(&(function, hash));
+            }
+        }
+        if buckets.is_empty() {
+            self.decisions.remove(&gateway_id);
+        }
+    }
+}
+
+// Id that scopes the decisions of a function registered as pure, so processes sharing a cache don't
+// get each other's decisions for gateways with the same id
+pub(crate) fn next_function() -> u64 {
+    NEXT_FUNCTION.fetch_add(1, Ordering::Relaxed)
+}
+
+pub(crate) fn hash_key(key: impl Hash) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,83 @@
+//! Registrations generated by the attribute macros. Not a public API, the items are only used by the
+//! code generated by `snurr-macros` and `collect_handlers!`.
+
+use super::{Build, Process};
+use crate::{
+    Error,
+    api::{Context, Data, IntermediateEvent, Outcome, TaskResult, With},
+};
+use std::any::Any;
+
+pub use inventory;
+
+/// Function annotated with `#[snurr::task(...)]`, `#[snurr::exclusive(...)]`, `#[snurr::inclusive(...)]`
+/// or `#[snurr::event_based(...)]`. Functions without a context are wrapped by the macro.
+pub enum Handler<T> {
+    Task(fn(&Context, Data<T>) -> Result<TaskResult, Error>),
+    Exclusive(fn(&Context, Data<T>) -> Result<Option<&'static str>, Error>),
+    Inclusive(fn(&Context, Data<T>) -> Result<With, Error>),
+    EventBased(fn(&Context, Data<T>) -> Result<IntermediateEvent, Error>),
+}
+
+/// Name or bpmn id of the element and its `Handler`. The handler is type erased, so annotated functions
+/// for different data types are collected together.
+pub struct Registration {
+    name: &'static str,
+    handler: &'static (dyn Any + Send + Sync),
+}
+
+impl Registration {
+    pub const fn new<T: 'static>(name: &'static str, handler: &'static Handler<T>) -> Self {
+        Self { name, handler }
+    }
+}
+
+inventory::collect!(Registration);
+
+/// Register every annotated function with the data type of the process
+pub fn collect<T: 'static, O: Outcome>(mut process: Process<T, Build<O>>) -> Process<T, Build<O>> {
+    for registration in inventory::iter::<Registration> {
+        let name = registration.name;
+        process = match registration.handler.downcast_ref::<Handler<T>>() {
+            Some(Handler::Task(func)) => process.task_with_context(name, *func),
+            Some(Handler::Exclusive(func)) => process.exclusive_with_context(name, *func),
+            Some(Handler::Inclusive(func)) => process.inclusive_with_context(name, *func),
+            Some(Handler::EventBased(func)) => process.event_based_with_context(name, *func),
+            None => process,
+        };
+    }
+    process
+}
+
+/// Register every function annotated with `#[snurr::task(...)]`, `#[snurr::exclusive(...)]`,
+/// `#[snurr::inclusive(...)]` or `#[snurr::event_based(...)]` that takes the data type of the process.
+/// The functions are collected from the whole program, also from other modules and function bodies.
+/// Requires the `macros` feature.
+///
+/// ```
+/// use snurr::{Data, Process, TaskResult, collect_handlers};
+///
+/// #[snurr::task("Count 1")]
+/// fn count(input: Data<u32>) -> snurr::Result<TaskResult> {
+///     *input.lock().unwrap() += 1;
+///     Ok(None)
+/// }
+///
+/// #[snurr::exclusive("equal to 3")]
+/// fn equal_to_3(input: Data<u32>) -> snurr::Result<Option<&'static str>> {
+///     Ok(Some(if *input.lock().unwrap() == 3 { "YES" } else { "NO" }))
+/// }
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let process = Process::new("examples/example.bpmn")?;
+///     let bpmn = collect_handlers!(process).build()?;
+///     bpmn.run(0)?;
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! collect_handlers {
+    ($process:expr $(,)?) => {
+        $crate::__private::collect($process)
+    };
+}
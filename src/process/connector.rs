@@ -0,0 +1,60 @@
+use crate::{
+    api::{Context, TaskResult},
+    bpmn::PROPERTY_CONNECTOR,
+    error::Error,
+};
+use std::sync::{Arc, Mutex};
+
+#[cfg(all(feature = "http", feature = "variables"))]
+pub(crate) mod http;
+
+/// Integration that is configured in the model and implemented once in code. A task with a `connector`
+/// property and no registered function runs the connector registered with that name by `Process::connector`.
+/// The other properties of the task, like an url, are the configuration.
+///
+/// Implemented for closures with the same signature as `execute`. `L` is the lock of the data, see `Process::data_lock`.
+pub trait Connector<T, L = Mutex<T>>: Send + Sync {
+    fn execute(&self, config: &ConnectorConfig, data: Arc<L>) -> Result<TaskResult, Error>;
+}
+
+impl<T, L, F> Connector<T, L> for F
+where
+    F: Fn(&ConnectorConfig, Arc<L>) -> Result<TaskResult, Error> + Send + Sync,
+{
+    fn execute(&self, config: &ConnectorConfig, data: Arc<L>) -> Result<TaskResult, Error> {
+        self(config, data)
+    }
+}
+
+/// Configuration of a connector task from the properties in the `extensionElements` of the task
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectorConfig<'a> {
+    pub(crate) context: &'a Context<'a>,
+}
+
+impl<'a> ConnectorConfig<'a> {
+    /// Name of the connector
+    pub fn connector(&self) -> &'a str {
+        self.get(PROPERTY_CONNECTOR).unwrap_or_default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.context.property(key)
+    }
+
+    /// The property or `Error::MissingConnectorProperty`
+    pub fn require(&self, key: &str) -> Result<&'a str, Error> {
+        self.get(key)
+            .ok_or_else(|| Error::MissingConnectorProperty(key.into(), self.context.id().into()))
+    }
+
+    /// Every property of the task, the `connector` property included
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.context.properties()
+    }
+
+    /// Context of the task
+    pub fn context(&self) -> &'a Context<'a> {
+        self.context
+    }
+}
@@ -0,0 +1,123 @@
+use super::{Connector, ConnectorConfig};
+use crate::{api::TaskResult, diagram::Source, error::Error, variables::Variables};
+use serde_json::{Value, json};
+use std::{sync::Arc, time::Duration};
+use ureq::{Agent, http::Request};
+
+// Headers are properties prefixed with header, like header.Content-Type
+const HEADER_PREFIX: &str = "header.";
+const DEFAULT_METHOD: &str = "GET";
+const DEFAULT_RESULT_VARIABLE: &str = "response";
+
+/// Connector that sends the HTTP request configured by the properties of the task. Requires the `http` and
+/// `variables` features.
+///
+/// | Property | Description |
+/// |---|---|
+/// | `url` | Required |
+/// | `method` | Default `GET` |
+/// | `header.<name>` | A request header, like `header.Content-Type` |
+/// | `body` | Request body |
+/// | `resultVariable` | Variable for the response. Default `response`. |
+///
+/// `${path}` in the url, headers and body is replaced with the process variable, like `${order.id}`.
+/// The response is stored as `{"status": 200, "body": ...}` with the body parsed as JSON when possible.
+/// An error status doesn't fail the task, a gateway can select a flow by the status.
+///
+/// ```
+/// use snurr::{HttpConnector, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<()>::new("tests/files/http_connector.bpmn")?
+///         .connector("http", HttpConnector::new())
+///         .build()?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpConnector {
+    agent: Agent,
+}
+
+impl Default for HttpConnector {
+    fn default() -> Self {
+        Self::with_timeout(None)
+    }
+}
+
+impl HttpConnector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail requests that take longer than `timeout`
+    pub fn timeout(timeout: Duration) -> Self {
+        Self::with_timeout(Some(timeout))
+    }
+
+    fn with_timeout(timeout: Option<Duration>) -> Self {
+        Self {
+            agent: Agent::config_builder()
+                .http_status_as_error(false)
+                .timeout_global(timeout)
+                .build()
+                .into(),
+        }
+    }
+}
+
+impl<T, L> Connector<T, L> for HttpConnector {
+    fn execute(&self, config: &ConnectorConfig, _: Arc<L>) -> Result<TaskResult, Error> {
+        let variables = config.context().variables();
+        let mut request = Request::builder()
+            .method(config.get("method").unwrap_or(DEFAULT_METHOD))
+            .uri(render(config.require("url")?, variables)?);
+        for (key, value) in config.iter() {
+            if let Some(name) = key.strip_prefix(HEADER_PREFIX) {
+                request = request.header(name, render(value, variables)?);
+            }
+        }
+        let mut response = match config.get("body") {
+            Some(body) => self.agent.run(
+                request
+                    .body(render(body, variables)?)
+                    .map_err(ureq::Error::from)?,
+            ),
+            None => self.agent.run(request.body(()).map_err(ureq::Error::from)?),
+        }?;
+
+        let status = response.status().as_u16();
+        let body = response.body_mut().read_to_string()?;
+        let body = serde_json::from_str(&body).unwrap_or(Value::String(body));
+        variables.set(
+            config
+                .get("resultVariable")
+                .unwrap_or(DEFAULT_RESULT_VARIABLE),
+            json!({ "status": status, "body": body }),
+        );
+        Ok(None)
+    }
+}
+
+// Replace every ${path} with the variable. Strings are inserted without quotes, other values as JSON.
+fn render(template: &str, variables: &Variables) -> Result<String, Error> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("${") {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let path = rest[start + 2..start + end].trim();
+        rendered.push_str(&rest[..start]);
+        match variables.resolve(&Source::Variable(path.into())) {
+            Some(Value::String(value)) => rendered.push_str(&value),
+            Some(value) => rendered.push_str(&value.to_string()),
+            None => Err(Error::ProcessExecution(
+                format!("Unknown variable {path} in {template}").into(),
+            ))?,
+        }
+        rest = &rest[start + end + 1..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
@@ -1,22 +1,86 @@
 mod execute_handler;
 
-use super::Run;
+use super::{Run, handler::Selected};
 use crate::{
     Process,
-    api::{Data, With},
+    api::{
+        Boundary, Context, DuplicateFlows, ElementType, EndNode, EngineEvent, ErrorDecision,
+        ExecutionEvent, ExecutionEventKind, Milestone, Position, SharedData, Stats, TraceContext,
+        Transition, TransitionDecision, With,
+    },
     bpmn::{Activity, ActivityType, Bpmn, Event, EventType, Gateway, GatewayType, Symbol},
     diagram::ProcessData,
-    error::{AT_LEAST_TWO_OUTGOING, Error},
+    error::{AT_LEAST_TWO_OUTGOING, DATA_LOCK_ERROR_MSG, Error},
+    history::{HistoryEntry, RunHistory},
+    process::{
+        breaker,
+        cache::DecisionCache,
+        options::{BoundaryTrigger, CancelToken, RunHandle, RunOptions},
+        retry,
+        schedule::{Limit, Shuffle},
+    },
+    time::{self, Instant},
 };
-use execute_handler::ExecuteHandler;
+use execute_handler::{ExecuteHandler, Tokens};
 use log::{info, warn};
-use std::{borrow::Cow, collections::HashSet, sync::Arc};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+        mpsc::Sender,
+    },
+    time::Duration,
+};
+// Waiting for a condition and idle detection need threads
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::process::schedule;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+
+// Retries the error hook can grant a single call before it fails with the error
+const MAX_HOOK_RETRIES: u32 = 10;
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+// State of a single token while it moves through a process
+#[derive(Default)]
+struct TokenState<'a> {
+    // Symbol end event of a subprocess that selected the next boundary event
+    subprocess_end: Option<&'a Event>,
+    // Element the token left before the current element
+    previous: Option<usize>,
+}
+
+// Data of a parallel branch and the branch it was forked from. See `Process::branch_data`.
+struct Branch<L> {
+    data: Arc<L>,
+    base: Scope<L>,
+}
+
+// Branch of a token. None is the data of the process.
+type Scope<L> = Option<Arc<Branch<L>>>;
 
 #[derive(Debug)]
 enum Return<'a> {
     Fork(Cow<'a, [usize]>),
-    Join(&'a Gateway),
+    // Join gateway and the sequence flow the token arrived from
+    Join(&'a Gateway, Option<usize>),
     End(&'a Event),
+    // Error or escalation end event of a subprocess that is caught by an enclosing subprocess or ends the run
+    Propagate(&'a Event),
+    // Conditional catch event with a false condition. The token waits there while the other tokens run.
+    Park(usize, Option<usize>),
 }
 
 macro_rules! maybe_fork {
@@ -26,7 +90,9 @@ macro_rules! maybe_fork {
                 .first()
                 .ok_or_else(|| Error::MissingOutput($ty.to_string()))?
         } else {
-            return Ok(Return::Fork(Cow::Borrowed($outputs.ids())));
+            return Ok(ControlFlow::Break(Return::Fork(Cow::Borrowed(
+                $outputs.ids(),
+            ))));
         }
     };
 }
@@ -40,46 +106,161 @@ macro_rules! find_flow {
     };
 }
 
-impl<T> Process<T, Run> {
-    pub(super) fn execute<'a>(&'a self, input: ExecuteInput<'a, T>) -> Result<&'a Event, Error>
+impl<T, O, L: SharedData<T> + Send + Sync> Process<T, Run<O>, L> {
+    pub(super) fn execute<'a>(&'a self, mut input: ExecuteInput<'a, L>) -> Result<&'a Event, Error>
     where
         T: Send,
     {
         let mut last_visited_end = None;
-        let start = [input.process.start().ok_or(Error::MissingStartEvent)?];
-        let mut handler = ExecuteHandler::new(Cow::from(&start));
+        let start = [input
+            .start
+            .or(input.process.start())
+            .ok_or(Error::MissingStartEvent)?];
+        let mut handler = ExecuteHandler::new(Cow::from(&start), input.parent);
+        let mut live = LiveTokens::new(input.state);
+        // Branch of the tokens that forked and of the tokens waiting at each join
+        let mut scopes: HashMap<usize, Scope<L>> = HashMap::new();
+        let mut joined: HashMap<usize, Vec<Scope<L>>> = HashMap::new();
+        // Tokens park at conditional events while other tokens can change the data
+        let mut progressed = true;
         loop {
-            let active_tokens = handler.active_tokens();
+            let mut active_tokens = handler.active_tokens();
             if active_tokens.is_empty() {
-                return last_visited_end.ok_or(Error::MissingEndEvent);
+                // Every token has ended or waits at a join
+                match handler.stalled(|index| input.process.reachable_from([index]))? {
+                    Some((gateway, token)) => {
+                        let scope = self.merge_join(&input, joined.remove(gateway.id.local()))?;
+                        handler.fork(
+                            self.join_flows(&input.scoped(&scope), gateway, token)?,
+                            token,
+                        );
+                        scopes.insert(token, scope);
+                        continue;
+                    }
+                    None => return last_visited_end.ok_or(Error::MissingEndEvent),
+                }
+            }
+
+            live.count(&active_tokens, &handler, input.process)?;
+            let ready: usize = active_tokens.iter().map(|tokens| tokens.flows.len()).sum();
+            input.state.shuffle_tokens(&mut active_tokens);
+            let mut branches = Vec::with_capacity(active_tokens.len());
+            for tokens in &active_tokens {
+                let scope = tokens
+                    .token
+                    .or(tokens.parent)
+                    .and_then(|token| scopes.remove(&token))
+                    .flatten();
+                branches.push(self.fork_branches(&input, scope, tokens.flows.len())?);
             }
 
+            input.park = progressed;
             let flows_iter = {
                 #[cfg(feature = "parallel")]
                 {
-                    use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-                    let results: Vec<Vec<_>> = active_tokens
-                        .par_iter()
-                        .map(|tokens| {
-                            tokens
-                                .par_iter()
-                                .map(|token| self.flow(token, &input))
-                                .collect()
-                        })
-                        .collect::<Vec<_>>();
+                    use rayon::iter::{
+                        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+                        ParallelIterator,
+                    };
+                    let results: Vec<Vec<_>> = if ready < self.handler.parallel_threshold()
+                        || input.state.is_sequential()
+                    {
+                        active_tokens
+                            .iter()
+                            .zip(branches)
+                            .map(|(tokens, branches)| {
+                                tokens
+                                    .flows
+                                    .iter()
+                                    .zip(branches)
+                                    .map(|(flow, scope)| {
+                                        (
+                                            self.flow(
+                                                flow,
+                                                tokens.parent,
+                                                tokens.token,
+                                                &input.scoped(&scope),
+                                            ),
+                                            scope,
+                                        )
+                                    })
+                                    .collect()
+                            })
+                            .collect()
+                    } else {
+                        active_tokens
+                            .par_iter()
+                            .zip(branches.into_par_iter())
+                            .map(|(tokens, branches)| {
+                                tokens
+                                    .flows
+                                    .par_iter()
+                                    .zip(branches.into_par_iter())
+                                    .map(|(flow, scope)| {
+                                        (
+                                            self.flow(
+                                                flow,
+                                                tokens.parent,
+                                                tokens.token,
+                                                &input.scoped(&scope),
+                                            ),
+                                            scope,
+                                        )
+                                    })
+                                    .collect()
+                            })
+                            .collect()
+                    };
                     results.into_iter()
                 }
                 #[cfg(not(feature = "parallel"))]
-                active_tokens
-                    .iter()
-                    .map(|tokens| tokens.iter().map(|token| self.flow(token, &input)))
+                {
+                    let input = &input;
+                    active_tokens
+                        .iter()
+                        .zip(branches)
+                        .map(move |(tokens, branches)| {
+                            tokens.flows.iter().zip(branches).map(move |(flow, scope)| {
+                                (
+                                    self.flow(
+                                        flow,
+                                        tokens.parent,
+                                        tokens.token,
+                                        &input.scoped(&scope),
+                                    ),
+                                    scope,
+                                )
+                            })
+                        })
+                }
             };
 
+            let mut cancelled = Vec::new();
+            let mut timed_out = Vec::new();
+            progressed = false;
             for flows_result in flows_iter.rev() {
-                for flow_result in flows_result {
+                for (flow_result, scope) in flows_result {
+                    progressed |= !matches!(flow_result, Ok((_, Return::Park(..))));
                     match flow_result {
-                        Ok(Return::Join(gateway)) => handler.consume_token(Some(gateway)),
-                        Ok(Return::End(event)) => {
+                        Ok((token, Return::Join(gateway, flow))) => {
+                            let waiting = joined.entry(*gateway.id.local()).or_default();
+                            waiting.push(scope);
+                            // Proceed with the outputs once all inputs of a parallel join have arrived.
+                            if let Some(token) = handler.join(gateway, flow, token) {
+                                let scope =
+                                    self.merge_join(&input, joined.remove(gateway.id.local()))?;
+                                handler.fork(
+                                    self.join_flows(&input.scoped(&scope), gateway, token)?,
+                                    token,
+                                );
+                                scopes.insert(token, scope);
+                            }
+                        }
+                        Ok((_, Return::End(event))) => {
+                            self.end_branch(&input, scope)?;
+                            if input.parent.is_none() {
+                                input.state.reached_end(event);
+                            }
                             if let Event {
                                 event_type: EventType::End,
                                 symbol: Some(Symbol::Terminate | Symbol::Cancel),
@@ -89,151 +270,726 @@ impl<T> Process<T, Run> {
                                 return Ok(event);
                             }
                             last_visited_end.replace(event);
-                            handler.consume_token(None);
                         }
-                        Ok(Return::Fork(item)) => handler.pending_fork(item),
-                        Err(value) => return Err(value),
-                    }
-                }
-
-                // Check if all inputs have been merged for a gateway, then proceed with its outputs.
-                if let Some(
-                    gateway @ Gateway {
-                        gateway_type,
-                        outputs,
-                        ..
-                    },
-                ) = handler.tokens_consumed()?
-                {
-                    match gateway_type {
-                        GatewayType::Parallel | GatewayType::Inclusive if outputs.len() == 1 => {
-                            handler.immediate(Cow::Borrowed(outputs.ids()));
+                        Ok((token, Return::Fork(item))) => {
+                            handler.fork(item, token);
+                            scopes.insert(token, scope);
                         }
-                        GatewayType::Parallel => {
-                            handler.pending_fork(Cow::Borrowed(outputs.ids()));
+                        Ok((_, Return::Propagate(event))) => {
+                            self.end_branch(&input, scope)?;
+                            // Only errors leave the process. An escalation is caught before.
+                            if input.parent.is_none() {
+                                return Err(Error::UncaughtError(
+                                    event
+                                        .code
+                                        .as_deref()
+                                        .or(event.name.as_deref())
+                                        .unwrap_or(event.id.bpmn())
+                                        .into(),
+                                ));
+                            }
+                            return Ok(event);
+                        }
+                        Ok((token, Return::Park(element, parent))) => {
+                            handler.park(element, token, parent);
+                            scopes.insert(token, scope);
                         }
-                        GatewayType::Inclusive => {
-                            handler.pending_fork(self.handle_inclusive_gateway(&input, gateway)?);
+                        // Collect the active elements of every cancelled token
+                        Err(Error::Cancelled(ids)) => cancelled.extend(ids),
+                        Err(Error::Timeout(ids)) => timed_out.extend(ids),
+                        Err(value) => {
+                            if ready > 1 || handler.waiting().next().is_some() {
+                                input.state.failed_with_tokens();
+                            }
+                            return Err(value);
                         }
-                        _ => {}
                     }
                 }
             }
-            handler.commit();
+
+            if !cancelled.is_empty() {
+                cancelled.extend(timed_out);
+                cancelled.extend(handler.waiting());
+                return Err(Error::Cancelled(cancelled));
+            }
+            if !timed_out.is_empty() {
+                timed_out.extend(handler.waiting());
+                return Err(Error::Timeout(timed_out));
+            }
+        }
+    }
+
+    // Scope of every flow started by a token. Each flow of a fork gets its own branch with data from
+    // the split function, otherwise the flows continue with the scope of the token.
+    fn fork_branches(
+        &self,
+        input: &ExecuteInput<'_, L>,
+        scope: Scope<L>,
+        flows: usize,
+    ) -> Result<Vec<Scope<L>>, Error> {
+        let Some((split, _)) = self.handler.branch_data().filter(|_| flows > 1) else {
+            return Ok(vec![scope; flows]);
+        };
+        let lock = input.scoped(&scope).user_data;
+        let data = lock
+            .read()
+            .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?;
+        Ok((0..flows)
+            .map(|_| {
+                Some(Arc::new(Branch {
+                    data: Arc::new(L::new(split(&data))),
+                    base: scope.clone(),
+                }))
+            })
+            .collect())
+    }
+
+    // Merge the branches that arrived at a join into the data they were forked from.
+    // The joined token continues with the scope of the first branch.
+    fn merge_join(
+        &self,
+        input: &ExecuteInput<'_, L>,
+        waiting: Option<Vec<Scope<L>>>,
+    ) -> Result<Scope<L>, Error> {
+        let waiting = waiting.unwrap_or_default();
+        let scope = waiting
+            .first()
+            .and_then(|scope| scope.as_ref())
+            .and_then(|branch| branch.base.clone());
+        for branch in waiting.into_iter().flatten() {
+            self.merge_branch(input, &branch)?;
+        }
+        Ok(scope)
+    }
+
+    // Merge a branch that ended, and the branches it was forked from that no token uses anymore.
+    fn end_branch(&self, input: &ExecuteInput<'_, L>, scope: Scope<L>) -> Result<(), Error> {
+        let mut scope = scope.and_then(Arc::into_inner);
+        while let Some(branch) = scope {
+            self.merge_branch(input, &branch)?;
+            scope = branch.base.and_then(Arc::into_inner);
         }
+        Ok(())
     }
 
-    // Each flow process one "token" and returns on a Fork, Join or End.
+    fn merge_branch(&self, input: &ExecuteInput<'_, L>, branch: &Branch<L>) -> Result<(), Error> {
+        let Some((_, merge)) = self.handler.branch_data() else {
+            return Ok(());
+        };
+        let data = branch
+            .data
+            .read()
+            .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?;
+        let target = input.scoped(&branch.base).user_data;
+        let mut target = target
+            .lock()
+            .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?;
+        merge(&mut target, &data);
+        Ok(())
+    }
+
+    // Escalation boundary of a user task or receive task that completed after the idle threshold.
+    fn idle_escalation<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        activity: &'a Activity,
+        started: Instant,
+    ) -> Option<&'a usize> {
+        let threshold = self.handler.idle_escalation()?;
+        if !matches!(
+            activity.activity_type,
+            ActivityType::UserTask | ActivityType::ReceiveTask
+        ) || started.elapsed() < threshold
+        {
+            return None;
+        }
+
+        let boundary = input
+            .process
+            .activity_boundaries(&activity.id)?
+            .iter()
+            .find(|index| {
+                matches!(
+                    input.process.get(**index),
+                    Some(Bpmn::Event(Event {
+                        symbol: Some(Symbol::Escalation),
+                        ..
+                    }))
+                )
+            })?;
+        warn!(
+            "{}{activity} has been idle longer than {threshold:?}. Escalating.",
+            input.state.log_prefix
+        );
+        Some(boundary)
+    }
+
+    // Registered condition of a conditional event, checked on the data of the run. Is None without a condition.
+    fn condition(&self, event: &Event, input: &ExecuteInput<'_, L>) -> Option<Result<bool, Error>> {
+        let condition = self
+            .handler
+            .condition(event.id.bpmn(), event.name.as_deref())?;
+        Some(
+            input
+                .user_data()
+                .read()
+                .map(|data| condition(&data))
+                .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into())),
+        )
+    }
+
+    // First conditional boundary event of the activity with a true condition
+    fn conditional_boundary<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        activity: &'a Activity,
+    ) -> Result<Option<&'a usize>, Error> {
+        for index in input
+            .process
+            .activity_boundaries(&activity.id)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(Bpmn::Event(
+                event @ Event {
+                    symbol: Some(Symbol::Conditional),
+                    ..
+                },
+            )) = input.process.get(*index)
+                && self.condition(event, input).transpose()? == Some(true)
+            {
+                info!("{}Condition of {event} is true", input.state.log_prefix);
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    // Boundary of the activity triggered with the boundary trigger of the run
+    fn triggered_boundary<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        activity: &'a Activity,
+    ) -> Result<Option<&'a usize>, Error> {
+        let Some((symbol, name)) = input
+            .state
+            .boundary_trigger
+            .as_ref()
+            .and_then(|trigger| trigger.take(activity.id.bpmn(), activity.name.as_deref()))
+        else {
+            return Ok(None);
+        };
+        info!(
+            "{}{activity} interrupted by {symbol} boundary",
+            input.state.log_prefix
+        );
+        input
+            .process
+            .find_boundary(&activity.id, name.as_deref(), &symbol)
+            .map(Some)
+            .ok_or_else(|| Error::MissingBoundary(symbol.to_string(), activity.to_string()))
+    }
+
+    // Report tokens waiting longer than the threshold to the listeners until the sender is dropped.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub(super) fn watch_idle(&self, state: &RunState, threshold: Duration, stop: Receiver<()>) {
+        let interval = (threshold / 4).max(Duration::from_millis(1));
+        while let Err(RecvTimeoutError::Timeout) = stop.recv_timeout(interval) {
+            state.report_idle(threshold, |event| {
+                warn!(
+                    "{}Token {} has been idle at {} longer than {threshold:?}",
+                    state.log_prefix,
+                    event.token,
+                    event.name.unwrap_or(event.id)
+                );
+                self.handler.notify(&event);
+            });
+        }
+    }
+
+    // Outputs of a join gateway. An inclusive join with multiple outputs is also a fork.
+    fn join_flows<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        gateway: &'a Gateway,
+        token: usize,
+    ) -> Result<Cow<'a, [usize]>, Error> {
+        let flows = match gateway.gateway_type {
+            GatewayType::Inclusive if gateway.outputs.len() > 1 => {
+                self.handle_inclusive_gateway(input, gateway, token)?
+            }
+            _ => Cow::Borrowed(gateway.outputs.ids()),
+        };
+        match input.process.get(*gateway.id.local()) {
+            Some(bpmn) => {
+                let flows = self.transitions(input, bpmn, token, flows)?;
+                self.emit_decision(bpmn, token, &flows, input);
+                Ok(flows)
+            }
+            None => Ok(flows),
+        }
+    }
+
+    // Each flow process one "token" and returns the token with a Fork, Join or End.
+    // A parked token continues with its own token number.
     fn flow<'a: 'b, 'b>(
         &'a self,
         mut current_id: &'b usize,
-        input: &ExecuteInput<'a, T>,
-    ) -> Result<Return<'a>, Error>
+        parent: Option<usize>,
+        parked: Option<usize>,
+        input: &ExecuteInput<'a, L>,
+    ) -> Result<(usize, Return<'a>), Error>
     where
         T: Send,
     {
+        let token = parked.unwrap_or_else(|| input.state.next_token());
+        if parked.is_none() {
+            input.state.emit(|| EngineEvent::TokenCreated {
+                token,
+                parent_token: parent,
+                id: input
+                    .process
+                    .get(*current_id)
+                    .and_then(Bpmn::id)
+                    .unwrap_or_default()
+                    .into(),
+            });
+        }
+
+        // Explicit parent as the token might run on another thread than the process
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(parent: &input.span, "token", token, parent_token = parent);
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+        #[cfg(feature = "otel")]
+        let _otel = input.otel.clone().attach();
+
+        let mut token_state = TokenState::default();
         loop {
-            current_id = match input
+            let bpmn = input
                 .process
                 .get(*current_id)
-                .ok_or_else(|| Error::MisssingBpmnData(current_id.to_string()))?
-            {
-                Bpmn::Event(
-                    event @ Event {
-                        event_type,
-                        symbol,
-                        id,
-                        name,
-                        outputs,
-                        ..
-                    },
-                ) => {
-                    info!("{event}");
-                    match event_type {
-                        EventType::Start | EventType::IntermediateCatch | EventType::Boundary => {
-                            maybe_fork!(outputs, event)
+                .ok_or_else(|| Error::MisssingBpmnData(current_id.to_string()))?;
+
+            if input.state.is_cancelled() {
+                return Err(Error::Cancelled(
+                    bpmn.id().into_iter().map(Into::into).collect(),
+                ));
+            }
+            if input.state.is_timed_out() {
+                return Err(Error::Timeout(
+                    bpmn.id().into_iter().map(Into::into).collect(),
+                ));
+            }
+
+            if self.is_parked(bpmn, input)? {
+                return Ok((token, Return::Park(*current_id, parent)));
+            }
+
+            input.state.visit(bpmn)?;
+            self.notify(ExecutionEventKind::Enter, bpmn, token, parent, input);
+            self.emit_entered(bpmn, token, input);
+            let watched = self.handler.idle_threshold().is_some() && is_wait_state(bpmn);
+            if watched {
+                input.state.wait(token, parent, bpmn);
+            }
+            #[cfg(feature = "otel")]
+            let span = match bpmn {
+                Bpmn::Activity(activity) => Some(input.state.telemetry.activity(activity, token)),
+                _ => None,
+            };
+            let step = self.step(bpmn, token, input, &mut token_state);
+            #[cfg(feature = "otel")]
+            if let Some(span) = span {
+                span.end(&step);
+            }
+            if watched {
+                input.state.done_waiting(token);
+            }
+            let step = step?;
+            self.notify(ExecutionEventKind::Leave, bpmn, token, parent, input);
+            self.emit_left(bpmn, token, input);
+
+            match step {
+                ControlFlow::Continue(next_id) => {
+                    token_state.previous = Some(*current_id);
+                    current_id = self.transition(input, bpmn, token, next_id)?;
+                    self.emit_decision(bpmn, token, std::slice::from_ref(current_id), input);
+                }
+                ControlFlow::Break(Return::Fork(flows)) => {
+                    let flows = self.transitions(input, bpmn, token, flows)?;
+                    self.emit_decision(bpmn, token, &flows, input);
+                    return Ok((token, Return::Fork(flows)));
+                }
+                ControlFlow::Break(value) => return Ok((token, value)),
+            }
+        }
+    }
+
+    fn notify(
+        &self,
+        kind: ExecutionEventKind,
+        bpmn: &Bpmn,
+        token: usize,
+        parent: Option<usize>,
+        input: &ExecuteInput<'_, L>,
+    ) {
+        if !self.handler.has_listeners() {
+            return;
+        }
+
+        if let Some((id, element_type)) = bpmn.id().zip(bpmn.element_type()) {
+            self.handler.notify_with_data(
+                &ExecutionEvent {
+                    kind,
+                    id,
+                    name: bpmn.name(),
+                    element_type,
+                    token,
+                    parent_token: parent,
+                },
+                &input.user_data,
+            );
+        }
+    }
+
+    fn emit_entered(&self, bpmn: &Bpmn, token: usize, input: &ExecuteInput<'_, L>) {
+        if let Some(handle) = &input.state.run_handle {
+            handle.entered(token, bpmn);
+        }
+        let Some((id, element_type)) = bpmn.id().zip(bpmn.element_type()) else {
+            return;
+        };
+        input.state.emit(|| EngineEvent::ElementEntered {
+            token,
+            id: id.into(),
+            name: bpmn.name().map(Into::into),
+            element_type,
+        });
+        if let Bpmn::Event(Event {
+            event_type: EventType::Boundary,
+            symbol,
+            attached_to_ref: Some(attached_to),
+            ..
+        }) = bpmn
+        {
+            #[cfg(feature = "otel")]
+            input
+                .state
+                .telemetry
+                .boundary(id, attached_to.bpmn(), symbol.as_ref());
+            input.state.emit(|| EngineEvent::BoundaryTriggered {
+                token,
+                id: id.into(),
+                name: bpmn.name().map(Into::into),
+                symbol: symbol.clone(),
+                attached_to: attached_to.bpmn().into(),
+            });
+        }
+    }
+
+    fn emit_left(&self, bpmn: &Bpmn, token: usize, input: &ExecuteInput<'_, L>) {
+        if let Some(handle) = &input.state.run_handle {
+            handle.left(token);
+        }
+        let Some((id, element_type)) = bpmn.id().zip(bpmn.element_type()) else {
+            return;
+        };
+        input.state.emit(|| EngineEvent::ElementLeft {
+            token,
+            id: id.into(),
+            name: bpmn.name().map(Into::into),
+            element_type,
+        });
+    }
+
+    // Sequence flows selected by a gateway
+    fn emit_decision(
+        &self,
+        bpmn: &Bpmn,
+        token: usize,
+        flows: &[usize],
+        input: &ExecuteInput<'_, L>,
+    ) {
+        let Bpmn::Gateway(gateway) = bpmn else {
+            return;
+        };
+        #[cfg(feature = "otel")]
+        for flow in flows
+            .iter()
+            .filter_map(|flow| input.process.get(*flow).and_then(Bpmn::id))
+        {
+            input.state.telemetry.decision(gateway.id.bpmn(), flow);
+        }
+        input.state.emit(|| EngineEvent::GatewayDecision {
+            token,
+            id: gateway.id.bpmn().into(),
+            name: gateway.name.as_deref().map(Into::into),
+            flows: flows
+                .iter()
+                .filter_map(|flow| input.process.get(*flow).and_then(Bpmn::id))
+                .map(Into::into)
+                .collect(),
+        });
+    }
+
+    // Process a single element and return the next element or a Fork, Join or End.
+    fn step<'a>(
+        &'a self,
+        bpmn: &'a Bpmn,
+        token: usize,
+        input: &ExecuteInput<'a, L>,
+        token_state: &mut TokenState<'a>,
+    ) -> Result<ControlFlow<Return<'a>, &'a usize>, Error>
+    where
+        T: Send,
+    {
+        let ended = token_state.subprocess_end.take();
+        Ok(ControlFlow::Continue(match bpmn {
+            Bpmn::Event(
+                event @ Event {
+                    event_type,
+                    symbol,
+                    id,
+                    name,
+                    outputs,
+                    func_idx,
+                    ..
+                },
+            ) => {
+                info!("{}{event}", input.state.log_prefix);
+                match event_type {
+                    EventType::Start => {
+                        maybe_fork!(outputs, event)
+                    }
+                    // Function that waits for the event, like a message from a channel
+                    EventType::IntermediateCatch => {
+                        if symbol == &Some(Symbol::Conditional) {
+                            self.wait_for_condition(bpmn, event, token, input)?;
                         }
-                        EventType::IntermediateThrow => {
-                            match (name.as_ref(), symbol.as_ref()) {
-                                (Some(name), Some(Symbol::Link)) => {
-                                    input.process.catch_event_link(name)?
-                                }
-                                // Follow outputs for other throw events
-                                (Some(_), _) => {
-                                    maybe_fork!(outputs, event)
-                                }
-                                _ => {
-                                    Err(Error::MissingIntermediateThrowEventName(id.bpmn().into()))?
+                        self.run_event(bpmn, func_idx.as_ref(), token, input)?;
+                        maybe_fork!(outputs, event)
+                    }
+                    EventType::Boundary => {
+                        if let Some(index) = func_idx {
+                            let mut context = input.context(bpmn, token);
+                            context.subprocess_end = ended;
+                            input.invoke(context, |context, data| {
+                                self.guard(
+                                    *context,
+                                    &data,
+                                    |context, data| {
+                                        self.handler.run_boundary(*index, context, data)
+                                    },
+                                    |_| None,
+                                )
+                            })?;
+                        }
+                        // A non-interrupting boundary continues the activity as well
+                        if !event.cancel_activity
+                            && let Some(Bpmn::Activity(activity)) = event
+                                .attached_to_ref
+                                .as_ref()
+                                .and_then(|attached_to| input.process.get(*attached_to.local()))
+                        {
+                            return Ok(ControlFlow::Break(Return::Fork(Cow::Owned(
+                                outputs
+                                    .ids()
+                                    .iter()
+                                    .chain(activity.outputs.ids())
+                                    .copied()
+                                    .collect(),
+                            ))));
+                        }
+                        maybe_fork!(outputs, event)
+                    }
+                    EventType::IntermediateThrow => {
+                        match (name.as_ref(), symbol.as_ref()) {
+                            (Some(name), Some(Symbol::Link)) => {
+                                input.process.catch_event_link(name)?
+                            }
+                            // Follow outputs for other throw events
+                            (Some(_), _) => {
+                                if symbol.is_none() {
+                                    input.state.reached_milestone(event);
                                 }
+                                self.run_event(bpmn, func_idx.as_ref(), token, input)?;
+                                maybe_fork!(outputs, event)
                             }
-                        }
-                        EventType::End => {
-                            return Ok(Return::End(event));
+                            _ => Err(Error::MissingIntermediateThrowEventName(id.bpmn().into()))?,
                         }
                     }
+                    EventType::End => {
+                        self.run_event(bpmn, func_idx.as_ref(), token, input)?;
+                        return Ok(ControlFlow::Break(Return::End(event)));
+                    }
                 }
-                Bpmn::Activity(
-                    activity @ Activity {
-                        activity_type,
-                        id,
-                        func_idx,
-                        outputs,
-                        ..
-                    },
-                ) => {
-                    info!("{activity}");
-                    match activity_type {
-                        ActivityType::Task
-                        | ActivityType::ScriptTask
-                        | ActivityType::UserTask
-                        | ActivityType::ServiceTask
-                        | ActivityType::CallActivity
-                        | ActivityType::ReceiveTask
-                        | ActivityType::SendTask
-                        | ActivityType::ManualTask
-                        | ActivityType::BusinessRuleTask => {
-                            match func_idx
-                                .map(|index| self.handler.run_task(index, input.user_data()))
+            }
+            Bpmn::Activity(
+                activity @ Activity {
+                    activity_type,
+                    id,
+                    func_idx,
+                    outputs,
+                    ..
+                },
+            ) => {
+                info!("{}{activity}", input.state.log_prefix);
+                match activity_type {
+                    ActivityType::Task
+                    | ActivityType::ScriptTask
+                    | ActivityType::UserTask
+                    | ActivityType::ServiceTask
+                    | ActivityType::CallActivity
+                    | ActivityType::ReceiveTask
+                    | ActivityType::SendTask
+                    | ActivityType::ManualTask
+                    | ActivityType::BusinessRuleTask
+                        if self
+                            .handler
+                            .is_disabled(id.bpmn(), activity.name.as_deref()) =>
+                    {
+                        warn!("{}{activity} is disabled", input.state.log_prefix);
+                        match self.handler.fallback(id.bpmn(), activity.name.as_deref()) {
+                            Some(flow) => find_flow!(outputs, flow, input, activity)?,
+                            None => maybe_fork!(outputs, activity),
+                        }
+                    }
+                    ActivityType::Task
+                    | ActivityType::ScriptTask
+                    | ActivityType::UserTask
+                    | ActivityType::ServiceTask
+                    | ActivityType::CallActivity
+                    | ActivityType::ReceiveTask
+                    | ActivityType::SendTask
+                    | ActivityType::ManualTask
+                    | ActivityType::BusinessRuleTask => {
+                        // The condition of a boundary can be true before the task starts
+                        if let Some(boundary) = self.triggered_boundary(input, activity)? {
+                            boundary
+                        } else if let Some(boundary) = self.conditional_boundary(input, activity)? {
+                            boundary
+                        } else {
+                            let started = Instant::now();
+                            let result = func_idx
+                                .map(|index| {
+                                    let workload =
+                                        self.handler.workload(id.bpmn(), activity.name.as_deref());
+                                    let retry =
+                                        self.handler.retry(id.bpmn(), activity.name.as_deref());
+                                    let limit =
+                                        self.handler.limit(id.bpmn(), activity.name.as_deref());
+                                    let breaker =
+                                        self.handler.breaker(id.bpmn(), activity.name.as_deref());
+                                    input.invoke(input.context(bpmn, token), |context, data| {
+                                        input.map_io(bpmn, *context, |context| {
+                                            self.handler.io_pool().run(workload, || {
+                                                // Acquired on the thread that runs the task. The rayon
+                                                // thread waiting for an IO task runs other tokens meanwhile.
+                                                let _permit = limit.map(Limit::acquire);
+                                                self.guard(
+                                                    context,
+                                                    &data,
+                                                    |context, data| {
+                                                        breaker::run(breaker, context, |context| {
+                                                            retry::run(retry, context, |context| {
+                                                                self.handler.run_task(
+                                                                    index,
+                                                                    context,
+                                                                    Arc::clone(&data),
+                                                                )
+                                                            })
+                                                        })
+                                                    },
+                                                    |boundary| Some(Some(boundary)),
+                                                )
+                                            })
+                                        })
+                                    })
+                                })
                                 .ok_or_else(|| {
                                     Error::MissingImplementation(activity.to_string())
-                                })?? {
-                                Some(boundary) => input
-                                    .process
-                                    .find_boundary(id, boundary.name(), boundary.symbol())
-                                    .ok_or_else(|| {
-                                        Error::MissingBoundary(
-                                            boundary.to_string(),
-                                            activity.to_string(),
-                                        )
-                                    })?,
-                                None => maybe_fork!(outputs, activity),
+                                })?;
+                            // A boundary triggered while the task runs discards the result
+                            if let Some(boundary) = self.triggered_boundary(input, activity)? {
+                                return Ok(ControlFlow::Continue(boundary));
+                            }
+                            match result.inspect_err(|_| input.failed_at(activity))? {
+                                Some(boundary) => match input.process.find_boundary(
+                                    id,
+                                    boundary.name(),
+                                    boundary.symbol(),
+                                ) {
+                                    Some(index) => index,
+                                    None if self.handler.ignore_missing_boundaries() => {
+                                        warn!(
+                                            "{}{activity} has no boundary {boundary}",
+                                            input.state.log_prefix
+                                        );
+                                        maybe_fork!(outputs, activity)
+                                    }
+                                    None => Err(Error::MissingBoundary(
+                                        boundary.to_string(),
+                                        activity.to_string(),
+                                    ))?,
+                                },
+                                None => match self.idle_escalation(input, activity, started) {
+                                    Some(boundary) => boundary,
+                                    None => match self.conditional_boundary(input, activity)? {
+                                        Some(boundary) => boundary,
+                                        None => maybe_fork!(outputs, activity),
+                                    },
+                                },
                             }
                         }
-                        ActivityType::SubProcess {
-                            data_index: Some(index),
-                        } => {
-                            let sp_data = self
-                                .diagram
-                                .get_process(*index)
-                                .ok_or_else(|| Error::MissingProcessData(id.bpmn().into()))?;
+                    }
+                    ActivityType::SubProcess {
+                        data_index: Some(index),
+                    } => {
+                        let sp_data = self
+                            .diagram
+                            .get_process(*index)
+                            .ok_or_else(|| Error::MissingProcessData(id.bpmn().into()))?;
 
-                            if let Event {
-                                event_type: EventType::End,
-                                symbol:
-                                    Some(
-                                        symbol @ (Symbol::Cancel
-                                        | Symbol::Compensation
-                                        | Symbol::Conditional
-                                        | Symbol::Error
-                                        | Symbol::Escalation
-                                        | Symbol::Message
-                                        | Symbol::Signal
-                                        | Symbol::Timer),
-                                    ),
-                                name,
-                                ..
-                            } = self.execute(ExecuteInput::new(sp_data, input.user_data()))?
-                            {
+                        if let end @ Event {
+                            event_type: EventType::End,
+                            symbol:
+                                Some(
+                                    symbol @ (Symbol::Cancel
+                                    | Symbol::Compensation
+                                    | Symbol::Conditional
+                                    | Symbol::Error
+                                    | Symbol::Escalation
+                                    | Symbol::Message
+                                    | Symbol::Signal
+                                    | Symbol::Timer),
+                                ),
+                            name,
+                            ..
+                        } = self.execute(input.subprocess(sp_data, token, activity))?
+                        {
+                            token_state.subprocess_end = Some(end);
+                            if matches!(symbol, Symbol::Error | Symbol::Escalation) {
+                                match input.process.find_catch_boundary(id, end) {
+                                    Some(boundary) => boundary.id.local(),
+                                    // Leave this subprocess too. An enclosing subprocess catches it, or an
+                                    // error that is never caught ends the run.
+                                    None if *symbol == Symbol::Error
+                                        || input.enclosing.iter().any(|(process, activity)| {
+                                            process.find_catch_boundary(&activity.id, end).is_some()
+                                        }) =>
+                                    {
+                                        return Ok(ControlFlow::Break(Return::Propagate(end)));
+                                    }
+                                    None => {
+                                        warn!(
+                                            "{}{end} of {activity} is not caught",
+                                            input.state.log_prefix
+                                        );
+                                        maybe_fork!(outputs, activity)
+                                    }
+                                }
+                            } else {
                                 input
                                     .process
                                     .find_boundary(id, name.as_deref(), symbol)
@@ -243,101 +999,159 @@ impl<T> Process<T, Run> {
                                             activity.to_string(),
                                         )
                                     })?
-                            } else {
-                                // Continue from subprocess
-                                maybe_fork!(outputs, activity)
                             }
+                        } else {
+                            // Continue from subprocess
+                            maybe_fork!(outputs, activity)
                         }
-                        ActivityType::SubProcess { .. } => {
-                            return Err(Error::MissingProcessData(activity.to_string()));
-                        }
+                    }
+                    ActivityType::SubProcess { .. } => {
+                        return Err(Error::MissingProcessData(activity.to_string()));
                     }
                 }
+            }
 
-                Bpmn::Gateway(
-                    gateway @ Gateway {
-                        gateway_type,
-                        func_idx,
-                        outputs,
-                        inputs,
-                        ..
-                    },
-                ) => {
-                    info!("{gateway}");
-                    match gateway_type {
-                        _ if outputs.len() == 0 => {
-                            return Err(Error::MissingOutput(gateway.to_string()));
-                        }
-                        // Handle 1 to 1, probably a temporary design or mistake
-                        _ if outputs.len() == 1 && *inputs == 1 => outputs.first().unwrap(),
-                        GatewayType::Exclusive if outputs.len() == 1 => outputs.first().unwrap(),
-                        GatewayType::Exclusive => {
-                            match func_idx
-                                .map(|index| self.handler.run_exclusive(index, input.user_data()))
-                                .ok_or_else(|| {
-                                    Error::MissingImplementation(gateway.to_string())
-                                })?? {
-                                Some(value) => find_flow!(outputs, value, input, gateway)?,
-                                None => gateway.default_path()?,
-                            }
-                        }
-                        // Handle a regular Join or a JoinFork. In both cases, we need to wait for all tokens.
-                        GatewayType::Parallel | GatewayType::Inclusive if *inputs > 1 => {
-                            return Ok(Return::Join(gateway));
-                        }
-                        GatewayType::Parallel => {
-                            return Ok(Return::Fork(Cow::Borrowed(outputs.ids())));
-                        }
-                        GatewayType::Inclusive => {
-                            return Ok(Return::Fork(
-                                self.handle_inclusive_gateway(input, gateway)?,
-                            ));
-                        }
-                        GatewayType::EventBased if outputs.len() == 1 => {
-                            return Err(Error::BpmnRequirement(AT_LEAST_TWO_OUTGOING.into()));
-                        }
-                        GatewayType::EventBased => {
-                            let value = func_idx
-                                .map(|index| self.handler.run_eventbased(index, input.user_data()))
-                                .ok_or_else(|| {
-                                    Error::MissingImplementation(gateway.to_string())
-                                })??;
-
-                            input
-                                .process
-                                .find_by_intermediate_event(&value, outputs)
-                                .ok_or_else(|| {
-                                    Error::MissingIntermediateEvent(
-                                        gateway.to_string(),
-                                        value.to_string(),
+            Bpmn::Gateway(
+                gateway @ Gateway {
+                    gateway_type,
+                    func_idx,
+                    outputs,
+                    inputs,
+                    ..
+                },
+            ) => {
+                info!("{}{gateway}", input.state.log_prefix);
+                match gateway_type {
+                    _ if outputs.len() == 0 => {
+                        return Err(Error::MissingOutput(gateway.to_string()));
+                    }
+                    // Handle 1 to 1, probably a temporary design or mistake
+                    _ if outputs.len() == 1 && *inputs == 1 => outputs.first().unwrap(),
+                    GatewayType::Exclusive if outputs.len() == 1 => outputs.first().unwrap(),
+                    GatewayType::Exclusive => {
+                        match func_idx
+                            .map(|index| {
+                                input.invoke(input.context(bpmn, token), |context, data| {
+                                    self.guard(
+                                        *context,
+                                        &data,
+                                        |context, data| {
+                                            self.handler.run_exclusive(index, context, data, || {
+                                                outputs
+                                                    .iter()
+                                                    .filter_map(|flow| input.process.get(*flow))
+                                                    .filter_map(Bpmn::name_or_id)
+                                                    .collect()
+                                            })
+                                        },
+                                        |_| None,
                                     )
-                                })?
+                                })
+                            })
+                            .ok_or_else(|| Error::MissingImplementation(gateway.to_string()))??
+                        {
+                            Some(Selected::Name(value)) => self.enabled_flow(
+                                input,
+                                gateway,
+                                find_flow!(outputs, value, input, gateway)?,
+                            )?,
+                            Some(Selected::Index(value)) => self.enabled_flow(
+                                input,
+                                gateway,
+                                outputs.ids().get(value).ok_or_else(|| {
+                                    Error::MissingOutput(format!("{gateway} index {value}"))
+                                })?,
+                            )?,
+                            None => self.enabled_flow(input, gateway, gateway.default_path()?)?,
                         }
                     }
+                    // Handle a regular Join or a JoinFork. In both cases, we need to wait for all tokens.
+                    GatewayType::Parallel | GatewayType::Inclusive if *inputs > 1 => {
+                        return Ok(ControlFlow::Break(Return::Join(
+                            gateway,
+                            token_state.previous,
+                        )));
+                    }
+                    GatewayType::Parallel => {
+                        return Ok(ControlFlow::Break(Return::Fork(Cow::Borrowed(
+                            outputs.ids(),
+                        ))));
+                    }
+                    GatewayType::Inclusive => {
+                        return Ok(ControlFlow::Break(Return::Fork(
+                            self.handle_inclusive_gateway(input, gateway, token)?,
+                        )));
+                    }
+                    GatewayType::EventBased if outputs.len() == 1 => {
+                        return Err(Error::BpmnRequirement(AT_LEAST_TWO_OUTGOING.into()));
+                    }
+                    GatewayType::EventBased => {
+                        let value = func_idx
+                            .map(|index| {
+                                input.invoke(input.context(bpmn, token), |context, data| {
+                                    self.guard(
+                                        *context,
+                                        &data,
+                                        |context, data| {
+                                            self.handler.run_eventbased(index, context, data)
+                                        },
+                                        |_| None,
+                                    )
+                                })
+                            })
+                            .ok_or_else(|| Error::MissingImplementation(gateway.to_string()))??;
+
+                        input
+                            .process
+                            .find_by_intermediate_event(&value, outputs)
+                            .ok_or_else(|| {
+                                Error::MissingIntermediateEvent(
+                                    gateway.to_string(),
+                                    value.to_string(),
+                                )
+                            })?
+                    }
                 }
-                Bpmn::SequenceFlow {
-                    id,
-                    name,
-                    target_ref,
-                    ..
-                } => {
-                    info!(r#"SequenceFlow "{}""#, name.as_deref().unwrap_or(id.bpmn()));
-                    target_ref.local()
-                }
-                bpmn => return Err(Error::TypeNotImplemented(format!("{bpmn:?}"))),
-            };
-        }
+            }
+            Bpmn::SequenceFlow {
+                id,
+                name,
+                target_ref,
+                ..
+            } => {
+                info!(
+                    r#"{}SequenceFlow "{}""#,
+                    input.state.log_prefix,
+                    name.as_deref().unwrap_or(id.bpmn())
+                );
+                target_ref.local()
+            }
+            bpmn => return Err(Error::TypeNotImplemented(format!("{bpmn:?}"))),
+        }))
     }
 
     fn handle_inclusive_gateway<'a>(
         &'a self,
-        input: &ExecuteInput<'a, T>,
+        input: &ExecuteInput<'a, L>,
         gateway @ Gateway {
             func_idx, outputs, ..
         }: &'a Gateway,
+        token: usize,
     ) -> Result<Cow<'a, [usize]>, Error> {
         let value = match func_idx
-            .map(|index| self.handler.run_inclusive(index, input.user_data()))
+            .map(|index| {
+                let mut context =
+                    input.element_context(gateway.id.bpmn(), gateway.name.as_deref(), token);
+                context.properties = input.process.properties(*gateway.id.local());
+                input.invoke(context, |context, data| {
+                    self.guard(
+                        *context,
+                        &data,
+                        |context, data| self.handler.run_inclusive(index, context, data),
+                        |_| None,
+                    )
+                })
+            })
             .ok_or_else(|| Error::MissingImplementation(gateway.to_string()))??
         {
             With::Flow(value) => find_flow!(outputs, value, input, gateway)?,
@@ -345,37 +1159,933 @@ impl<T> Process<T, Run> {
                 [] => gateway.default_path()?,
                 [value] => find_flow!(outputs, value, input, gateway)?,
                 [..] => {
-                    let mut tokens = HashSet::with_capacity(values.len());
-                    for &value in values.iter() {
+                    let mut flows = Vec::with_capacity(values.len());
+                    for value in values.iter() {
                         // Breaks on first error
-                        if !tokens.insert(*find_flow!(outputs, value, input, gateway)?) {
-                            // The flow has already been used, we just log an warning and continue.
-                            warn!(
-                                "{gateway} used flow {value} multiple times. Discarded the duplicates."
-                            );
-                        }
+                        let flow = find_flow!(outputs, value, input, gateway)?;
+                        flows.push(*self.enabled_flow(input, gateway, flow)?);
                     }
-                    return Ok(Cow::Owned(tokens.into_iter().collect()));
+                    return Ok(Cow::Owned(self.dedup_flows(input, gateway, flows)?));
                 }
             },
             With::Default => gateway.default_path()?,
         };
-        Ok(Cow::Owned(vec![*value]))
+        Ok(Cow::Owned(vec![*self.enabled_flow(input, gateway, value)?]))
+    }
+
+    // Token at a conditional catch event with a false condition while other tokens can run. It is parked before
+    // it enters the event, so the other tokens can change the data, also when they run one at a time.
+    fn is_parked(&self, bpmn: &Bpmn, input: &ExecuteInput<'_, L>) -> Result<bool, Error> {
+        if !input.park {
+            return Ok(false);
+        }
+        match bpmn {
+            Bpmn::Event(
+                event @ Event {
+                    event_type: EventType::IntermediateCatch,
+                    symbol: Some(Symbol::Conditional),
+                    ..
+                },
+            ) => Ok(self.condition(event, input).transpose()? == Some(false)),
+            _ => Ok(false),
+        }
+    }
+
+    // Wait until the condition of a conditional catch event is true, or the run stops.
+    // Only when no other token can change the data, as the token is parked otherwise.
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn wait_for_condition<'a>(
+        &self,
+        bpmn: &'a Bpmn,
+        event: &Event,
+        token: usize,
+        input: &ExecuteInput<'a, L>,
+    ) -> Result<(), Error> {
+        input
+            .context(bpmn, token)
+            .wait_for_message(|timeout| match self.condition(event, input) {
+                Some(Ok(false)) => {
+                    schedule::pause(timeout);
+                    Err(RecvTimeoutError::Timeout)
+                }
+                result => Ok(result.transpose()),
+            })?
+            .map(|_| ())
+    }
+
+    // Without threads nothing changes the data while the token waits, so a false condition never becomes true
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    fn wait_for_condition<'a>(
+        &self,
+        _: &'a Bpmn,
+        event: &Event,
+        _: usize,
+        input: &ExecuteInput<'a, L>,
+    ) -> Result<(), Error> {
+        match self.condition(event, input).transpose()? {
+            Some(false) => Err(Error::NotSupported(format!(
+                "Waiting for the condition of {} on this target",
+                event.id.bpmn()
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    // Function of a catch event or of a message or signal throw event, like delivering the message.
+    // A returned boundary is ignored as events have none.
+    fn run_event<'a>(
+        &self,
+        bpmn: &'a Bpmn,
+        func_idx: Option<&usize>,
+        token: usize,
+        input: &ExecuteInput<'a, L>,
+    ) -> Result<(), Error> {
+        let Some(index) = func_idx else {
+            return Ok(());
+        };
+        input.invoke(input.context(bpmn, token), |context, data| {
+            self.guard(
+                *context,
+                &data,
+                |context, data| self.handler.run_task(*index, context, data),
+                |_| None,
+            )
+        })?;
+        Ok(())
+    }
+
+    // Call a registered function. An error is given to the error hook that decides to abort, call the function again
+    // or continue with a boundary event. Only tasks can continue with a boundary event, other functions abort.
+    // A panic is returned as an error when panics are caught.
+    fn guard<R>(
+        &self,
+        mut context: Context,
+        data: &Arc<L>,
+        mut func: impl FnMut(&mut Context, Arc<L>) -> Result<R, Error>,
+        boundary: impl FnOnce(Boundary) -> Option<R>,
+    ) -> Result<R, Error> {
+        let mut retries = 0;
+        loop {
+            let result = if self.handler.catch_panics() {
+                panic::catch_unwind(AssertUnwindSafe(|| func(&mut context, Arc::clone(data))))
+                    .unwrap_or_else(|payload| {
+                        // Keep the data usable for the error hook and the caller
+                        data.clear_poison();
+                        Err(Error::ProcessExecution(
+                            format!(
+                                "{} panicked: {}",
+                                context.name.unwrap_or(context.id),
+                                panic_message(payload.as_ref())
+                            )
+                            .into(),
+                        ))
+                    })
+            } else {
+                func(&mut context, Arc::clone(data))
+            };
+            let error = match result {
+                Err(error) => error,
+                result => return result,
+            };
+
+            match self.handler.on_error(&context, &error) {
+                ErrorDecision::Retry if retries < MAX_HOOK_RETRIES && !context.is_cancelled() => {
+                    warn!(
+                        "{}{} failed with {error}. Retry",
+                        context.log_prefix, context.id
+                    );
+                    retries += 1;
+                    context.attempt += 1;
+                }
+                ErrorDecision::Retry => {
+                    warn!(
+                        "{}{} failed with {error}. No retries left",
+                        context.log_prefix, context.id
+                    );
+                    return Err(error);
+                }
+                ErrorDecision::Boundary(value) => return boundary(value).ok_or(error),
+                _ => return Err(error),
+            }
+        }
+    }
+
+    // Let the transition hook veto or redirect the outgoing sequence flow chosen for an element.
+    fn transition<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        bpmn: &'a Bpmn,
+        token: usize,
+        flow: &'a usize,
+    ) -> Result<&'a usize, Error> {
+        let Some(hook) = self.handler.on_transition() else {
+            return Ok(flow);
+        };
+        // Only sequence flows leaving the element. Boundary events and links are not transitions.
+        let Some(outputs) = bpmn
+            .outputs()
+            .filter(|outputs| outputs.ids().contains(flow))
+        else {
+            return Ok(flow);
+        };
+        let (Some(id), Some(element_type), Some(next @ Bpmn::SequenceFlow { target_ref, .. })) =
+            (bpmn.id(), bpmn.element_type(), input.process.get(*flow))
+        else {
+            return Ok(flow);
+        };
+
+        let transition = Transition {
+            id,
+            name: bpmn.name(),
+            element_type,
+            flow: next.name_or_id().unwrap_or_default(),
+            target: input
+                .process
+                .get(*target_ref.local())
+                .and_then(Bpmn::name_or_id)
+                .unwrap_or(target_ref.bpmn()),
+            outputs: outputs
+                .iter()
+                .filter_map(|flow| input.process.get(*flow))
+                .filter_map(Bpmn::name_or_id)
+                .collect(),
+            token,
+        };
+        match hook(&transition) {
+            TransitionDecision::Continue => Ok(flow),
+            TransitionDecision::Veto => Err(Error::TransitionVetoed(
+                bpmn.name_or_id().unwrap_or_default().into(),
+                transition.flow.into(),
+            )),
+            TransitionDecision::Redirect(value) => input
+                .process
+                .find_by_name_or_id(&value, outputs)
+                .ok_or_else(|| Error::MissingOutput(format!("{id} redirect {value}"))),
+        }
+    }
+
+    // Pass every forked flow to the transition hook
+    fn transitions<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        bpmn: &'a Bpmn,
+        token: usize,
+        flows: Cow<'a, [usize]>,
+    ) -> Result<Cow<'a, [usize]>, Error> {
+        if self.handler.on_transition().is_none() {
+            return Ok(flows);
+        }
+
+        flows
+            .iter()
+            .map(|flow| self.transition(input, bpmn, token, flow).copied())
+            .collect::<Result<Vec<_>, _>>()
+            .map(Cow::Owned)
+    }
+
+    // Apply the duplicate flows policy to the flows selected by a gateway. Keeps the selection order.
+    fn dedup_flows(
+        &self,
+        input: &ExecuteInput<'_, L>,
+        gateway: &Gateway,
+        flows: Vec<usize>,
+    ) -> Result<Vec<usize>, Error> {
+        let policy = self.handler.duplicate_flows();
+        if policy == DuplicateFlows::Allow {
+            return Ok(flows);
+        }
+
+        let mut seen = HashSet::with_capacity(flows.len());
+        let mut unique = Vec::with_capacity(flows.len());
+        for flow in flows {
+            if seen.insert(flow) {
+                unique.push(flow);
+                continue;
+            }
+
+            let name_or_id = input
+                .process
+                .get(flow)
+                .and_then(Bpmn::name_or_id)
+                .unwrap_or_default();
+            if policy == DuplicateFlows::Error {
+                return Err(Error::DuplicateFlow(
+                    gateway.to_string(),
+                    name_or_id.to_string(),
+                ));
+            }
+            warn!(
+                "{}{gateway} used flow {name_or_id} multiple times. Discarded the duplicates.",
+                input.state.log_prefix
+            );
+        }
+        Ok(unique)
+    }
+
+    // Replace a sequence flow selected by a gateway if it is disabled by the feature flags.
+    fn enabled_flow<'a>(
+        &'a self,
+        input: &ExecuteInput<'a, L>,
+        gateway: &'a Gateway,
+        flow: &'a usize,
+    ) -> Result<&'a usize, Error> {
+        let Some(Bpmn::SequenceFlow { id, name, .. }) = input.process.get(*flow) else {
+            return Ok(flow);
+        };
+
+        if !self.handler.is_disabled(id.bpmn(), name.as_deref()) {
+            return Ok(flow);
+        }
+
+        let name_or_id = name.as_deref().unwrap_or(id.bpmn());
+        warn!(
+            "{}{gateway} sequence flow {name_or_id} is disabled",
+            input.state.log_prefix
+        );
+        let replacement = match self.handler.fallback(id.bpmn(), name.as_deref()) {
+            Some(fallback) => find_flow!(&gateway.outputs, fallback, input, gateway)?,
+            None => gateway.default_path()?,
+        };
+
+        // The replacement must be enabled
+        match input.process.get(*replacement) {
+            Some(Bpmn::SequenceFlow { id, name, .. })
+                if !self.handler.is_disabled(id.bpmn(), name.as_deref()) =>
+            {
+                Ok(replacement)
+            }
+            _ => Err(Error::Disabled(format!("SequenceFlow {name_or_id}"))),
+        }
+    }
+}
+
+// Tokens of a process counted in the live tokens of the run until dropped
+struct LiveTokens<'a> {
+    state: &'a RunState<'a>,
+    count: usize,
+}
+
+impl<'a> LiveTokens<'a> {
+    fn new(state: &'a RunState<'a>) -> Self {
+        Self { state, count: 0 }
+    }
+
+    // Replace the count with the tokens about to run and the tokens waiting at joins. Fails with the elements
+    // where the tokens of the process are when the run has too many tokens.
+    fn count(
+        &mut self,
+        active_tokens: &[Tokens],
+        handler: &ExecuteHandler,
+        process: &ProcessData,
+    ) -> Result<(), Error> {
+        let Some(max) = self.state.max_tokens else {
+            return Ok(());
+        };
+        let count = active_tokens
+            .iter()
+            .map(|tokens| tokens.flows.len())
+            .chain(handler.waiting_tokens().map(|(_, count)| count))
+            .sum();
+        self.state
+            .live_tokens
+            .fetch_sub(self.count, Ordering::Relaxed);
+        self.count = count;
+        if self.state.live_tokens.fetch_add(count, Ordering::Relaxed) + count <= max {
+            return Ok(());
+        }
+
+        // Tokens on a sequence flow are counted at its target
+        let mut locations: HashMap<String, usize> = HashMap::new();
+        for flow in active_tokens.iter().flat_map(|tokens| tokens.flows.iter()) {
+            let id = match process.get(*flow) {
+                Some(Bpmn::SequenceFlow { target_ref, .. }) => target_ref.bpmn(),
+                Some(bpmn) => bpmn.id().unwrap_or_default(),
+                None => continue,
+            };
+            *locations.entry(id.into()).or_default() += 1;
+        }
+        for (gateway, count) in handler.waiting_tokens() {
+            *locations.entry(gateway.id.bpmn().into()).or_default() += count;
+        }
+        let mut locations: Vec<_> = locations.into_iter().collect();
+        locations.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Err(Error::TokenLimit(max, locations))
+    }
+}
+
+impl Drop for LiveTokens<'_> {
+    fn drop(&mut self) {
+        self.state
+            .live_tokens
+            .fetch_sub(self.count, Ordering::Relaxed);
+    }
+}
+
+// State shared by every process and sub process during a single run.
+#[derive(Default)]
+pub(super) struct RunState<'a> {
+    token_counter: AtomicUsize,
+    services: Option<&'a (dyn Any + Send + Sync)>,
+    trace: Option<&'a TraceContext>,
+    cancel: Option<CancelToken>,
+    boundary_trigger: Option<BoundaryTrigger>,
+    pub(super) run_handle: Option<RunHandle>,
+    deadline: Option<Instant>,
+    max_visits: Option<usize>,
+    max_steps: Option<usize>,
+    // Times each element has been entered. Only counted with a visit limit.
+    visits: Mutex<HashMap<String, usize>>,
+    steps: AtomicUsize,
+    max_tokens: Option<usize>,
+    // Tokens alive in every process and sub process. Only counted with a token limit.
+    live_tokens: AtomicUsize,
+    #[cfg(feature = "parallel")]
+    deterministic: bool,
+    shuffle: Option<Mutex<Shuffle>>,
+    events: Option<Sender<EngineEvent>>,
+    // Recorded when the process has a history sink
+    pub(super) history: Option<Mutex<Vec<HistoryEntry>>>,
+    started: Option<Instant>,
+    // Time since the Unix epoch when the run started
+    started_at: Duration,
+    correlation_id: Option<String>,
+    metadata: BTreeMap<String, String>,
+    log_prefix: String,
+    #[cfg(feature = "otel")]
+    pub(super) telemetry: super::otel::Telemetry,
+    // Shared decision cache installed on the process
+    pub(super) decisions: Option<&'a DecisionCache>,
+    run_decisions: DecisionCache,
+    stats: Mutex<Stats>,
+    // End events reached by the top level processes
+    end_nodes: Mutex<Vec<EndNode>>,
+    // Named intermediate none throw events reached by any process
+    milestones: Mutex<Vec<Milestone>>,
+    // First task that failed with the BPMN id of the task. No position when other tokens were live.
+    failed: Mutex<Option<(Arc<str>, Option<Position>)>>,
+    // Starts of the subprocesses entered on the way to a resumed task, innermost first
+    pub(super) resume: Mutex<Vec<usize>>,
+    #[cfg(feature = "variables")]
+    variables: crate::Variables,
+    // Tokens at wait states, watched for idle detection
+    waiting: Mutex<HashMap<usize, Waiting>>,
+}
+
+// Only read by idle detection, which needs threads
+#[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code))]
+struct Waiting {
+    id: String,
+    name: Option<String>,
+    element_type: ElementType,
+    parent: Option<usize>,
+    since: Instant,
+    reported: bool,
+}
+
+// Elements where a token can wait for someone else
+fn is_wait_state(bpmn: &Bpmn) -> bool {
+    matches!(
+        bpmn,
+        Bpmn::Activity(Activity {
+            activity_type: ActivityType::UserTask | ActivityType::ReceiveTask,
+            ..
+        }) | Bpmn::Event(Event {
+            event_type: EventType::IntermediateCatch,
+            ..
+        })
+    )
+}
+
+impl<'a> RunState<'a> {
+    pub(super) fn new(options: RunOptions<'a>) -> Self {
+        Self {
+            services: options.services,
+            trace: options.trace,
+            cancel: options.cancel,
+            boundary_trigger: options.boundary_trigger,
+            run_handle: options.run_handle,
+            deadline: options.timeout.map(|timeout| Instant::now() + timeout),
+            max_visits: options.max_visits,
+            max_steps: options.max_steps,
+            max_tokens: options.max_tokens,
+            #[cfg(feature = "parallel")]
+            deterministic: options.deterministic,
+            shuffle: options.shuffle.map(|seed| Mutex::new(Shuffle::new(seed))),
+            events: options.event_sink,
+            started: Some(Instant::now()),
+            started_at: time::since_epoch(),
+            log_prefix: options
+                .correlation_id
+                .as_ref()
+                .map(|id| format!("[{id}] "))
+                .unwrap_or_default(),
+            correlation_id: options.correlation_id,
+            metadata: options.metadata,
+            #[cfg(feature = "variables")]
+            variables: options.variables.unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    // Tokens run one at a time in a repeatable order
+    #[cfg(feature = "parallel")]
+    fn is_sequential(&self) -> bool {
+        self.deterministic || self.shuffle.is_some()
+    }
+
+    // Shuffle the order of the tokens and of the flows of each token when a seed is given
+    fn shuffle_tokens(&self, tokens: &mut [Tokens]) {
+        let Some(Ok(mut shuffle)) = self.shuffle.as_ref().map(Mutex::lock) else {
+            return;
+        };
+        shuffle.shuffle(tokens);
+        for tokens in tokens {
+            shuffle.shuffle(tokens.flows.to_mut());
+        }
+    }
+
+    // Count the visit of the element and fail when a visit limit is exceeded
+    fn visit(&self, bpmn: &Bpmn) -> Result<(), Error> {
+        if self.max_visits.is_none() && self.max_steps.is_none() {
+            return Ok(());
+        }
+        let (Some(id), Ok(mut visits)) = (bpmn.id(), self.visits.lock()) else {
+            return Ok(());
+        };
+        let count = visits.entry(id.into()).or_default();
+        *count += 1;
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        let limit = match (self.max_visits, self.max_steps) {
+            (Some(max), _) if *count > max => max,
+            (_, Some(max)) if steps > max => max,
+            _ => return Ok(()),
+        };
+
+        // Elements entered more than once, most visited first
+        let mut cycling: Vec<_> = visits.iter().filter(|(_, count)| **count > 1).collect();
+        cycling.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        Err(Error::VisitLimit(
+            limit,
+            cycling.into_iter().map(|(id, _)| id.clone()).collect(),
+        ))
+    }
+
+    fn is_timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(super) fn trace_id(&self) -> Option<&str> {
+        self.trace.map(TraceContext::trace_id)
+    }
+
+    pub(super) fn correlation_id(&self) -> Option<&str> {
+        self.correlation_id.as_deref()
+    }
+
+    pub(super) fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    fn wait(&self, token: usize, parent: Option<usize>, bpmn: &Bpmn) {
+        let (Some(id), Some(element_type)) = (bpmn.id(), bpmn.element_type()) else {
+            return;
+        };
+        if let Ok(mut waiting) = self.waiting.lock() {
+            waiting.insert(
+                token,
+                Waiting {
+                    id: id.into(),
+                    name: bpmn.name().map(Into::into),
+                    element_type,
+                    parent,
+                    since: Instant::now(),
+                    reported: false,
+                },
+            );
+        }
+    }
+
+    fn done_waiting(&self, token: usize) {
+        if let Ok(mut waiting) = self.waiting.lock() {
+            waiting.remove(&token);
+        }
+    }
+
+    // Report tokens waiting longer than the threshold once
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn report_idle(&self, threshold: Duration, report: impl Fn(ExecutionEvent)) {
+        let Ok(mut waiting) = self.waiting.lock() else {
+            return;
+        };
+        waiting
+            .iter_mut()
+            .filter(|(_, waiting)| !waiting.reported && waiting.since.elapsed() >= threshold)
+            .for_each(|(token, waiting)| {
+                waiting.reported = true;
+                report(ExecutionEvent {
+                    kind: ExecutionEventKind::Idle,
+                    id: &waiting.id,
+                    name: waiting.name.as_deref(),
+                    element_type: waiting.element_type,
+                    token: *token,
+                    parent_token: waiting.parent,
+                })
+            });
+    }
+
+    // The event is only created when the run has an event sink or records history
+    pub(super) fn emit(&self, event: impl FnOnce() -> EngineEvent) {
+        if self.events.is_none() && self.history.is_none() {
+            return;
+        }
+        let event = event();
+        if let Some(Ok(mut history)) = self.history.as_ref().map(Mutex::lock) {
+            history.push(HistoryEntry {
+                elapsed: self.elapsed(),
+                event: event.clone(),
+            });
+        }
+        if let Some(events) = &self.events {
+            // A dropped receiver doesn't stop the run
+            let _ = events.send(event);
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started
+            .map(|started| started.elapsed())
+            .unwrap_or_default()
+    }
+
+    // History of the run with the end event of the run or the error that stopped it
+    pub(super) fn take_history(&self, result: Result<EndNode, String>) -> RunHistory {
+        RunHistory {
+            correlation_id: self.correlation_id.clone(),
+            metadata: self.metadata.clone(),
+            started: self.started_at,
+            duration: self.elapsed(),
+            entries: self
+                .history
+                .as_ref()
+                .and_then(|history| history.lock().ok())
+                .map(|mut history| std::mem::take(&mut *history))
+                .unwrap_or_default(),
+            result,
+        }
+    }
+
+    fn next_token(&self) -> usize {
+        self.token_counter.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn record(&self, context: &Context, elapsed: Duration) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.record(context.id, context.name, elapsed);
+        }
+    }
+
+    fn reached_end(&self, event: &Event) {
+        if let Ok(mut end_nodes) = self.end_nodes.lock() {
+            end_nodes.push(EndNode::from(Some(event)));
+        }
+    }
+
+    fn reached_milestone(&self, event: &Event) {
+        let Some(name) = event.name.clone() else {
+            return;
+        };
+        if let Ok(mut milestones) = self.milestones.lock() {
+            milestones.push(Milestone {
+                id: event.id.bpmn_arc(),
+                name,
+                elapsed: self.elapsed(),
+                reached_at: time::since_epoch(),
+            });
+        }
+    }
+
+    pub(super) fn take_milestones(&self) -> Vec<Milestone> {
+        self.milestones
+            .lock()
+            .map(|mut milestones| std::mem::take(&mut *milestones))
+            .unwrap_or_default()
+    }
+
+    fn failed_at(&self, id: Arc<str>, path: Vec<usize>, ids: Vec<Arc<str>>) {
+        if let Ok(mut failed) = self.failed.lock() {
+            failed.get_or_insert((
+                id,
+                Some(Position {
+                    process: None,
+                    path,
+                    ids,
+                }),
+            ));
+        }
+    }
+
+    // The top level process of the failed task
+    pub(super) fn failed_in(&self, process: &str) {
+        if let Ok(mut failed) = self.failed.lock()
+            && let Some((_, Some(position))) = failed.as_mut()
+        {
+            position.process.get_or_insert_with(|| process.into());
+        }
+    }
+
+    // Other tokens were live when the task failed. Only the failed token would be resumed, so the run can't
+    // continue from the task.
+    fn failed_with_tokens(&self) {
+        if let Ok(mut failed) = self.failed.lock()
+            && let Some((_, position)) = failed.as_mut()
+        {
+            *position = None;
+        }
+    }
+
+    pub(super) fn take_failed(&self) -> Option<(Arc<str>, Option<Position>)> {
+        self.failed.lock().ok().and_then(|mut failed| failed.take())
+    }
+
+    fn resume_subprocess(&self) -> Option<usize> {
+        self.resume.lock().ok().and_then(|mut resume| resume.pop())
+    }
+
+    pub(super) fn take_end_nodes(&self) -> Vec<EndNode> {
+        self.end_nodes
+            .lock()
+            .map(|mut end_nodes| std::mem::take(&mut *end_nodes))
+            .unwrap_or_default()
+    }
+
+    #[cfg(feature = "variables")]
+    pub(super) fn take_variables(&self) -> crate::Variables {
+        self.variables.take()
+    }
+
+    pub(super) fn take_stats(&self) -> Stats {
+        self.stats
+            .lock()
+            .map(|mut stats| std::mem::take(&mut *stats))
+            .unwrap_or_default()
     }
 }
 
 // Data for the execution engine.
-pub(super) struct ExecuteInput<'a, T> {
+pub(super) struct ExecuteInput<'a, L> {
     process: &'a ProcessData,
-    user_data: Data<T>,
+    user_data: Arc<L>,
+    state: &'a RunState<'a>,
+    // Token that started the process. Set for subprocesses.
+    parent: Option<usize>,
+    // Start event used instead of the start event of type none, or the task of a resumed run
+    start: Option<usize>,
+    // Park tokens at conditional events with a false condition
+    park: bool,
+    // Subprocess activities enclosing the process with the process they belong to, outermost first
+    enclosing: Arc<Vec<(&'a ProcessData, &'a Activity)>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "otel")]
+    otel: opentelemetry::Context,
 }
 
-impl<'a, T> ExecuteInput<'a, T> {
-    pub(super) fn new(process: &'a ProcessData, user_data: Data<T>) -> Self {
-        Self { process, user_data }
+impl<'a, L> ExecuteInput<'a, L> {
+    pub(super) fn new(
+        process: &'a ProcessData,
+        user_data: Arc<L>,
+        state: &'a RunState<'a>,
+    ) -> Self {
+        Self {
+            process,
+            user_data,
+            state,
+            parent: None,
+            start: None,
+            park: false,
+            enclosing: Default::default(),
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+            #[cfg(feature = "otel")]
+            otel: opentelemetry::Context::current(),
+        }
+    }
+
+    // Start the process from another start event, like a message or signal start event, or from a failed task
+    pub(super) fn with_start(mut self, start: usize) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    // Input for a subprocess started by a token
+    fn subprocess(&self, process: &'a ProcessData, token: usize, activity: &'a Activity) -> Self {
+        let mut enclosing = Vec::clone(&self.enclosing);
+        enclosing.push((self.process, activity));
+        Self {
+            parent: Some(token),
+            start: self.state.resume_subprocess(),
+            enclosing: Arc::new(enclosing),
+            ..Self::new(process, self.user_data(), self.state)
+        }
+    }
+
+    // Record the failed task to resume the run from it
+    fn failed_at(&self, activity: &Activity) {
+        let activities = self
+            .enclosing
+            .iter()
+            .map(|(_, activity)| *activity)
+            .chain(std::iter::once(activity));
+        let path = activities
+            .clone()
+            .map(|activity| *activity.id.local())
+            .collect();
+        let ids = activities.map(|activity| activity.id.bpmn_arc()).collect();
+        self.state.failed_at(activity.id.bpmn_arc(), path, ids);
+    }
+
+    // Input of a token that runs with the data of its branch
+    fn scoped(&self, scope: &Scope<L>) -> Self {
+        Self {
+            process: self.process,
+            user_data: scope
+                .as_ref()
+                .map_or_else(|| self.user_data(), |branch| Arc::clone(&branch.data)),
+            state: self.state,
+            parent: self.parent,
+            start: self.start,
+            park: self.park,
+            enclosing: Arc::clone(&self.enclosing),
+            #[cfg(feature = "tracing")]
+            span: self.span.clone(),
+            #[cfg(feature = "otel")]
+            otel: self.otel.clone(),
+        }
     }
 
-    fn user_data(&self) -> Data<T> {
+    fn user_data(&self) -> Arc<L> {
         Arc::clone(&self.user_data)
     }
+
+    // Invoke a registered function and record the time spent in it.
+    fn invoke<R>(&self, context: Context<'a>, func: impl FnOnce(&Context, Arc<L>) -> R) -> R {
+        let start = Instant::now();
+        let result = func(&context, self.user_data());
+        self.state.record(&context, start.elapsed());
+        result
+    }
+
+    // Run a task with the input parameters of its mapping as variables and set the output parameters
+    // in the process variables when the task succeeds.
+    #[cfg(feature = "variables")]
+    fn map_io<R>(
+        &self,
+        bpmn: &Bpmn,
+        context: Context<'_>,
+        func: impl FnOnce(Context<'_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        let Some(mapping) = bpmn
+            .local_id()
+            .and_then(|index| self.process.io_mapping(*index))
+        else {
+            return func(context);
+        };
+        let scope = mapping
+            .inputs
+            .iter()
+            .filter_map(|input| {
+                self.state
+                    .variables
+                    .resolve(&input.source)
+                    .map(|value| (input.target.clone(), value))
+            })
+            .collect::<crate::Variables>();
+        let result = func(Context {
+            variables: &scope,
+            ..context
+        })?;
+        for output in &mapping.outputs {
+            if let Some(value) = scope.resolve(&output.source) {
+                self.state.variables.set(output.target.clone(), value);
+            }
+        }
+        Ok(result)
+    }
+
+    #[cfg(not(feature = "variables"))]
+    fn map_io<R>(
+        &self,
+        _bpmn: &Bpmn,
+        context: Context<'_>,
+        func: impl FnOnce(Context<'_>) -> Result<R, Error>,
+    ) -> Result<R, Error> {
+        func(context)
+    }
+
+    // Context given to the registered functions
+    fn element_context(&self, id: &'a str, name: Option<&'a str>, token: usize) -> Context<'a> {
+        Context {
+            id,
+            name,
+            symbol: None,
+            attached_to: None,
+            token,
+            trace: self.state.trace,
+            correlation_id: self.state.correlation_id.as_deref(),
+            metadata: &self.state.metadata,
+            log_prefix: &self.state.log_prefix,
+            cancel: self.state.cancel.as_ref(),
+            boundary_trigger: self.state.boundary_trigger.as_ref(),
+            deadline: self.state.deadline,
+            subprocess_end: None,
+            attempt: 1,
+            task_type: None,
+            assignment: None,
+            form: None,
+            message: None,
+            properties: None,
+            data: None,
+            services: self.state.services,
+            decisions: self.state.decisions.unwrap_or(&self.state.run_decisions),
+            #[cfg(feature = "variables")]
+            variables: &self.state.variables,
+        }
+    }
+
+    fn context(&self, bpmn: &'a Bpmn, token: usize) -> Context<'a> {
+        let mut context = self.element_context(bpmn.id().unwrap_or_default(), bpmn.name(), token);
+        context.properties = bpmn
+            .local_id()
+            .and_then(|index| self.process.properties(*index));
+        context.data = bpmn
+            .local_id()
+            .and_then(|index| self.process.data_associations(*index));
+        context.message = bpmn
+            .local_id()
+            .and_then(|index| self.process.message(*index));
+        if let Bpmn::Activity(activity) = bpmn {
+            context.task_type = activity.task_type.as_deref();
+            context.assignment = activity.assignment.as_ref();
+            context.form = activity.form.as_ref();
+        }
+        if let Bpmn::Event(Event {
+            symbol,
+            attached_to_ref,
+            ..
+        }) = bpmn
+        {
+            context.symbol = symbol.as_ref();
+            context.attached_to = attached_to_ref
+                .as_ref()
+                .and_then(|id| self.process.get(*id.local()))
+                .and_then(Bpmn::name_or_id);
+        }
+        context
+    }
 }
@@ -3,144 +3,204 @@ use crate::{
     bpmn::{Gateway, GatewayType},
 };
 use log::debug;
-use std::{borrow::Cow, fmt::Display};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+};
+
+// Flows to run and the token that created them.
+#[derive(Debug)]
+pub(super) struct Tokens<'a> {
+    pub(super) parent: Option<usize>,
+    // Parked token that continues instead of a new token
+    pub(super) token: Option<usize>,
+    pub(super) flows: Cow<'a, [usize]>,
+}
 
+// Token accounting per join and incoming sequence flow. Forks and joins don't need to pair up,
+// and multiple tokens can arrive on the same sequence flow.
 #[derive(Default, Debug)]
 pub(super) struct ExecuteHandler<'a> {
-    tokens_ready: Vec<Cow<'a, [usize]>>,
-    uncommitted: Vec<Cow<'a, [usize]>>,
-    token_stack: Vec<TokenData<'a>>,
+    tokens_ready: Vec<Tokens<'a>>,
+    // Tokens waiting at a join gateway, ordered by the gateway local id
+    joins: BTreeMap<usize, JoinData<'a>>,
 }
 
 impl<'a> ExecuteHandler<'a> {
-    pub(super) fn new(tokens: Cow<'a, [usize]>) -> Self {
+    pub(super) fn new(flows: Cow<'a, [usize]>, parent: Option<usize>) -> Self {
         Self {
-            tokens_ready: vec![tokens],
-            uncommitted: Default::default(),
-            token_stack: Default::default(),
+            tokens_ready: vec![Tokens {
+                parent,
+                token: None,
+                flows,
+            }],
+            joins: Default::default(),
         }
     }
 
     // Return tokens to be processed.
-    pub(super) fn active_tokens(&mut self) -> Vec<Cow<'a, [usize]>> {
+    pub(super) fn active_tokens(&mut self) -> Vec<Tokens<'a>> {
         std::mem::take(&mut self.tokens_ready)
     }
 
-    // Push directly to tokens_ready without the involvement of token_stack.
-    // When we JOIN a gateway with one output we should not increase the token_stack.
-    pub(super) fn immediate(&mut self, item: Cow<'a, [usize]>) {
-        self.tokens_ready.push(item);
+    // New tokens created by a fork or a join.
+    pub(super) fn fork(&mut self, flows: Cow<'a, [usize]>, parent: usize) {
+        debug!("NEW TOKENS {}", flows.len());
+        self.tokens_ready.push(Tokens {
+            parent: Some(parent),
+            token: None,
+            flows,
+        });
     }
 
-    // If a gateway FORK is involved, we need to use the token stack. Even if the gateway only selects one flow.
-    pub(super) fn pending_fork(&mut self, item: Cow<'a, [usize]>) {
-        self.uncommitted.push(item);
+    // A token parked at an element continues from it with the next tokens.
+    pub(super) fn park(&mut self, element: usize, token: usize, parent: Option<usize>) {
+        debug!("PARKED TOKEN {token}");
+        self.tokens_ready.push(Tokens {
+            parent,
+            token: Some(token),
+            flows: Cow::Owned(vec![element]),
+        });
     }
 
-    // Commit all new tokens.
-    pub(super) fn commit(&mut self) {
-        for item in self.uncommitted.drain(..) {
-            debug!("NEW TOKENS {}", item.len());
-            self.token_stack.push(TokenData::new(item.len()));
-            self.tokens_ready.push(item);
+    // A token arrived at a join from a sequence flow. Returns the token that completed a parallel join
+    // when a token has arrived on every input.
+    pub(super) fn join(
+        &mut self,
+        gateway: &'a Gateway,
+        flow: Option<usize>,
+        token: usize,
+    ) -> Option<usize> {
+        let join = self
+            .joins
+            .entry(*gateway.id.local())
+            .or_insert_with(|| JoinData::new(gateway));
+        join.arrive(flow, token);
+        debug!("JOIN {join}");
+
+        if gateway.gateway_type == GatewayType::Parallel
+            && join.arrived.len() >= gateway.inputs as usize
+        {
+            if join.consume() {
+                self.joins.remove(gateway.id.local());
+            }
+            return Some(token);
         }
+        None
     }
 
-    // Consume a token. Might be a gateway join or end event.
-    pub(super) fn consume_token(&mut self, join: Option<&'a Gateway>) {
-        if let Some(token_data) = self.token_stack.last_mut() {
-            token_data.consume(join);
-        }
+    // Joins with waiting tokens and the number of tokens
+    pub(super) fn waiting_tokens(&self) -> impl Iterator<Item = (&'a Gateway, usize)> {
+        self.joins
+            .values()
+            .map(|join| (join.gateway, join.arrived.values().sum()))
+    }
+
+    // BPMN ids of the joins with waiting tokens
+    pub(super) fn waiting(&self) -> impl Iterator<Item = String> {
+        self.joins
+            .values()
+            .map(|join| join.gateway.id.bpmn().to_string())
     }
 
-    // Once all tokens have been consumed, return the gateway involved.
-    pub(super) fn tokens_consumed(&mut self) -> Result<Option<&'a Gateway>, Error> {
-        if let Some(token_data) = self.token_stack.last()
-            && token_data.consumed()
+    // No tokens are running and all remaining tokens wait at joins. Returns an inclusive join that no other waiting
+    // join can reach, as no more tokens can arrive to it. A parallel join that is still waiting can never complete.
+    pub(super) fn stalled(
+        &mut self,
+        reachable: impl Fn(usize) -> HashSet<usize>,
+    ) -> Result<Option<(&'a Gateway, usize)>, Error> {
+        let inclusive: Vec<usize> = self
+            .joins
+            .iter()
+            .filter(|(_, join)| join.gateway.gateway_type == GatewayType::Inclusive)
+            .map(|(index, _)| *index)
+            .collect();
+
+        let next = if inclusive.is_empty() {
+            None
+        } else {
+            let reached: Vec<(usize, HashSet<usize>)> = self
+                .joins
+                .keys()
+                .map(|index| (*index, reachable(*index)))
+                .collect();
+            inclusive
+                .iter()
+                .find(|index| {
+                    !reached
+                        .iter()
+                        .any(|(other, reached)| other != *index && reached.contains(index))
+                })
+                // Joins that can reach each other. Pick the first to keep going.
+                .or(inclusive.first())
+                .copied()
+        };
+
+        if let Some(index) = next
+            && let Some(join) = self.joins.get_mut(&index)
         {
-            debug!("ALL CONSUMED {}", token_data);
-
-            if let Some(gateways) = self.token_stack.pop().map(|data| data.joined) {
-                let gateway = gateways.first().copied();
-
-                // Determines whether enough tokens have arrived at the parallel gateway.
-                // Without this, parallel gateways are too permissive.
-                if let Some(
-                    gateway @ Gateway {
-                        gateway_type: GatewayType::Parallel,
-                        inputs,
-                        ..
-                    },
-                ) = gateway
-                    && gateways.len() < *inputs as usize
-                {
-                    return Err(Error::BpmnRequirement(format!(
-                        "Execution stopped. Not enough tokens at {gateway}"
-                    )));
-                }
-
-                #[cfg(debug_assertions)]
-                check_unbalanced_diagram(gateways)?;
-                return Ok(gateway);
+            debug!("ALL ARRIVED {join}");
+            let fired = (join.gateway, join.last_token);
+            // Tokens left on a sequence flow fire the join again
+            if join.consume() {
+                self.joins.remove(&index);
             }
+            return Ok(Some(fired));
+        }
+
+        // Determines whether enough tokens have arrived at the parallel gateway.
+        // Without this, parallel gateways are too permissive.
+        if let Some(join) = self.joins.values().next() {
+            return Err(Error::BpmnRequirement(format!(
+                "Execution stopped. Not enough tokens at {}",
+                join.gateway
+            )));
         }
         Ok(None)
     }
 }
 
-#[derive(Default, Debug)]
-struct TokenData<'a> {
-    created: usize,
-    joined: Vec<&'a Gateway>,
-    consumed: usize,
+#[derive(Debug)]
+struct JoinData<'a> {
+    gateway: &'a Gateway,
+    // Number of waiting tokens per incoming sequence flow
+    arrived: BTreeMap<Option<usize>, usize>,
+    last_token: usize,
 }
 
-impl<'a> TokenData<'a> {
-    fn new(created: usize) -> Self {
+impl<'a> JoinData<'a> {
+    fn new(gateway: &'a Gateway) -> Self {
         Self {
-            created,
-            joined: Default::default(),
-            consumed: Default::default(),
+            gateway,
+            arrived: Default::default(),
+            last_token: Default::default(),
         }
     }
 
-    fn consume(&mut self, maybe_gateway: Option<&'a Gateway>) {
-        if let Some(gateway) = maybe_gateway {
-            self.joined.push(gateway)
-        }
-        self.consumed += 1;
-        debug!("TOKENS CONSUMED {}", self.consumed);
+    fn arrive(&mut self, flow: Option<usize>, token: usize) {
+        *self.arrived.entry(flow).or_default() += 1;
+        self.last_token = token;
     }
 
-    fn consumed(&self) -> bool {
-        self.created.saturating_sub(self.consumed) == 0
+    // Consume one token from every sequence flow with waiting tokens. Returns true if no tokens are left.
+    fn consume(&mut self) -> bool {
+        self.arrived.retain(|_, count| {
+            *count -= 1;
+            *count > 0
+        });
+        self.arrived.is_empty()
     }
 }
 
-impl<'a> Display for TokenData<'a> {
+impl<'a> Display for JoinData<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "created: {}, consumed: {}, joined: {}",
-            self.created,
-            self.consumed,
-            self.joined
-                .iter()
-                .map(|gw| gw.to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
+            "{}, arrived: {}, inputs: {}",
+            self.gateway,
+            self.arrived.values().sum::<usize>(),
+            self.gateway.inputs
         )
     }
 }
-
-#[cfg(debug_assertions)]
-fn check_unbalanced_diagram(mut input: Vec<&Gateway>) -> Result<(), Error> {
-    let mut seen = std::collections::HashSet::new();
-    input.retain(|v| seen.insert(*v.id.local()));
-
-    // If many different gateways are visited, we have an unbalanced graph
-    if input.len() > 1 {
-        return Err(Error::NotSupported("Unbalanced diagram".into()));
-    }
-    Ok(())
-}
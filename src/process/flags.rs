@@ -0,0 +1,26 @@
+use std::collections::HashSet;
+
+/// Feature flags consulted by the engine to disable tasks and sequence flows at runtime.
+///
+/// A disabled task is not invoked and the process continues with its declared fallback flow, or its regular outgoing flow.
+/// A disabled sequence flow selected by a gateway is replaced by its declared fallback flow, or the gateway default flow.
+pub trait FeatureFlags: Send + Sync {
+    /// Return `false` to disable the element with the name or BPMN id
+    fn is_enabled(&self, name_or_id: &str) -> bool;
+}
+
+impl<F> FeatureFlags for F
+where
+    F: Fn(&str) -> bool + Send + Sync,
+{
+    fn is_enabled(&self, name_or_id: &str) -> bool {
+        self(name_or_id)
+    }
+}
+
+/// Set with disabled element names or BPMN ids
+impl FeatureFlags for HashSet<String> {
+    fn is_enabled(&self, name_or_id: &str) -> bool {
+        !self.contains(name_or_id)
+    }
+}
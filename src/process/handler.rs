@@ -1,48 +1,232 @@
 use crate::{
     Error,
-    api::{Data, IntermediateEvent, TaskResult, With},
+    api::{
+        Context, DuplicateFlows, ErrorDecision, ExecutionEvent, IntermediateEvent, SharedData,
+        TaskResult, Transition, TransitionDecision, With,
+    },
     error::FUNC_MAP_ERROR_MSG,
+    history::HistorySink,
+    process::{
+        breaker::CircuitBreaker,
+        cache::DecisionCache,
+        flags::FeatureFlags,
+        options::ParseOptions,
+        retry::RetryPolicy,
+        schedule::{IoPool, Limit, Workload},
+    },
 };
-use std::{collections::HashMap, fmt::Display};
-
-type TaskCallback<T> = Box<dyn Fn(Data<T>) -> Result<TaskResult, Error> + Sync + Send>;
-type ExclusiveCallback<T> =
-    Box<dyn Fn(Data<T>) -> Result<Option<&'static str>, Error> + Sync + Send>;
-type InclusiveCallback<T> = Box<dyn Fn(Data<T>) -> Result<With, Error> + Sync + Send>;
-type EventBasedCallback<T> = Box<dyn Fn(Data<T>) -> Result<IntermediateEvent, Error> + Sync + Send>;
-
-pub(super) enum Callback<T> {
-    Task(TaskCallback<T>),
-    Exclusive(ExclusiveCallback<T>),
-    Inclusive(InclusiveCallback<T>),
-    EventBased(EventBasedCallback<T>),
+use std::{borrow::Cow, collections::HashMap, fmt::Display, sync::Arc, time::Duration};
+
+type TaskCallback<L> = Arc<dyn Fn(&Context, Arc<L>) -> Result<TaskResult, Error> + Sync + Send>;
+type ExclusiveCallback<L> =
+    Arc<dyn Fn(&Context, Arc<L>) -> Result<Option<Cow<'static, str>>, Error> + Sync + Send>;
+type ExclusiveIndexCallback<L> =
+    Arc<dyn Fn(&Context, Arc<L>, &[&str]) -> Result<Option<usize>, Error> + Sync + Send>;
+type InclusiveCallback<L> = Arc<dyn Fn(&Context, Arc<L>) -> Result<With, Error> + Sync + Send>;
+type EventBasedCallback<L> =
+    Arc<dyn Fn(&Context, Arc<L>) -> Result<IntermediateEvent, Error> + Sync + Send>;
+type BoundaryCallback<L> = Arc<dyn Fn(&Context, Arc<L>) -> Result<(), Error> + Sync + Send>;
+pub(super) type Listener = Arc<dyn Fn(&ExecutionEvent) + Sync + Send>;
+pub(super) type DataListener<T> = Arc<dyn Fn(&ExecutionEvent, &T) + Sync + Send>;
+pub(super) type ErrorHook = Arc<dyn Fn(&Context, &Error) -> ErrorDecision + Sync + Send>;
+pub(super) type TransitionHook = Arc<dyn Fn(&Transition) -> TransitionDecision + Sync + Send>;
+pub(super) type BranchSplit<T> = Arc<dyn Fn(&T) -> T + Sync + Send>;
+pub(super) type BranchMerge<T> = Arc<dyn Fn(&mut T, &T) + Sync + Send>;
+pub(super) type Condition<T> = Arc<dyn Fn(&T) -> bool + Sync + Send>;
+
+pub(super) enum Callback<L> {
+    Task(TaskCallback<L>),
+    Exclusive(ExclusiveCallback<L>),
+    ExclusiveIndex(ExclusiveIndexCallback<L>),
+    Inclusive(InclusiveCallback<L>),
+    EventBased(EventBasedCallback<L>),
+    Boundary(BoundaryCallback<L>),
+    // Runs like a task for the tasks with the connector in their properties
+    Connector(TaskCallback<L>),
+}
+
+impl<L> Clone for Callback<L> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Task(func) => Self::Task(Arc::clone(func)),
+            Self::Exclusive(func) => Self::Exclusive(Arc::clone(func)),
+            Self::ExclusiveIndex(func) => Self::ExclusiveIndex(Arc::clone(func)),
+            Self::Inclusive(func) => Self::Inclusive(Arc::clone(func)),
+            Self::EventBased(func) => Self::EventBased(Arc::clone(func)),
+            Self::Boundary(func) => Self::Boundary(Arc::clone(func)),
+            Self::Connector(func) => Self::Connector(Arc::clone(func)),
+        }
+    }
+}
+
+// Outgoing flow selected by an exclusive gateway function
+pub(super) enum Selected {
+    Name(Cow<'static, str>),
+    // Position in the outgoing flows
+    Index(usize),
 }
 
-pub(super) struct Handler<T> {
-    callbacks: Vec<Callback<T>>,
+pub(super) struct Handler<T, L> {
+    callbacks: Vec<Callback<L>>,
+    listeners: Vec<Listener>,
+    data_listeners: Vec<DataListener<T>>,
+    on_error: Option<ErrorHook>,
+    on_transition: Option<TransitionHook>,
+    branch_data: Option<(BranchSplit<T>, BranchMerge<T>)>,
+    decision_cache: Option<Arc<DecisionCache>>,
+    feature_flags: Option<Arc<dyn FeatureFlags>>,
+    history: Option<Arc<dyn HistorySink>>,
+    fallbacks: HashMap<String, String>,
+    workloads: HashMap<String, Workload>,
+    retries: HashMap<String, RetryPolicy>,
+    limits: HashMap<String, Arc<Limit>>,
+    breakers: HashMap<String, Arc<CircuitBreaker>>,
+    conditions: HashMap<String, Condition<T>>,
+    idle_threshold: Option<Duration>,
+    idle_escalation: bool,
+    #[cfg(feature = "parallel")]
+    parallel_threshold: usize,
+    io_pool: Arc<IoPool>,
+    duplicate_flows: DuplicateFlows,
+    catch_panics: bool,
+    ignore_missing_boundaries: bool,
+    task_types: bool,
+    strict: bool,
+    parse_options: ParseOptions,
+    // Gateway name or id and the flows of its FlowName type
+    flow_names: Vec<(String, &'static [&'static str])>,
 
     // Used while building. Is None after use.
     handler_map: Option<HandlerMap>,
+    // Installed by build. Used to install the functions in a reloaded diagram.
+    installed_map: HandlerMap,
+    // Registered functions and the number of callbacks before build_lenient added the stubs.
+    // A reloaded diagram gets its own stubs.
+    before_stubs: Option<(HandlerMap, usize)>,
 }
 
-impl<T> Default for Handler<T> {
+impl<T, L> Default for Handler<T, L> {
     fn default() -> Self {
         Self {
             callbacks: Default::default(),
+            listeners: Default::default(),
+            data_listeners: Default::default(),
+            on_error: Default::default(),
+            on_transition: Default::default(),
+            branch_data: Default::default(),
+            decision_cache: Default::default(),
+            feature_flags: Default::default(),
+            history: Default::default(),
+            fallbacks: Default::default(),
+            workloads: Default::default(),
+            retries: Default::default(),
+            limits: Default::default(),
+            breakers: Default::default(),
+            conditions: Default::default(),
+            idle_threshold: Default::default(),
+            idle_escalation: Default::default(),
+            #[cfg(feature = "parallel")]
+            parallel_threshold: Default::default(),
+            io_pool: Default::default(),
+            duplicate_flows: Default::default(),
+            catch_panics: Default::default(),
+            ignore_missing_boundaries: Default::default(),
+            task_types: Default::default(),
+            strict: Default::default(),
+            parse_options: Default::default(),
+            flow_names: Default::default(),
             handler_map: Some(Default::default()),
+            installed_map: Default::default(),
+            before_stubs: Default::default(),
         }
     }
 }
 
-impl<T> Handler<T> {
-    pub(super) fn add_callback(&mut self, name: impl Into<String>, callback: Callback<T>) {
+// Functions, hooks and the state of limits and breakers are shared with the clone
+impl<T, L> Clone for Handler<T, L> {
+    fn clone(&self) -> Self {
+        Self {
+            callbacks: self.callbacks.clone(),
+            listeners: self.listeners.clone(),
+            data_listeners: self.data_listeners.clone(),
+            on_error: self.on_error.clone(),
+            on_transition: self.on_transition.clone(),
+            branch_data: self.branch_data.clone(),
+            decision_cache: self.decision_cache.clone(),
+            feature_flags: self.feature_flags.clone(),
+            history: self.history.clone(),
+            fallbacks: self.fallbacks.clone(),
+            workloads: self.workloads.clone(),
+            retries: self.retries.clone(),
+            limits: self.limits.clone(),
+            breakers: self.breakers.clone(),
+            conditions: self.conditions.clone(),
+            idle_threshold: self.idle_threshold,
+            idle_escalation: self.idle_escalation,
+            #[cfg(feature = "parallel")]
+            parallel_threshold: self.parallel_threshold,
+            io_pool: Arc::clone(&self.io_pool),
+            duplicate_flows: self.duplicate_flows,
+            catch_panics: self.catch_panics,
+            ignore_missing_boundaries: self.ignore_missing_boundaries,
+            task_types: self.task_types,
+            strict: self.strict,
+            parse_options: self.parse_options,
+            flow_names: self.flow_names.clone(),
+            handler_map: self.handler_map.clone(),
+            installed_map: self.installed_map.clone(),
+            before_stubs: self.before_stubs.clone(),
+        }
+    }
+}
+
+impl<T, L> Handler<T, L> {
+    // The settings for data behind another lock. The registered functions take the old lock and are dropped.
+    pub(super) fn with_lock<U>(self) -> Handler<T, U> {
+        Handler {
+            callbacks: Vec::new(),
+            listeners: self.listeners,
+            data_listeners: self.data_listeners,
+            on_error: self.on_error,
+            on_transition: self.on_transition,
+            branch_data: self.branch_data,
+            decision_cache: self.decision_cache,
+            feature_flags: self.feature_flags,
+            history: self.history,
+            fallbacks: self.fallbacks,
+            workloads: self.workloads,
+            retries: self.retries,
+            limits: self.limits,
+            breakers: self.breakers,
+            conditions: self.conditions,
+            idle_threshold: self.idle_threshold,
+            idle_escalation: self.idle_escalation,
+            #[cfg(feature = "parallel")]
+            parallel_threshold: self.parallel_threshold,
+            io_pool: self.io_pool,
+            duplicate_flows: self.duplicate_flows,
+            catch_panics: self.catch_panics,
+            ignore_missing_boundaries: self.ignore_missing_boundaries,
+            task_types: self.task_types,
+            strict: self.strict,
+            parse_options: self.parse_options,
+            flow_names: self.flow_names,
+            handler_map: Some(Default::default()),
+            installed_map: Default::default(),
+            before_stubs: None,
+        }
+    }
+
+    pub(super) fn add_callback(&mut self, name: impl Into<String>, callback: Callback<L>) {
         if let Some(hm) = &mut self.handler_map {
             hm.insert(
                 match callback {
                     Callback::Task(_) => HandlerType::Task,
-                    Callback::Exclusive(_) => HandlerType::Exclusive,
+                    Callback::Exclusive(_) | Callback::ExclusiveIndex(_) => HandlerType::Exclusive,
                     Callback::Inclusive(_) => HandlerType::Inclusive,
                     Callback::EventBased(_) => HandlerType::EventBased,
+                    Callback::Boundary(_) => HandlerType::Boundary,
+                    Callback::Connector(_) => HandlerType::Connector,
                 },
                 name,
                 self.callbacks.len(),
@@ -51,9 +235,252 @@ impl<T> Handler<T> {
         }
     }
 
-    pub(super) fn run_task(&self, index: usize, data: Data<T>) -> Result<TaskResult, Error> {
-        if let Some(Callback::Task(func)) = self.callbacks.get(index) {
-            func(data)
+    pub(super) fn add_listener(&mut self, listener: Listener) {
+        self.listeners.push(listener);
+    }
+
+    pub(super) fn add_data_listener(&mut self, listener: DataListener<T>) {
+        self.data_listeners.push(listener);
+    }
+
+    pub(super) fn has_listeners(&self) -> bool {
+        !self.listeners.is_empty() || !self.data_listeners.is_empty()
+    }
+
+    pub(super) fn notify(&self, event: &ExecutionEvent) {
+        self.listeners.iter().for_each(|listener| listener(event));
+    }
+
+    pub(super) fn notify_with_data(&self, event: &ExecutionEvent, data: &L)
+    where
+        L: SharedData<T>,
+    {
+        self.notify(event);
+        if self.data_listeners.is_empty() {
+            return;
+        }
+
+        // Skip the data listeners if a function panicked while holding the lock
+        if let Ok(data) = data.read() {
+            self.data_listeners
+                .iter()
+                .for_each(|listener| listener(event, &data));
+        }
+    }
+
+    pub(super) fn set_on_error(&mut self, hook: ErrorHook) {
+        self.on_error = Some(hook);
+    }
+
+    pub(super) fn on_error(&self, context: &Context, error: &Error) -> ErrorDecision {
+        self.on_error
+            .as_ref()
+            .map(|hook| hook(context, error))
+            .unwrap_or_default()
+    }
+
+    pub(super) fn set_on_transition(&mut self, hook: TransitionHook) {
+        self.on_transition = Some(hook);
+    }
+
+    pub(super) fn on_transition(&self) -> Option<&TransitionHook> {
+        self.on_transition.as_ref()
+    }
+
+    pub(super) fn set_branch_data(&mut self, split: BranchSplit<T>, merge: BranchMerge<T>) {
+        self.branch_data = Some((split, merge));
+    }
+
+    pub(super) fn branch_data(&self) -> Option<&(BranchSplit<T>, BranchMerge<T>)> {
+        self.branch_data.as_ref()
+    }
+
+    pub(super) fn set_decision_cache(&mut self, cache: Arc<DecisionCache>) {
+        self.decision_cache = Some(cache);
+    }
+
+    pub(super) fn decision_cache(&self) -> Option<&DecisionCache> {
+        self.decision_cache.as_deref()
+    }
+
+    pub(super) fn set_feature_flags(&mut self, flags: Arc<dyn FeatureFlags>) {
+        self.feature_flags = Some(flags);
+    }
+
+    pub(super) fn set_history(&mut self, history: Arc<dyn HistorySink>) {
+        self.history = Some(history);
+    }
+
+    pub(super) fn history(&self) -> Option<&dyn HistorySink> {
+        self.history.as_deref()
+    }
+
+    pub(super) fn add_fallback(&mut self, name: String, flow: String) {
+        self.fallbacks.insert(name, flow);
+    }
+
+    // Element is disabled by the feature flags by name or id
+    pub(super) fn is_disabled(&self, id: &str, name: Option<&str>) -> bool {
+        self.feature_flags.as_ref().is_some_and(|flags| {
+            !flags.is_enabled(id) || name.is_some_and(|name| !flags.is_enabled(name))
+        })
+    }
+
+    pub(super) fn fallback(&self, id: &str, name: Option<&str>) -> Option<&str> {
+        name.and_then(|name| self.fallbacks.get(name))
+            .or_else(|| self.fallbacks.get(id))
+            .map(String::as_str)
+    }
+
+    pub(super) fn add_workload(&mut self, name: String, workload: Workload) {
+        self.workloads.insert(name, workload);
+    }
+
+    pub(super) fn workload(&self, id: &str, name: Option<&str>) -> Workload {
+        name.and_then(|name| self.workloads.get(name))
+            .or_else(|| self.workloads.get(id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub(super) fn add_retry(&mut self, name: String, policy: RetryPolicy) {
+        self.retries.insert(name, policy);
+    }
+
+    pub(super) fn retry(&self, id: &str, name: Option<&str>) -> Option<&RetryPolicy> {
+        name.and_then(|name| self.retries.get(name))
+            .or_else(|| self.retries.get(id))
+    }
+
+    pub(super) fn add_limit(&mut self, name: String, permits: usize) {
+        self.limits.insert(name, Arc::new(Limit::new(permits)));
+    }
+
+    pub(super) fn limit(&self, id: &str, name: Option<&str>) -> Option<&Limit> {
+        name.and_then(|name| self.limits.get(name))
+            .or_else(|| self.limits.get(id))
+            .map(Arc::as_ref)
+    }
+
+    pub(super) fn add_breaker(&mut self, name: String, breaker: CircuitBreaker) {
+        self.breakers.insert(name, Arc::new(breaker));
+    }
+
+    pub(super) fn breaker(&self, id: &str, name: Option<&str>) -> Option<&CircuitBreaker> {
+        name.and_then(|name| self.breakers.get(name))
+            .or_else(|| self.breakers.get(id))
+            .map(Arc::as_ref)
+    }
+
+    pub(super) fn add_condition(&mut self, name: String, condition: Condition<T>) {
+        self.conditions.insert(name, condition);
+    }
+
+    pub(super) fn condition(&self, id: &str, name: Option<&str>) -> Option<&Condition<T>> {
+        name.and_then(|name| self.conditions.get(name))
+            .or_else(|| self.conditions.get(id))
+    }
+
+    pub(super) fn set_idle_threshold(&mut self, threshold: Duration) {
+        self.idle_threshold = Some(threshold);
+    }
+
+    pub(super) fn idle_threshold(&self) -> Option<Duration> {
+        self.idle_threshold
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(super) fn set_parallel_threshold(&mut self, tokens: usize) {
+        self.parallel_threshold = tokens;
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(super) fn parallel_threshold(&self) -> usize {
+        self.parallel_threshold
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(super) fn set_io_threads(&mut self, threads: usize) {
+        self.io_pool = Arc::new(IoPool::new(threads));
+    }
+
+    pub(super) fn io_pool(&self) -> &IoPool {
+        &self.io_pool
+    }
+
+    pub(super) fn set_idle_escalation(&mut self) {
+        self.idle_escalation = true;
+    }
+
+    // Threshold if idle tasks should continue with their escalation boundary
+    pub(super) fn idle_escalation(&self) -> Option<Duration> {
+        self.idle_threshold.filter(|_| self.idle_escalation)
+    }
+
+    pub(super) fn set_duplicate_flows(&mut self, policy: DuplicateFlows) {
+        self.duplicate_flows = policy;
+    }
+
+    pub(super) fn duplicate_flows(&self) -> DuplicateFlows {
+        self.duplicate_flows
+    }
+
+    pub(super) fn set_catch_panics(&mut self) {
+        self.catch_panics = true;
+    }
+
+    pub(super) fn catch_panics(&self) -> bool {
+        self.catch_panics
+    }
+
+    pub(super) fn set_ignore_missing_boundaries(&mut self) {
+        self.ignore_missing_boundaries = true;
+    }
+
+    pub(super) fn ignore_missing_boundaries(&self) -> bool {
+        self.ignore_missing_boundaries
+    }
+
+    pub(super) fn add_flow_names(&mut self, name: String, flows: &'static [&'static str]) {
+        self.flow_names.push((name, flows));
+    }
+
+    pub(super) fn flow_names(&self) -> &[(String, &'static [&'static str])] {
+        &self.flow_names
+    }
+
+    pub(super) fn set_task_types(&mut self) {
+        self.task_types = true;
+    }
+
+    pub(super) fn task_types(&self) -> bool {
+        self.task_types
+    }
+
+    pub(super) fn set_strict(&mut self) {
+        self.strict = true;
+    }
+
+    pub(super) fn strict(&self) -> bool {
+        self.strict
+    }
+
+    pub(super) fn set_parse_options(&mut self, options: ParseOptions) {
+        self.parse_options = options;
+    }
+
+    pub(super) fn parse_options(&self) -> ParseOptions {
+        self.parse_options
+    }
+
+    pub(super) fn run_task(
+        &self,
+        index: usize,
+        context: &Context,
+        data: Arc<L>,
+    ) -> Result<TaskResult, Error> {
+        if let Some(Callback::Task(func) | Callback::Connector(func)) = self.callbacks.get(index) {
+            func(context, data)
         } else {
             Err(Error::MissingImplementation(format!(
                 "Task with index: {index}"
@@ -61,23 +488,33 @@ impl<T> Handler<T> {
         }
     }
 
-    pub(super) fn run_exclusive(
+    // The outgoing flow names are only collected for functions that select by index
+    pub(super) fn run_exclusive<'a>(
         &self,
         index: usize,
-        data: Data<T>,
-    ) -> Result<Option<&'static str>, Error> {
-        if let Some(Callback::Exclusive(func)) = self.callbacks.get(index) {
-            func(data)
-        } else {
-            Err(Error::MissingImplementation(format!(
+        context: &Context,
+        data: Arc<L>,
+        flows: impl FnOnce() -> Vec<&'a str>,
+    ) -> Result<Option<Selected>, Error> {
+        match self.callbacks.get(index) {
+            Some(Callback::Exclusive(func)) => Ok(func(context, data)?.map(Selected::Name)),
+            Some(Callback::ExclusiveIndex(func)) => {
+                Ok(func(context, data, &flows())?.map(Selected::Index))
+            }
+            _ => Err(Error::MissingImplementation(format!(
                 "Exclusive with index: {index}"
-            )))
+            ))),
         }
     }
 
-    pub(super) fn run_inclusive(&self, index: usize, data: Data<T>) -> Result<With, Error> {
+    pub(super) fn run_inclusive(
+        &self,
+        index: usize,
+        context: &Context,
+        data: Arc<L>,
+    ) -> Result<With, Error> {
         if let Some(Callback::Inclusive(func)) = self.callbacks.get(index) {
-            func(data)
+            func(context, data)
         } else {
             Err(Error::MissingImplementation(format!(
                 "Inclusive with index: {index}"
@@ -88,10 +525,11 @@ impl<T> Handler<T> {
     pub(super) fn run_eventbased(
         &self,
         index: usize,
-        data: Data<T>,
+        context: &Context,
+        data: Arc<L>,
     ) -> Result<IntermediateEvent, Error> {
         if let Some(Callback::EventBased(func)) = self.callbacks.get(index) {
-            func(data)
+            func(context, data)
         } else {
             Err(Error::MissingImplementation(format!(
                 "Eventbased with index: {index}"
@@ -99,12 +537,67 @@ impl<T> Handler<T> {
         }
     }
 
+    pub(super) fn run_boundary(
+        &self,
+        index: usize,
+        context: &Context,
+        data: Arc<L>,
+    ) -> Result<(), Error> {
+        if let Some(Callback::Boundary(func)) = self.callbacks.get(index) {
+            func(context, data)
+        } else {
+            Err(Error::MissingImplementation(format!(
+                "Boundary with index: {index}"
+            )))
+        }
+    }
+
+    // Is None after build
+    pub(super) fn handler_map(&self) -> Option<&HandlerMap> {
+        self.handler_map.as_ref()
+    }
+
+    // Is always false after build
+    pub(super) fn is_registered(&self, handler_type: HandlerType, name: &str) -> bool {
+        self.handler_map
+            .as_ref()
+            .is_some_and(|hm| hm.get(handler_type, name).is_some())
+    }
+
     // Consumes the handler_map and cannot add more things with add_
     pub(super) fn build(&mut self) -> Result<HandlerMap, Error> {
         self.handler_map
             .take()
             .ok_or_else(|| Error::Builder(FUNC_MAP_ERROR_MSG.into()))
     }
+
+    pub(super) fn set_installed_map(&mut self, handler_map: HandlerMap) {
+        self.installed_map = handler_map;
+    }
+
+    // Called by build_lenient before the stubs are registered
+    pub(super) fn set_lenient(&mut self) {
+        self.before_stubs = self
+            .handler_map
+            .clone()
+            .map(|handler_map| (handler_map, self.callbacks.len()));
+    }
+
+    // Accept functions again to build a reloaded diagram. Drops the stubs of build_lenient and returns
+    // true if the diagram needs new stubs.
+    pub(super) fn reopen(&mut self) -> bool {
+        match self.before_stubs.take() {
+            Some((handler_map, len)) => {
+                self.callbacks.truncate(len);
+                self.handler_map = Some(handler_map);
+                true
+            }
+            None => {
+                self.handler_map = Some(self.installed_map.clone());
+                false
+            }
+        }
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
@@ -113,6 +606,8 @@ pub enum HandlerType {
     Exclusive,
     Inclusive,
     EventBased,
+    Boundary,
+    Connector,
 }
 
 impl Display for HandlerType {
@@ -121,7 +616,7 @@ impl Display for HandlerType {
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct HandlerMap {
     map: HashMap<HandlerType, HashMap<String, usize>>,
 }
@@ -135,6 +630,15 @@ impl HandlerMap {
         }
     }
 
+    // Handler type, name and index of every registered function
+    pub fn iter(&self) -> impl Iterator<Item = (HandlerType, &str, usize)> {
+        self.map.iter().flat_map(|(handler_type, inner_map)| {
+            inner_map
+                .iter()
+                .map(|(name, index)| (*handler_type, name.as_str(), *index))
+        })
+    }
+
     fn insert(&mut self, handler_type: HandlerType, name: impl Into<String>, index: usize) {
         let name = name.into();
         if self
@@ -0,0 +1,116 @@
+use super::{Build, Process};
+use crate::{
+    Error,
+    api::{Context, Data, IntermediateEvent, Outcome, TaskResult, With},
+};
+use std::{borrow::Cow, sync::Arc};
+
+/// Functions for a group of elements, registered with `Process::register`. Large processes can split
+/// the functions across modules and structs instead of one long builder chain.
+///
+/// List the element names or ids in `task_names`, `exclusive_names`, `inclusive_names` and `event_based_names`.
+/// The matching method is called with the name of the element. Unlisted elements must be registered elsewhere.
+///
+/// ```
+/// use snurr::{Context, Data, Error, ProcessHandlers, TaskResult};
+///
+/// struct Counting;
+///
+/// impl ProcessHandlers<u32> for Counting {
+///     fn task_names(&self) -> Vec<&'static str> {
+///         vec!["Count 1"]
+///     }
+///
+///     fn task(&self, name: &str, _: &Context, data: Data<u32>) -> Result<TaskResult, Error> {
+///         match name {
+///             "Count 1" => *data.lock().unwrap() += 1,
+///             _ => unreachable!(),
+///         }
+///         Ok(None)
+///     }
+/// }
+/// ```
+pub trait ProcessHandlers<T>: Sync + Send + 'static {
+    /// Task names or ids handled by `task`
+    fn task_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn task(&self, name: &str, context: &Context, data: Data<T>) -> Result<TaskResult, Error> {
+        let _ = (context, data);
+        Err(Error::MissingImplementation(name.into()))
+    }
+
+    /// Exclusive gateway names or ids handled by `exclusive`
+    fn exclusive_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn exclusive(
+        &self,
+        name: &str,
+        context: &Context,
+        data: Data<T>,
+    ) -> Result<Option<Cow<'static, str>>, Error> {
+        let _ = (context, data);
+        Err(Error::MissingImplementation(name.into()))
+    }
+
+    /// Inclusive gateway names or ids handled by `inclusive`
+    fn inclusive_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn inclusive(&self, name: &str, context: &Context, data: Data<T>) -> Result<With, Error> {
+        let _ = (context, data);
+        Err(Error::MissingImplementation(name.into()))
+    }
+
+    /// Event based gateway names or ids handled by `event_based`
+    fn event_based_names(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn event_based(
+        &self,
+        name: &str,
+        context: &Context,
+        data: Data<T>,
+    ) -> Result<IntermediateEvent, Error> {
+        let _ = (context, data);
+        Err(Error::MissingImplementation(name.into()))
+    }
+}
+
+impl<T, O: Outcome> Process<T, Build<O>> {
+    /// Register the functions of a `ProcessHandlers` implementation for every element it lists.
+    /// Can be called several times with different implementations.
+    pub fn register<H: ProcessHandlers<T>>(mut self, handlers: H) -> Self {
+        let handlers = Arc::new(handlers);
+        for name in handlers.task_names() {
+            let handlers = Arc::clone(&handlers);
+            self = self.task_with_context(name, move |context, data| {
+                handlers.task(name, context, data)
+            });
+        }
+        for name in handlers.exclusive_names() {
+            let handlers = Arc::clone(&handlers);
+            self = self.exclusive_owned_with_context(name, move |context, data| {
+                handlers.exclusive(name, context, data)
+            });
+        }
+        for name in handlers.inclusive_names() {
+            let handlers = Arc::clone(&handlers);
+            self = self.inclusive_with_context(name, move |context, data| {
+                handlers.inclusive(name, context, data)
+            });
+        }
+        for name in handlers.event_based_names() {
+            let handlers = Arc::clone(&handlers);
+            self = self.event_based_with_context(name, move |context, data| {
+                handlers.event_based(name, context, data)
+            });
+        }
+        self
+    }
+}
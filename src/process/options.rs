@@ -0,0 +1,530 @@
+use crate::{
+    api::{ElementType, EngineEvent, TraceContext},
+    bpmn::{Bpmn, Symbol},
+    error::Error,
+    time::Instant,
+};
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
+    time::Duration,
+};
+
+/// Options for a single run with `Process::run_with`.
+///
+/// ```
+/// use snurr::{CancelToken, Process, RunOptions};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///
+///     let token = CancelToken::default();
+///     // Give a clone to someone that might cancel the run
+///     let result = bpmn.run_with(0, RunOptions::default().cancel_token(token.clone()))?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Default, Clone)]
+pub struct RunOptions<'a> {
+    pub(super) services: Option<&'a (dyn Any + Send + Sync)>,
+    pub(super) trace: Option<&'a TraceContext>,
+    pub(super) cancel: Option<CancelToken>,
+    pub(super) boundary_trigger: Option<BoundaryTrigger>,
+    pub(super) run_handle: Option<RunHandle>,
+    pub(super) timeout: Option<Duration>,
+    pub(super) max_visits: Option<usize>,
+    pub(super) max_steps: Option<usize>,
+    pub(super) max_tokens: Option<usize>,
+    pub(super) deterministic: bool,
+    pub(super) shuffle: Option<u64>,
+    pub(super) process: Option<&'a str>,
+    pub(super) start_event: Option<&'a str>,
+    pub(super) event_sink: Option<Sender<EngineEvent>>,
+    pub(super) correlation_id: Option<String>,
+    pub(super) metadata: BTreeMap<String, String>,
+    #[cfg(feature = "variables")]
+    pub(super) variables: Option<crate::Variables>,
+    #[cfg(feature = "parallel")]
+    pub(super) thread_pool: Option<&'a rayon::ThreadPool>,
+    #[cfg(feature = "parallel")]
+    pub(super) max_concurrency: Option<usize>,
+}
+
+impl<'a> RunOptions<'a> {
+    /// Immutable services value (database pools, clients, configuration etc.) available to every function
+    /// registered with a `Context` through `Context::services`.
+    ///
+    /// ```
+    /// use snurr::{Process, RunOptions};
+    ///
+    /// struct Services {
+    ///     increment: u32,
+    /// }
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+    ///         .task_with_context("Count 1", |ctx, input| {
+    ///             let services = ctx.services::<Services>().expect("services");
+    ///             *input.lock().unwrap() += services.increment;
+    ///             Ok(None)
+    ///         })
+    ///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+    ///             3 => Ok(Some("YES")),
+    ///             _ => Ok(Some("NO")),
+    ///         })
+    ///         .build()?;
+    ///
+    ///     let services = Services { increment: 1 };
+    ///     let result = bpmn.run_with(0, RunOptions::default().services(&services))?;
+    ///     assert_eq!(result.data, 3);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn services<S: Any + Send + Sync>(mut self, services: &'a S) -> Self {
+        self.services = Some(services);
+        self
+    }
+
+    /// Trace context propagated to every token. The trace id and baggage is available to every function
+    /// registered with a `Context` through `Context::trace`, together with the token that invoked it.
+    /// With the `tracing` feature, every token runs in a span that is a child of the run span, also when
+    /// the token runs on another thread.
+    pub fn trace(mut self, trace: &'a TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Abort the run with `Error::Cancelled` when the token is cancelled.
+    pub fn cancel_token(mut self, token: CancelToken) -> Self {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Interrupt activities through their boundary events when the trigger is used.
+    pub fn boundary_trigger(mut self, trigger: BoundaryTrigger) -> Self {
+        self.boundary_trigger = Some(trigger);
+        self
+    }
+
+    /// Follow the status and the active elements of the run from another thread with the handle.
+    /// See `Process::spawn`.
+    pub fn run_handle(mut self, handle: RunHandle) -> Self {
+        self.run_handle = Some(handle);
+        self
+    }
+
+    /// Abort the run with `Error::Timeout` when the whole run takes longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Fail the run with `Error::VisitLimit` when a token enters the same element more than `visits` times,
+    /// like a loop in the diagram that never ends. The error has the elements visited more than once.
+    pub fn max_visits(mut self, visits: usize) -> Self {
+        self.max_visits = Some(visits);
+        self
+    }
+
+    /// Fail the run with `Error::VisitLimit` when the tokens enter more than `steps` elements in total.
+    pub fn max_steps(mut self, steps: usize) -> Self {
+        self.max_steps = Some(steps);
+        self
+    }
+
+    /// Fail the run with `Error::TokenLimit` when more than `tokens` tokens are alive at the same time, like a fork
+    /// in a loop that never ends. Tokens waiting at a join and tokens in sub-processes are included. The error has
+    /// the elements where the tokens are, most tokens first.
+    pub fn max_tokens(mut self, tokens: usize) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+
+    /// Run the tokens one at a time in the order of the diagram, so every run has the same order. Useful for tests
+    /// that compare logs. Only changes the run with the `parallel` feature, the tokens always run one at a time
+    /// without it.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Run the tokens one at a time in an order shuffled with the seed, to find a diagram or function that depends
+    /// on the order of parallel branches. The same seed gives the same order.
+    pub fn shuffle(mut self, seed: u64) -> Self {
+        self.shuffle = Some(seed);
+        self
+    }
+
+    /// Only run the process with the BPMN id or name instead of every process in the definitions.
+    /// See `Process::process_ids`.
+    pub fn process(mut self, id_or_name: &'a str) -> Self {
+        self.process = Some(id_or_name);
+        self
+    }
+
+    /// Initial process variables of the run. See `Context::variables`.
+    #[cfg(feature = "variables")]
+    pub fn variables(mut self, variables: crate::Variables) -> Self {
+        self.variables = Some(variables);
+        self
+    }
+
+    /// Start from the start event with the name or BPMN id, like a message, signal or timer start event.
+    /// Only the processes with the start event are run.
+    pub fn start_event(mut self, name_or_id: &'a str) -> Self {
+        self.start_event = Some(name_or_id);
+        self
+    }
+
+    /// Correlation id of the run, for example the id of the request or the business key of the instance.
+    /// Available from `Context::correlation_id`, written in front of every log line of the run and returned
+    /// in the `ProcessOutput`.
+    pub fn correlation_id(mut self, id: impl Into<String>) -> Self {
+        self.correlation_id = Some(id.into());
+        self
+    }
+
+    /// Add a metadata item to the run. Available from `Context::metadata` and returned in the `ProcessOutput`.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Send an `EngineEvent` to the channel for every token created, element entered and left, gateway decision,
+    /// boundary event triggered and process ended. The run continues if the receiver is dropped.
+    pub fn event_sink(mut self, sink: Sender<EngineEvent>) -> Self {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Run the parallel tokens on the thread pool instead of the global rayon pool.
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(mut self, pool: &'a rayon::ThreadPool) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    /// Run the parallel tokens on a new thread pool with at most `threads` threads. The pool is created for
+    /// every run, so share a pool with `thread_pool` when running often. Ignored if `thread_pool` is set.
+    #[cfg(feature = "parallel")]
+    pub fn max_concurrency(mut self, threads: usize) -> Self {
+        self.max_concurrency = Some(threads);
+        self
+    }
+}
+
+impl Debug for RunOptions<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("RunOptions");
+        debug
+            .field("services", &self.services.is_some())
+            .field("trace", &self.trace)
+            .field("cancel", &self.cancel)
+            .field("boundary_trigger", &self.boundary_trigger)
+            .field("run_handle", &self.run_handle)
+            .field("timeout", &self.timeout)
+            .field("max_visits", &self.max_visits)
+            .field("max_steps", &self.max_steps)
+            .field("max_tokens", &self.max_tokens)
+            .field("deterministic", &self.deterministic)
+            .field("shuffle", &self.shuffle)
+            .field("process", &self.process)
+            .field("start_event", &self.start_event)
+            .field("event_sink", &self.event_sink.is_some())
+            .field("correlation_id", &self.correlation_id)
+            .field("metadata", &self.metadata);
+        #[cfg(feature = "variables")]
+        debug.field("variables", &self.variables);
+        #[cfg(feature = "parallel")]
+        debug
+            .field("thread_pool", &self.thread_pool.is_some())
+            .field("max_concurrency", &self.max_concurrency);
+        debug.finish()
+    }
+}
+
+/// Token to cancel a run from another thread. Clones share the same state.
+///
+/// The engine checks the token before every element. A running function is not interrupted,
+/// but can check `Context::is_cancelled` to stop early.
+#[derive(Debug, Default, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Trigger boundary events of the activities of a run from another thread. Clones share the same state.
+///
+/// The triggered activity follows the boundary event instead of its outgoing flow. A waiting user, receive or
+/// external task stops waiting. A running function is not interrupted, but can check `Context::is_interrupted`
+/// to stop early. Its result is discarded. An activity without a token is interrupted when a token arrives.
+///
+/// ```
+/// use snurr::{BoundaryTrigger, Process, RunOptions, Symbol};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///
+///     let trigger = BoundaryTrigger::default();
+///     // Give a clone to someone that might interrupt the task
+///     trigger.trigger_boundary("Count 1", Symbol::Message, Some("Stop"));
+///     let result = bpmn.run_with(0, RunOptions::default().boundary_trigger(trigger.clone()));
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct BoundaryTrigger(Arc<Mutex<Vec<Trigger>>>);
+
+#[derive(Debug)]
+struct Trigger {
+    activity: String,
+    symbol: Symbol,
+    name: Option<String>,
+}
+
+impl Trigger {
+    fn matches(&self, id: &str, name: Option<&str>) -> bool {
+        self.activity == id || Some(self.activity.as_str()) == name
+    }
+}
+
+impl BoundaryTrigger {
+    /// Trigger the boundary event with the symbol and name of the activity with the name or BPMN id
+    pub fn trigger_boundary(
+        &self,
+        activity: impl Into<String>,
+        symbol: Symbol,
+        name: Option<&str>,
+    ) {
+        if let Ok(mut triggers) = self.0.lock() {
+            triggers.push(Trigger {
+                activity: activity.into(),
+                symbol,
+                name: name.map(Into::into),
+            });
+        }
+    }
+
+    pub(crate) fn is_triggered(&self, id: &str, name: Option<&str>) -> bool {
+        self.0
+            .lock()
+            .is_ok_and(|triggers| triggers.iter().any(|trigger| trigger.matches(id, name)))
+    }
+
+    // Remove the first trigger of the activity
+    pub(crate) fn take(&self, id: &str, name: Option<&str>) -> Option<(Symbol, Option<String>)> {
+        let mut triggers = self.0.lock().ok()?;
+        let index = triggers
+            .iter()
+            .position(|trigger| trigger.matches(id, name))?;
+        let trigger = triggers.remove(index);
+        Some((trigger.symbol, trigger.name))
+    }
+}
+
+/// Status of a run followed with a `RunHandle`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    #[default]
+    NotStarted,
+    Running,
+    Completed,
+    /// Cancelled with the `CancelToken` of the run
+    Cancelled,
+    Failed,
+}
+
+/// Element where a token of a run is
+#[derive(Debug, Clone)]
+pub struct ActiveElement {
+    pub token: usize,
+    /// The BPMN ID of the element
+    pub id: String,
+    /// The name of the element (if it has one)
+    pub name: Option<String>,
+    pub element_type: ElementType,
+    /// Time since the token entered the element
+    pub elapsed: Duration,
+}
+
+/// Follow a long running run from another thread. Clones share the same state.
+///
+/// Give the handle to the run with `RunOptions::run_handle`, or start the run on a new thread with
+/// `Process::spawn` that returns the handle.
+///
+/// ```
+/// use snurr::{Process, RunHandle, RunOptions, RunStatus};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///
+///     let handle = RunHandle::default();
+///     // Give a clone to someone that inspects the run
+///     bpmn.run_with(0, RunOptions::default().run_handle(handle.clone()))?;
+///     assert_eq!(handle.status(), RunStatus::Completed);
+///     assert!(handle.active_elements().is_empty());
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct RunHandle(Arc<Mutex<Progress>>);
+
+#[derive(Debug, Default)]
+struct Progress {
+    status: RunStatus,
+    // Token to the element it is at and when it entered
+    active: BTreeMap<usize, (ActiveElement, Instant)>,
+}
+
+impl RunHandle {
+    pub fn status(&self) -> RunStatus {
+        self.0
+            .lock()
+            .map(|progress| progress.status)
+            .unwrap_or_default()
+    }
+
+    /// Elements where the tokens of the run are, ordered by token. A token at a sub-process or call activity
+    /// is listed together with the tokens inside it.
+    pub fn active_elements(&self) -> Vec<ActiveElement> {
+        self.0
+            .lock()
+            .map(|progress| {
+                progress
+                    .active
+                    .values()
+                    .map(|(element, entered)| ActiveElement {
+                        elapsed: entered.elapsed(),
+                        ..element.clone()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn started(&self) {
+        if let Ok(mut progress) = self.0.lock() {
+            progress.status = RunStatus::Running;
+            progress.active.clear();
+        }
+    }
+
+    pub(crate) fn entered(&self, token: usize, bpmn: &Bpmn) {
+        let (Some(id), Some(element_type)) = (bpmn.id(), bpmn.element_type()) else {
+            return;
+        };
+        if let Ok(mut progress) = self.0.lock() {
+            progress.active.insert(
+                token,
+                (
+                    ActiveElement {
+                        token,
+                        id: id.into(),
+                        name: bpmn.name().map(Into::into),
+                        element_type,
+                        elapsed: Duration::ZERO,
+                    },
+                    Instant::now(),
+                ),
+            );
+        }
+    }
+
+    pub(crate) fn left(&self, token: usize) {
+        if let Ok(mut progress) = self.0.lock() {
+            progress.active.remove(&token);
+        }
+    }
+
+    pub(crate) fn finished<R>(&self, result: &Result<R, Error>) {
+        if let Ok(mut progress) = self.0.lock() {
+            progress.status = match result {
+                Ok(_) => RunStatus::Completed,
+                Err(Error::Cancelled(_)) => RunStatus::Cancelled,
+                Err(_) => RunStatus::Failed,
+            };
+            progress.active.clear();
+        }
+    }
+}
+
+/// Options for reading a diagram with `Process::new_with_options`.
+///
+/// By default unsupported elements and markers are skipped with a `ParseWarning` and conditional sequence flows
+/// return `Error::NotSupported`, the same as `Process::new`.
+///
+/// ```
+/// use snurr::{ParseOptions, Process};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<u32>::new_with_options("examples/example.bpmn", ParseOptions::default().strict())?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub(crate) mode: ParseMode,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ParseMode {
+    #[default]
+    Default,
+    Strict,
+    Lenient,
+}
+
+impl ParseOptions {
+    /// Return `Error::NotSupported` for every unsupported element, marker and conditional sequence flow.
+    pub fn strict(mut self) -> Self {
+        self.mode = ParseMode::Strict;
+        self
+    }
+
+    /// Skip every unsupported element and marker with a `ParseWarning`. The condition of a conditional
+    /// sequence flow is skipped too and the flow is used like any other flow.
+    pub fn lenient(mut self) -> Self {
+        self.mode = ParseMode::Lenient;
+        self
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{
+    bpmn::{Activity, Symbol},
+    error::Error,
+};
+use opentelemetry::{
+    Context, ContextGuard, KeyValue,
+    global::{self, BoxedTracer},
+    metrics::Counter,
+    trace::{Span, Status, TraceContextExt, Tracer},
+};
+use std::collections::BTreeMap;
+
+const NAME: &str = "snurr";
+
+// Tracer and counters of a run, from the global providers installed by the application
+pub(super) struct Telemetry {
+    tracer: BoxedTracer,
+    decisions: Counter<u64>,
+    boundaries: Counter<u64>,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        let meter = global::meter(NAME);
+        Self {
+            tracer: global::tracer(NAME),
+            decisions: meter
+                .u64_counter("snurr.gateway.decisions")
+                .with_description("Sequence flows selected by gateways")
+                .build(),
+            boundaries: meter
+                .u64_counter("snurr.boundary.triggers")
+                .with_description("Boundary events a token continued with")
+                .build(),
+        }
+    }
+}
+
+// Span that is the current context of the thread until it ends. Spans started meanwhile are its children.
+pub(super) struct ActiveSpan {
+    context: Context,
+    _guard: ContextGuard,
+}
+
+impl ActiveSpan {
+    fn new(span: impl Span + Send + Sync + 'static) -> Self {
+        let context = Context::current_with_span(span);
+        Self {
+            _guard: context.clone().attach(),
+            context,
+        }
+    }
+
+    pub(super) fn end<R>(self, result: &Result<R, Error>) {
+        let span = self.context.span();
+        if let Err(error) = result {
+            span.set_status(Status::error(error.to_string()));
+        }
+        span.end();
+    }
+}
+
+impl Telemetry {
+    pub(super) fn process(
+        &self,
+        id: &str,
+        name: Option<&str>,
+        correlation_id: Option<&str>,
+        metadata: &BTreeMap<String, String>,
+    ) -> ActiveSpan {
+        let mut attributes = vec![KeyValue::new("bpmn.process.id", id.to_string())];
+        if let Some(name) = name {
+            attributes.push(KeyValue::new("bpmn.process.name", name.to_string()));
+        }
+        if let Some(correlation_id) = correlation_id {
+            attributes.push(KeyValue::new(
+                "snurr.correlation_id",
+                correlation_id.to_string(),
+            ));
+        }
+        attributes.extend(
+            metadata.iter().map(|(key, value)| {
+                KeyValue::new(format!("snurr.metadata.{key}"), value.to_string())
+            }),
+        );
+        ActiveSpan::new(
+            self.tracer
+                .span_builder(format!("process {}", name.unwrap_or(id)))
+                .with_attributes(attributes)
+                .start(&self.tracer),
+        )
+    }
+
+    pub(super) fn activity(&self, activity: &Activity, token: usize) -> ActiveSpan {
+        let id = activity.id.bpmn();
+        let mut attributes = vec![
+            KeyValue::new("bpmn.element.id", id.to_string()),
+            KeyValue::new("bpmn.element.type", activity.activity_type.to_string()),
+            KeyValue::new("snurr.token", token as i64),
+        ];
+        if let Some(name) = activity.name.as_deref() {
+            attributes.push(KeyValue::new("bpmn.element.name", name.to_string()));
+        }
+        ActiveSpan::new(
+            self.tracer
+                .span_builder(activity.name.as_deref().unwrap_or(id).to_string())
+                .with_attributes(attributes)
+                .start(&self.tracer),
+        )
+    }
+
+    pub(super) fn decision(&self, gateway: &str, flow: &str) {
+        self.decisions.add(
+            1,
+            &[
+                KeyValue::new("bpmn.element.id", gateway.to_string()),
+                KeyValue::new("bpmn.flow.id", flow.to_string()),
+            ],
+        );
+    }
+
+    pub(super) fn boundary(&self, id: &str, attached_to: &str, symbol: Option<&Symbol>) {
+        let mut attributes = vec![
+            KeyValue::new("bpmn.element.id", id.to_string()),
+            KeyValue::new("bpmn.attached_to.id", attached_to.to_string()),
+        ];
+        if let Some(symbol) = symbol {
+            attributes.push(KeyValue::new("bpmn.symbol", symbol.to_string()));
+        }
+        self.boundaries.add(1, &attributes);
+    }
+}
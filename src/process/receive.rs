@@ -0,0 +1,28 @@
+use std::{
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// Channel receiver a receive task or message catch event waits on. Register it with `Process::receive`.
+///
+/// Implemented for `std::sync::mpsc::Receiver` and for closures, so other channels like crossbeam or tokio
+/// receivers can be given as a closure that waits at most the timeout for the next message.
+pub trait MessageReceiver<M>: Send {
+    /// Wait at most `timeout` for the next message
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<M, RecvTimeoutError>;
+}
+
+impl<M: Send> MessageReceiver<M> for Receiver<M> {
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<M, RecvTimeoutError> {
+        Receiver::recv_timeout(self, timeout)
+    }
+}
+
+impl<M, F> MessageReceiver<M> for F
+where
+    F: FnMut(Duration) -> Result<M, RecvTimeoutError> + Send,
+{
+    fn recv_timeout(&mut self, timeout: Duration) -> Result<M, RecvTimeoutError> {
+        self(timeout)
+    }
+}
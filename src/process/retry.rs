@@ -0,0 +1,124 @@
+use crate::{Context, Error};
+use log::warn;
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+type RetryOn = Arc<dyn Fn(&Error) -> bool + Sync + Send>;
+
+/// Delay before the next attempt of a retried task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Same delay before every attempt
+    Fixed(Duration),
+    /// Delay starts at `initial` and doubles before every attempt, but never exceeds `max`
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::Fixed(Duration::ZERO)
+    }
+}
+
+impl Backoff {
+    // Delay after the failed attempt, starting from 1
+    fn delay(&self, attempt: u32) -> Duration {
+        match self {
+            Backoff::Fixed(delay) => *delay,
+            Backoff::Exponential { initial, max } => initial
+                .saturating_mul(2u32.saturating_pow(attempt - 1))
+                .min(*max),
+        }
+    }
+}
+
+/// Retry policy for a task. Register it with `Process::retry`.
+///
+/// By default a task is retried without delay when it returns `Error::ProcessExecution`.
+///
+/// ```
+/// use snurr::{Backoff, Error, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new(3)
+///     .backoff(Backoff::Exponential {
+///         initial: Duration::from_millis(100),
+///         max: Duration::from_secs(2),
+///     })
+///     .retry_on(|error| matches!(error, Error::ProcessExecution(_)));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    retry_on: RetryOn,
+}
+
+impl RetryPolicy {
+    /// Run the task at most `max_attempts` times, including the first attempt.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Default::default(),
+            retry_on: Arc::new(|error| matches!(error, Error::ProcessExecution(_))),
+        }
+    }
+
+    /// Delay between the attempts
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Only retry the errors where the predicate returns true
+    pub fn retry_on<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Error) -> bool + 'static + Sync + Send,
+    {
+        self.retry_on = Arc::new(predicate);
+        self
+    }
+}
+
+impl Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish()
+    }
+}
+
+// Run the function and retry it according to the policy. No more attempts are made when the run is cancelled,
+// also not while waiting for the next attempt.
+pub(super) fn run<R>(
+    policy: Option<&RetryPolicy>,
+    context: &mut Context,
+    mut func: impl FnMut(&Context) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let Some(policy) = policy else {
+        return func(context);
+    };
+
+    let mut attempt = 1;
+    loop {
+        match func(context) {
+            Err(error)
+                if attempt < policy.max_attempts
+                    && (policy.retry_on)(&error)
+                    && !context.is_cancelled() =>
+            {
+                let delay = policy.backoff.delay(attempt);
+                warn!(
+                    "{}Attempt {attempt} failed with {error}. Retry in {delay:?}",
+                    context.log_prefix
+                );
+                if !context.sleep(delay) {
+                    return Err(error);
+                }
+                attempt += 1;
+                context.attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
@@ -1,9 +1,11 @@
-use std::{collections::HashSet, io::Write, path::Path};
+use std::{collections::HashSet, io::Write, path::Path, sync::Arc};
 
 use crate::{
     Process,
+    api::Outcome,
     bpmn::{Activity, ActivityType, Bpmn, Event, Gateway, GatewayType, Symbol},
     error::Error,
+    process::{Build, handler::HandlerType},
 };
 
 impl<T> Process<T> {
@@ -80,18 +82,316 @@ impl<T> Process<T> {
         });
         scaffold.create(path)
     }
+
+    /// Generate a Rust module with `pub const` names for every task, gateway and sequence flow to the given file path.
+    /// Use the constants when registering functions and returning flows instead of typing the names.
+    /// Unnamed flows leaving a gateway get a constant with their bpmn id.
+    /// No file with same name is allowed to exist at the target location.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn: Process<()> = Process::new("examples/example.bpmn")?;
+    ///     bpmn.scaffold_names("examples/names.rs")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn scaffold_names(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let mut tasks = Identifiers::default();
+        let mut gateways = Identifiers::default();
+        let mut flows = Identifiers::default();
+        for process in self.diagram.data().iter() {
+            for bpmn in process.iter() {
+                match bpmn {
+                    Bpmn::Activity(Activity {
+                        activity_type: ActivityType::Task,
+                        id,
+                        name,
+                        ..
+                    }) => tasks.add(name.as_deref().unwrap_or(id.bpmn())),
+                    Bpmn::Gateway(Gateway {
+                        gateway_type:
+                            GatewayType::Exclusive | GatewayType::Inclusive | GatewayType::EventBased,
+                        id,
+                        name,
+                        outputs,
+                        ..
+                    }) => {
+                        gateways.add(name.as_deref().unwrap_or(id.bpmn()));
+                        for output in outputs.iter() {
+                            if let Some(Bpmn::SequenceFlow { id, name: None, .. }) =
+                                process.get(*output)
+                            {
+                                flows.add(id.bpmn());
+                            }
+                        }
+                    }
+                    Bpmn::SequenceFlow {
+                        name: Some(name), ..
+                    } => flows.add(name),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut content =
+            vec!["// Generated by snurr. Element names from the BPMN diagram.".to_string()];
+        for (module, constants) in [("tasks", tasks), ("gateways", gateways), ("flows", flows)] {
+            content.push(String::new());
+            content.push(format!("pub mod {module} {{"));
+            for (constant, value) in constants.values {
+                content.push(format!(
+                    "    pub const {}: &str = {value:?};",
+                    constant.to_uppercase()
+                ));
+            }
+            content.push("}".into());
+        }
+        content.push(String::new());
+
+        let mut file = std::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(path)?;
+
+        file.write_all(content.join("\n").as_bytes())?;
+        Ok(())
+    }
+}
+
+// Identifiers for element names. Same name only once and colliding identifiers get a suffix.
+#[derive(Debug, Default)]
+struct Identifiers<'a> {
+    seen: HashSet<&'a str>,
+    values: Vec<(String, &'a str)>,
+}
+
+impl<'a> Identifiers<'a> {
+    fn add(&mut self, value: &'a str) {
+        if self.seen.insert(value) {
+            self.unique(value);
+        }
+    }
+
+    // New identifier also for a value added before
+    fn unique(&mut self, value: &'a str) -> String {
+        let base = identifier(value);
+        let mut ident = base.clone();
+        let mut suffix = 1;
+        while self.values.iter().any(|(name, _)| *name == ident) {
+            suffix += 1;
+            ident = format!("{base}_{suffix}");
+        }
+        self.values.push((ident.clone(), value));
+        ident
+    }
+}
+
+const KEYWORDS: [&str; 38] = [
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut",
+    "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+
+// "equal to 3" -> equal_to_3
+fn identifier(value: &str) -> String {
+    let mut ident = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            ident.push(c.to_ascii_lowercase());
+        } else if !ident.is_empty() && !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+    let ident = ident.trim_end_matches('_');
+    match ident.chars().next() {
+        None => "_".into(),
+        Some(c) if c.is_ascii_digit() => format!("_{ident}"),
+        _ if KEYWORDS.contains(&ident) => format!("{ident}_"),
+        _ => ident.into(),
+    }
+}
+
+impl<T, O: Outcome> Process<T, Build<O>> {
+    /// Generate a source file with a stub function for every task and gateway without a registered function,
+    /// and a `register` function that registers them on the process. Fill in the stubs and call `register` before `build`.
+    /// No file with same name is allowed to exist at the target location.
+    /// ```
+    /// use snurr::Process;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let bpmn: Process<()> = Process::new("examples/example.bpmn")?;
+    ///     bpmn.scaffold_handlers("examples/handlers.rs")?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn scaffold_handlers(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let task_types = self.handler.task_types();
+        let mut functions = Identifiers::default();
+        // Name or id, function, element description and the kind of function
+        let mut stubs: Vec<(&str, String, String, HandlerType)> = Vec::new();
+        for process in self.diagram.data().iter() {
+            for bpmn in process.iter() {
+                let (name_or_id, comment, handler_type) = match bpmn {
+                    Bpmn::Activity(Activity {
+                        activity_type:
+                            activity_type @ (ActivityType::Task
+                            | ActivityType::ScriptTask
+                            | ActivityType::UserTask
+                            | ActivityType::ServiceTask
+                            | ActivityType::CallActivity
+                            | ActivityType::ReceiveTask
+                            | ActivityType::SendTask
+                            | ActivityType::ManualTask
+                            | ActivityType::BusinessRuleTask),
+                        id,
+                        name,
+                        task_type,
+                        ..
+                    }) => {
+                        let name_or_id = match task_type {
+                            Some(task_type) if task_types => task_type,
+                            _ => name.as_deref().unwrap_or(id.bpmn()),
+                        };
+                        let symbols: Vec<_> = process
+                            .activity_boundaries(id)
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|index| match process.get(*index) {
+                                Some(Bpmn::Event(Event {
+                                    symbol: Some(symbol),
+                                    ..
+                                })) => Some(symbol.to_string()),
+                                _ => None,
+                            })
+                            .collect();
+                        let comment = if symbols.is_empty() {
+                            format!("{activity_type}")
+                        } else {
+                            format!("{activity_type}. Boundary symbols: {}.", symbols.join(", "))
+                        };
+                        (name_or_id, comment, HandlerType::Task)
+                    }
+                    Bpmn::Gateway(Gateway {
+                        gateway_type:
+                            gateway_type @ (GatewayType::Exclusive
+                            | GatewayType::Inclusive
+                            | GatewayType::EventBased),
+                        id,
+                        name,
+                        outputs,
+                        ..
+                    }) if outputs.len() > 1 => {
+                        let flows: Vec<_> = outputs
+                            .iter()
+                            .filter_map(|index| match process.get(*index) {
+                                Some(Bpmn::SequenceFlow { id, name, .. }) => {
+                                    Some(name.as_deref().unwrap_or(id.bpmn()))
+                                }
+                                _ => None,
+                            })
+                            .collect();
+                        let handler_type = match gateway_type {
+                            GatewayType::Exclusive => HandlerType::Exclusive,
+                            GatewayType::Inclusive => HandlerType::Inclusive,
+                            _ => HandlerType::EventBased,
+                        };
+                        (
+                            name.as_deref().unwrap_or(id.bpmn()),
+                            format!("{gateway_type} gateway. Flows: {}.", flows.join(", ")),
+                            handler_type,
+                        )
+                    }
+                    _ => continue,
+                };
+                if self.handler.is_registered(handler_type, name_or_id)
+                    || stubs
+                        .iter()
+                        .any(|(name, _, _, ty)| *name == name_or_id && *ty == handler_type)
+                {
+                    continue;
+                }
+                stubs.push((
+                    name_or_id,
+                    functions.unique(name_or_id),
+                    comment,
+                    handler_type,
+                ));
+            }
+        }
+
+        let mut imports = vec!["Data", "Error", "Process"];
+        for (handler_type, import) in [
+            (HandlerType::EventBased, "IntermediateEvent"),
+            (HandlerType::Task, "TaskResult"),
+            (HandlerType::Inclusive, "With"),
+        ] {
+            if stubs.iter().any(|(_, _, _, ty)| *ty == handler_type) {
+                imports.push(import);
+            }
+        }
+        imports.sort_unstable();
+
+        let mut content = vec![
+            "// Generated by snurr. Fill in the functions and call `register` before `build`."
+                .into(),
+            format!("use snurr::{{{}}};", imports.join(", ")),
+            "".into(),
+            "// Replace () with your type".into(),
+            "pub type MyData = ();".into(),
+            "".into(),
+            "pub fn register(process: Process<MyData>) -> Process<MyData> {".into(),
+            "    process".into(),
+        ];
+        for (name_or_id, function, _, handler_type) in stubs.iter() {
+            let method = match handler_type {
+                HandlerType::Task => "task",
+                HandlerType::Exclusive => "exclusive",
+                HandlerType::Inclusive => "inclusive",
+                _ => "event_based",
+            };
+            content.push(format!("        .{method}({name_or_id:?}, {function})"));
+        }
+        content.push("}".into());
+
+        for (_, function, comment, handler_type) in stubs.iter() {
+            let output = match handler_type {
+                HandlerType::Task => "TaskResult",
+                HandlerType::Exclusive => "Option<&'static str>",
+                HandlerType::Inclusive => "With",
+                _ => "IntermediateEvent",
+            };
+            content.push("".into());
+            content.push(format!("// {comment}"));
+            content.push(format!(
+                "pub fn {function}(_input: Data<MyData>) -> Result<{output}, Error> {{"
+            ));
+            content.push("    todo!()".into());
+            content.push("}".into());
+        }
+        content.push("".into());
+
+        let mut file = std::fs::OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(path)?;
+
+        file.write_all(content.join("\n").as_bytes())?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
 struct GatewayInner<'a> {
     gateway: &'a Gateway,
-    names: Vec<&'a String>,
+    names: Vec<&'a Arc<str>>,
 }
 
 #[derive(Debug)]
 struct Task<'a> {
     bpmn: &'a Bpmn,
-    symbols: Vec<(&'a Option<String>, &'a Symbol)>,
+    symbols: Vec<(&'a Option<Arc<str>>, &'a Symbol)>,
 }
 
 #[derive(Debug, Default)]
@@ -101,11 +401,11 @@ struct Scaffold<'a> {
 }
 
 impl<'a> Scaffold<'a> {
-    fn add_task(&mut self, bpmn: &'a Bpmn, symbols: Vec<(&'a Option<String>, &'a Symbol)>) {
+    fn add_task(&mut self, bpmn: &'a Bpmn, symbols: Vec<(&'a Option<Arc<str>>, &'a Symbol)>) {
         self.tasks.push(Task { bpmn, symbols });
     }
 
-    fn add_gateway(&mut self, gateway: &'a Gateway, names: Vec<&'a String>) {
+    fn add_gateway(&mut self, gateway: &'a Gateway, names: Vec<&'a Arc<str>>) {
         self.gateways.push(GatewayInner { gateway, names });
     }
 
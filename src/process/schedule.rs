@@ -0,0 +1,194 @@
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use crate::time;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Duration;
+use std::{
+    fmt::Display,
+    sync::{Condvar, Mutex, PoisonError},
+};
+
+/// Scheduling hint for a task. Only used with the `parallel` feature.
+///
+/// CPU bound tasks run on the rayon pool together with the engine. IO bound tasks run on a separate pool of
+/// blocking threads while the rayon thread keeps executing other tokens, so waiting on IO doesn't starve the compute.
+/// Size the pool with `Process::io_threads`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Workload {
+    #[default]
+    Cpu,
+    Io,
+}
+
+impl Display for Workload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Workload::Cpu => "cpu",
+            Workload::Io => "io",
+        })
+    }
+}
+
+#[cfg(feature = "parallel")]
+const DEFAULT_IO_THREADS: usize = 16;
+
+// Threads for the IO tasks. Started by the first IO task and shared by every run of the process.
+// If the threads can't be started, the IO tasks run on the thread of the engine.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "parallel"), derive(Default))]
+#[cfg_attr(all(target_arch = "wasm32", target_os = "unknown"), allow(dead_code))]
+pub(super) struct IoPool {
+    #[cfg(feature = "parallel")]
+    threads: usize,
+    #[cfg(feature = "parallel")]
+    pool: std::sync::OnceLock<Option<rayon::ThreadPool>>,
+}
+
+#[cfg(feature = "parallel")]
+impl Default for IoPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_IO_THREADS)
+    }
+}
+
+impl IoPool {
+    #[cfg(feature = "parallel")]
+    pub(super) fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            pool: Default::default(),
+        }
+    }
+
+    // Run the function according to the workload.
+    #[cfg(all(
+        feature = "parallel",
+        not(all(target_arch = "wasm32", target_os = "unknown"))
+    ))]
+    pub(super) fn run<R: Send>(&self, workload: Workload, func: impl FnOnce() -> R + Send) -> R {
+        if workload == Workload::Cpu {
+            return func();
+        }
+
+        let pool = self.pool.get_or_init(|| {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(self.threads)
+                .thread_name(|_| "snurr-io".into())
+                .build()
+                .inspect_err(|err| {
+                    log::warn!(
+                        "IO tasks run on the engine threads. Couldn't start io threads: {err}"
+                    )
+                })
+                .ok()
+        });
+        let Some(pool) = pool else {
+            return func();
+        };
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
+        // A thread of the rayon pool keeps executing other tokens while it waits for the IO thread
+        pool.install(move || {
+            #[cfg(feature = "tracing")]
+            let _enter = span.enter();
+            func()
+        })
+    }
+
+    // Without threads every workload runs on the thread of the engine
+    #[cfg(any(
+        not(feature = "parallel"),
+        all(target_arch = "wasm32", target_os = "unknown")
+    ))]
+    pub(super) fn run<R: Send>(&self, _workload: Workload, func: impl FnOnce() -> R + Send) -> R {
+        func()
+    }
+}
+
+// Semaphore for the tasks with a concurrency limit. Shared by every token and every run of the process.
+#[derive(Debug)]
+pub(super) struct Limit {
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Limit {
+    pub(super) fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    // Block until a permit is available. The permit is released when the guard is dropped.
+    pub(super) fn acquire(&self) -> Permit<'_> {
+        let mut available = self
+            .released
+            .wait_while(
+                self.available
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner),
+                |available| *available == 0,
+            )
+            .unwrap_or_else(PoisonError::into_inner);
+        *available -= 1;
+        Permit(self)
+    }
+}
+
+pub(super) struct Permit<'a>(&'a Limit);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *self
+            .0
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += 1;
+        self.0.released.notify_one();
+    }
+}
+
+// Pause a waiting token. The rayon pool gets help with other work instead if there is any,
+// so the token doesn't starve the tokens it waits for.
+#[cfg(all(
+    feature = "parallel",
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub(super) fn pause(timeout: Duration) {
+    if rayon::yield_now() != Some(rayon::Yield::Executed) {
+        time::sleep(timeout);
+    }
+}
+
+#[cfg(all(
+    not(feature = "parallel"),
+    not(all(target_arch = "wasm32", target_os = "unknown"))
+))]
+pub(super) fn pause(timeout: Duration) {
+    time::sleep(timeout);
+}
+
+// Seeded order of the tokens to stress test a diagram. SplitMix64, so a seed always gives the same order.
+#[derive(Debug)]
+pub(super) struct Shuffle(u64);
+
+impl Shuffle {
+    pub(super) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(super) fn shuffle<I>(&mut self, items: &mut [I]) {
+        for index in (1..items.len()).rev() {
+            let other = (self.next() % (index as u64 + 1)) as usize;
+            items.swap(index, other);
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{Error, Outcome, Process, ProcessOutput, Run};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+/// Built processes deployed by name and version. A long-lived service deploys a new version of a diagram
+/// while instances of the previous versions keep running.
+///
+/// ```
+/// use snurr::{Process, Registry};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let registry = Registry::<u32>::new();
+///     let bpmn = Process::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///     registry.deploy("order", 1, bpmn)?;
+///
+///     // Latest version
+///     let result = registry.start("order", None, 0)?;
+///     assert_eq!(result.data, 3);
+///     Ok(())
+/// }
+/// ```
+pub struct Registry<T, O = ()> {
+    processes: RwLock<HashMap<String, Versions<T, O>>>,
+}
+
+type Versions<T, O> = BTreeMap<u32, Arc<Process<T, Run<O>>>>;
+
+impl<T, O> Default for Registry<T, O> {
+    fn default() -> Self {
+        Self {
+            processes: Default::default(),
+        }
+    }
+}
+
+impl<T, O> Registry<T, O> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deploy a built process with the name and version. Returns `Error::AlreadyDeployed` if the version is deployed.
+    pub fn deploy(
+        &self,
+        name: impl Into<String>,
+        version: u32,
+        process: Process<T, Run<O>>,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        let mut processes = self.write();
+        if processes
+            .get(&name)
+            .is_some_and(|versions| versions.contains_key(&version))
+        {
+            return Err(Error::AlreadyDeployed(format!("{name} version {version}")));
+        }
+        processes
+            .entry(name)
+            .or_default()
+            .insert(version, Arc::new(process));
+        Ok(())
+    }
+
+    /// Remove a deployed version. Instances that are running keep the process until they complete.
+    pub fn undeploy(&self, name: &str, version: u32) -> Option<Arc<Process<T, Run<O>>>> {
+        let mut processes = self.write();
+        let versions = processes.get_mut(name)?;
+        let process = versions.remove(&version);
+        if versions.is_empty() {
+            processes.remove(name);
+        }
+        process
+    }
+
+    /// The process with the name and version, or the latest version with `None`.
+    pub fn get(&self, name: &str, version: Option<u32>) -> Option<Arc<Process<T, Run<O>>>> {
+        let processes = self.read();
+        let versions = processes.get(name)?;
+        match version {
+            Some(version) => versions.get(&version),
+            None => versions.values().next_back(),
+        }
+        .cloned()
+    }
+
+    /// Latest deployed version of the name
+    pub fn latest(&self, name: &str) -> Option<u32> {
+        self.versions(name).last().copied()
+    }
+
+    /// Deployed versions of the name in ascending order
+    pub fn versions(&self, name: &str) -> Vec<u32> {
+        self.read()
+            .get(name)
+            .map(|versions| versions.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Every deployed name and version, sorted by name and version
+    pub fn deployed(&self) -> Vec<(String, u32)> {
+        let mut deployed: Vec<(String, u32)> = self
+            .read()
+            .iter()
+            .flat_map(|(name, versions)| versions.keys().map(|version| (name.clone(), *version)))
+            .collect();
+        deployed.sort();
+        deployed
+    }
+
+    // No user function runs while the lock is held, so a poisoned lock is still consistent
+    fn read(&self) -> RwLockReadGuard<'_, HashMap<String, Versions<T, O>>> {
+        self.processes
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HashMap<String, Versions<T, O>>> {
+        self.processes
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Send, O: Outcome> Registry<T, O> {
+    /// Run the process with the name and version, or the latest version with `None`. Returns
+    /// `Error::MissingProcessData` if it is not deployed.
+    pub fn start(
+        &self,
+        name: &str,
+        version: Option<u32>,
+        data: T,
+    ) -> Result<ProcessOutput<T, O>, Error> {
+        // The lock is not held while the process runs
+        let process = self.get(name, version).ok_or_else(|| {
+            Error::MissingProcessData(match version {
+                Some(version) => format!("{name} version {version}"),
+                None => name.into(),
+            })
+        })?;
+        process.run(data)
+    }
+}
@@ -0,0 +1,239 @@
+use crate::{
+    Error, Outcome, Process, ProcessOutput, Run,
+    error::{INSTANCE_PANICKED_ERROR_MSG, RUNNER_STOPPED_ERROR_MSG},
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError},
+    },
+    thread::{self, JoinHandle},
+};
+
+/// Run many independent instances of a process on a bounded pool of threads. Create it with `Process::runner`.
+///
+/// Instances are queued with `submit` and get an id in submission order. `submit` blocks while the queue is
+/// full, and `try_submit` gives the data back instead. Completed instances are collected in completion order
+/// with `recv`, `try_recv` or the iterator returned by `finish`, or sent to a channel given to
+/// `Process::runner_with_results`. The runner can be shared by threads that submit instances.
+///
+/// ```
+/// use snurr::Process;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let runner = Process::<u32>::new("examples/example.bpmn")?
+///         .task("Count 1", |input| {
+///             *input.lock().unwrap() += 1;
+///             Ok(None)
+///         })
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?
+///         .runner(4, 16)?;
+///
+///     for _ in 0..100 {
+///         runner.submit(0)?;
+///     }
+///     for output in runner.finish() {
+///         assert_eq!(output.result?.data, 3);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct Runner<T, O = ()> {
+    queue: Option<SyncSender<(u64, T)>>,
+    // None when the instances are sent to the channel of the caller
+    results: Option<Mutex<Receiver<InstanceOutput<T, O>>>>,
+    handles: Vec<JoinHandle<()>>,
+    next_id: AtomicU64,
+    // Submitted instances that are not collected yet, or not completed with the channel of the caller
+    pending: Arc<AtomicUsize>,
+}
+
+/// Result of an instance run by a `Runner`
+#[derive(Debug)]
+pub struct InstanceOutput<T, O = ()> {
+    /// Id given by `submit`
+    pub id: u64,
+    pub result: Result<ProcessOutput<T, O>, Error>,
+}
+
+impl<T: Send + 'static, O: Outcome + Send + 'static> Process<T, Run<O>> {
+    /// Run instances of the process on `workers` threads. At most `capacity` submitted instances wait for
+    /// a thread before `submit` blocks. See `Runner`.
+    /// Returns `Error::Io` if a thread could not be spawned.
+    pub fn runner(self, workers: usize, capacity: usize) -> Result<Runner<T, O>, Error> {
+        let (sender, results) = mpsc::channel();
+        let mut runner = self.start_runner(workers, capacity, sender, false)?;
+        runner.results = Some(Mutex::new(results));
+        Ok(runner)
+    }
+
+    /// Like `runner`, but every completed instance is sent to `results`, for example to select over several
+    /// channels or to forward the results to an async task. `recv`, `try_recv` and `finish` return nothing.
+    pub fn runner_with_results(
+        self,
+        workers: usize,
+        capacity: usize,
+        results: Sender<InstanceOutput<T, O>>,
+    ) -> Result<Runner<T, O>, Error> {
+        self.start_runner(workers, capacity, results, true)
+    }
+
+    // Completed instances are counted by the threads if nobody collects them from the runner
+    fn start_runner(
+        self,
+        workers: usize,
+        capacity: usize,
+        sender: Sender<InstanceOutput<T, O>>,
+        count_completed: bool,
+    ) -> Result<Runner<T, O>, Error> {
+        let process = Arc::new(self);
+        let (queue, receiver) = mpsc::sync_channel(capacity);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let pending: Arc<AtomicUsize> = Default::default();
+        let handles = (0..workers.max(1))
+            .map(|index| {
+                let process = Arc::clone(&process);
+                let receiver = Arc::clone(&receiver);
+                let sender = sender.clone();
+                let completed = count_completed.then(|| Arc::clone(&pending));
+                thread::Builder::new()
+                    .name(format!("snurr-runner-{index}"))
+                    .spawn(move || {
+                        run_instances(&process, &receiver, &sender, completed.as_deref())
+                    })
+            })
+            // Threads that were spawned stop when the queue is dropped
+            .collect::<Result<_, _>>()?;
+
+        Ok(Runner {
+            queue: Some(queue),
+            results: None,
+            handles,
+            next_id: Default::default(),
+            pending,
+        })
+    }
+}
+
+impl<T, O> Runner<T, O> {
+    /// Queue an instance with the data and return its id. Blocks while the queue is full.
+    pub fn submit(&self, data: T) -> Result<u64, Error> {
+        let queue = self
+            .queue
+            .as_ref()
+            .ok_or_else(|| Error::ProcessExecution(RUNNER_STOPPED_ERROR_MSG.into()))?;
+        // Counted before it is queued, so a result is never collected before its instance is pending
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        queue.send((id, data)).map_err(|_| {
+            self.pending.fetch_sub(1, Ordering::SeqCst);
+            Error::ProcessExecution(RUNNER_STOPPED_ERROR_MSG.into())
+        })?;
+        Ok(id)
+    }
+
+    /// Queue an instance with the data and return its id, or give the data back if the queue is full.
+    /// The id of an instance that is given back is not used.
+    pub fn try_submit(&self, data: T) -> Result<u64, T> {
+        let Some(queue) = &self.queue else {
+            return Err(data);
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        match queue.try_send((id, data)) {
+            Ok(()) => Ok(id),
+            Err(TrySendError::Full((_, data)) | TrySendError::Disconnected((_, data))) => {
+                self.pending.fetch_sub(1, Ordering::SeqCst);
+                Err(data)
+            }
+        }
+    }
+
+    /// Number of submitted instances that are not collected yet. With `runner_with_results`, the number of
+    /// submitted instances that are not completed yet.
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    // Claim one pending instance to collect
+    fn claim(&self) -> bool {
+        self.pending
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+                pending.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Wait for the next completed instance. Returns `None` if every submitted instance is collected.
+    pub fn recv(&self) -> Option<InstanceOutput<T, O>> {
+        let results = self.results.as_ref()?;
+        if !self.claim() {
+            return None;
+        }
+        results.lock().ok()?.recv().ok()
+    }
+
+    /// Next completed instance without waiting
+    pub fn try_recv(&self) -> Option<InstanceOutput<T, O>> {
+        let results = self.results.as_ref()?;
+        if !self.claim() {
+            return None;
+        }
+        match results.lock().ok()?.try_recv() {
+            Ok(output) => Some(output),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => {
+                self.pending.fetch_add(1, Ordering::SeqCst);
+                None
+            }
+        }
+    }
+
+    /// Stop accepting instances and collect the remaining instances as they complete
+    pub fn finish(mut self) -> impl Iterator<Item = InstanceOutput<T, O>> {
+        self.queue.take();
+        std::iter::from_fn(move || self.recv())
+    }
+}
+
+impl<T, O> Drop for Runner<T, O> {
+    fn drop(&mut self) {
+        // Close the queue. Queued instances still run before the threads stop.
+        self.queue.take();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_instances<T: Send, O: Outcome>(
+    process: &Process<T, Run<O>>,
+    receiver: &Mutex<Receiver<(u64, T)>>,
+    sender: &Sender<InstanceOutput<T, O>>,
+    completed: Option<&AtomicUsize>,
+) {
+    loop {
+        let instance = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        // Queue is closed
+        let Ok((id, data)) = instance else {
+            return;
+        };
+
+        // Keep the thread alive if a function panics
+        let result = panic::catch_unwind(AssertUnwindSafe(|| process.run(data)))
+            .unwrap_or_else(|_| Err(Error::ProcessExecution(INSTANCE_PANICKED_ERROR_MSG.into())));
+        if let Some(completed) = completed {
+            completed.fetch_sub(1, Ordering::SeqCst);
+        }
+        let _ = sender.send(InstanceOutput { id, result });
+    }
+}
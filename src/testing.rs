@@ -0,0 +1,131 @@
+//! Test a diagram without implementing every function.
+//!
+//! `ProcessTest` registers a stub for every task and gateway without a function, forces the outcome of
+//! gateways and asserts the path taken by the run.
+//!
+//! ```
+//! use snurr::{Process, testing::ProcessTest};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     ProcessTest::new(Process::<u32>::new("examples/example.bpmn")?)
+//!         .force_exclusive("equal to 3", "YES")
+//!         .expect_visits(["Begin process", "count", "Count 1", "control", "equal to 3", "YES", "End process"])
+//!         .run(0)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    Build, Error, ExecutionEventKind, IntermediateEvent, Outcome, Process, ProcessOutput, With,
+};
+use std::sync::{Arc, Mutex};
+
+/// Run a process in a test with stubs for missing functions, forced gateway outcomes and path assertions.
+///
+/// Tasks without a registered function do nothing. Gateways without a registered function take the
+/// default flow, or the first outgoing flow. Event based gateways take the event of the first outgoing flow.
+pub struct ProcessTest<T, O = ()> {
+    process: Process<T, Build<O>>,
+    visits: Option<Vec<String>>,
+    end: Option<String>,
+}
+
+impl<T, O: Outcome> ProcessTest<T, O> {
+    /// Test the process. Functions already registered on the process are used.
+    pub fn new(process: Process<T, Build<O>>) -> Self {
+        Self {
+            process,
+            visits: None,
+            end: None,
+        }
+    }
+
+    /// The exclusive gateway with the name or id always takes the sequence flow with the name or id.
+    /// Replaces a registered function.
+    pub fn force_exclusive(mut self, gateway: impl Into<String>, flow: impl Into<String>) -> Self {
+        let flow = flow.into();
+        self.process = self
+            .process
+            .exclusive_owned(gateway, move |_| Ok(Some(flow.clone())));
+        self
+    }
+
+    /// The inclusive gateway with the name or id always takes the sequence flows.
+    /// Replaces a registered function.
+    pub fn force_inclusive(mut self, gateway: impl Into<String>, flows: impl Into<With>) -> Self {
+        let flows = flows.into();
+        self.process = self.process.inclusive(gateway, move |_| Ok(flows.clone()));
+        self
+    }
+
+    /// The event based gateway with the name or id always continues with the intermediate event.
+    /// Replaces a registered function.
+    pub fn force_event_based(
+        mut self,
+        gateway: impl Into<String>,
+        event: impl Into<IntermediateEvent>,
+    ) -> Self {
+        let event = event.into();
+        self.process = self
+            .process
+            .event_based(gateway, move |_| Ok(event.clone()));
+        self
+    }
+
+    /// Assert the names of the elements and sequence flows entered by the run, in order.
+    /// Elements and sequence flows without a name are left out.
+    pub fn expect_visits<I, S>(mut self, visits: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.visits = Some(visits.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Assert the name or id of the end event where the run completed.
+    pub fn expect_end(mut self, end: impl Into<String>) -> Self {
+        self.end = Some(end.into());
+        self
+    }
+
+    /// Build and run the process. Panics if the run does not match the expected visits or end event.
+    pub fn run(self, data: T) -> Result<ProcessOutput<T, O>, Error>
+    where
+        T: Send,
+    {
+        let visited = Arc::new(Mutex::new(Vec::new()));
+        let process = {
+            let visited = Arc::clone(&visited);
+            self.process
+                .stub_unregistered()
+                .listener(move |event| {
+                    if event.kind == ExecutionEventKind::Enter
+                        && let Some(name) = event.name
+                        && let Ok(mut visited) = visited.lock()
+                    {
+                        visited.push(name.to_string());
+                    }
+                })
+                .build()?
+        };
+
+        let output = process.run(data)?;
+        if let Some(expected) = self.visits {
+            let visited = visited
+                .lock()
+                .map(|visited| visited.clone())
+                .unwrap_or_default();
+            assert_eq!(visited, expected, "visited elements differ");
+        }
+        if let Some(expected) = self.end {
+            let end = &output.end_node;
+            assert!(
+                end.name.as_deref() == Some(expected.as_str()) || end.id == expected,
+                "expected end event {expected}, ended at {}",
+                end.name.as_deref().unwrap_or(&end.id)
+            );
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    Assignment, Build, Data, Error, Form, Outcome, Process, TaskResult, error::DATA_LOCK_ERROR_MSG,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        mpsc::{self, Sender},
+    },
+};
+
+/// Open user tasks waiting to be completed by a person. The token of a user task registered with
+/// `Process::user_task` waits until the task is completed with `complete`. The tasks can be cloned and
+/// shared between processes and the application that shows them.
+///
+/// ```
+/// use snurr::{Process, UserTasks};
+/// use std::{thread, time::Duration};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let tasks = UserTasks::new();
+///     let inbox = tasks.clone();
+///     thread::spawn(move || loop {
+///         for task in inbox.open() {
+///             inbox.claim(task.id, "alice").unwrap();
+///             inbox.complete(task.id, |count: &mut u32| *count += 1).unwrap();
+///         }
+///         thread::sleep(Duration::from_millis(1));
+///     });
+///
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .user_task("Count 1", &tasks)
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///     assert_eq!(bpmn.run(0)?.data, 3);
+///     Ok(())
+/// }
+/// ```
+pub struct UserTasks<T> {
+    inner: Arc<Mutex<Open<T>>>,
+}
+
+struct Open<T> {
+    next_id: u64,
+    tasks: BTreeMap<u64, OpenTask<T>>,
+}
+
+struct OpenTask<T> {
+    task: UserTask,
+    data: Data<T>,
+    reply: Sender<Result<TaskResult, Error>>,
+}
+
+/// User task waiting to be completed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserTask {
+    /// Id used to claim and complete the task
+    pub id: u64,
+    /// BPMN id of the task
+    pub element_id: String,
+    /// Name of the task
+    pub name: Option<String>,
+    /// Assignee and candidates from the diagram
+    pub assignment: Option<Assignment>,
+    /// Form to render for the task
+    pub form: Option<Form>,
+    /// User that claimed the task
+    pub claimed_by: Option<String>,
+    /// Correlation id of the run
+    pub correlation_id: Option<String>,
+}
+
+impl<T> Clone for UserTasks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Default for UserTasks<T> {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Open {
+                next_id: 1,
+                tasks: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl<T> UserTasks<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every open task in the order they were opened
+    pub fn open(&self) -> Vec<UserTask> {
+        self.lock()
+            .tasks
+            .values()
+            .map(|open| open.task.clone())
+            .collect()
+    }
+
+    /// Open tasks the user can work on. The task is claimed by the user, or not claimed and the user is the
+    /// assignee, a candidate user or in a candidate group. Tasks without an assignment are open to everyone.
+    pub fn for_user(&self, user: &str, groups: &[&str]) -> Vec<UserTask> {
+        self.lock()
+            .tasks
+            .values()
+            .map(|open| &open.task)
+            .filter(|task| match &task.claimed_by {
+                Some(claimed_by) => claimed_by == user,
+                None => task
+                    .assignment
+                    .as_ref()
+                    .is_none_or(|assignment| assignment.is_candidate(user, groups)),
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, task_id: u64) -> Option<UserTask> {
+        self.lock()
+            .tasks
+            .get(&task_id)
+            .map(|open| open.task.clone())
+    }
+
+    /// Claim the task for the user. Returns `Error::AlreadyClaimed` if another user claimed it.
+    pub fn claim(&self, task_id: u64, user: &str) -> Result<(), Error> {
+        let mut open = self.lock();
+        let task = &mut open
+            .tasks
+            .get_mut(&task_id)
+            .ok_or(Error::UnknownUserTask(task_id))?
+            .task;
+        match &task.claimed_by {
+            Some(claimed_by) if claimed_by != user => {
+                Err(Error::AlreadyClaimed(task_id, claimed_by.clone()))
+            }
+            _ => {
+                task.claimed_by = Some(user.into());
+                Ok(())
+            }
+        }
+    }
+
+    /// Release the claim so another user can claim the task
+    pub fn unclaim(&self, task_id: u64) -> Result<(), Error> {
+        self.lock()
+            .tasks
+            .get_mut(&task_id)
+            .ok_or(Error::UnknownUserTask(task_id))?
+            .task
+            .claimed_by = None;
+        Ok(())
+    }
+
+    /// Complete the task. The process data is updated with `updater` and the token continues.
+    pub fn complete(&self, task_id: u64, updater: impl FnOnce(&mut T)) -> Result<(), Error> {
+        let OpenTask { data, reply, .. } = self
+            .lock()
+            .tasks
+            .remove(&task_id)
+            .ok_or(Error::UnknownUserTask(task_id))?;
+        let result = data
+            .lock()
+            .map(|mut data| updater(&mut data))
+            .map(|_| None)
+            .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()));
+        // The run takes the data back when it ends
+        drop(data);
+        // The token is gone if the run was cancelled meanwhile
+        let _ = reply.send(result);
+        Ok(())
+    }
+
+    /// Number of open tasks
+    pub fn len(&self) -> usize {
+        self.lock().tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // No user function runs while the lock is held, so a poisoned lock is still consistent
+    fn lock(&self) -> MutexGuard<'_, Open<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: Send + 'static, O: Outcome> Process<T, Build<O>> {
+    /// Register a user task with name or bpmn id. The token waits until the task is completed with
+    /// `UserTasks::complete`, or the run is cancelled.
+    pub fn user_task(self, name: impl Into<String>, tasks: &UserTasks<T>) -> Self {
+        let tasks = tasks.clone();
+        self.task_with_context(name, move |ctx, data| {
+            let (reply, result) = mpsc::channel();
+            let id = {
+                let mut open = tasks.lock();
+                let id = open.next_id;
+                open.next_id += 1;
+                open.tasks.insert(
+                    id,
+                    OpenTask {
+                        task: UserTask {
+                            id,
+                            element_id: ctx.id().into(),
+                            name: ctx.name().map(Into::into),
+                            assignment: ctx.assignment().cloned(),
+                            form: ctx.form().cloned(),
+                            claimed_by: None,
+                            correlation_id: ctx.correlation_id().map(Into::into),
+                        },
+                        data,
+                        reply,
+                    },
+                );
+                id
+            };
+            let result = ctx.wait_for(&result);
+            // Still open if the run stopped first
+            tasks.lock().tasks.remove(&id);
+            result
+        })
+    }
+}
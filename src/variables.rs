@@ -0,0 +1,143 @@
+use crate::diagram::Source;
+use serde_json::Value;
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{PoisonError, RwLock},
+};
+
+/// Loosely typed process variables carried with a run next to the data. Functions registered with a `Context`
+/// read and write them with `Context::variables`. Give initial variables with `RunOptions::variables` and read
+/// the result from `ProcessOutput::variables`.
+///
+/// ```
+/// use snurr::{Process, RunOptions, Variables};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let bpmn = Process::<()>::new("examples/example.bpmn")?
+///         .task_with_context("Count 1", |ctx, _| {
+///             let count = ctx.variables().get("count").and_then(|value| value.as_u64());
+///             ctx.variables().set("count", count.unwrap_or_default() + 1);
+///             Ok(None)
+///         })
+///         .exclusive_by_variable("equal to 3", "decision")
+///         .build()?;
+///
+///     let variables = Variables::from_iter([("decision", "YES")]);
+///     let result = bpmn.run_with((), RunOptions::default().variables(variables))?;
+///     println!("{:?}", result.variables.get("count"));
+///     Ok(())
+/// }
+/// ```
+#[derive(Default)]
+pub struct Variables {
+    inner: RwLock<BTreeMap<String, Value>>,
+}
+
+impl Variables {
+    /// Copy of the variable value
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(name)
+            .cloned()
+    }
+
+    /// Set the variable and return the previous value
+    pub fn set(&self, name: impl Into<String>, value: impl Into<Value>) -> Option<Value> {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(name.into(), value.into())
+    }
+
+    /// Remove the variable and return its value
+    pub fn remove(&self, name: &str) -> Option<Value> {
+        self.inner
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(name)
+    }
+
+    /// Copy of every variable
+    pub fn snapshot(&self) -> BTreeMap<String, Value> {
+        self.inner
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    // Value of a mapping source. A variable path like order.id reads a field of an object variable.
+    pub(crate) fn resolve(&self, source: &Source) -> Option<Value> {
+        match source {
+            Source::Variable(path) => {
+                let mut parts = path.split('.');
+                let variables = self.inner.read().unwrap_or_else(PoisonError::into_inner);
+                let mut value = variables.get(parts.next()?)?;
+                for part in parts {
+                    value = match value {
+                        Value::Array(values) => values.get(part.parse::<usize>().ok()?)?,
+                        _ => value.get(part)?,
+                    };
+                }
+                Some(value.clone())
+            }
+            Source::Literal(literal) => Some(Value::String(literal.clone())),
+        }
+    }
+
+    // Move the variables out at the end of a run
+    pub(crate) fn take(&self) -> Self {
+        Self::from(std::mem::take(
+            &mut *self.inner.write().unwrap_or_else(PoisonError::into_inner),
+        ))
+    }
+
+    pub fn into_inner(self) -> BTreeMap<String, Value> {
+        self.inner
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for Variables {
+    fn clone(&self) -> Self {
+        Self::from(self.snapshot())
+    }
+}
+
+impl Debug for Variables {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.snapshot()).finish()
+    }
+}
+
+impl From<BTreeMap<String, Value>> for Variables {
+    fn from(value: BTreeMap<String, Value>) -> Self {
+        Self {
+            inner: RwLock::new(value),
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Variables
+where
+    K: Into<String>,
+    V: Into<Value>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from(
+            iter.into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect::<BTreeMap<_, _>>(),
+        )
+    }
+}
@@ -0,0 +1,382 @@
+//! Embedded worker queue.
+//!
+//! Tasks registered with [`Process::job`] are not executed by the engine thread. The job is
+//! placed on the queue of a [`WorkerPool`] and executed by one of its worker threads.
+//! The token waits for the job to complete and then resumes with the job result.
+//!
+//! ```
+//! use snurr::{Process, worker::WorkerPool};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let workers = WorkerPool::new(4)?;
+//!     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+//!         .job("Count 1", &workers, |input| {
+//!             *input.lock().unwrap() += 1;
+//!             Ok(None)
+//!         })
+//!         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+//!             3 => Ok(Some("YES")),
+//!             _ => Ok(Some("NO")),
+//!         })
+//!         .build()?;
+//!     bpmn.run(0)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Tasks registered with [`Process::external`] are placed on an [`ExternalTasks`] queue instead.
+//! Workers, in other threads or behind a remote API, fetch and lock them by topic and report the result
+//! with [`ExternalTasks::complete`] or [`ExternalTasks::fail`].
+
+use crate::{
+    Build, Data, Error, Outcome, Process, TaskResult,
+    error::{DATA_LOCK_ERROR_MSG, WORKER_STOPPED_ERROR_MSG},
+};
+use std::{
+    collections::BTreeMap,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex, MutexGuard, PoisonError,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle, ThreadId},
+    time::{Duration, Instant},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Pool of worker threads executing jobs from a shared queue.
+/// The pool can be cloned and shared between processes. Threads are stopped and joined when the last clone is dropped.
+#[derive(Clone)]
+pub struct WorkerPool {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    sender: Mutex<Option<Sender<Job>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    /// Create a pool with `workers` threads. At least one thread is created.
+    /// Returns `Error::Io` if a thread could not be spawned.
+    pub fn new(workers: usize) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handles = (0..workers.max(1))
+            .map(|index| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("snurr-worker-{index}"))
+                    .spawn(move || work(receiver))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                sender: Mutex::new(Some(sender)),
+                handles,
+            }),
+        })
+    }
+
+    /// Number of worker threads
+    pub fn workers(&self) -> usize {
+        self.inner.handles.len()
+    }
+
+    // Place the job on the queue and wait for the result.
+    pub(crate) fn execute<R: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> Result<R, Error> + Send + 'static,
+    ) -> Result<R, Error> {
+        let (reply, result) = mpsc::channel();
+        self.inner
+            .sender
+            .lock()
+            .map_err(|_| Error::ProcessExecution(DATA_LOCK_ERROR_MSG.into()))?
+            .as_ref()
+            .ok_or_else(|| Error::ProcessExecution(WORKER_STOPPED_ERROR_MSG.into()))?
+            .send(Box::new(move || {
+                let _ = reply.send(job());
+            }))
+            .map_err(|_| Error::ProcessExecution(WORKER_STOPPED_ERROR_MSG.into()))?;
+
+        // The reply is dropped without a result if the job panics
+        result
+            .recv()
+            .map_err(|_| Error::ProcessExecution(WORKER_STOPPED_ERROR_MSG.into()))?
+    }
+}
+
+fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+
+        match job {
+            // Keep the worker alive if the job panics. The waiting token gets an error.
+            Ok(job) => {
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+            // Queue is closed
+            Err(_) => return,
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Close the queue
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+
+        let current: ThreadId = thread::current().id();
+        for handle in self.handles.drain(..) {
+            // Cannot join itself if the last clone is dropped by a worker
+            if handle.thread().id() != current {
+                let _ = handle.join();
+            }
+        }
+    }
+}
+
+impl<T: Send + 'static, O: Outcome> Process<T, Build<O>> {
+    /// Register a task function with name or bpmn id that is executed as a job by the worker pool.
+    /// The token waits until the job is completed.
+    pub fn job<F>(self, name: impl Into<String>, pool: &WorkerPool, func: F) -> Self
+    where
+        F: Fn(Data<T>) -> Result<TaskResult, Error> + 'static + Sync + Send,
+    {
+        let pool = pool.clone();
+        let func = Arc::new(func);
+        self.task(name, move |data| {
+            let func = Arc::clone(&func);
+            pool.execute(move || func(data))
+        })
+    }
+
+    /// Register a task with name or bpmn id that is executed by an external worker. The task is placed on
+    /// the queue with the task type of the element as topic, or the name or bpmn id if it has none.
+    /// The token waits until a worker completes or fails the task, or the run is cancelled.
+    pub fn external(self, name: impl Into<String>, tasks: &ExternalTasks<T>) -> Self {
+        let tasks = tasks.clone();
+        self.task_with_context(name, move |ctx, data| {
+            let topic = ctx.task_type().or(ctx.name()).unwrap_or(ctx.id());
+            let (id, result) = tasks.push(topic, ctx.id(), ctx.name(), data);
+            let result = ctx.wait_for(&result);
+            // Still queued if the run stopped first
+            tasks.lock().tasks.remove(&id);
+            result
+        })
+    }
+}
+
+/// Queue of external tasks waiting for a worker. The queue can be cloned and shared between processes and workers.
+///
+/// ```
+/// use snurr::{Process, worker::ExternalTasks};
+/// use std::{thread, time::Duration};
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let tasks = ExternalTasks::new();
+///     let worker = tasks.clone();
+///     thread::spawn(move || loop {
+///         for task in worker.fetch_and_lock("worker-1", &["Count 1"], 10, Duration::from_secs(30)) {
+///             *task.data.lock().unwrap() += 1;
+///             worker.complete(task, None).unwrap();
+///         }
+///         thread::sleep(Duration::from_millis(1));
+///     });
+///
+///     let bpmn = Process::<u32>::new("examples/example.bpmn")?
+///         .external("Count 1", &tasks)
+///         .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+///             3 => Ok(Some("YES")),
+///             _ => Ok(Some("NO")),
+///         })
+///         .build()?;
+///     assert_eq!(bpmn.run(0)?.data, 3);
+///     Ok(())
+/// }
+/// ```
+pub struct ExternalTasks<T> {
+    inner: Arc<Mutex<Pending<T>>>,
+}
+
+struct Pending<T> {
+    next_id: u64,
+    tasks: BTreeMap<u64, PendingTask<T>>,
+}
+
+struct PendingTask<T> {
+    topic: String,
+    element_id: String,
+    name: Option<String>,
+    data: Data<T>,
+    // Worker id and when the lock expires
+    lock: Option<(String, Instant)>,
+    reply: Sender<Result<TaskResult, Error>>,
+}
+
+/// External task locked by a worker. Give it back with `ExternalTasks::complete` or `ExternalTasks::fail`,
+/// so the process gets its data back when the run ends.
+pub struct LockedTask<T> {
+    /// Id of the task in the queue
+    pub id: u64,
+    /// Worker that locked the task
+    pub worker_id: String,
+    /// Task type of the element, or its name or BPMN id
+    pub topic: String,
+    /// BPMN id of the task
+    pub element_id: String,
+    /// Name of the task
+    pub name: Option<String>,
+    /// Data of the run that queued the task
+    pub data: Data<T>,
+}
+
+impl<T> Clone for ExternalTasks<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> Default for ExternalTasks<T> {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Pending {
+                next_id: 1,
+                tasks: BTreeMap::new(),
+            })),
+        }
+    }
+}
+
+impl<T> ExternalTasks<T> {
+    /// Create an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lock at most `max_tasks` tasks with one of the topics, in the order they were queued. Tasks locked by another
+    /// worker are skipped until their lock expires. A task that is not completed or failed before the lock expires
+    /// can be fetched again.
+    pub fn fetch_and_lock(
+        &self,
+        worker_id: &str,
+        topics: &[&str],
+        max_tasks: usize,
+        lock_duration: Duration,
+    ) -> Vec<LockedTask<T>> {
+        let now = Instant::now();
+        self.lock()
+            .tasks
+            .iter_mut()
+            .filter(|(_, task)| {
+                topics.contains(&task.topic.as_str())
+                    && task
+                        .lock
+                        .as_ref()
+                        .is_none_or(|(_, expires)| *expires <= now)
+            })
+            .take(max_tasks)
+            .map(|(id, task)| {
+                task.lock = Some((worker_id.to_string(), now + lock_duration));
+                LockedTask {
+                    id: *id,
+                    worker_id: worker_id.to_string(),
+                    topic: task.topic.clone(),
+                    element_id: task.element_id.clone(),
+                    name: task.name.clone(),
+                    data: Arc::clone(&task.data),
+                }
+            })
+            .collect()
+    }
+
+    /// Complete a locked task. The token continues with the result.
+    /// Returns `Error::NotLocked` if the lock expired and another worker fetched the task.
+    pub fn complete(&self, task: LockedTask<T>, result: TaskResult) -> Result<(), Error> {
+        self.reply(task, Ok(result))
+    }
+
+    /// Fail a locked task. The token gets the error, so boundaries and retry policies of the task apply.
+    /// Returns `Error::NotLocked` if the lock expired and another worker fetched the task.
+    pub fn fail(&self, task: LockedTask<T>, error: Error) -> Result<(), Error> {
+        self.reply(task, Err(error))
+    }
+
+    /// Number of queued tasks, locked or not
+    pub fn len(&self) -> usize {
+        self.lock().tasks.len()
+    }
+
+    /// Returns true if no tasks are queued
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // A lock that has expired is still held until another worker fetches the task
+    fn reply(&self, task: LockedTask<T>, result: Result<TaskResult, Error>) -> Result<(), Error> {
+        let LockedTask {
+            id,
+            worker_id,
+            data,
+            ..
+        } = task;
+        drop(data);
+        let mut pending = self.lock();
+        match pending.tasks.get(&id) {
+            Some(PendingTask {
+                lock: Some((worker, _)),
+                ..
+            }) if *worker == worker_id => {}
+            _ => return Err(Error::NotLocked(id, worker_id)),
+        }
+        let Some(PendingTask { data, reply, .. }) = pending.tasks.remove(&id) else {
+            return Ok(());
+        };
+        drop(pending);
+        // Both clones of the data are dropped, so the run takes it back when it ends
+        drop(data);
+        // The token is gone if the run was cancelled meanwhile
+        let _ = reply.send(result);
+        Ok(())
+    }
+
+    fn push(
+        &self,
+        topic: &str,
+        element_id: &str,
+        name: Option<&str>,
+        data: Data<T>,
+    ) -> (u64, Receiver<Result<TaskResult, Error>>) {
+        let (reply, result) = mpsc::channel();
+        let mut pending = self.lock();
+        let id = pending.next_id;
+        pending.next_id += 1;
+        pending.tasks.insert(
+            id,
+            PendingTask {
+                topic: topic.to_string(),
+                element_id: element_id.to_string(),
+                name: name.map(Into::into),
+                data,
+                lock: None,
+                reply,
+            },
+        );
+        (id, result)
+    }
+
+    // No user function runs while the lock is held, so a poisoned lock is still consistent
+    fn lock(&self) -> MutexGuard<'_, Pending<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -1,4 +1,17 @@
-use snurr::{Data, Error, Process, Result, Symbol, TaskResult};
+use snurr::{
+    Backoff, BoundaryTrigger, CancelToken, CircuitBreaker, ConnectorConfig, Context, Coverage,
+    Data, DataKind, DataReference, DebugRecorder, DecisionCache, DiagnosticKind, DiagramBuilder,
+    DiagramCache, DuplicateFlows, ElementType, EndNode, EngineEvent, Error, ErrorDecision,
+    EventPosition, ExecutionEventKind, Form, FormFieldType, History, Outcome, ParseOptions,
+    ParseWarningKind, Process, ProcessCatalog, ProcessHandlers, Registry, Result, RetryPolicy, Run,
+    RunOptions, RunRecord, RunRecorder, RunStatus, Severity, SharedData, Symbol, TaskResult,
+    TraceContext, TransitionDecision, UserTasks, Workload, testing::ProcessTest,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{
+    Arc, LockResult, Mutex, MutexGuard, RwLock,
+    atomic::{AtomicUsize, Ordering},
+};
 
 const COUNT_1: &str = "Count 1";
 const COUNT_2: &str = "Count 2";
@@ -30,6 +43,112 @@ fn one_task() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn execution_listener() -> Result<()> {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let bpmn = Process::new("tests/files/one_task.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .listener({
+            let events = Arc::clone(&events);
+            move |event| {
+                events.lock().unwrap().push((
+                    event.kind,
+                    event.element_type,
+                    event.name.map(ToString::to_string),
+                ))
+            }
+        })
+        .build()?;
+    bpmn.run(Counter::default())?;
+
+    let events = events.lock().unwrap();
+    let entered: Vec<_> = events
+        .iter()
+        .filter(|(kind, ..)| *kind == ExecutionEventKind::Enter)
+        .map(|(_, element_type, _)| *element_type)
+        .collect();
+    assert_eq!(
+        entered,
+        [
+            ElementType::StartEvent,
+            ElementType::SequenceFlow,
+            ElementType::Task,
+            ElementType::SequenceFlow,
+            ElementType::EndEvent
+        ]
+    );
+    assert_eq!(events.len(), 10);
+    assert!(events.contains(&(
+        ExecutionEventKind::Leave,
+        ElementType::Task,
+        Some(COUNT_1.into())
+    )));
+    Ok(())
+}
+
+#[test]
+fn dot_export_with_trace() -> Result<()> {
+    let visited = Arc::new(Mutex::new(Vec::new()));
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .listener({
+            let visited = Arc::clone(&visited);
+            move |event| visited.lock().unwrap().push(event.id.to_string())
+        })
+        .build()?;
+
+    let dot = bpmn.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains(r#""Activity_0udnmhf" [label="Count 1", shape=box, style=rounded];"#));
+    assert!(dot.contains(r#""Activity_0udnmhf" -> "Event_1269c08" [style=dashed"#));
+    assert!(!dot.contains("color=red"));
+
+    bpmn.run(Counter::default())?;
+    let dot = bpmn.to_dot_with_trace(visited.lock().unwrap().iter());
+    assert!(dot.contains(
+        r#""Activity_0lg8igm" [label="Count 3", shape=box, style=rounded, color=red, penwidth=2];"#
+    ));
+    assert!(dot.contains(r#""Activity_1qalksa" [label="Count 2", shape=box, style=rounded];"#));
+    assert!(dot.contains(
+        r#""Event_1269c08" -> "Activity_0lg8igm" [label="Add 3", color=red, penwidth=2];"#
+    ));
+    Ok(())
+}
+
+#[test]
+fn mermaid_export() -> Result<()> {
+    let visited = Arc::new(Mutex::new(Vec::new()));
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .listener({
+            let visited = Arc::clone(&visited);
+            move |event| visited.lock().unwrap().push(event.id.to_string())
+        })
+        .build()?;
+
+    let mermaid = bpmn.to_mermaid();
+    assert!(mermaid.starts_with("flowchart LR"));
+    assert!(mermaid.contains(r#"    Activity_0udnmhf("Count 1")"#));
+    assert!(mermaid.contains("  Activity_0udnmhf -.- Event_1269c08"));
+    assert!(!mermaid.contains("trace"));
+
+    bpmn.run(Counter::default())?;
+    let mermaid = bpmn.to_mermaid_with_trace(visited.lock().unwrap().iter());
+    assert!(mermaid.contains(r#"  Event_1269c08 -->|"Add 3"| Activity_0lg8igm"#));
+    assert!(mermaid.contains("linkStyle "));
+    let class = mermaid
+        .lines()
+        .find(|line| line.starts_with("  class "))
+        .unwrap();
+    assert!(class.contains("Activity_0lg8igm"));
+    assert!(!class.contains("Activity_1qalksa"));
+    Ok(())
+}
+
 #[test]
 fn two_task() -> Result<()> {
     let bpmn = Process::new("tests/files/two_task.bpmn")?
@@ -41,6 +160,153 @@ fn two_task() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn process_output_stats() -> Result<()> {
+    let bpmn = Process::<Counter>::new("examples/example.bpmn")?
+        .task(COUNT_1, |input| {
+            std::thread::sleep(std::time::Duration::from_millis(2));
+            input.lock().unwrap().count += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    let task = result.stats().get(COUNT_1).unwrap();
+    assert_eq!(task.count, 3);
+    assert!(task.total >= std::time::Duration::from_millis(6));
+    assert!(task.max <= task.total);
+    assert_eq!(result.stats().get("equal to 3").unwrap().count, 3);
+    assert_eq!(result.stats().slowest()[0].1.name.as_deref(), Some(COUNT_1));
+    Ok(())
+}
+
+#[test]
+fn run_services() -> Result<()> {
+    struct Services {
+        increment: u32,
+    }
+
+    let bpmn = Process::<Counter>::new("tests/files/two_task.bpmn")?
+        .task_with_context(COUNT_1, |ctx, input| {
+            assert_eq!(ctx.name(), Some(COUNT_1));
+            input.lock().unwrap().count += ctx.services::<Services>().unwrap().increment;
+            Ok(None)
+        })
+        .task_with_context(COUNT_2, |ctx, _| {
+            assert!(ctx.services::<String>().is_none());
+            Ok(None)
+        })
+        .build()?;
+    let services = Services { increment: 5 };
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().services(&services),
+    )?;
+    assert_eq!(result.data.count, 5);
+    Ok(())
+}
+
+#[cfg(feature = "worker")]
+#[test]
+fn worker_pool_job() -> Result<()> {
+    use snurr::worker::WorkerPool;
+
+    let workers = WorkerPool::new(2)?;
+    let bpmn = Process::new("tests/files/two_task.bpmn")?
+        .job(COUNT_1, &workers, |input: Data<Counter>| {
+            let name = std::thread::current().name().map(ToString::to_string);
+            assert!(name.is_some_and(|name| name.starts_with("snurr-worker")));
+            input.lock().unwrap().count += 1;
+            Ok(None)
+        })
+        .job(COUNT_2, &workers, |_| {
+            Err(Error::ProcessExecution("job failed".into()))
+        })
+        .build()?;
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::ProcessExecution(error)) if error.to_string() == "job failed"
+    ));
+    assert_eq!(workers.workers(), 2);
+    Ok(())
+}
+
+#[cfg(feature = "worker")]
+#[test]
+fn external_tasks() -> Result<()> {
+    use snurr::worker::ExternalTasks;
+    use std::time::Duration;
+
+    let tasks = ExternalTasks::<Counter>::new();
+    let worker = tasks.clone();
+    let handle = std::thread::spawn(move || {
+        let mut completed = 0;
+        while completed < 3 {
+            for task in worker.fetch_and_lock("worker-1", &[COUNT_1], 1, Duration::ZERO) {
+                assert_eq!(task.element_id, "Activity_1x3acv7");
+                // The lock expired and another worker fetched the task
+                let mut fetched =
+                    worker.fetch_and_lock("worker-2", &[COUNT_1], 1, Duration::from_secs(30));
+                assert!(matches!(
+                    worker.complete(task, None),
+                    Err(Error::NotLocked(_, id)) if id == "worker-1"
+                ));
+                let task = fetched.pop().unwrap();
+                task.data.lock().unwrap().count += 1;
+                worker.complete(task, None).unwrap();
+                completed += 1;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+
+    let bpmn = Process::new("examples/example.bpmn")?
+        .external(COUNT_1, &tasks)
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+    handle.join().unwrap();
+    assert!(tasks.is_empty());
+
+    // A failed task stops the run
+    let worker = tasks.clone();
+    let handle = std::thread::spawn(move || {
+        loop {
+            if let Some(task) = worker
+                .fetch_and_lock("worker-1", &[COUNT_1], 1, Duration::from_secs(30))
+                .pop()
+            {
+                worker
+                    .fail(task, Error::ProcessExecution("job failed".into()))
+                    .unwrap();
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    });
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::ProcessExecution(error)) if error.to_string() == "job failed"
+    ));
+    handle.join().unwrap();
+
+    // The task is removed from the queue when the run times out
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().timeout(Duration::from_millis(50)),
+    );
+    assert!(matches!(result, Err(Error::Timeout(_))));
+    assert!(tasks.is_empty());
+    Ok(())
+}
+
 #[test]
 fn subprocess() -> Result<()> {
     let bpmn = Process::new("tests/files/subprocess.bpmn")?
@@ -52,6 +318,116 @@ fn subprocess() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn resume_failed_task() -> Result<()> {
+    let bpmn = Process::new("tests/files/subprocess.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, |input: Data<Counter>| {
+            let mut input = input.lock().unwrap();
+            if input.count < 10 {
+                return Err(Error::ProcessExecution("count too low".into()));
+            }
+            input.count += 2;
+            Ok(None)
+        })
+        .build()?;
+
+    let Err(snapshot) = bpmn.run_resumable(Counter::default(), RunOptions::default()) else {
+        panic!("run should fail in Count 2");
+    };
+    assert!(matches!(snapshot.error, Error::ProcessExecution(_)));
+    assert_eq!(snapshot.element.as_deref(), Some("Activity_0r7ghgi"));
+    assert!(snapshot.is_resumable());
+
+    // Count 1 is not run again and the parent process continues after the subprocess
+    snapshot.data.lock().unwrap().count = 10;
+    let result = bpmn
+        .resume(snapshot, RunOptions::default())
+        .map_err(|snapshot| snapshot.error)?;
+    assert_eq!(result.data.count, 12);
+    assert_eq!(result.end_node.id, "Event_197kqo0");
+    Ok(())
+}
+
+#[test]
+fn migrate_snapshot() -> Result<()> {
+    let v1 = Process::new("tests/files/subprocess.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, |_| {
+            Err(Error::ProcessExecution("not deployed".into()))
+        })
+        .build()?;
+    let Err(mut snapshot) = v1.run_resumable(Counter::default(), RunOptions::default()) else {
+        panic!("run should fail in Count 2");
+    };
+
+    // The subprocess and the task were renamed in the new version
+    let xml = std::fs::read_to_string("tests/files/subprocess.bpmn")?
+        .replace("Activity_1b4bocv", "Review")
+        .replace("Activity_0r7ghgi", "Count_2_v2");
+    let v2 = xml
+        .parse::<Process<Counter>>()?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .build()?;
+
+    let error = v2.migrate(
+        &mut snapshot,
+        &HashMap::from([("Activity_1b4bocv", "Review")]),
+    );
+    assert!(matches!(error, Err(Error::UnmappedElement(id)) if id == "Activity_0r7ghgi"));
+    assert_eq!(snapshot.element.as_deref(), Some("Activity_0r7ghgi"));
+
+    let remap = HashMap::from([
+        ("Activity_1b4bocv", "Review"),
+        ("Activity_0r7ghgi", "Count_2_v2"),
+    ]);
+    v2.migrate(&mut snapshot, &remap)?;
+    assert_eq!(snapshot.element.as_deref(), Some("Count_2_v2"));
+
+    // Count 1 is not run again
+    let result = v2
+        .resume(snapshot, RunOptions::default())
+        .map_err(|snapshot| snapshot.error)?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn resume_failed_parallel_branch() -> Result<()> {
+    let fixed = Arc::new(AtomicUsize::new(0));
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, {
+            let fixed = Arc::clone(&fixed);
+            move |input: Data<Counter>| {
+                if fixed.load(Ordering::SeqCst) == 0 {
+                    return Err(Error::ProcessExecution("not deployed".into()));
+                }
+                input.lock().unwrap().count += 2;
+                Ok(None)
+            }
+        })
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?;
+
+    let Err(snapshot) = bpmn.run_resumable(Counter::default(), RunOptions::default()) else {
+        panic!("run should fail in Count 2");
+    };
+    assert!(snapshot.element.is_some());
+    // Other branches were live, so the run starts again
+    assert!(!snapshot.is_resumable());
+
+    fixed.store(1, Ordering::SeqCst);
+    snapshot.data.lock().unwrap().count = 0;
+    let result = bpmn
+        .resume(snapshot, RunOptions::default())
+        .map_err(|snapshot| snapshot.error)?;
+    assert_eq!(result.data.count, 10);
+    Ok(())
+}
+
 #[test]
 fn subprocess_nested() -> Result<()> {
     let bpmn = Process::new("tests/files/subprocess_nested.bpmn")?
@@ -146,277 +522,799 @@ fn exclusive_gateway() -> Result<()> {
 }
 
 #[test]
-fn exclusive_gateway_with_id() -> Result<()> {
-    let bpmn = Process::new("tests/files/exclusive_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        // Navigate by Bpmn diagram Id instead of by Name.
-        .exclusive("CHOOSE", |_| Ok(Some("Flow_15z7fe3")))
+fn idle_detection() -> Result<()> {
+    let idle = Arc::new(Mutex::new(Vec::new()));
+    let build = |escalate: bool| -> Result<Process<Counter, Run>> {
+        let bpmn = Process::new("tests/files/idle_user_task.bpmn")?
+            .task("Approve", |_| {
+                std::thread::sleep(std::time::Duration::from_millis(50));
+                Ok(None)
+            })
+            .idle_threshold(std::time::Duration::from_millis(5))
+            .listener({
+                let idle = Arc::clone(&idle);
+                move |event| {
+                    if event.kind == ExecutionEventKind::Idle {
+                        idle.lock()
+                            .unwrap()
+                            .push(event.name.map(ToString::to_string));
+                    }
+                }
+            });
+        if escalate { bpmn.escalate_idle() } else { bpmn }.build()
+    };
+
+    let result = build(false)?.run(Counter::default())?;
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
+    assert_eq!(*idle.lock().unwrap(), [Some("Approve".to_string())]);
+
+    let result = build(true)?.run(Counter::default())?;
+    assert_eq!(result.end_node.name.as_deref(), Some("ESCALATED"));
+    assert_eq!(idle.lock().unwrap().len(), 2);
+    Ok(())
+}
+
+#[test]
+fn user_tasks() -> Result<()> {
+    let tasks = UserTasks::new();
+    let bpmn = Process::new("tests/files/user_tasks.bpmn")?
+        .user_task("Approve", &tasks)
+        .user_task("Review", &tasks)
         .build()?;
-    let result = bpmn.run(Counter::default())?;
+    let handle = std::thread::spawn(move || bpmn.run(Counter::default()));
+
+    // Wait until the token reaches the task
+    let next = |tasks: &UserTasks<Counter>| loop {
+        if let Some(task) = tasks.open().pop() {
+            return task;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    };
+
+    let task = next(&tasks);
+    assert_eq!(task.name.as_deref(), Some("Approve"));
+    let assignment = task.assignment.clone().unwrap();
+    assert_eq!(assignment.assignee.as_deref(), Some("alice"));
+    assert_eq!(assignment.candidate_groups, ["managers", "finance"]);
+    assert_eq!(
+        tasks.for_user("dave", &["finance"]),
+        std::slice::from_ref(&task)
+    );
+    assert!(tasks.for_user("dave", &["sales"]).is_empty());
+
+    tasks.claim(task.id, "dave")?;
+    assert!(matches!(
+        tasks.claim(task.id, "alice"),
+        Err(Error::AlreadyClaimed(_, user)) if user == "dave"
+    ));
+    assert!(tasks.for_user("alice", &[]).is_empty());
+    tasks.complete(task.id, |data| data.count += 1)?;
+    assert!(matches!(
+        tasks.complete(task.id, |_| {}),
+        Err(Error::UnknownUserTask(_))
+    ));
+
+    let task = next(&tasks);
+    assert_eq!(task.name.as_deref(), Some("Review"));
+    assert_eq!(task.assignment.unwrap().candidate_users, ["bob", "carol"]);
+    tasks.complete(task.id, |data| data.count += 2)?;
+
+    let result = handle.join().unwrap()?;
     assert_eq!(result.data.count, 3);
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
+    assert!(tasks.is_empty());
     Ok(())
 }
 
 #[test]
-fn exclusive_gateway_with_gateway_converge() -> Result<()> {
-    let bpmn = Process::new("tests/files/exclusive_gateway_with_gateway_converge.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .task(COUNT_4, func_cnt(4))
-        .exclusive("CHOOSE", |_| Ok(Some("YES")))
-        .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 7);
+fn user_task_form() -> Result<()> {
+    let forms = Arc::new(Mutex::new(Vec::new()));
+    let record = |forms: &Arc<Mutex<Vec<Form>>>| {
+        let forms = Arc::clone(forms);
+        move |ctx: &Context, _| {
+            forms.lock().unwrap().extend(ctx.form().cloned());
+            Ok(None)
+        }
+    };
+    Process::<Counter>::new("tests/files/user_tasks.bpmn")?
+        .task_with_context("Approve", record(&forms))
+        .task_with_context("Review", record(&forms))
+        .build()?
+        .run(Counter::default())?;
+
+    let forms = forms.lock().unwrap();
+    let [approve, review] = forms.as_slice() else {
+        panic!("expected two forms");
+    };
+    assert_eq!(
+        approve.key.as_deref(),
+        Some("embedded:app:forms/approve.html")
+    );
+    let [amount, decision, comment] = approve.fields.as_slice() else {
+        panic!("expected three fields");
+    };
+    assert_eq!(amount.label.as_deref(), Some("Amount"));
+    assert_eq!(amount.field_type, FormFieldType::Long);
+    assert_eq!(amount.default_value.as_deref(), Some("100"));
+    assert!(amount.is_required());
+    assert_eq!(amount.constraints[1].config.as_deref(), Some("1"));
+    assert_eq!(
+        decision.field_type,
+        FormFieldType::Enum(vec![
+            ("approve".into(), "Approve".into()),
+            ("reject".into(), "Reject".into())
+        ])
+    );
+    assert_eq!(comment.field_type, FormFieldType::String);
+    assert!(!comment.is_required());
+
+    assert_eq!(review.key.as_deref(), Some("review-form"));
+    assert!(review.fields.is_empty());
     Ok(())
 }
 
 #[test]
-fn exclusive_gateway_with_task_converge() -> Result<()> {
-    let bpmn = Process::new("tests/files/exclusive_gateway_with_task_converge.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .task(COUNT_4, func_cnt(4))
-        .exclusive("CHOOSE", |_| Ok(Some("YES")))
+fn receive_channels() -> Result<()> {
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    let (payments, payment_receiver) = mpsc::channel();
+    let (shipments, shipment_receiver) = mpsc::sync_channel::<&str>(1);
+    let bpmn = Process::new("tests/files/receive.bpmn")?
+        .receive(
+            "Wait for payment",
+            payment_receiver,
+            |input: Data<Counter>, amount: u32| {
+                input.lock().unwrap().count += amount;
+                Ok(None)
+            },
+        )
+        // Any channel can be given as a closure
+        .receive(
+            "Shipped",
+            move |timeout| shipment_receiver.recv_timeout(timeout),
+            |input, _| {
+                input.lock().unwrap().count += 1;
+                Ok(None)
+            },
+        )
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 7);
+
+    let handle = std::thread::spawn(move || {
+        payments.send(2).unwrap();
+        shipments.send("shipped").unwrap();
+        payments
+    });
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+
+    // The run fails when the channel is closed
+    drop(handle.join().unwrap());
+    let result = bpmn.run(Counter::default());
+    assert!(
+        matches!(result, Err(Error::ProcessExecution(error)) if error.to_string().contains("closed"))
+    );
+
+    let (_sender, receiver) = mpsc::channel::<u32>();
+    let result = Process::new("tests/files/receive.bpmn")?
+        .receive("Wait for payment", receiver, |_: Data<Counter>, _| Ok(None))
+        .receive(
+            "Shipped",
+            |_| Err::<(), _>(RecvTimeoutError::Disconnected),
+            |_, _| Ok(None),
+        )
+        .build()?
+        .run_with(
+            Counter::default(),
+            RunOptions::default().timeout(std::time::Duration::from_millis(20)),
+        );
+    assert!(matches!(result, Err(Error::Timeout(ids)) if ids == ["Activity_0payment"]));
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway_default_path() -> Result<()> {
-    // Test with Ok(Default::default())
-    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        // Empty vec runs default path
-        .inclusive("CHOOSE", |_| Ok(Default::default()))
-        .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 5);
+fn boundary_triggered_while_waiting() -> Result<()> {
+    use std::sync::mpsc;
 
-    // Verify that an explicit empty Vec produces the same result as Default::default()
-    let bpmn_empty_vec = Process::new("tests/files/inclusive_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .inclusive("CHOOSE", |_| Ok(Vec::<&str>::new().into()))
+    let (payments, receiver) = mpsc::channel::<u32>();
+    let bpmn = Process::new("tests/files/boundary_trigger.bpmn")?
+        .receive(
+            "Wait for payment",
+            receiver,
+            |input: Data<Counter>, amount| {
+                input.lock().unwrap().count += amount;
+                Ok(None)
+            },
+        )
         .build()?;
-    let result_empty = bpmn_empty_vec.run(Counter::default())?;
-    assert_eq!(
-        result_empty.data.count, 5,
-        "Empty Vec should behave identically to Default::default()"
+
+    // Interrupt the waiting receive task
+    let trigger = BoundaryTrigger::default();
+    let handle = std::thread::spawn({
+        let trigger = trigger.clone();
+        move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            trigger.trigger_boundary("Wait for payment", Symbol::Message, Some("Cancel order"));
+        }
+    });
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().boundary_trigger(trigger.clone()),
+    )?;
+    handle.join().unwrap();
+    assert_eq!(result.end_node.name.as_deref(), Some("CANCELLED"));
+    assert_eq!(result.data.count, 0);
+
+    // Triggered before the token arrives, by BPMN id
+    payments.send(1).unwrap();
+    trigger.trigger_boundary("Activity_0payment", Symbol::Message, Some("Cancel order"));
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().boundary_trigger(trigger.clone()),
+    )?;
+    assert_eq!(result.end_node.name.as_deref(), Some("CANCELLED"));
+
+    // The trigger is used once, the payment is still in the channel
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().boundary_trigger(trigger.clone()),
+    )?;
+    assert_eq!(result.end_node.name.as_deref(), Some("PAID"));
+    assert_eq!(result.data.count, 1);
+
+    // A boundary that doesn't exist
+    trigger.trigger_boundary("Wait for payment", Symbol::Signal, None);
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().boundary_trigger(trigger),
     );
+    assert!(matches!(result, Err(Error::MissingBoundary(..))));
+    Ok(())
+}
+
+#[test]
+fn sender() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let outbox = Arc::clone(&sent);
+    let bpmn = Process::new("tests/files/send.bpmn")?
+        .sender("Notify customer", {
+            let outbox = Arc::clone(&outbox);
+            move |input: Data<Counter>, meta| {
+                input.lock().unwrap().count += 1;
+                outbox
+                    .lock()
+                    .unwrap()
+                    .push((meta.name.to_string(), meta.symbol.clone()));
+                Ok(())
+            }
+        })
+        .sender("Shipped", {
+            let outbox = Arc::clone(&outbox);
+            move |_, meta| {
+                outbox
+                    .lock()
+                    .unwrap()
+                    .push((meta.name.to_string(), meta.symbol.clone()));
+                Ok(())
+            }
+        })
+        .sender("Closed", move |_, meta| {
+            assert_eq!(meta.element_id, "Event_0closed");
+            outbox
+                .lock()
+                .unwrap()
+                .push((meta.name.to_string(), meta.symbol.clone()));
+            Ok(())
+        })
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 1);
     assert_eq!(
-        result.data.count, result_empty.data.count,
-        "Both approaches should produce the same result"
+        *sent.lock().unwrap(),
+        [
+            ("Invoice".to_string(), Symbol::Message),
+            ("Order shipped".to_string(), Symbol::Message),
+            ("Order closed".to_string(), Symbol::Signal),
+        ]
     );
 
+    // Throw events without a delivery are passed through and a failed delivery stops the run
+    let bpmn = Process::new("tests/files/send.bpmn")?
+        .sender("Notify customer", |_: Data<Counter>, _| {
+            Err(Error::ProcessExecution("Mail server down".into()))
+        })
+        .build()?;
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::ProcessExecution(_))
+    ));
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .inclusive("CHOOSE", |_| Ok(vec!["YES", "NO"].into()))
+fn conditional_events() -> Result<()> {
+    let process = |amount| {
+        Process::new("tests/files/conditional.bpmn")?
+            .task("Count", move |input: Data<Counter>| {
+                input.lock().unwrap().count += amount;
+                Ok(None)
+            })
+            .condition("Too many", |data| data.count > 5)
+            .condition("Counted", |data| data.count >= 1)
+            .build()
+    };
+
+    let result = process(1)?.run(Counter::default())?;
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
+    assert_eq!(result.data.count, 1);
+
+    // The boundary interrupts when the task completes
+    let result = process(10)?.run(Counter::default())?;
+    assert_eq!(result.end_node.name.as_deref(), Some("INTERRUPTED"));
+    assert_eq!(result.data.count, 10);
+
+    // The task doesn't run when the condition is true as the token arrives
+    let result = process(1)?.run(Counter { count: 6 })?;
+    assert_eq!(result.end_node.name.as_deref(), Some("INTERRUPTED"));
+    assert_eq!(result.data.count, 6);
+
+    // The catch event waits until the run times out
+    let result = process(0)?.run_with(
+        Counter::default(),
+        RunOptions::default().timeout(std::time::Duration::from_millis(20)),
+    );
+    assert!(matches!(result, Err(Error::Timeout(ids)) if ids == ["Event_0counted"]));
+    Ok(())
+}
+
+#[test]
+fn conditional_event_waits_for_branch() -> Result<()> {
+    let bpmn = Process::new("tests/files/conditional_parallel.bpmn")?
+        .task("Count", |input: Data<Counter>| {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            input.lock().unwrap().count += 1;
+            Ok(None)
+        })
+        .condition("Counted", |data| data.count == 1)
+        .task("After", |input| {
+            input.lock().unwrap().count *= 10;
+            Ok(None)
+        })
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 7);
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 10);
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway_same_flow_used_multiple_times() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .inclusive("CHOOSE", |_| Ok(vec!["YES", "YES", "NO", "NO"].into()))
+fn escalation_propagates_to_enclosing_boundary() -> Result<()> {
+    let bpmn = Process::new("tests/files/escalation_nested.bpmn")?
+        .exclusive("Level", |input: Data<Counter>| {
+            Ok(Some(match input.lock().unwrap().count {
+                1 => "HIGH",
+                2 => "LOW",
+                3 => "OTHER",
+                _ => "NONE",
+            }))
+        })
+        .task("Continue", func_cnt(10))
+        .task("Notify", func_cnt(100))
         .build()?;
+
+    // Caught by the escalation code two levels up
+    let result = bpmn.run(Counter { count: 1 })?;
+    assert_eq!(result.end_node.name.as_deref(), Some("ESCALATED"));
+    assert_eq!(result.data.count, 1);
+
+    // Caught by name without interrupting the outer subprocess
+    assert_eq!(bpmn.run(Counter { count: 2 })?.data.count, 112);
+
+    // Not caught
+    let result = bpmn.run(Counter { count: 3 })?;
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
+    assert_eq!(result.data.count, 13);
+
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 7);
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
+    assert_eq!(result.data.count, 10);
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway_split_end() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_gateway_split_end.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
-        .inclusive("Gateway_0jgakfl", |_| Ok(vec!["YES", "NO"].into()))
+fn error_propagates_to_enclosing_boundary() -> Result<()> {
+    let bpmn = Process::new("tests/files/error_nested.bpmn")?
+        .exclusive("Payment", |input: Data<Counter>| {
+            Ok(Some(match input.lock().unwrap().count {
+                1 => "DECLINED",
+                _ => "NONE",
+            }))
+        })
         .build()?;
+
+    // Caught by the error code two levels up
+    let result = bpmn.run(Counter { count: 1 })?;
+    assert_eq!(result.end_node.name.as_deref(), Some("REFUNDED"));
+
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 6);
+    assert_eq!(result.end_node.name.as_deref(), Some("DONE"));
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway_no_output() -> Result<()> {
-    // Test with Ok(Default::default())
-    let bpmn = Process::new("tests/files/inclusive_gateway_no_output.bpmn")?
-        .task("A", |_| Ok(None))
-        .task("B", |_| Ok(None))
-        // Empty vec runs default path
-        .inclusive("Gateway_0qmfmmo", |_| Ok(Default::default()))
+fn uncaught_error_fails_run() -> Result<()> {
+    let bpmn = Process::new("tests/files/error_nested.bpmn")?
+        .exclusive("Payment", |_: Data<Counter>| Ok(Some("FRAUD")))
         .build()?;
-
     match bpmn.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::MissingOutput(_)),
-            "Expected missing output"
-        ),
-        _ => panic!("Expected an error"),
-    }
-
-    // Verify that an explicit empty Vec behaves the same as Default::default()
-    let bpmn_empty = Process::new("tests/files/inclusive_gateway_no_output.bpmn")?
-        .task("A", |_| Ok(None))
-        .task("B", |_| Ok(None))
-        .inclusive("Gateway_0qmfmmo", |_| Ok(Vec::<&str>::new().into()))
-        .build()?;
-
-    match bpmn_empty.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::MissingOutput(_)),
-            "Default::default() and empty Vec should both produce MissingOutput error"
-        ),
-        _ => panic!("Expected an error"),
+        Err(Error::UncaughtError(code)) => assert_eq!(code, "FRAUD"),
+        _ => panic!("expected an uncaught error"),
     }
-
     Ok(())
 }
 
 #[test]
-fn inclusive_join_fork() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("GW B", |_| Ok(vec!["A", "B", "C"].into()))
+fn milestones() -> Result<()> {
+    let bpmn = Process::new("tests/files/milestones.bpmn")?
+        .task("Approve", |_: Data<Counter>| {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            Ok(None)
+        })
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 6);
+
+    // The message throw event is not a milestone
+    let names: Vec<_> = result
+        .milestones
+        .iter()
+        .map(|milestone| &*milestone.name)
+        .collect();
+    assert_eq!(names, ["Offer sent", "Approved"]);
+    assert_eq!(&*result.milestones[0].id, "Event_0offer");
+    assert!(
+        result.milestones[1].elapsed - result.milestones[0].elapsed
+            >= std::time::Duration::from_millis(5)
+    );
+    assert!(result.milestones[0].reached_at <= result.milestones[1].reached_at);
     Ok(())
 }
 
 #[test]
-fn inclusive_join_fork_gwb_one_flow() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("GW B", |_| Ok("A".into()))
+fn run_handle() -> Result<()> {
+    use std::sync::mpsc;
+
+    let (entered, started) = mpsc::channel();
+    let (release, wait) = mpsc::channel::<()>();
+    let wait = Mutex::new(wait);
+    let bpmn = Process::<Counter>::new("examples/example.bpmn")?
+        .task(COUNT_1, move |input| {
+            entered.send(()).unwrap();
+            wait.lock().unwrap().recv().unwrap();
+            input.lock().unwrap().count += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 4);
+
+    let (handle, thread) = Arc::new(bpmn).spawn(Counter::default(), RunOptions::default());
+    for _ in 0..3 {
+        started.recv().unwrap();
+        assert_eq!(handle.status(), RunStatus::Running);
+        let active = handle.active_elements();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].name.as_deref(), Some(COUNT_1));
+        assert_eq!(active[0].element_type, ElementType::Task);
+        release.send(()).unwrap();
+    }
+    assert_eq!(thread.join().unwrap()?.data.count, 3);
+    assert_eq!(handle.status(), RunStatus::Completed);
+    assert!(handle.active_elements().is_empty());
     Ok(())
 }
 
 #[test]
-fn inclusive_join_fork_gateway_verify_sync() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
+fn visit_limit() -> Result<()> {
+    // The gateway never leaves the loop
+    let bpmn = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
-        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("GW B", |input| {
-            // Make sure that the gateway is only executed once. Do not recommend to mutate data in gateway.
-            input.lock().unwrap().count += 1;
-            Ok(vec!["A", "B", "C"].into())
-        })
+        .exclusive("equal to 3", |_| Ok(Some("NO")))
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 7);
+
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().max_visits(10));
+    let Err(Error::VisitLimit(10, cycling)) = result else {
+        panic!("Expected a visit limit error, got {result:?}");
+    };
+    assert!(cycling.iter().any(|id| id == "Activity_1x3acv7"));
+    assert!(!cycling.iter().any(|id| id == "StartEvent_0vpy957"));
+
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().max_steps(25));
+    assert!(matches!(result, Err(Error::VisitLimit(25, _))));
     Ok(())
 }
 
 #[test]
-fn parallel_inclusive_join_fork() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_inclusive_join_fork.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("GW AA", |_| Ok(vec!["A", "B", "C"].into()))
-        .inclusive("GW AAA", |_| Ok(vec!["A", "B", "C", "D", "E"].into()))
-        .inclusive("GW B", |_| Ok(vec!["A", "B", "C"].into()))
-        .inclusive("GW BB", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("GW BBB", |_| Ok(vec!["A", "B", "C", "D"].into()))
-        .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 23);
+fn token_limit() -> Result<()> {
+    // Every round doubles the tokens
+    let bpmn = Process::<Counter>::new("tests/files/fork_loop.bpmn")?.build()?;
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().max_tokens(100));
+    let Err(Error::TokenLimit(100, locations)) = result else {
+        panic!("Expected a token limit error, got {result:?}");
+    };
+    assert_eq!(locations, [("Gateway_0merge".to_string(), 128)]);
     Ok(())
 }
 
 #[test]
-fn inclusive_with_parallel() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_with_parallel.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+fn deterministic_order() -> Result<()> {
+    let record = |name: &'static str| {
+        move |input: Data<Vec<&'static str>>| {
+            input.lock().unwrap().push(name);
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, record(COUNT_1))
+        .task(COUNT_2, record(COUNT_2))
+        .task(COUNT_3, record(COUNT_3))
+        .task(COUNT_4, record(COUNT_4))
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 5);
+    let order = |options| -> Result<Vec<&str>> { Ok(bpmn.run_with(Vec::new(), options)?.data) };
+
+    // The order of the diagram
+    assert_eq!(
+        order(RunOptions::default().deterministic())?,
+        [COUNT_1, COUNT_2, COUNT_3, COUNT_4]
+    );
+
+    // The same seed gives the same order, and some seed changes it
+    let shuffled = order(RunOptions::default().shuffle(7))?;
+    assert_eq!(shuffled.len(), 4);
+    assert_eq!(order(RunOptions::default().shuffle(7))?, shuffled);
+    assert!(
+        (0..20)
+            .map(|seed| order(RunOptions::default().shuffle(seed)))
+            .any(|order| order.unwrap()[1..] != [COUNT_2, COUNT_3, COUNT_4])
+    );
     Ok(())
 }
 
 #[test]
-fn parallell_gateway() -> Result<()> {
+fn run_cancelled() -> Result<()> {
+    let cancel = CancelToken::default();
     let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
-        .task(COUNT_1, func_cnt(1))
+        .task_with_context(COUNT_1, {
+            let cancel = cancel.clone();
+            move |ctx, input: Data<Counter>| {
+                assert!(!ctx.is_cancelled());
+                cancel.cancel();
+                assert!(ctx.is_cancelled());
+                input.lock().unwrap().count += 1;
+                Ok(None)
+            }
+        })
         .task(COUNT_2, func_cnt(2))
         .task(COUNT_3, func_cnt(3))
         .task(COUNT_4, func_cnt(4))
         .build()?;
-    let result = bpmn.run(Counter::default())?;
+
+    match bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().cancel_token(cancel.clone()),
+    ) {
+        Err(Error::Cancelled(ids)) => assert!(!ids.is_empty()),
+        _ => panic!("Expected the run to be cancelled"),
+    }
+
+    // A token that is not cancelled
+    let result = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?
+        .run_with(
+            Counter::default(),
+            RunOptions::default().cancel_token(CancelToken::default()),
+        )?;
     assert_eq!(result.data.count, 10);
     Ok(())
 }
 
 #[test]
-fn error_handling() -> Result<()> {
-    let bpmn = Process::new("tests/files/error_handling.bpmn")?
-        .task(COUNT_1, |_| Ok(Some(Symbol::Error.into())))
-        .task(COUNT_2, |_| Ok(Some(Symbol::Error.into())))
-        .task(COUNT_3, func_cnt(3))
+fn golden_run_diff() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("snurr_golden_{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let run = |limit: u32| -> Result<RunRecord> {
+        let recorder = RunRecorder::default();
+        let bpmn = Process::new("examples/example.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .exclusive("equal to 3", move |input| {
+                match input.lock().unwrap().count >= limit {
+                    true => Ok(Some("YES")),
+                    false => Ok(Some("NO")),
+                }
+            })
+            .listener(recorder.listener())
+            .build()?;
+        let result = bpmn.run(Counter::default())?;
+        Ok(recorder.take(&result.end_node))
+    };
+
+    // First run is saved as the golden run
+    let golden = run(3)?;
+    assert_eq!(golden.decisions.len(), 3);
+    assert!(golden.golden(&path)?.is_empty());
+    assert_eq!(RunRecord::load(&path)?, golden);
+    assert!(run(3)?.golden(&path)?.is_empty());
+
+    let diff = run(2)?.golden(&path)?;
+    std::fs::remove_file(&path)?;
+    assert!(!diff.is_empty());
+    assert!(diff.end_node.is_none());
+    assert_eq!(diff.decisions.len(), 1);
+    assert_eq!(diff.decisions[0].expected.len(), 3);
+    assert_eq!(diff.decisions[0].actual.len(), 2);
+    let divergence = diff.first_divergence.expect("divergence");
+    assert_ne!(divergence.expected, divergence.actual);
+    Ok(())
+}
+
+#[test]
+fn exclusive_gateway_cached() -> Result<()> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let cache = Arc::new(DecisionCache::default());
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_cached("equal to 3", |data: &Counter| data.count == 3, {
+            let calls = Arc::clone(&calls);
+            move |input| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                match input.lock().unwrap().count {
+                    3 => Ok(Some("YES")),
+                    _ => Ok(Some("NO")),
+                }
+            }
+        })
         .build()?;
+
+    // Within a run. Count 1 and 2 share the same key.
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 3);
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+
+    // Across runs with a shared cache
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_cached("equal to 3", |data: &Counter| data.count == 3, {
+            let calls = Arc::clone(&calls);
+            move |input| {
+                calls.fetch_add(1, Ordering::Relaxed);
+                match input.lock().unwrap().count {
+                    3 => Ok(Some("YES")),
+                    _ => Ok(Some("NO")),
+                }
+            }
+        })
+        .decision_cache(Arc::clone(&cache))
+        .build()?;
+    calls.store(0, Ordering::Relaxed);
+    bpmn.run(Counter::default())?;
+    bpmn.run(Counter::default())?;
+    assert_eq!(calls.load(Ordering::Relaxed), 2);
+    assert_eq!(cache.len(), 2);
     Ok(())
 }
 
+// Every key has the same hash
+#[derive(PartialEq, Eq)]
+struct Collide(u32);
+
+impl std::hash::Hash for Collide {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        state.write_u8(0);
+    }
+}
+
 #[test]
-fn two_boundary_timer_thrown() -> Result<()> {
-    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
-        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
-        .task(COUNT_2, func_cnt(2))
-        .task(COUNT_3, func_cnt(3))
+fn decision_cache_keys() -> Result<()> {
+    // Keys with the same hash get their own decision
+    let cache = Arc::new(DecisionCache::default());
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_cached(
+            "equal to 3",
+            |data: &Counter| Collide(data.count),
+            |input| match input.lock().unwrap().count {
+                3 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            },
+        )
+        .decision_cache(Arc::clone(&cache))
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 3);
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+    assert_eq!(cache.len(), 3);
+
+    // Another process with the same gateway id doesn't get the decisions of the first
+    let other = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_cached(
+            "equal to 3",
+            |data: &Counter| Collide(data.count),
+            |input| match input.lock().unwrap().count {
+                2 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            },
+        )
+        .decision_cache(Arc::clone(&cache))
+        .build()?;
+    assert_eq!(other.run(Counter::default())?.data.count, 2);
+    assert_eq!(cache.len(), 5);
+
+    // The oldest decisions are removed when the cache is full
+    let cache = Arc::new(DecisionCache::with_capacity(2));
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_cached(
+            "equal to 3",
+            |data: &Counter| data.count,
+            |input| match input.lock().unwrap().count {
+                3 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            },
+        )
+        .decision_cache(Arc::clone(&cache))
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
     Ok(())
 }
 
 #[test]
-fn two_boundary_error_thrown() -> Result<()> {
-    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
-        .task(COUNT_1, |_| Ok(Some(("Error", Symbol::Error).into())))
+fn feature_flags() -> Result<()> {
+    let build = |disabled: &'static [&'static str]| {
+        Process::new("tests/files/exclusive_gateway.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .task(COUNT_2, func_cnt(2))
+            .task(COUNT_3, func_cnt(3))
+            .exclusive("CHOOSE", |_| Ok(Some("YES")))
+            .feature_flags(move |name: &str| !disabled.contains(&name))
+            .build()
+    };
+
+    // Disabled task is skipped, disabled flow use the gateway default flow
+    assert_eq!(build(&[])?.run(Counter::default())?.data.count, 3);
+    assert_eq!(build(&[COUNT_1])?.run(Counter::default())?.data.count, 2);
+    assert_eq!(build(&["YES"])?.run(Counter::default())?.data.count, 4);
+
+    // The default flow cannot replace itself
+    assert!(matches!(
+        build(&["YES", "NO"])?.run(Counter::default()),
+        Err(Error::Disabled(_))
+    ));
+
+    // Declared fallback flow
+    let bpmn = Process::new("tests/files/exclusive_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
         .task(COUNT_3, func_cnt(3))
+        .exclusive("CHOOSE", |_| Ok(Some("NO")))
+        .feature_flags(HashSet::from(["NO".to_string()]))
+        .fallback("NO", "YES")
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 2);
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
     Ok(())
 }
 
 #[test]
-fn multiple_boundaries_same_symbol() -> Result<()> {
-    let bpmn = Process::new("tests/files/multiple_boundaries_same_symbol.bpmn")?
-        .task(COUNT_1, |_| Ok(Some(("M2", Symbol::Message).into())))
+fn exclusive_gateway_with_id() -> Result<()> {
+    let bpmn = Process::new("tests/files/exclusive_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
         .task(COUNT_3, func_cnt(3))
+        // Navigate by Bpmn diagram Id instead of by Name.
+        .exclusive("CHOOSE", |_| Ok(Some("Flow_15z7fe3")))
         .build()?;
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 3);
@@ -424,127 +1322,209 @@ fn multiple_boundaries_same_symbol() -> Result<()> {
 }
 
 #[test]
-fn intermediate_event() -> Result<()> {
-    let bpmn = Process::new("tests/files/intermediate_event.bpmn")?
+fn exclusive_gateway_with_gateway_converge() -> Result<()> {
+    let bpmn = Process::new("tests/files/exclusive_gateway_with_gateway_converge.bpmn")?
         .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
         .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .exclusive("CHOOSE", |_| Ok(Some("YES")))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 6);
+    assert_eq!(result.data.count, 7);
     Ok(())
 }
 
 #[test]
-fn two_process_pools() -> Result<()> {
-    let bpmn = Process::new("tests/files/two_process_pools.bpmn")?
+fn exclusive_gateway_with_task_converge() -> Result<()> {
+    let bpmn = Process::new("tests/files/exclusive_gateway_with_task_converge.bpmn")?
         .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .exclusive("CHOOSE", |_| Ok(Some("YES")))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 3);
-    Ok(())
-}
-
-#[test]
-fn subprocess_external_link_fail() -> snurr::Result<()> {
-    let bpmn = Process::new("tests/files/subprocess_external_link_fail.bpmn")?.build()?;
-    match bpmn.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::MissingIntermediateCatchEvent(symbol, name) if symbol == "Link" && name == "Link 2"),
-            "Expected Link Symbol with name Link 2"
-        ),
-        _ => panic!("Expected an error"),
-    }
+    assert_eq!(result.data.count, 7);
     Ok(())
 }
 
 #[test]
-fn showcase() -> Result<()> {
-    // Test with Ok(None) for exclusive gateway
-    let bpmn = Process::new("tests/files/showcase.bpmn")?
+fn inclusive_gateway_default_path() -> Result<()> {
+    // Test with Ok(Default::default())
+    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
         .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
-        .task("Timeout 1", |_| Ok(Some(Symbol::Timer.into())))
-        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("RUN A", |_| Ok("A".into()))
-        .exclusive("RUN DEFAULT", |_| Ok(None))
+        .task(COUNT_3, func_cnt(3))
+        // Empty vec runs default path
+        .inclusive("CHOOSE", |_| Ok(Default::default()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 16);
+    assert_eq!(result.data.count, 5);
 
-    // Verify that Ok(Default::default()) produces the same result
-    let bpmn_default = Process::new("tests/files/showcase.bpmn")?
+    // Verify that an explicit empty Vec produces the same result as Default::default()
+    let bpmn_empty_vec = Process::new("tests/files/inclusive_gateway.bpmn")?
         .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
-        .task("Timeout 1", |_| Ok(Some(Symbol::Timer.into())))
-        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
-        .inclusive("RUN A", |_| Ok("A".into()))
-        .exclusive("RUN DEFAULT", |_| Ok(Default::default()))
+        .task(COUNT_3, func_cnt(3))
+        .inclusive("CHOOSE", |_| Ok(Vec::<&str>::new().into()))
         .build()?;
-    let result_default = bpmn_default.run(Counter::default())?;
+    let result_empty = bpmn_empty_vec.run(Counter::default())?;
     assert_eq!(
-        result.data.count, result_default.data.count,
-        "None and Default::default() should produce identical results"
+        result_empty.data.count, 5,
+        "Empty Vec should behave identically to Default::default()"
+    );
+    assert_eq!(
+        result.data.count, result_empty.data.count,
+        "Both approaches should produce the same result"
     );
 
     Ok(())
 }
 
 #[test]
-fn task_fork() -> Result<()> {
-    let bpmn = Process::new("tests/files/task_fork.bpmn")?
+fn inclusive_gateway() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .inclusive("CHOOSE", |_| Ok(vec!["YES", "NO"].into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 6);
+    assert_eq!(result.data.count, 7);
     Ok(())
 }
 
 #[test]
-fn fork_explosion() -> Result<()> {
-    let bpmn = Process::new("tests/files/fork_explosion.bpmn")?
+fn inclusive_gateway_same_flow_used_multiple_times() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .inclusive("CHOOSE", |_| Ok(vec!["YES", "YES", "NO", "NO"].into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 33);
+    assert_eq!(result.data.count, 7);
     Ok(())
 }
 
 #[test]
-fn process_end_with_symbol() -> Result<()> {
-    let bpmn = Process::new("tests/files/process_end_with_symbol.bpmn")?.build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 0);
+fn inclusive_gateway_duplicate_flows_policy() -> Result<()> {
+    let build = |policy| {
+        Process::new("tests/files/inclusive_gateway.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .task(COUNT_2, func_cnt(2))
+            .task(COUNT_3, func_cnt(3))
+            .inclusive("CHOOSE", |_| Ok(vec!["YES", "YES", "NO"].into()))
+            .duplicate_flows(policy)
+            .build()
+    };
+
+    // The inclusive join fires once for the YES and NO tokens and once for the second YES token
+    let result = build(DuplicateFlows::Allow)?.run(Counter::default())?;
+    assert_eq!(result.data.count, 10);
+
+    let result = build(DuplicateFlows::Error)?.run(Counter::default());
+    assert!(matches!(result, Err(Error::DuplicateFlow(_, flow)) if flow == "YES"));
     Ok(())
 }
 
 #[test]
-fn inclusive_gateway_not_all_joined() -> Result<()> {
-    let bpmn = Process::new("tests/files/inclusive_gateway_not_all_joined.bpmn")?
+fn inclusive_gateway_split_end() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_gateway_split_end.bpmn")?
         .task(COUNT_1, func_cnt(1))
-        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
-        .exclusive("RUN C", |_| Ok(Some("C")))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .inclusive("Gateway_0jgakfl", |_| Ok(vec!["YES", "NO"].into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 3);
+    assert_eq!(result.data.count, 6);
     Ok(())
 }
 
 #[test]
-fn parallel_gateway_not_all_joined() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_gateway_not_all_joined.bpmn")?
+fn end_nodes() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_gateway_split_end.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .inclusive("Gateway_0jgakfl", |_| Ok(vec!["YES", "NO"].into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 4);
+    let mut ids: Vec<_> = result.end_nodes.iter().map(|end| end.id.as_str()).collect();
+    ids.sort();
+    assert_eq!(ids, ["Event_0pih68u", "Event_0xrylm0"]);
+    assert!(ids.contains(&result.end_node.id.as_str()));
+
+    // Every process in the definitions adds its end event
+    let bpmn = Process::new("tests/files/two_executable_pools.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    let names: Vec<_> = result
+        .end_nodes
+        .iter()
+        .filter_map(|end| end.name.as_deref())
+        .collect();
+    assert_eq!(names, ["End 1", "End 2"]);
     Ok(())
 }
 
 #[test]
-fn parallel_gateway_not_all_joined_inverse() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_gateway_not_all_joined_inverse.bpmn")?
+fn inclusive_gateway_no_output() -> Result<()> {
+    // Test with Ok(Default::default())
+    let bpmn = Process::new("tests/files/inclusive_gateway_no_output.bpmn")?
+        .task("A", |_| Ok(None))
+        .task("B", |_| Ok(None))
+        // Empty vec runs default path
+        .inclusive("Gateway_0qmfmmo", |_| Ok(Default::default()))
+        .build()?;
+
+    match bpmn.run(Counter::default()) {
+        Err(error) => assert!(
+            matches!(error, Error::MissingOutput(_)),
+            "Expected missing output"
+        ),
+        _ => panic!("Expected an error"),
+    }
+
+    // Verify that an explicit empty Vec behaves the same as Default::default()
+    let bpmn_empty = Process::new("tests/files/inclusive_gateway_no_output.bpmn")?
+        .task("A", |_| Ok(None))
+        .task("B", |_| Ok(None))
+        .inclusive("Gateway_0qmfmmo", |_| Ok(Vec::<&str>::new().into()))
+        .build()?;
+
+    match bpmn_empty.run(Counter::default()) {
+        Err(error) => assert!(
+            matches!(error, Error::MissingOutput(_)),
+            "Default::default() and empty Vec should both produce MissingOutput error"
+        ),
+        _ => panic!("Expected an error"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn inclusive_join_fork() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("GW B", |_| Ok(vec!["A", "B", "C"].into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 6);
+    Ok(())
+}
+
+#[test]
+fn inclusive_join_fork_gwb_one_flow() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("GW B", |_| Ok("A".into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 4);
@@ -552,9 +1532,15 @@ fn parallel_gateway_not_all_joined_inverse() -> Result<()> {
 }
 
 #[test]
-fn parallel_multi() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_multi.bpmn")?
+fn inclusive_join_fork_gateway_verify_sync() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_join_fork.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("GW B", |input| {
+            // Make sure that the gateway is only executed once. Do not recommend to mutate data in gateway.
+            input.lock().unwrap().count += 1;
+            Ok(vec!["A", "B", "C"].into())
+        })
         .build()?;
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 7);
@@ -562,9 +1548,369 @@ fn parallel_multi() -> Result<()> {
 }
 
 #[test]
-fn parallel_join_fork() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_join_fork.bpmn")?
+fn parallel_inclusive_join_fork() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_inclusive_join_fork.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("GW AA", |_| Ok(vec!["A", "B", "C"].into()))
+        .inclusive("GW AAA", |_| Ok(vec!["A", "B", "C", "D", "E"].into()))
+        .inclusive("GW B", |_| Ok(vec!["A", "B", "C"].into()))
+        .inclusive("GW BB", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("GW BBB", |_| Ok(vec!["A", "B", "C", "D"].into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 23);
+    Ok(())
+}
+
+#[test]
+fn inclusive_with_parallel() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_with_parallel.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("GW A", |_| Ok(vec!["A", "B"].into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 5);
+    Ok(())
+}
+
+#[test]
+fn parallell_gateway() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 10);
+    Ok(())
+}
+
+#[test]
+fn error_handling() -> Result<()> {
+    let bpmn = Process::new("tests/files/error_handling.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(Symbol::Error.into())))
+        .task(COUNT_2, |_| Ok(Some(Symbol::Error.into())))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn circuit_breaker() -> Result<()> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let process = |breaker| {
+        let calls = Arc::clone(&calls);
+        Process::new("tests/files/error_handling.bpmn").map(|process| {
+            process
+                .task(COUNT_1, move |_| {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err(Error::ProcessExecution("service unavailable".into()))
+                })
+                .task(COUNT_2, func_cnt(2))
+                .task(COUNT_3, func_cnt(3))
+                .circuit_breaker(COUNT_1, breaker)
+        })
+    };
+
+    let bpmn = process(
+        CircuitBreaker::new(2, std::time::Duration::from_secs(3600)).boundary(Symbol::Error),
+    )?
+    .build()?;
+    assert!(bpmn.run(Counter::default()).is_err());
+    assert!(bpmn.run(Counter::default()).is_err());
+    // The circuit is open. The token continues with the error boundary without calling the task.
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+    let bpmn = process(CircuitBreaker::new(1, std::time::Duration::from_secs(3600)))?.build()?;
+    assert!(bpmn.run(Counter::default()).is_err());
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::CircuitOpen(task)) if task == COUNT_1
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+    // A call is let through after the cool-down
+    let bpmn = process(CircuitBreaker::new(1, std::time::Duration::ZERO))?.build()?;
+    assert!(bpmn.run(Counter::default()).is_err());
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::ProcessExecution(_))
+    ));
+    assert_eq!(calls.load(Ordering::SeqCst), 5);
+    Ok(())
+}
+
+#[test]
+fn circuit_breaker_panic() -> Result<()> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let bpmn = Process::new("tests/files/error_handling.bpmn")?
+        .task(COUNT_1, {
+            let calls = Arc::clone(&calls);
+            move |_: Data<Counter>| {
+                if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(Error::ProcessExecution("service unavailable".into()));
+                }
+                panic!("service crashed");
+            }
+        })
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .circuit_breaker(COUNT_1, CircuitBreaker::new(1, std::time::Duration::ZERO))
+        .catch_panics()
+        .build()?;
+    assert!(bpmn.run(Counter::default()).is_err());
+    // The call after the cool-down panics and opens the circuit again, so the next call is let through
+    for _ in 0..2 {
+        assert!(matches!(
+            bpmn.run(Counter::default()),
+            Err(Error::ProcessExecution(error)) if error.to_string().contains("service crashed")
+        ));
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+    Ok(())
+}
+
+#[test]
+fn ignore_missing_boundaries() -> Result<()> {
+    let process = || {
+        Process::<Counter>::new("examples/example.bpmn").map(|process| {
+            process
+                .task(COUNT_1, |input| {
+                    input.lock().unwrap().count += 1;
+                    Ok(Some(Symbol::Escalation.into()))
+                })
+                .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+                    3 => Ok(Some("YES")),
+                    _ => Ok(Some("NO")),
+                })
+        })
+    };
+    let result = process()?.build()?.run(Counter::default());
+    assert!(matches!(result, Err(Error::MissingBoundary(..))));
+
+    // Continue with the outgoing flow
+    let result = process()?
+        .ignore_missing_boundaries()
+        .build()?
+        .run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn two_boundary_timer_thrown() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn two_boundary_error_thrown() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Error", Symbol::Error).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    Ok(())
+}
+
+#[test]
+fn event_sink() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    bpmn.run_with(Counter::default(), RunOptions::default().event_sink(sender))?;
+    let events: Vec<EngineEvent> = receiver.iter().collect();
+
+    assert!(matches!(
+        events.first(),
+        Some(EngineEvent::TokenCreated {
+            parent_token: None,
+            ..
+        })
+    ));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        EngineEvent::BoundaryTriggered {
+            symbol: Some(Symbol::Timer),
+            attached_to,
+            ..
+        } if &**attached_to == "Activity_0udnmhf"
+    )));
+    assert!(!events.iter().any(|event| matches!(
+        event,
+        EngineEvent::ElementEntered { name: Some(name), .. } if &**name == COUNT_2
+    )));
+    assert!(matches!(
+        events.last(),
+        Some(EngineEvent::ProcessEnded { .. })
+    ));
+
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    bpmn.run_with(Counter::default(), RunOptions::default().event_sink(sender))?;
+    let decisions = receiver
+        .iter()
+        .filter(|event| matches!(event, EngineEvent::GatewayDecision { .. }))
+        .count();
+    assert_eq!(decisions, 3);
+    Ok(())
+}
+
+#[test]
+fn history() -> Result<()> {
+    let history = History::default();
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            let mut input = input.lock().unwrap();
+            input.count += 1;
+            match input.count {
+                10 => Err(Error::ProcessExecution("job failed".into())),
+                _ => Ok(None),
+            }
+        })
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .history(history.clone())
+        .build()?;
+
+    bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().correlation_id("order-42"),
+    )?;
+    assert!(bpmn.run(Counter { count: 9 }).is_err());
+
+    let runs = history.runs();
+    assert_eq!(runs.len(), 2);
+    let run = &history.by_correlation_id("order-42")[0];
+    assert_eq!(
+        run.result.as_ref().map(|end| end.id.as_str()).ok(),
+        Some("Event_1tfc3xd")
+    );
+    assert_eq!(run.path().first(), Some(&"StartEvent_0vpy957"));
+    assert_eq!(run.decisions("equal to 3").len(), 3);
+    assert!(run.entries.iter().any(|entry| matches!(
+        &entry.event,
+        EngineEvent::ElementLeft { name: Some(name), .. } if &**name == COUNT_1
+    )));
+    assert!(
+        run.entries
+            .windows(2)
+            .all(|entries| entries[0].elapsed <= entries[1].elapsed)
+    );
+
+    let failed = history.failed();
+    assert_eq!(failed.len(), 1);
+    assert!(
+        failed[0]
+            .result
+            .as_ref()
+            .is_err_and(|error| error.contains("job failed"))
+    );
+
+    assert_eq!(history.take().len(), 2);
+    assert!(history.runs().is_empty());
+    Ok(())
+}
+
+#[test]
+fn simulation_json() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    bpmn.run_with(Counter::default(), RunOptions::default().event_sink(sender))?;
+    let lines: Vec<String> = receiver
+        .iter()
+        .map(|event| event.to_simulation_json(std::time::Duration::from_millis(1000)))
+        .collect();
+
+    assert_eq!(
+        lines.first().map(String::as_str),
+        Some(
+            r#"{"action":"createScope","scope":{"id":0,"parent":null},"element":{"id":"StartEvent_0vpy957"},"timestamp":1000}"#
+        )
+    );
+    assert!(lines.iter().any(|line| line.contains(
+        r#""action":"enter","scope":{"id":0},"element":{"id":"Activity_1x3acv7","type":"bpmn:Task","name":"Count 1"}"#
+    )));
+    assert!(
+        lines
+            .last()
+            .is_some_and(|line| line.starts_with(r#"{"action":"end","process":"#))
+    );
+    Ok(())
+}
+
+#[test]
+fn boundary_handler() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .boundary("Timeout", |ctx, input| {
+            assert_eq!(ctx.symbol(), Some(&Symbol::Timer));
+            assert_eq!(ctx.attached_to(), Some(COUNT_1));
+            input.lock().unwrap().count += 10;
+            Ok(())
+        })
+        .boundary("Error", func_boundary_unreachable)
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 13);
+    Ok(())
+}
+
+fn func_boundary_unreachable(_: &Context, _: Data<Counter>) -> Result<()> {
+    unreachable!("boundary was never triggered")
+}
+
+#[test]
+fn multiple_boundaries_same_symbol() -> Result<()> {
+    let bpmn = Process::new("tests/files/multiple_boundaries_same_symbol.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("M2", Symbol::Message).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn intermediate_event() -> Result<()> {
+    let bpmn = Process::new("tests/files/intermediate_event.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
         .build()?;
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 6);
@@ -572,260 +1918,2713 @@ fn parallel_join_fork() -> Result<()> {
 }
 
 #[test]
-fn parallel_parallel_join_fork() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_parallel_join_fork.bpmn")?
+fn two_process_pools() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_executable_pools.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn run_process() -> Result<()> {
+    let bpmn = Process::new("tests/files/two_executable_pools.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .build()?;
+    assert_eq!(bpmn.process_ids(), ["Process_0dfok7y", "Process_188fdbe"]);
+
+    let result = bpmn.run_process("Process_188fdbe", Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    assert_eq!(result.end_node.name.as_deref(), Some("End 2"));
+
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().process("Process_0dfok7y"),
+    )?;
+    assert_eq!(result.data.count, 1);
+
+    assert!(matches!(
+        bpmn.run_process("Process_missing", Counter::default()),
+        Err(Error::MissingProcessData(id)) if id == "Process_missing"
+    ));
+    Ok(())
+}
+
+#[test]
+fn non_executable_pool() -> Result<()> {
+    // Only the second pool of the two pool model is executable. No function is needed for Count 1.
+    let bpmn = Process::new("tests/files/two_process_pools.bpmn")?
+        .task(COUNT_2, func_cnt(2))
+        .build()?;
+    assert_eq!(bpmn.process_ids(), ["Process_188fdbe"]);
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    assert_eq!(result.end_node.name.as_deref(), Some("End 2"));
+
+    // A single process marked as not executable by the modeler is still run
+    let bpmn = Process::new("tests/files/one_task.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 1);
+    Ok(())
+}
+
+#[test]
+fn subprocess_external_link_fail() -> snurr::Result<()> {
+    let bpmn = Process::new("tests/files/subprocess_external_link_fail.bpmn")?.build()?;
+    match bpmn.run(Counter::default()) {
+        Err(error) => assert!(
+            matches!(error, Error::MissingIntermediateCatchEvent(symbol, name) if symbol == "Link" && name == "Link 2"),
+            "Expected Link Symbol with name Link 2"
+        ),
+        _ => panic!("Expected an error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn showcase() -> Result<()> {
+    // Test with Ok(None) for exclusive gateway
+    let bpmn = Process::new("tests/files/showcase.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task("Timeout 1", |_| Ok(Some(Symbol::Timer.into())))
+        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("RUN A", |_| Ok("A".into()))
+        .exclusive("RUN DEFAULT", |_| Ok(None))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 16);
+
+    // Verify that Ok(Default::default()) produces the same result
+    let bpmn_default = Process::new("tests/files/showcase.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task("Timeout 1", |_| Ok(Some(Symbol::Timer.into())))
+        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
+        .inclusive("RUN A", |_| Ok("A".into()))
+        .exclusive("RUN DEFAULT", |_| Ok(Default::default()))
+        .build()?;
+    let result_default = bpmn_default.run(Counter::default())?;
+    assert_eq!(
+        result.data.count, result_default.data.count,
+        "None and Default::default() should produce identical results"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn task_fork() -> Result<()> {
+    let bpmn = Process::new("tests/files/task_fork.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 6);
+    Ok(())
+}
+
+#[test]
+fn fork_explosion() -> Result<()> {
+    let bpmn = Process::new("tests/files/fork_explosion.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 33);
+    Ok(())
+}
+
+#[test]
+fn process_end_with_symbol() -> Result<()> {
+    let bpmn = Process::new("tests/files/process_end_with_symbol.bpmn")?.build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 0);
+    Ok(())
+}
+
+#[test]
+fn inclusive_gateway_not_all_joined() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_gateway_not_all_joined.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("RUN ALL", |_| Ok(vec!["A", "B"].into()))
+        .exclusive("RUN C", |_| Ok(Some("C")))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn parallel_gateway_not_all_joined() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_gateway_not_all_joined.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 4);
+    Ok(())
+}
+
+#[test]
+fn parallel_gateway_not_all_joined_inverse() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_gateway_not_all_joined_inverse.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 4);
+    Ok(())
+}
+
+#[test]
+fn parallel_multi() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_multi.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 7);
+    Ok(())
+}
+
+#[test]
+fn run_trace() -> Result<()> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let parents = Arc::new(Mutex::new(HashSet::new()));
+    let task = |count| {
+        let seen = Arc::clone(&seen);
+        move |ctx: &Context, input: Data<Counter>| {
+            let trace = ctx.trace().expect("trace context");
+            seen.lock().unwrap().push((
+                ctx.token(),
+                trace.trace_id().to_string(),
+                trace.baggage("tenant").map(ToString::to_string),
+            ));
+            input.lock().unwrap().count += count;
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task_with_context(COUNT_1, task(1))
+        .task_with_context(COUNT_2, task(2))
+        .task_with_context(COUNT_3, task(3))
+        .task_with_context(COUNT_4, task(4))
+        .listener({
+            let parents = Arc::clone(&parents);
+            move |event| {
+                parents
+                    .lock()
+                    .unwrap()
+                    .insert((event.token, event.parent_token));
+            }
+        })
+        .build()?;
+
+    let trace = TraceContext::new("trace-1").with_baggage("tenant", "acme");
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().trace(&trace))?;
+    assert_eq!(result.data.count, 10);
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 4);
+    assert!(seen.iter().all(|(_, trace_id, tenant)| trace_id == "trace-1"
+        && tenant.as_deref() == Some("acme")));
+
+    // Every token except the first was forked or joined by another token
+    let parents = parents.lock().unwrap();
+    assert!(parents.contains(&(0, None)));
+    assert!(
+        parents
+            .iter()
+            .filter(|(token, _)| *token != 0)
+            .all(|(token, parent)| parent.is_some_and(|parent| parent < *token))
+    );
+    assert!(parents.len() > 1);
+    Ok(())
+}
+
+#[test]
+fn correlation_id() -> Result<()> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let task = |count| {
+        let seen = Arc::clone(&seen);
+        move |ctx: &Context, input: Data<Counter>| {
+            seen.lock().unwrap().push((
+                ctx.correlation_id().map(ToString::to_string),
+                ctx.metadata("tenant").map(ToString::to_string),
+            ));
+            input.lock().unwrap().count += count;
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task_with_context(COUNT_1, task(1))
+        .task_with_context(COUNT_2, task(2))
+        .task_with_context(COUNT_3, task(3))
+        .task_with_context(COUNT_4, task(4))
+        .build()?;
+
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default()
+            .correlation_id("order-42")
+            .metadata("tenant", "acme"),
+    )?;
+    assert_eq!(result.data.count, 10);
+    assert_eq!(result.correlation_id.as_deref(), Some("order-42"));
+    assert_eq!(
+        result.metadata.get("tenant").map(String::as_str),
+        Some("acme")
+    );
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 4);
+    assert!(
+        seen.iter()
+            .all(|(id, tenant)| id.as_deref() == Some("order-42")
+                && tenant.as_deref() == Some("acme"))
+    );
+    drop(seen);
+
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.correlation_id, None);
+    assert!(result.metadata.is_empty());
+    Ok(())
+}
+
+#[test]
+fn task_workload() -> Result<()> {
+    let threads = Arc::new(Mutex::new(Vec::new()));
+    let task = |count| {
+        let threads = Arc::clone(&threads);
+        move |input: Data<Counter>| {
+            threads
+                .lock()
+                .unwrap()
+                .push(std::thread::current().name().map(ToString::to_string));
+            input.lock().unwrap().count += count;
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, task(1))
+        .task(COUNT_2, task(2))
+        .task(COUNT_3, task(3))
+        .task(COUNT_4, task(4))
+        .workload(COUNT_2, Workload::Io)
+        .workload(COUNT_3, Workload::Io)
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 10);
+
+    let io = threads
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|name| name.as_deref() == Some("snurr-io"))
+        .count();
+    assert_eq!(io, if cfg!(feature = "parallel") { 2 } else { 0 });
+    Ok(())
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn io_threads() -> Result<()> {
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let bpmn = Process::new("tests/files/parallel_multi.bpmn")?
+        .task(COUNT_1, {
+            let running = Arc::clone(&running);
+            let most = Arc::clone(&most);
+            move |input: Data<Counter>| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                input.lock().unwrap().count += 1;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(None)
+            }
+        })
+        .workload(COUNT_1, Workload::Io)
+        .io_threads(1)
+        .build()?;
+
+    // The threads are shared by the runs of the process
+    std::thread::scope(|scope| {
+        let runs: Vec<_> = (0..2)
+            .map(|_| scope.spawn(|| bpmn.run(Counter::default())))
+            .collect();
+        runs.into_iter()
+            .try_for_each(|run| run.join().unwrap().map(|_| ()))
+    })?;
+    assert_eq!(most.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[test]
+fn concurrency_limit() -> Result<()> {
+    let running = Arc::new(AtomicUsize::new(0));
+    let most = Arc::new(AtomicUsize::new(0));
+    let bpmn = Process::new("tests/files/parallel_multi.bpmn")?
+        .task(COUNT_1, {
+            let running = Arc::clone(&running);
+            let most = Arc::clone(&most);
+            move |input: Data<Counter>| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                input.lock().unwrap().count += 1;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(None)
+            }
+        })
+        .concurrency(COUNT_1, 2)
+        .build()?;
+
+    // The limit is shared by the runs of the process
+    std::thread::scope(|scope| {
+        let runs: Vec<_> = (0..2)
+            .map(|_| scope.spawn(|| bpmn.run(Counter::default())))
+            .collect();
+        runs.into_iter()
+            .try_for_each(|run| run.join().unwrap().map(|_| ()))
+    })?;
+    assert!(most.load(Ordering::SeqCst) <= 2);
+    Ok(())
+}
+
+#[test]
+fn parallel_join_fork() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_join_fork.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 6);
+    Ok(())
+}
+
+#[test]
+fn branch_data() -> Result<()> {
+    let merged = |file, count| -> Result<()> {
+        let result = Process::new(file)?
+            .task(COUNT_1, func_cnt(1))
+            .branch_data(
+                |_| Counter::default(),
+                |joined: &mut Counter, branch| joined.count += branch.count,
+            )
+            .build()?
+            .run(Counter::default())?;
+        assert_eq!(result.data.count, count, "{file}");
+        Ok(())
+    };
+    merged("tests/files/parallel_multi.bpmn", 7)?;
+    merged("tests/files/parallel_join_fork.bpmn", 6)?;
+    merged("tests/files/parallel_parallel_join_fork.bpmn", 23)?;
+
+    // Every branch starts from its own data and the branches are merged at the join
+    let result = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, |input: Data<Counter>| {
+            let mut data = input.lock().unwrap();
+            assert_eq!(data.count, 0);
+            data.count += 2;
+            Ok(None)
+        })
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .branch_data(
+            |_| Counter::default(),
+            |joined, branch| joined.count += branch.count,
+        )
+        .build()?
+        .run(Counter { count: 100 })?;
+    assert_eq!(result.data.count, 110);
+    Ok(())
+}
+
+#[test]
+fn parallel_parallel_join_fork() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_parallel_join_fork.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 23);
+    Ok(())
+}
+
+#[test]
+fn parallel_one_in_and_out() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_one_in_and_out.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 1);
+    Ok(())
+}
+
+#[test]
+fn conditional_sequence_flows() -> Result<()> {
+    let failed = Process::<Counter>::new("tests/files/conditional_sequence_flows.bpmn").is_err();
+    assert!(failed, "Expected an error");
+    Ok(())
+}
+
+#[test]
+fn exclusive_gateway_merging_branching() -> Result<()> {
+    let bpmn = Process::new("tests/files/exclusive_gateway_merging_branching.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .exclusive("BRANCHING", |_| Ok(A))
+        .exclusive("MERGE AND BRANCH", |_| Ok(B))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn event_gateway() -> Result<()> {
+    let bpmn = Process::new("tests/files/event_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task("Investigate", |_| Ok(None))
+        .event_based("JUNIOR GATEKEEPER", |_| {
+            Ok(("Investigate", Symbol::Message).into())
+        })
+        .event_based("SENIOR GATEKEEPER", |_| {
+            Ok(("Sleeping", Symbol::Timer).into())
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    Ok(())
+}
+
+#[test]
+fn single_flow() -> Result<()> {
+    let bpmn = Process::new("tests/files/single_flow.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("GW A", |_| Ok(A))
+        .exclusive("GW B", |_| Ok(A))
+        .exclusive("GW C", |_| Ok(A))
+        .exclusive("GW D", |_| Ok(A))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 18);
+    Ok(())
+}
+
+#[test]
+fn terminate_event() -> Result<()> {
+    let bpmn = Process::new("tests/files/terminate_event.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Terminate?", |_| Ok(Some("YES")))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    // NOTE 2 or 3 is OK result. The order in concurrent scenarios might differ.
+    assert!(matches!(result.data.count, 2 | 3));
+    Ok(())
+}
+
+#[test]
+fn terminate_event_sub_process() -> Result<()> {
+    let bpmn = Process::new("tests/files/terminate_event_sub_process.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Terminate?", |_| Ok(Some("YES")))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    // NOTE 4 or 5 is OK result. The order in concurrent scenarios might differ.
+    assert!(matches!(result.data.count, 4 | 5));
+    Ok(())
+}
+
+#[test]
+fn startevent_not_first() -> Result<()> {
+    // StartEvent out of order in XML file
+    let bpmn = Process::new("tests/files/startevent_not_first.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 4);
+    Ok(())
+}
+
+#[test]
+fn process_multiple_startevent_none() -> Result<()> {
+    match Process::<Counter>::new("tests/files/process_multiple_startevent_none.bpmn") {
+        Err(error) => assert!(
+            matches!(error, Error::BpmnRequirement(_)),
+            "Expected BpmnRequirement"
+        ),
+        _ => panic!("Expected an error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn run_from_event() -> Result<()> {
+    let bpmn = Process::new("tests/files/alternative_start_events.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+
+    // The start event of type none
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 1);
+
+    // Message start event by name and timer start event by id
+    let result = bpmn.run_from_event("Order received", Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    let result = bpmn.run_from_event("Event_1i7s8mw", Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    assert!(matches!(
+        bpmn.run_from_event("Payment received", Counter::default()),
+        Err(Error::MissingStartEventName(name)) if name == "Payment received"
+    ));
+    Ok(())
+}
+
+#[test]
+fn process_test_harness() -> Result<()> {
+    // No functions are registered. The gateway is forced to leave the loop.
+    let result = ProcessTest::new(Process::<Counter>::new("examples/example.bpmn")?)
+        .force_exclusive("equal to 3", "YES")
+        .expect_visits([
+            "Begin process",
+            "count",
+            "Count 1",
+            "control",
+            "equal to 3",
+            "YES",
+            "End process",
+        ])
+        .expect_end("End process")
+        .run(Counter::default())?;
+    assert_eq!(result.data.count, 0);
+
+    // Registered functions are kept and the stubbed gateway takes its first flow
+    let result = ProcessTest::new(
+        Process::new("tests/files/inclusive_gateway_split_end.bpmn")?.task(COUNT_1, func_cnt(1)),
+    )
+    .force_inclusive("Gateway_0jgakfl", vec!["YES", "NO"])
+    .run(Counter::default())?;
+    assert_eq!(result.data.count, 1);
+    assert_eq!(result.end_nodes.len(), 2);
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "visited elements differ")]
+fn process_test_harness_wrong_path() {
+    let _ = ProcessTest::new(Process::<Counter>::new("examples/example.bpmn").unwrap())
+        .force_exclusive("equal to 3", "YES")
+        .expect_visits(["Begin process", "NO"])
+        .run(Counter::default());
+}
+
+#[test]
+fn coverage() -> Result<()> {
+    let coverage = Coverage::default();
+    let bpmn = Process::<u32>::new("examples/example.bpmn")?
+        .task(COUNT_1, |input| {
+            *input.lock().unwrap() += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input| {
+            Ok(Some(if *input.lock().unwrap() >= 3 {
+                "YES"
+            } else {
+                "NO"
+            }))
+        })
+        .listener(coverage.listener())
+        .build()?;
+
+    bpmn.run(5)?;
+    let report = coverage.report(&bpmn);
+    assert!(!report.is_complete());
+    let uncovered: Vec<_> = report
+        .uncovered()
+        .filter_map(|item| item.name.as_deref())
+        .collect();
+    assert_eq!(uncovered, ["NO"]);
+    assert!(
+        report
+            .to_string()
+            .contains(r#"uncovered SequenceFlow Flow_0rsqhpi "NO""#)
+    );
+    assert!(
+        report
+            .to_json()
+            .contains(r#""name":"NO","type":"SequenceFlow","hits":0"#)
+    );
+
+    // Collected across runs
+    bpmn.run(0)?;
+    let report = coverage.report(&bpmn);
+    assert!(report.is_complete(), "{report}");
+    assert_eq!(report.covered(), report.items.len());
+    Ok(())
+}
+
+#[test]
+fn build_lenient() -> Result<()> {
+    assert!(matches!(
+        Process::<Counter>::new("tests/files/showcase.bpmn")?.build(),
+        Err(Error::MissingImplementations(_))
+    ));
+
+    // Exclusive and inclusive gateways take the default or first flow
+    let bpmn = Process::new("tests/files/showcase.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build_lenient()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 13);
+
+    // Event based gateways take the event of the first flow
+    let bpmn = Process::<Counter>::new("tests/files/event_gateway.bpmn")?.build_lenient()?;
+    bpmn.run(Counter::default())?;
+    Ok(())
+}
+
+#[test]
+fn data_associations() -> Result<()> {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let process = Process::new("tests/files/data_objects.bpmn")?;
+    assert!(process.parse_warnings().is_empty());
+
+    let names = |references: &[DataReference]| -> Vec<(String, DataKind)> {
+        references
+            .iter()
+            .map(|reference| (reference.name.clone().unwrap_or_default(), reference.kind))
+            .collect()
+    };
+    let bpmn = {
+        let seen_load = Arc::clone(&seen);
+        let seen_ship = Arc::clone(&seen);
+        process
+            .task_with_context("Load order", move |ctx, _| {
+                seen_load
+                    .lock()
+                    .unwrap()
+                    .push((names(ctx.data_inputs()), names(ctx.data_outputs())));
+                Ok(None)
+            })
+            .task_with_context("Ship order", move |ctx, _| {
+                seen_ship
+                    .lock()
+                    .unwrap()
+                    .push((names(ctx.data_inputs()), names(ctx.data_outputs())));
+                Ok(None)
+            })
+            .build()?
+    };
+    bpmn.run(Counter::default())?;
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![
+            (
+                vec![("Orders DB".to_string(), DataKind::Store)],
+                vec![("Order".to_string(), DataKind::Object)]
+            ),
+            (vec![("Order".to_string(), DataKind::Object)], vec![]),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn call_activity() -> Result<()> {
+    #[derive(Debug, Default)]
+    struct Order {
+        start: u32,
+        counted: Option<u32>,
+    }
+
+    let child = Process::<u32>::new("examples/example.bpmn")?
+        .task(COUNT_1, |input| {
+            *input.lock().unwrap() += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input| match *input.lock().unwrap() {
+            value if value >= 3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+
+    let bpmn = Process::<Order>::new("tests/files/call_activity.bpmn")?
+        .call_activity(
+            "Count",
+            child,
+            |order| order.start,
+            |order, output| {
+                order.counted = Some(output.data);
+                (output.data > 3).then_some(Symbol::Error.into())
+            },
+        )
+        .build()?;
+
+    let result = bpmn.run(Order::default())?;
+    assert_eq!(result.data.counted, Some(3));
+    assert_eq!(result.end_node.name.as_deref(), Some("Counted"));
+
+    // The child starts from the data given by the parent and the boundary of the call activity is taken
+    let result = bpmn.run(Order {
+        start: 5,
+        ..Default::default()
+    })?;
+    assert_eq!(result.data.counted, Some(6));
+    assert_eq!(result.end_node.name.as_deref(), Some("Too many"));
+    Ok(())
+}
+
+#[test]
+fn data_lock() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_multi.bpmn")?
+        .data_lock::<RwLock<Counter>>()
+        .task(COUNT_1, |data| {
+            data.write().unwrap().count += 1;
+            Ok(None)
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 7);
+
+    let bpmn = Process::new("examples/example.bpmn")?
+        .data_lock::<RwLock<Counter>>()
+        .task(COUNT_1, |data| {
+            data.write().unwrap().count += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |data| match data.read().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+// Counts the exclusive accesses to the data
+struct CountingLock {
+    data: Mutex<Counter>,
+    locked: AtomicUsize,
+}
+
+impl SharedData<Counter> for CountingLock {
+    type Guard<'a> = MutexGuard<'a, Counter>;
+    type ReadGuard<'a> = MutexGuard<'a, Counter>;
+
+    fn new(data: Counter) -> Self {
+        Self {
+            data: Mutex::new(data),
+            locked: AtomicUsize::new(0),
+        }
+    }
+
+    fn lock(&self) -> LockResult<MutexGuard<'_, Counter>> {
+        self.locked.fetch_add(1, Ordering::Relaxed);
+        self.data.lock()
+    }
+
+    fn read(&self) -> LockResult<MutexGuard<'_, Counter>> {
+        self.data.lock()
+    }
+
+    fn into_inner(self) -> LockResult<Counter> {
+        self.data.into_inner()
+    }
+}
+
+#[test]
+fn custom_data_lock() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .data_lock::<CountingLock>()
+        .task(COUNT_1, |data| {
+            let mut counter = data.lock().unwrap();
+            counter.count = data.locked.load(Ordering::Relaxed) as u32;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |data| match data.read().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    // Only the task locks the data, the gateway and the engine read it
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[cfg(not(feature = "parallel"))]
+#[test]
+fn sequential() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .sequential()
+        .task_mut(COUNT_1, |data: &mut Counter| {
+            data.count += 1;
+            Ok(None)
+        })
+        .exclusive_mut("equal to 3", |data| match data.count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    // A panic doesn't poison the data
+    let bpmn = Process::new("tests/files/one_task.bpmn")?
+        .sequential()
+        .catch_panics()
+        .task_mut(COUNT_1, |data: &mut Counter| {
+            data.count += 1;
+            panic!("counted");
+        })
+        .build()?;
+    let snapshot = bpmn
+        .run_resumable(Counter::default(), RunOptions::default())
+        .unwrap_err();
+    assert_eq!(snapshot.data.read().unwrap().count, 1);
+    Ok(())
+}
+
+#[test]
+fn runner() -> Result<()> {
+    let runner = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |input: Data<Counter>| {
+            match input.lock().unwrap().count {
+                3 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            }
+        })
+        .build()?
+        .runner(4, 2)?;
+
+    for count in 0..50 {
+        assert_eq!(runner.submit(Counter { count: count % 3 })?, count as u64);
+    }
+    let first = runner.recv().expect("completed instance");
+    assert_eq!(runner.pending(), 49);
+
+    let mut ids = vec![first.id];
+    for output in runner.finish() {
+        assert_eq!(output.result?.data.count, 3);
+        ids.push(output.id);
+    }
+    ids.sort_unstable();
+    assert_eq!(ids, (0..50).collect::<Vec<_>>());
+    Ok(())
+}
+
+#[test]
+fn runner_shared() -> Result<()> {
+    let build = || {
+        Process::new("examples/example.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .exclusive("equal to 3", |input: Data<Counter>| {
+                match input.lock().unwrap().count {
+                    3 => Ok(Some("YES")),
+                    _ => Ok(Some("NO")),
+                }
+            })
+            .build()
+    };
+
+    // Submit from several threads
+    let runner = build()?.runner(2, 4)?;
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..10 {
+                    runner.submit(Counter::default()).unwrap();
+                }
+            });
+        }
+    });
+    let mut ids = runner.finish().map(|output| output.id).collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, (0..40).collect::<Vec<_>>());
+
+    // Completed instances are sent to the channel
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let runner = build()?.runner_with_results(2, 4, sender)?;
+    for _ in 0..10 {
+        runner.submit(Counter::default())?;
+    }
+    assert!(runner.recv().is_none());
+    for output in receiver.iter().take(10) {
+        assert_eq!(output.result?.data.count, 3);
+    }
+    assert_eq!(runner.pending(), 0);
+    Ok(())
+}
+
+#[test]
+fn run_batch() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?;
+
+    let results = bpmn.run_batch((0..20).map(|count| Counter { count }));
+    assert_eq!(results.len(), 20);
+    for (count, result) in (0..20).zip(results) {
+        assert_eq!(result?.data.count, count + 10);
+    }
+    Ok(())
+}
+
+#[test]
+fn registry() -> Result<()> {
+    let version = |count| -> Result<Process<Counter, Run>> {
+        Process::new("tests/files/parallell_gateway.bpmn")?
+            .task(COUNT_1, func_cnt(count))
+            .task(COUNT_2, func_cnt(2))
+            .task(COUNT_3, func_cnt(3))
+            .task(COUNT_4, func_cnt(4))
+            .build()
+    };
+    let registry = Registry::new();
+    registry.deploy("order", 1, version(1)?)?;
+    registry.deploy("order", 2, version(100)?)?;
+    registry.deploy("invoice", 1, version(1)?)?;
+    assert!(matches!(
+        registry.deploy("order", 2, version(1)?),
+        Err(Error::AlreadyDeployed(_))
+    ));
+
+    assert_eq!(registry.versions("order"), vec![1, 2]);
+    assert_eq!(registry.latest("order"), Some(2));
+    assert_eq!(
+        registry.deployed(),
+        vec![
+            ("invoice".into(), 1),
+            ("order".into(), 1),
+            ("order".into(), 2)
+        ]
+    );
+
+    assert_eq!(
+        registry
+            .start("order", None, Counter::default())?
+            .data
+            .count,
+        109
+    );
+    assert_eq!(
+        registry
+            .start("order", Some(1), Counter::default())?
+            .data
+            .count,
+        10
+    );
+    assert!(matches!(
+        registry.start("order", Some(3), Counter::default()),
+        Err(Error::MissingProcessData(_))
+    ));
+
+    assert!(registry.undeploy("order", 2).is_some());
+    assert_eq!(registry.latest("order"), Some(1));
+    Ok(())
+}
+
+#[test]
+fn reload() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 10);
+
+    let content = std::fs::read_to_string("tests/files/parallell_gateway.bpmn")?;
+    let path = std::env::temp_dir().join(format!("snurr_reload_{}.bpmn", std::process::id()));
+
+    // Count 4 is renamed to a task without a function
+    std::fs::write(&path, content.replace(r#""Count 4""#, r#""Count 5""#))?;
+    assert!(matches!(
+        bpmn.reload(&path),
+        Err(Error::MissingImplementations(missing)) if missing.contains("Count 5")
+    ));
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 10);
+
+    std::fs::write(&path, content.replace(r#""Count 4""#, r#""Count 3""#))?;
+    let reloaded = bpmn.reload(&path)?;
+    assert_eq!(reloaded.run(Counter::default())?.data.count, 9);
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 10);
+
+    // A lenient process gets a stub for the new task
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .build_lenient()?;
+    std::fs::write(&path, content.replace(r#""Count 4""#, r#""Count 5""#))?;
+    assert_eq!(bpmn.reload(&path)?.run(Counter::default())?.data.count, 3);
+    std::fs::remove_file(path)?;
+
+    // The parse options of the process are used again
+    let path = "tests/files/conditional_sequence_flows.bpmn";
+    let bpmn = Process::<Counter>::new_with_options(path, ParseOptions::default().lenient())?
+        .build_lenient()?;
+    bpmn.reload(path)?;
+    Ok(())
+}
+
+#[test]
+fn diagram_builder() -> Result<()> {
+    let diagram = DiagramBuilder::start()
+        .task(COUNT_1)
+        .exclusive("equal to 3", ["YES", "NO"])
+        .flow("YES")
+        .parallel("Fork")
+        .task(COUNT_2)
+        .parallel("Join")
+        .end_named("Done")
+        .from("Fork")
+        .task(COUNT_3)
+        .goto("Join")
+        .flow("NO")
+        .goto(COUNT_1);
+
+    let bpmn = Process::from_builder(diagram)?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .exclusive("equal to 3", |input: Data<Counter>| {
+            match input.lock().unwrap().count {
+                3 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            }
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 8);
+    assert_eq!(result.end_node.name.as_deref(), Some("Done"));
+
+    // The NO flow is never connected
+    assert!(matches!(
+        Process::<Counter>::from_builder(
+            DiagramBuilder::start()
+                .exclusive("equal to 3", ["YES", "NO"])
+                .flow("YES")
+                .end()
+        ),
+        Err(Error::Builder(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn elements() -> Result<()> {
+    let bpmn = Process::<()>::new("tests/files/call_activity.bpmn")?;
+    let elements = bpmn.elements();
+    assert_eq!(elements.len(), 8);
+    assert!(
+        elements
+            .iter()
+            .all(|element| element.process == "Process_0call")
+    );
+
+    let activity = elements
+        .iter()
+        .find(|element| element.id == "Activity_1")
+        .unwrap();
+    assert_eq!(activity.name, Some("Count"));
+    assert_eq!(activity.kind, ElementType::CallActivity);
+    assert_eq!(activity.incoming, vec!["Flow_1"]);
+    assert_eq!(activity.outgoing, vec!["Flow_2"]);
+    assert_eq!(activity.boundaries, vec!["Event_2"]);
+
+    let boundary = elements
+        .iter()
+        .find(|element| element.id == "Event_2")
+        .unwrap();
+    assert_eq!(boundary.kind, ElementType::BoundaryEvent);
+    assert_eq!(boundary.symbol, Some(&Symbol::Error));
+    assert_eq!(boundary.attached_to, Some("Activity_1"));
+
+    let flow = elements
+        .iter()
+        .find(|element| element.id == "Flow_3")
+        .unwrap();
+    assert_eq!(flow.incoming, vec!["Event_2"]);
+    assert_eq!(flow.outgoing, vec!["Event_3"]);
+    Ok(())
+}
+
+#[test]
+fn subprocess_multiple_startevent_none() -> Result<()> {
+    match Process::<Counter>::new("tests/files/subprocess_multiple_startevent_none.bpmn") {
+        Err(error) => assert!(
+            matches!(error, Error::BpmnRequirement(_)),
+            "Expected BpmnRequirement"
+        ),
+        _ => panic!("Expected an error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn cancel_transaction() -> Result<()> {
+    // An cancel end event terminates the transaction and use the cancel boundary.
+    let bpmn = Process::new("tests/files/cancel_transaction.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Cancel?", |_| Ok(Some("YES")))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    // NOTE 2 or 3 is OK result. The order in concurrent scenarios might differ.
+    assert!(matches!(result.data.count, 3 | 4));
+    Ok(())
+}
+
+#[test]
+fn parallel_stalled_execution() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_stalled_execution.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Message?", |_| Ok(Some("YES")))
+        .build()?;
+
+    match bpmn.run(Counter::default()) {
+        Err(error) => assert!(
+            matches!(error, Error::BpmnRequirement(_)),
+            "Expected BpmnRequirement"
+        ),
+        _ => panic!("Expected an error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn parallel_unbalanced() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_unbalanced.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 5);
+    Ok(())
+}
+
+#[test]
+fn parallel_unbalanced2() -> Result<()> {
+    let bpmn = Process::new("tests/files/parallel_unbalanced2.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 6);
+    Ok(())
+}
+
+#[test]
+fn inclusive_unbalanced() -> Result<()> {
+    let bpmn = Process::new("tests/files/inclusive_unbalanced.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("FORK", |_| Ok(vec!["A", "B", "C"].into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 5);
+
+    // Only the inner join gets a token and the outer join proceeds with one token
+    let bpmn = Process::new("tests/files/inclusive_unbalanced.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .inclusive("FORK", |_| Ok(vec!["B", "C"].into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 4);
+    Ok(())
+}
+
+#[test]
+fn process_output_contains_end_node_info() -> Result<()> {
+    let bpmn = Process::new("tests/files/one_task.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    // Verify we get the data
+    assert_eq!(result.data.count, 1);
+
+    // Verify we get end node information
+    assert!(
+        !result.end_node.id.is_empty(),
+        "End node ID should not be empty"
+    );
+
+    // Regular end event should have no symbol
+    assert_eq!(result.end_node.symbol, Symbol::None);
+
+    Ok(())
+}
+
+#[test]
+fn process_output_message_end_event() -> Result<()> {
+    let bpmn = Process::new("tests/files/process_end_with_symbol.bpmn")?.build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    // Verify the data
+    assert_eq!(result.data.count, 0);
+
+    // Verify the end event symbol is captured
+    assert_eq!(
+        result.end_node.symbol,
+        Symbol::Message,
+        "Expected Message end event symbol"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn process_output_terminate_end_event() -> Result<()> {
+    let bpmn = Process::new("tests/files/terminate_event.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Terminate?", |_| Ok(Some("YES")))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+
+    // Verify the data (2 or 3 is OK due to concurrent execution)
+    assert!(matches!(result.data.count, 2 | 3));
+
+    // Verify the end event symbol is Terminate
+    assert_eq!(
+        result.end_node.symbol,
+        Symbol::Terminate,
+        "Expected Terminate end event symbol"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn process_catalog_load_dir() -> Result<()> {
+    let catalog = ProcessCatalog::load_dir("tests/files")?;
+
+    // Parse errors and duplicated process ids are reported, not returned.
+    let diagnostics = catalog.diagnostics();
+    assert!(diagnostics.iter().any(|diagnostic| {
+        diagnostic
+            .path
+            .ends_with("process_multiple_startevent_none.bpmn")
+    }));
+    assert!(
+        diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message.contains("Process_1"))
+    );
+    assert!(catalog.summary().contains("diagnostic(s)"));
+
+    // two_boundary.bpmn is the only file with this process id
+    assert!(
+        catalog
+            .path("Process_0ufiu7y")
+            .is_some_and(|path| path.ends_with("two_boundary.bpmn"))
+    );
+    let bpmn = catalog
+        .process("Process_0ufiu7y")?
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    assert!(matches!(
+        catalog.process::<Counter>("Unknown"),
+        Err(Error::MissingProcessData(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn parallel_join_deadlock() -> Result<()> {
+    let result = Process::new("tests/files/parallel_join_deadlock.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("CHOOSE", |_| Ok(Some("YES")))
+        .build();
+
+    match result {
+        Err(Error::Deadlock(message)) => {
+            assert!(message.contains("JOIN") && message.contains("CHOOSE"))
+        }
+        _ => panic!("Expected a deadlock error"),
+    }
+
+    let bpmn: Process<Counter> = Process::new("tests/files/parallel_join_deadlock.bpmn")?;
+    assert!(
+        bpmn.validate()
+            .iter()
+            .any(|diagnostic| diagnostic.kind == DiagnosticKind::JoinDeadlock)
+    );
+
+    // The join can receive enough tokens, so it fails at runtime instead
+    Process::<Counter>::new("tests/files/parallel_stalled_execution.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("Message?", |_| Ok(Some("YES")))
+        .build()?;
+    Ok(())
+}
+
+#[test]
+fn validate_diagram() -> Result<()> {
+    let bpmn: Process<Counter> = Process::new("tests/files/validation.bpmn")?;
+    let diagnostics = bpmn.validate();
+    let found = |kind: DiagnosticKind, name: &str| {
+        diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.kind == kind && diagnostic.name.as_deref() == Some(name))
+    };
+
+    assert!(found(DiagnosticKind::Unreachable, "Orphan"));
+    assert!(found(DiagnosticKind::MissingDefault, "CHOOSE"));
+    assert!(found(DiagnosticKind::EventBasedSingleOutput, "WAIT"));
+    assert!(found(DiagnosticKind::Unsupported, "COMPLEX"));
+    assert!(found(DiagnosticKind::Unsupported, COUNT_1));
+    assert!(!found(DiagnosticKind::Unreachable, COUNT_1));
+    assert_eq!(diagnostics.len(), 6);
+    assert!(
+        diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    );
+
+    let bpmn: Process<Counter> = Process::new("tests/files/two_boundary.bpmn")?;
+    assert!(bpmn.validate().is_empty());
+    Ok(())
+}
+
+#[test]
+fn validate_links_across_processes() -> Result<()> {
+    let bpmn: Process<Counter> = Process::new("tests/files/link_processes.bpmn")?;
+    let diagnostics = bpmn.validate();
+    let link = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.kind == DiagnosticKind::UnpairedLink)
+        .unwrap();
+    assert_eq!(link.severity, Severity::Error);
+    assert_eq!(link.name.as_deref(), Some("To page 2"));
+    assert!(link.message.contains(r#"The catch event is in "Page 2""#));
+
+    // A link from a sub-process to its parent
+    let bpmn: Process<Counter> = Process::new("tests/files/subprocess_external_link_fail.bpmn")?;
+    assert!(bpmn.validate().iter().any(|diagnostic| {
+        diagnostic.kind == DiagnosticKind::UnpairedLink
+            && diagnostic.name.as_deref() == Some("Link 2")
+    }));
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Approval {
+    Done,
+    Escalated,
+}
+
+impl Outcome for Approval {
+    fn from_end_node(end_node: &EndNode) -> Option<Self> {
+        match end_node.name.as_deref()? {
+            "DONE" => Some(Approval::Done),
+            "ESCALATED" => Some(Approval::Escalated),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn typed_outcomes() -> Result<()> {
+    let bpmn = Process::<Counter>::new("tests/files/idle_user_task.bpmn")?
+        .task("Approve", |_| {
+            Ok(Some(("Too slow", Symbol::Escalation).into()))
+        })
+        .outcomes::<Approval>()
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.outcome, Approval::Escalated);
+
+    let result = Process::<Counter>::new("examples/example.bpmn")?
+        .task(COUNT_1, |_| Ok(None))
+        .exclusive("equal to 3", |_| Ok(Some("YES")))
+        .outcomes::<Approval>()
+        .build();
+    assert!(matches!(result, Err(Error::MissingOutcomes(_))));
+    Ok(())
+}
+
+#[test]
+fn run_timeout() -> Result<()> {
+    // Never leaves the loop
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            input.lock().unwrap().count += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |_| Ok(Some("NO")))
+        .build()?;
+
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().timeout(std::time::Duration::from_millis(20)),
+    );
+    match result {
+        Err(Error::Timeout(active)) => assert!(!active.is_empty()),
+        _ => panic!("expected timeout"),
+    }
+    Ok(())
+}
+
+#[test]
+fn subprocess_end_in_boundary() -> Result<()> {
+    let bpmn = Process::new("tests/files/subprocess_error_message_end.bpmn")?
+        .task(COUNT_1, |_| Ok(Some(("Overflow", Symbol::Error).into())))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .boundary("Overflow", |ctx, _: Data<Counter>| {
+            assert!(ctx.subprocess_end().is_none());
+            Ok(())
+        })
+        .boundary("B7", |ctx, _| {
+            let end_node = ctx.subprocess_end().expect("subprocess end node");
+            assert_eq!(end_node.id, "Event_1pwwg7u");
+            assert_eq!(end_node.name.as_deref(), Some("B7"));
+            assert_eq!(end_node.symbol, Symbol::Message);
+            Ok(())
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 5);
+    Ok(())
+}
+
+#[test]
+fn task_retry() -> Result<()> {
+    let build = |policy: RetryPolicy| {
+        let attempts = Arc::new(Mutex::new(0));
+        Process::new("examples/example.bpmn")?
+            .task(COUNT_1, move |input: Data<Counter>| {
+                let mut attempts = attempts.lock().unwrap();
+                *attempts += 1;
+                if *attempts < 3 {
+                    return Err(Error::ProcessExecution("transient".into()));
+                }
+                input.lock().unwrap().count += 1;
+                Ok(None)
+            })
+            .exclusive("equal to 3", |_| Ok(Some("YES")))
+            .retry(COUNT_1, policy)
+            .build()
+    };
+
+    let policy = RetryPolicy::new(3).backoff(Backoff::Exponential {
+        initial: std::time::Duration::from_millis(1),
+        max: std::time::Duration::from_millis(2),
+    });
+    let result = build(policy)?.run(Counter::default())?;
+    assert_eq!(result.data.count, 1);
+
+    let result = build(RetryPolicy::new(2))?.run(Counter::default());
+    assert!(matches!(result, Err(Error::ProcessExecution(_))));
+
+    let result = build(RetryPolicy::new(3).retry_on(|_| false))?.run(Counter::default());
+    assert!(matches!(result, Err(Error::ProcessExecution(_))));
+    Ok(())
+}
+
+#[test]
+fn task_retry_cancelled() -> Result<()> {
+    use std::time::{Duration, Instant};
+
+    let attempts = Arc::new(Mutex::new(0));
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, {
+            let attempts = Arc::clone(&attempts);
+            move |_: Data<Counter>| {
+                *attempts.lock().unwrap() += 1;
+                Err(Error::ProcessExecution("transient".into()))
+            }
+        })
+        .exclusive("equal to 3", |_| Ok(Some("YES")))
+        .retry(
+            COUNT_1,
+            RetryPolicy::new(3).backoff(Backoff::Fixed(Duration::from_secs(60))),
+        )
+        .build()?;
+
+    // The run stops waiting for the next attempt when the timeout passes
+    let start = Instant::now();
+    let result = bpmn.run_with(
+        Counter::default(),
+        RunOptions::default().timeout(Duration::from_millis(50)),
+    );
+    assert!(result.is_err());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(*attempts.lock().unwrap(), 1);
+
+    // Or when the run is cancelled
+    let cancel = CancelToken::default();
+    let start = Instant::now();
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            std::thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        let result = bpmn.run_with(
+            Counter::default(),
+            RunOptions::default().cancel_token(cancel.clone()),
+        );
+        assert!(result.is_err());
+    });
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(*attempts.lock().unwrap(), 2);
+    Ok(())
+}
+
+#[test]
+fn error_hook() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            let mut data = input.lock().unwrap();
+            data.count += 1;
+            if data.count == 2 {
+                return Err(Error::ProcessExecution("transient".into()));
+            }
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
+        .on_error(|ctx, _| {
+            assert_eq!(ctx.name(), Some(COUNT_1));
+            if ctx.attempt() < 2 {
+                ErrorDecision::Retry
+            } else {
+                ErrorDecision::Abort
+            }
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    let bpmn = Process::new("tests/files/two_boundary.bpmn")?
+        .task(COUNT_1, |_: Data<Counter>| {
+            Err(Error::ProcessExecution("failed".into()))
+        })
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .on_error(|_, _| ErrorDecision::Boundary(("Timeout", Symbol::Timer).into()))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    // Only tasks can continue with a boundary
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |_: Data<Counter>| {
+            Err(Error::ProcessExecution("failed".into()))
+        })
+        .on_error(|_, _| ErrorDecision::Boundary(("Timeout", Symbol::Timer).into()))
+        .build()?;
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::ProcessExecution(_))
+    ));
+
+    // A hook that always retries gives up with the error of the function
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            input.lock().unwrap().count += 1;
+            Err(Error::ProcessExecution("failed".into()))
+        })
+        .exclusive("equal to 3", |_| Ok(Some("YES")))
+        .on_error(|_, _| ErrorDecision::Retry)
+        .build()?;
+    match bpmn.run(Counter::default()) {
+        Err(error @ Error::ProcessExecution(_)) => assert!(error.to_string().contains("failed")),
+        _ => panic!("expected a process execution error"),
+    }
+    Ok(())
+}
+
+#[test]
+fn parallel_join_tokens_per_flow() -> Result<()> {
+    let build = |flows: Vec<&'static str>| {
+        Process::new("tests/files/inclusive_fork_parallel_join.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .task(COUNT_2, func_cnt(2))
+            .task(COUNT_3, func_cnt(3))
+            .inclusive("FORK", move |_| Ok(flows.clone().into()))
+            .duplicate_flows(DuplicateFlows::Allow)
+            .build()
+    };
+
+    // Every input needs a token. Two tokens on the same flow doesn't complete the join.
+    let result = build(vec!["YES", "YES"])?.run(Counter::default());
+    assert!(matches!(result, Err(Error::BpmnRequirement(_))));
+
+    // The join completes twice
+    let result = build(vec!["YES", "NO", "YES", "NO"])?.run(Counter::default())?;
+    assert_eq!(result.data.count, 12);
+    Ok(())
+}
+
+#[test]
+fn catch_panics() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            let mut data = input.lock().unwrap();
+            data.count += 1;
+            if data.count == 1 {
+                panic!("boom");
+            }
+            Ok(None)
+        })
+        .exclusive("equal to 3", |_| Ok(Some("YES")))
+        .catch_panics()
+        .build()?;
+    match bpmn.run(Counter::default()) {
+        Err(error @ Error::ProcessExecution(_)) => {
+            assert!(error.to_string().contains("Count 1 panicked: boom"))
+        }
+        _ => panic!("expected a process execution error"),
+    }
+
+    // The data lock is not poisoned when the function is called again
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, |input: Data<Counter>| {
+            let mut data = input.lock().unwrap();
+            data.count += 1;
+            if data.count == 1 {
+                panic!("boom");
+            }
+            Ok(None)
+        })
+        .exclusive("equal to 3", |_| Ok(Some("YES")))
+        .catch_panics()
+        .on_error(|_, _| ErrorDecision::Retry)
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 2);
+    Ok(())
+}
+
+#[test]
+fn diagram_cache() -> Result<()> {
+    let cache = DiagramCache::default();
+    for _ in 0..3 {
+        let bpmn = cache
+            .process::<Counter>("examples/example.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+                3 => Ok(Some("YES")),
+                _ => Ok(Some("NO")),
+            })
+            .build()?;
+        assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+    }
+    assert_eq!(cache.len(), 1);
+
+    // Same content from memory
+    let _: Process<Counter> = cache.parse(std::fs::read_to_string("examples/example.bpmn")?)?;
+    assert_eq!(cache.len(), 1);
+
+    let _: Process<Counter> = cache.process("tests/files/parallell_gateway.bpmn")?;
+    assert_eq!(cache.len(), 2);
+    cache.clear();
+    assert!(cache.is_empty());
+    Ok(())
+}
+
+#[test]
+fn exclusive_index() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_index("equal to 3", |input: Data<Counter>, flows| {
+            let flow = if input.lock().unwrap().count == 3 {
+                "YES"
+            } else {
+                "NO"
+            };
+            Ok(flows.iter().position(|name| *name == flow))
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_index("equal to 3", |_: Data<Counter>, flows| {
+            Ok(Some(flows.len()))
+        })
+        .build()?;
+    assert!(matches!(
+        bpmn.run(Counter::default()),
+        Err(Error::MissingOutput(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn extension_properties() -> Result<()> {
+    let bpmn = Process::new("tests/files/extension_properties.bpmn")?
+        .task_with_context(COUNT_1, |ctx, input: Data<Counter>| {
+            assert_eq!(ctx.property("url"), Some("https://example.com/count"));
+            assert_eq!(ctx.property("taskDefinition.type"), Some("counter"));
+            assert_eq!(ctx.property("taskDefinition.retries"), Some("5"));
+            assert_eq!(ctx.property("queue"), Some("counting"));
+            assert_eq!(ctx.properties().count(), 5);
+            let threshold: u32 = ctx.property("threshold").unwrap().parse().unwrap();
+            input.lock().unwrap().count += threshold;
+            Ok(None)
+        })
+        .task_with_context(COUNT_2, |ctx, _| {
+            assert_eq!(ctx.property("owner"), None);
+            assert_eq!(ctx.properties().count(), 0);
+            Ok(None)
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+    Ok(())
+}
+
+#[test]
+fn connectors() -> Result<()> {
+    fn add(config: &ConnectorConfig, input: Data<Counter>) -> Result<TaskResult> {
+        assert_eq!(config.connector(), "add");
+        let amount: u32 = config.require("amount")?.parse().unwrap();
+        input.lock().unwrap().count += amount;
+        Ok(None)
+    }
+
+    let bpmn = Process::new("tests/files/connector.bpmn")?
+        .connector("add", add)
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
+
+    // A registered task function is used instead of the connector
+    let bpmn = Process::new("tests/files/connector.bpmn")?
+        .connector("add", add)
+        .task("Add two", |input: Data<Counter>| {
+            input.lock().unwrap().count += 10;
+            Ok(None)
+        })
+        .build()?;
+    assert_eq!(bpmn.run(Counter::default())?.data.count, 11);
+
+    let report = Process::<Counter>::new("tests/files/connector.bpmn")?
+        .connector("mail", add)
+        .build_report();
+    assert_eq!(
+        report.missing,
+        ["ServiceTask: Add one", "ServiceTask: Add two"]
+    );
+    assert_eq!(report.unused, ["Connector: mail"]);
+
+    let result = Process::new("tests/files/connector.bpmn")?
+        .connector("add", |config: &ConnectorConfig, _: Data<Counter>| {
+            config.require("url")?;
+            Ok(None)
+        })
+        .build()?
+        .run(Counter::default());
+    assert!(
+        matches!(result, Err(Error::MissingConnectorProperty(key, id)) if key == "url" && id == "Activity_0one")
+    );
+    Ok(())
+}
+
+#[cfg(all(feature = "http", feature = "variables"))]
+#[test]
+fn http_connector() -> Result<()> {
+    use snurr::{HttpConnector, Variables, serde_json::json};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    // Answers two requests and returns them
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let server = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for response in [
+            "HTTP/1.1 201 Created\r\nContent-Length: 8\r\n\r\n{\"id\":7}",
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 7\r\n\r\nmissing",
+        ] {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = Vec::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+                request.push(line.trim_end().to_string());
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            request.push(String::from_utf8(body).unwrap());
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+
+    let bpmn = Process::<()>::new("tests/files/http_connector.bpmn")?
+        .connector("http", HttpConnector::new())
+        .build()?;
+    let variables = || {
+        Variables::from_iter([
+            ("port", json!(port)),
+            ("order", json!({ "id": 42 })),
+            ("customer", json!("ACME")),
+        ])
+    };
+    let result = bpmn.run_with((), RunOptions::default().variables(variables()))?;
+    assert_eq!(
+        result.variables.get("created"),
+        Some(json!({ "status": 201, "body": { "id": 7 } }))
+    );
+
+    // An error status is stored and doesn't fail the task
+    let result = bpmn.run_with((), RunOptions::default().variables(variables()))?;
+    assert_eq!(
+        result.variables.get("created"),
+        Some(json!({ "status": 404, "body": "missing" }))
+    );
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[0][0], "POST /orders/42 HTTP/1.1");
+    assert!(requests[0].contains(&"content-type: application/json".to_string()));
+    assert_eq!(requests[0].last().unwrap(), r#"{"customer": "ACME"}"#);
+
+    // The run fails when a variable of a template is missing
+    let result = bpmn.run(());
+    assert!(
+        matches!(result, Err(Error::ProcessExecution(error)) if error.to_string().contains("Unknown variable port"))
+    );
+    Ok(())
+}
+
+#[test]
+fn symbol_parse_and_positions() -> Result<()> {
+    for symbol in Symbol::ALL {
+        assert_eq!(symbol.to_string().parse::<Symbol>()?, symbol);
+    }
+    assert!(matches!(
+        "Unknown".parse::<Symbol>(),
+        Err(Error::UnknownSymbol(_))
+    ));
+    assert!(Symbol::Escalation.is_valid_for(EventPosition::Boundary));
+    assert!(!Symbol::Terminate.is_valid_for(EventPosition::Boundary));
+    assert_eq!(Symbol::Link.positions(), &[EventPosition::Intermediate]);
+    Ok(())
+}
+
+#[test]
+fn transition_hook() -> Result<()> {
+    // Redirect the loop back to the end
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |_| Ok(Some("NO")))
+        .on_transition(|transition| {
+            if transition.name == Some("equal to 3") {
+                assert_eq!(transition.flow, "NO");
+                assert_eq!(transition.outputs.len(), 2);
+                return TransitionDecision::Redirect("YES".into());
+            }
+            TransitionDecision::Continue
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 1);
+
+    let bpmn = Process::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |input| {
+            Ok(if input.lock().unwrap().count == 3 {
+                Some("YES")
+            } else {
+                Some("NO")
+            })
+        })
+        .on_transition(|transition| match transition.flow {
+            "YES" => TransitionDecision::Veto,
+            _ => TransitionDecision::Continue,
+        })
+        .build()?;
+    let result = bpmn.run(Counter::default());
+    assert!(matches!(result, Err(Error::TransitionVetoed(_, flow)) if flow == "YES"));
+    Ok(())
+}
+
+#[test]
+fn task_types() -> Result<()> {
+    let bpmn = Process::new("tests/files/extension_properties.bpmn")?
+        .task("counter", func_cnt(1))
+        .task("plain", func_cnt(2))
+        .task_types()
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
+
+    // Names are not used for tasks with a type
+    let result = Process::<Counter>::new("tests/files/extension_properties.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task_types()
+        .build();
+    assert!(matches!(result, Err(Error::MissingImplementations(_))));
+    Ok(())
+}
+
+#[test]
+fn debugger() -> Result<()> {
+    let recorder = DebugRecorder::default();
+    let bpmn = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .exclusive("equal to 3", |input: Data<Counter>| {
+            Ok(match input.lock().unwrap().count {
+                3 => Some("YES"),
+                _ => Some("NO"),
+            })
+        })
+        .listener_with_data(recorder.listener(|data: &Counter| data.count))
         .build()?;
-    let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 23);
+    bpmn.run(Counter::default())?;
+
+    let mut debugger = recorder.take();
+    assert!(!debugger.is_empty());
+    assert!(debugger.current().is_none());
+
+    // Data after the last step
+    assert_eq!(debugger.backward().map(|step| step.data), Some(3));
+
+    // Data when the task is entered the second time
+    debugger.seek(0);
+    debugger.forward_to(COUNT_1);
+    let step = debugger.forward_to(COUNT_1).unwrap();
+    assert_eq!(step.kind, ExecutionEventKind::Leave);
+    assert_eq!(step.data, 1);
+    let step = debugger.forward_to(COUNT_1).unwrap();
+    assert_eq!(step.kind, ExecutionEventKind::Enter);
+    assert_eq!(step.data, 1);
+    assert_eq!(debugger.backward().map(|step| step.data), Some(1));
+
+    debugger.seek(0);
+    assert!(debugger.backward().is_none());
+    assert_eq!(debugger.position(), Some(0));
+    assert!(recorder.take().is_empty());
     Ok(())
 }
 
+snurr::flow_names! {
+    enum Control {
+        Yes = "YES",
+        No = "NO",
+    }
+}
+
+snurr::flow_names! {
+    enum Renamed {
+        Done = "DONE",
+        No = "NO",
+    }
+}
+
 #[test]
-fn parallel_one_in_and_out() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_one_in_and_out.bpmn")?
+fn typed_flow_names() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .exclusive_flow("equal to 3", |input: Data<Counter>| {
+            Ok(Some(match input.lock().unwrap().count {
+                3 => Control::Yes,
+                _ => Control::No,
+            }))
+        })
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 1);
+    assert_eq!(result.data.count, 3);
+
+    // The diagram has no DONE flow
+    let result = Process::<Counter>::new("examples/example.bpmn")?
+        .task(COUNT_1, func_cnt(1))
+        .exclusive_flow("equal to 3", |_| Ok(Some(Renamed::No)))
+        .build();
+    assert!(matches!(result, Err(Error::UnknownFlows(flows)) if flows.ends_with("DONE")));
     Ok(())
 }
 
 #[test]
-fn conditional_sequence_flows() -> Result<()> {
-    let failed = Process::<Counter>::new("tests/files/conditional_sequence_flows.bpmn").is_err();
-    assert!(failed, "Expected an error");
+fn handbook() -> Result<()> {
+    let bpmn: Process<Counter> = Process::new("tests/files/extension_properties.bpmn")?;
+    let pages = bpmn.handbook();
+    assert_eq!(pages.len(), 1);
+    let page = &pages[0];
+    assert_eq!(page.id, "Process_1");
+    assert_eq!(page.name.as_deref(), Some("Counting"));
+    assert!(
+        page.markdown
+            .starts_with("# Counting\n\nCounts with configuration")
+    );
+    assert!(page.markdown.contains("![Counting](Process_1.svg)"));
+    assert!(page.markdown.contains(
+        "| Count 1 | ServiceTask | Adds the threshold.<br>Reads \\| from the properties. |"
+    ));
+    assert!(page.markdown.contains("| Count 2 | ServiceTask |  |"));
+    assert!(page.dot.contains("\"Activity_0fetch\""));
+    assert!(page.dot.contains("circle: start"));
+
+    let bpmn: Process<Counter> = Process::new("tests/files/subprocess_error_message_end.bpmn")?;
+    let page = &bpmn.handbook()[0];
+    assert_eq!(page.dot.matches("subgraph cluster_").count(), 3);
+    assert!(page.dot.contains("(Error): Error event"));
+    assert!(page.dot.contains("dashed line: boundary event"));
+
+    let dir = std::env::temp_dir().join("snurr_handbook");
+    page.save(&dir)?;
+    assert!(dir.join(format!("{}.dot", page.id)).exists());
+    assert!(dir.join(format!("{}.md", page.id)).exists());
     Ok(())
 }
 
 #[test]
-fn exclusive_gateway_merging_branching() -> Result<()> {
-    let bpmn = Process::new("tests/files/exclusive_gateway_merging_branching.bpmn")?
+fn owned_flow_names() -> Result<()> {
+    // Flow names computed at runtime
+    let done = String::from("YES");
+    let bpmn = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
-        .task(COUNT_2, func_cnt(2))
-        .exclusive("BRANCHING", |_| Ok(A))
-        .exclusive("MERGE AND BRANCH", |_| Ok(B))
+        .exclusive_owned("equal to 3", move |input: Data<Counter>| {
+            Ok(Some(match input.lock().unwrap().count {
+                3 => done.clone(),
+                _ => "NO".to_string(),
+            }))
+        })
         .build()?;
     let result = bpmn.run(Counter::default())?;
     assert_eq!(result.data.count, 3);
-    Ok(())
-}
 
-#[test]
-fn event_gateway() -> Result<()> {
-    let bpmn = Process::new("tests/files/event_gateway.bpmn")?
+    let flows = vec!["YES".to_string(), "NO".to_string()];
+    let bpmn = Process::new("tests/files/inclusive_gateway.bpmn")?
         .task(COUNT_1, func_cnt(1))
         .task(COUNT_2, func_cnt(2))
         .task(COUNT_3, func_cnt(3))
-        .task("Investigate", |_| Ok(None))
-        .event_based("JUNIOR GATEKEEPER", |_| {
-            Ok(("Investigate", Symbol::Message).into())
-        })
-        .event_based("SENIOR GATEKEEPER", |_| {
-            Ok(("Sleeping", Symbol::Timer).into())
-        })
+        .inclusive("CHOOSE", move |_| Ok(flows.clone().into()))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 2);
+    assert_eq!(result.data.count, 7);
     Ok(())
 }
 
+struct Counting;
+
+impl ProcessHandlers<Counter> for Counting {
+    fn task_names(&self) -> Vec<&'static str> {
+        vec![COUNT_1]
+    }
+
+    fn task(&self, name: &str, ctx: &Context, data: Data<Counter>) -> Result<TaskResult> {
+        assert_eq!(ctx.name(), Some(name));
+        data.lock().unwrap().count += 1;
+        Ok(None)
+    }
+}
+
+struct Limit {
+    limit: u32,
+}
+
+impl ProcessHandlers<Counter> for Limit {
+    fn exclusive_names(&self) -> Vec<&'static str> {
+        vec!["equal to 3"]
+    }
+
+    fn exclusive(
+        &self,
+        _: &str,
+        _: &Context,
+        data: Data<Counter>,
+    ) -> Result<Option<std::borrow::Cow<'static, str>>> {
+        Ok(Some(if data.lock().unwrap().count == self.limit {
+            "YES".into()
+        } else {
+            "NO".into()
+        }))
+    }
+}
+
 #[test]
-fn single_flow() -> Result<()> {
-    let bpmn = Process::new("tests/files/single_flow.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .exclusive("GW A", |_| Ok(A))
-        .exclusive("GW B", |_| Ok(A))
-        .exclusive("GW C", |_| Ok(A))
-        .exclusive("GW D", |_| Ok(A))
+fn process_handlers() -> Result<()> {
+    let bpmn = Process::new("examples/example.bpmn")?
+        .register(Counting)
+        .register(Limit { limit: 5 })
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 18);
+    assert_eq!(result.data.count, 5);
     Ok(())
 }
 
+#[cfg(feature = "macros")]
+#[snurr::task("Count 1")]
+fn count_with_macro(input: Data<Counter>) -> Result<TaskResult> {
+    input.lock().unwrap().count += 1;
+    Ok(None)
+}
+
+#[cfg(feature = "macros")]
+#[snurr::exclusive("equal to 3")]
+fn equal_to_3_with_macro(ctx: &Context, input: Data<Counter>) -> Result<Option<&'static str>> {
+    assert_eq!(ctx.name(), Some("equal to 3"));
+    Ok(Some(match input.lock().unwrap().count {
+        3 => "YES",
+        _ => "NO",
+    }))
+}
+
+#[cfg(feature = "macros")]
 #[test]
-fn terminate_event() -> Result<()> {
-    let bpmn = Process::new("tests/files/terminate_event.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .exclusive("Terminate?", |_| Ok(Some("YES")))
-        .build()?;
+fn attribute_macros() -> Result<()> {
+    let process = Process::new("examples/example.bpmn")?;
+    let bpmn = snurr::collect_handlers!(process).build()?;
     let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 3);
 
-    // NOTE 2 or 3 is OK result. The order in concurrent scenarios might differ.
-    assert!(matches!(result.data.count, 2 | 3));
+    // Functions in a function body are collected too. Only the functions for the data type are registered.
+    #[snurr::task("Count 1")]
+    fn count_number(input: Data<u32>) -> Result<TaskResult> {
+        *input.lock().unwrap() += 1;
+        Ok(None)
+    }
+
+    #[snurr::exclusive("equal to 3")]
+    fn number_equal_to_3(input: Data<u32>) -> Result<Option<&'static str>> {
+        Ok(Some(if *input.lock().unwrap() == 3 {
+            "YES"
+        } else {
+            "NO"
+        }))
+    }
+
+    let bpmn = snurr::collect_handlers!(Process::new("examples/example.bpmn")?).build()?;
+    assert_eq!(bpmn.run(0u32)?.data, 3);
+
+    // The annotated functions can still be called
+    let data = Arc::new(Mutex::new(Counter::default()));
+    count_with_macro(data.clone())?;
+    assert_eq!(data.lock().unwrap().count, 1);
     Ok(())
 }
 
 #[test]
-fn terminate_event_sub_process() -> Result<()> {
-    let bpmn = Process::new("tests/files/terminate_event_sub_process.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .exclusive("Terminate?", |_| Ok(Some("YES")))
-        .build()?;
-    let result = bpmn.run(Counter::default())?;
+fn scaffold_names() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("snurr_names_{}.rs", std::process::id()));
+    let bpmn: Process<Counter> = Process::new("examples/example.bpmn")?;
+    bpmn.scaffold_names(&path)?;
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
 
-    // NOTE 4 or 5 is OK result. The order in concurrent scenarios might differ.
-    assert!(matches!(result.data.count, 4 | 5));
+    assert!(content.contains("pub mod tasks {\n    pub const COUNT_1: &str = \"Count 1\";\n}"));
+    assert!(
+        content.contains("pub mod gateways {\n    pub const EQUAL_TO_3: &str = \"equal to 3\";\n}")
+    );
+    assert!(content.contains("    pub const YES: &str = \"YES\";"));
+    assert!(content.contains("    pub const NO: &str = \"NO\";"));
+
+    // Never overwrite
+    bpmn.scaffold_names(std::env::temp_dir())
+        .expect_err("directory exists");
     Ok(())
 }
 
 #[test]
-fn startevent_not_first() -> Result<()> {
-    // StartEvent out of order in XML file
-    let bpmn = Process::new("tests/files/startevent_not_first.bpmn")?
+fn scaffold_handlers() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("snurr_handlers_{}.rs", std::process::id()));
+    let bpmn = Process::new("examples/example.bpmn")?.task(COUNT_1, func_cnt(1));
+    bpmn.scaffold_handlers(&path)?;
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::remove_file(&path)?;
+
+    // Count 1 is already registered
+    assert!(!content.contains("count_1"));
+    assert!(content.contains("use snurr::{Data, Error, Process};"));
+    assert!(content.contains(
+        "pub fn register(process: Process<MyData>) -> Process<MyData> {\n    process\n        .exclusive(\"equal to 3\", equal_to_3)\n}"
+    ));
+    assert!(content.contains(
+        "pub fn equal_to_3(_input: Data<MyData>) -> Result<Option<&'static str>, Error> {\n    todo!()\n}"
+    ));
+    Ok(())
+}
+
+#[test]
+fn build_report() -> Result<()> {
+    let process = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
+        .task("Count 3", func_cnt(1))
+        .exclusive("equal to 4", |_| Ok(None));
+    let report = process.build_report();
+    assert_eq!(report.missing, vec!["Exclusive: equal to 3"]);
+    assert_eq!(
+        report.unused,
+        vec!["Exclusive: equal to 4", "Task: Count 3"]
+    );
+    assert!(!report.is_empty());
+
+    let process = process.exclusive("equal to 3", |_| Ok(Some("YES")));
+    assert_eq!(process.build_report().missing, Vec::<String>::new());
+
+    // Unused functions are only a warning unless strict
+    assert!(
+        Process::<Counter>::new("examples/example.bpmn")?
+            .task(COUNT_1, func_cnt(1))
+            .task("Count 3", func_cnt(1))
+            .exclusive("equal to 3", |_| Ok(Some("YES")))
+            .build()
+            .is_ok()
+    );
+    assert!(matches!(
+        process.strict().build(),
+        Err(Error::UnusedImplementations(unused)) if unused == "Exclusive: equal to 4, Task: Count 3"
+    ));
+    Ok(())
+}
+
+#[test]
+fn parse_warnings() -> Result<()> {
+    let process = Process::<Counter>::new("tests/files/parse_warnings.bpmn")?;
+    let warnings = process
+        .parse_warnings()
+        .iter()
+        .map(|warning| {
+            (
+                warning.kind,
+                warning.element.as_str(),
+                warning.id.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                ParseWarningKind::MultipleEventDefinitions,
+                "signalEventDefinition",
+                "Event_1"
+            ),
+            (
+                ParseWarningKind::EventDefinitionContent,
+                "timerEventDefinition",
+                "Event_2"
+            ),
+            (
+                ParseWarningKind::Ignored,
+                "textAnnotation",
+                "TextAnnotation_1"
+            ),
+            (ParseWarningKind::Ignored, "association", "Association_1"),
+        ]
+    );
+    assert_eq!(
+        process.parse_warnings()[1].to_string(),
+        "content of timerEventDefinition (Timeout) is ignored"
+    );
+
+    // The symbol of an event definition with content is used
+    let bpmn = process
+        .task(COUNT_1, |_| Ok(Some(("Timeout", Symbol::Timer).into())))
         .build()?;
     let result = bpmn.run(Counter::default())?;
-    assert_eq!(result.data.count, 4);
+    assert_eq!(result.end_node.id, "EndEvent_2");
     Ok(())
 }
 
 #[test]
-fn process_multiple_startevent_none() -> Result<()> {
-    match Process::<Counter>::new("tests/files/process_multiple_startevent_none.bpmn") {
-        Err(error) => assert!(
-            matches!(error, Error::BpmnRequirement(_)),
-            "Expected BpmnRequirement"
+fn parse_options() -> Result<()> {
+    // Unsupported elements are skipped unless strict
+    assert!(Process::<Counter>::new("tests/files/validation.bpmn").is_ok());
+    assert!(matches!(
+        Process::<Counter>::new_with_options(
+            "tests/files/validation.bpmn",
+            ParseOptions::default().strict()
         ),
-        _ => panic!("Expected an error"),
+        Err(Error::NotSupported(_))
+    ));
+
+    // Conditional sequence flows are only skipped when lenient
+    let path = "tests/files/conditional_sequence_flows.bpmn";
+    assert!(Process::<Counter>::new_with_options(path, ParseOptions::default()).is_err());
+    let process = Process::<Counter>::new_with_options(path, ParseOptions::default().lenient())?;
+    let conditions = process
+        .parse_warnings()
+        .iter()
+        .filter(|warning| {
+            warning.kind == ParseWarningKind::Unsupported
+                && warning.element == "conditionExpression"
+        })
+        .filter_map(|warning| warning.id.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(conditions, vec!["Flow_0ui1gbl", "Flow_04lf8c4"]);
+    Ok(())
+}
+
+#[test]
+fn parse_error_position() -> Result<()> {
+    let bpmn = r#"<?xml version="1.0" encoding="UTF-8"?>
+<bpmn:definitions xmlns:bpmn="http://www.omg.org/spec/BPMN/20100524/MODEL" id="Definitions_1">
+  <bpmn:process id="Process_1">
+    <bpmn:startEvent id="StartEvent_1">
+      <bpmn:outgoing>Flow_1</bpmn:outgoing>
+    </bpmn:startEvent>
+    <bpmn:sequenceFlow id="Flow_1" sourceRef="StartEvent_1" />
+  </bpmn:process>
+</bpmn:definitions>"#;
+    assert!(matches!(
+        bpmn.parse::<Process<Counter>>(),
+        Err(Error::MissingTargetRef)
+    ));
+
+    // Malformed XML
+    match bpmn
+        .replace("</bpmn:startEvent>", "</bpmn:start>")
+        .parse::<Process<Counter>>()
+    {
+        Err(error @ Error::Parse { line, .. }) => {
+            assert_eq!(line, 6);
+            assert!(error.to_string().contains("at line 6"), "{error}");
+        }
+        _ => panic!("Expected a parse error"),
     }
     Ok(())
 }
 
 #[test]
-fn subprocess_multiple_startevent_none() -> Result<()> {
-    match Process::<Counter>::new("tests/files/subprocess_multiple_startevent_none.bpmn") {
-        Err(error) => assert!(
-            matches!(error, Error::BpmnRequirement(_)),
-            "Expected BpmnRequirement"
-        ),
-        _ => panic!("Expected an error"),
+fn from_reader_and_bytes() -> Result<()> {
+    let file = std::fs::File::open("examples/example.bpmn")?;
+    let from_reader = Process::from_reader(std::io::BufReader::new(file))?;
+    let from_bytes = Process::from_bytes(include_bytes!("../examples/example.bpmn"))?;
+    for process in [from_reader, from_bytes] {
+        let bpmn = process
+            .task(COUNT_1, func_cnt(1))
+            .exclusive("equal to 3", |input: Data<Counter>| {
+                Ok(Some(if input.lock().unwrap().count == 3 {
+                    "YES"
+                } else {
+                    "NO"
+                }))
+            })
+            .build()?;
+        assert_eq!(bpmn.run(Counter::default())?.data.count, 3);
     }
     Ok(())
 }
 
+#[cfg(feature = "http")]
 #[test]
-fn cancel_transaction() -> Result<()> {
-    // An cancel end event terminates the transaction and use the cancel boundary.
-    let bpmn = Process::new("tests/files/cancel_transaction.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .exclusive("Cancel?", |_| Ok(Some("YES")))
-        .build()?;
-    let result = bpmn.run(Counter::default())?;
-    // NOTE 2 or 3 is OK result. The order in concurrent scenarios might differ.
-    assert!(matches!(result.data.count, 3 | 4));
+fn from_url() -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let address = listener.local_addr()?;
+    let server = std::thread::spawn(move || -> std::io::Result<()> {
+        let (mut stream, _) = listener.accept()?;
+        // Read the request headers
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+        let body = std::fs::read("examples/example.bpmn")?;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(&body)
+    });
+
+    let process = Process::<Counter>::from_url(&format!("http://{address}/example.bpmn"))?;
+    server.join().unwrap()?;
+    assert!(
+        process
+            .build_report()
+            .missing
+            .contains(&"Task: Count 1".to_string())
+    );
     Ok(())
 }
 
+#[cfg(feature = "variables")]
 #[test]
-fn parallel_stalled_execution() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_stalled_execution.bpmn")?
-        .task(COUNT_1, func_cnt(1))
-        .exclusive("Message?", |_| Ok(Some("YES")))
+fn variables() -> Result<()> {
+    use snurr::{Variables, serde_json::json};
+
+    let bpmn = Process::<()>::new("examples/example.bpmn")?
+        .task_with_context(COUNT_1, |ctx, _| {
+            let variables = ctx.variables();
+            let count = variables
+                .get("count")
+                .and_then(|value| value.as_u64())
+                .unwrap_or_default()
+                + 1;
+            variables.set("count", count);
+            if count == 3 {
+                variables.set("decision", "YES");
+            }
+            Ok(None)
+        })
+        .exclusive_by_variable("equal to 3", "decision")
         .build()?;
 
-    match bpmn.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::BpmnRequirement(_)),
-            "Expected BpmnRequirement"
-        ),
-        _ => panic!("Expected an error"),
-    }
+    let variables = Variables::from_iter([("decision", "NO"), ("customer", "ACME")]);
+    let result = bpmn.run_with((), RunOptions::default().variables(variables))?;
+    assert_eq!(result.variables.get("count"), Some(json!(3)));
+    assert_eq!(result.variables.get("customer"), Some(json!("ACME")));
+
+    // Every run starts with its own variables
+    let variables = Variables::from_iter([("decision", "NO")]);
+    let result = bpmn.run_with((), RunOptions::default().variables(variables))?;
+    assert_eq!(result.variables.get("count"), Some(json!(3)));
+    assert!(!result.variables.contains("customer"));
     Ok(())
 }
 
+#[cfg(feature = "variables")]
 #[test]
-#[cfg(debug_assertions)]
-fn parallel_unbalanced() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_unbalanced.bpmn")?
-        .task(COUNT_1, func_cnt(1))
+fn io_mapping() -> Result<()> {
+    use snurr::{Variables, serde_json::json};
+
+    let bpmn = Process::<()>::new("tests/files/io_mapping.bpmn")?
+        .task_with_context("Price order", |ctx, _| {
+            let variables = ctx.variables();
+            assert_eq!(variables.get("customer"), Some(json!("ACME")));
+            assert_eq!(variables.get("currency"), Some(json!("EUR")));
+            // Only the input parameters are visible to the task
+            assert!(!variables.contains("order"));
+            variables.set("total", 42);
+            variables.set("discount", 5);
+            Ok(None)
+        })
+        .task_with_context("Ship order", |ctx, _| {
+            assert_eq!(ctx.variables().get("item"), Some(json!("book")));
+            ctx.variables().set("tracking", "TR-1");
+            Ok(None)
+        })
+        .task_with_context("Send invoice", |ctx, _| {
+            let price = ctx.variables().get("price");
+            assert_eq!(price, Some(json!(42)));
+            ctx.variables()
+                .set("invoice", format!("invoice of {}", price.unwrap()));
+            Ok(None)
+        })
         .build()?;
 
-    match bpmn.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::NotSupported(_)),
-            "Expected NotSupported"
-        ),
-        _ => panic!("Expected an error"),
-    }
+    let variables = Variables::from_iter([(
+        "order",
+        json!({"customer": "ACME", "items": ["book", "pen"]}),
+    )]);
+    let result = bpmn.run_with((), RunOptions::default().variables(variables))?;
+    assert_eq!(result.variables.get("price"), Some(json!(42)));
+    assert_eq!(result.variables.get("trackingNumber"), Some(json!("TR-1")));
+    assert_eq!(
+        result.variables.get("invoice"),
+        Some(json!("invoice of 42"))
+    );
+    // Variables that are not output parameters stay in the task
+    assert!(!result.variables.contains("discount"));
+    assert!(!result.variables.contains("customer"));
     Ok(())
 }
 
+#[cfg(feature = "parallel")]
 #[test]
-#[cfg(debug_assertions)]
-fn parallel_unbalanced2() -> Result<()> {
-    let bpmn = Process::new("tests/files/parallel_unbalanced2.bpmn")?
-        .task(COUNT_1, func_cnt(1))
+fn thread_pool() -> Result<()> {
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+    let task = |count| {
+        let threads = Arc::clone(&threads);
+        move |input: Data<Counter>| {
+            threads
+                .lock()
+                .unwrap()
+                .insert(std::thread::current().name().map(String::from));
+            input.lock().unwrap().count += count;
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, task(1))
+        .task(COUNT_2, task(2))
+        .task(COUNT_3, task(3))
+        .task(COUNT_4, task(4))
         .build()?;
 
-    match bpmn.run(Counter::default()) {
-        Err(error) => assert!(
-            matches!(error, Error::NotSupported(_)),
-            "Expected NotSupported"
-        ),
-        _ => panic!("Expected an error"),
-    }
+    let pool = snurr::rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .thread_name(|index| format!("process-{index}"))
+        .build()
+        .unwrap();
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().thread_pool(&pool))?;
+    assert_eq!(result.data.count, 10);
+    assert!(threads.lock().unwrap().iter().all(|name| {
+        name.as_deref()
+            .is_some_and(|name| name.starts_with("process-"))
+    }));
+
+    threads.lock().unwrap().clear();
+    let result = bpmn.run_with(Counter::default(), RunOptions::default().max_concurrency(1))?;
+    assert_eq!(result.data.count, 10);
+    assert_eq!(threads.lock().unwrap().len(), 1);
     Ok(())
 }
 
+#[cfg(feature = "parallel")]
 #[test]
-fn process_output_contains_end_node_info() -> Result<()> {
-    let bpmn = Process::new("tests/files/one_task.bpmn")?
-        .task(COUNT_1, func_cnt(1))
+fn parallel_threshold() -> Result<()> {
+    let threads = Arc::new(Mutex::new(HashSet::new()));
+    let task = |count| {
+        let threads = Arc::clone(&threads);
+        move |input: Data<Counter>| {
+            threads.lock().unwrap().insert(std::thread::current().id());
+            input.lock().unwrap().count += count;
+            Ok(None)
+        }
+    };
+    let bpmn = Process::new("tests/files/parallell_gateway.bpmn")?
+        .task(COUNT_1, task(1))
+        .task(COUNT_2, task(2))
+        .task(COUNT_3, task(3))
+        .task(COUNT_4, task(4))
+        .parallel_threshold(4)
         .build()?;
+
+    // The three branches are below the threshold and run on the calling thread
     let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 10);
+    assert_eq!(
+        *threads.lock().unwrap(),
+        HashSet::from([std::thread::current().id()])
+    );
+    Ok(())
+}
 
-    // Verify we get the data
-    assert_eq!(result.data.count, 1);
+#[cfg(feature = "compiled")]
+#[test]
+fn compiled_diagram() -> Result<()> {
+    let path = std::env::temp_dir().join(format!("snurr_compiled_{}.snurr", std::process::id()));
+    Process::<Counter>::new("tests/files/parallell_gateway.bpmn")?.compile_to(&path)?;
 
-    // Verify we get end node information
-    assert!(
-        !result.end_node.id.is_empty(),
-        "End node ID should not be empty"
-    );
+    let bpmn = Process::from_compiled(&path)?
+        .task(COUNT_1, func_cnt(1))
+        .task(COUNT_2, func_cnt(2))
+        .task(COUNT_3, func_cnt(3))
+        .task(COUNT_4, func_cnt(4))
+        .build()?;
+    let result = bpmn.run(Counter::default())?;
+    assert_eq!(result.data.count, 10);
 
-    // Regular end event should have no symbol
-    assert_eq!(result.end_node.symbol, Symbol::None);
+    // A BPMN file is not a compiled diagram
+    assert!(matches!(
+        Process::<Counter>::from_compiled("tests/files/parallell_gateway.bpmn"),
+        Err(Error::Compiled(_))
+    ));
 
+    // A truncated file is rejected
+    let bytes = std::fs::read(&path)?;
+    std::fs::write(&path, &bytes[..bytes.len() / 2])?;
+    assert!(matches!(
+        Process::<Counter>::from_compiled(&path),
+        Err(Error::Compiled(_))
+    ));
+    std::fs::remove_file(path)?;
     Ok(())
 }
 
+#[cfg(feature = "cli")]
 #[test]
-fn process_output_message_end_event() -> Result<()> {
-    let bpmn = Process::new("tests/files/process_end_with_symbol.bpmn")?.build()?;
-    let result = bpmn.run(Counter::default())?;
+fn cli() -> Result<()> {
+    let snurr = |args: &[&str]| {
+        std::process::Command::new(env!("CARGO_BIN_EXE_snurr"))
+            .args(args)
+            .output()
+    };
 
-    // Verify the data
-    assert_eq!(result.data.count, 0);
+    let output = snurr(&["validate", "examples/example.bpmn"])?;
+    assert!(output.status.success());
 
-    // Verify the end event symbol is captured
-    assert_eq!(
-        result.end_node.symbol,
-        Symbol::Message,
-        "Expected Message end event symbol"
-    );
+    let output = snurr(&["run", "examples/example.bpmn", "--mock"])?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Task Count 1"));
+    assert!(stdout.ends_with("Ended at End process\n"));
 
+    let output = snurr(&["graph", "examples/example.bpmn", "--mermaid"])?;
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("flowchart LR"));
+
+    // Nothing can run without the mocks
+    assert!(!snurr(&["run", "examples/example.bpmn"])?.status.success());
     Ok(())
 }
 
+#[cfg(feature = "otel")]
 #[test]
-fn process_output_terminate_end_event() -> Result<()> {
-    let bpmn = Process::new("tests/files/terminate_event.bpmn")?
+fn otel_spans() -> Result<()> {
+    use opentelemetry::{
+        Context, global,
+        trace::{TraceContextExt, Tracer},
+    };
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    global::set_tracer_provider(provider);
+
+    let bpmn = Process::new("examples/example.bpmn")?
         .task(COUNT_1, func_cnt(1))
-        .exclusive("Terminate?", |_| Ok(Some("YES")))
+        .exclusive("equal to 3", |input| match input.lock().unwrap().count {
+            3 => Ok(Some("YES")),
+            _ => Ok(Some("NO")),
+        })
         .build()?;
-    let result = bpmn.run(Counter::default())?;
 
-    // Verify the data (2 or 3 is OK due to concurrent execution)
-    assert!(matches!(result.data.count, 2 | 3));
+    // The run is a child of the span of the application
+    let context = Context::current_with_span(global::tracer("test").start("request"));
+    {
+        let _guard = context.clone().attach();
+        bpmn.run(Counter::default())?;
+    }
+    let root = context.span().span_context().clone();
 
-    // Verify the end event symbol is Terminate
+    let spans: Vec<_> = exporter
+        .get_finished_spans()
+        .unwrap()
+        .into_iter()
+        .filter(|span| span.span_context.trace_id() == root.trace_id())
+        .collect();
+    let process = spans
+        .iter()
+        .find(|span| span.name == "process Process_10pudx4")
+        .unwrap();
+    assert_eq!(process.parent_span_id, root.span_id());
     assert_eq!(
-        result.end_node.symbol,
-        Symbol::Terminate,
-        "Expected Terminate end event symbol"
+        spans
+            .iter()
+            .filter(|span| span.name == COUNT_1
+                && span.parent_span_id == process.span_context.span_id())
+            .count(),
+        3
     );
-
     Ok(())
 }
@@ -0,0 +1,41 @@
+#![cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+
+use snurr::{Data, Process, RunOptions};
+use std::time::Duration;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+// No file system on the target
+const EXAMPLE: &str = include_str!("../examples/example.bpmn");
+
+fn example() -> Process<u32, snurr::Run> {
+    EXAMPLE
+        .parse::<Process<u32>>()
+        .unwrap()
+        .task("Count 1", |input: Data<u32>| {
+            *input.lock().unwrap() += 1;
+            Ok(None)
+        })
+        .exclusive("equal to 3", |input: Data<u32>| {
+            Ok(Some(if *input.lock().unwrap() == 3 {
+                "YES"
+            } else {
+                "NO"
+            }))
+        })
+        .build()
+        .unwrap()
+}
+
+#[wasm_bindgen_test]
+fn run() {
+    let result = example().run(0).unwrap();
+    assert_eq!(result.data, 3);
+}
+
+#[wasm_bindgen_test]
+fn run_with_timeout() {
+    let result = example()
+        .run_with(0, RunOptions::default().timeout(Duration::from_secs(1)))
+        .unwrap();
+    assert_eq!(result.data, 3);
+}